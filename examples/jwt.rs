@@ -0,0 +1,93 @@
+// Issue and check an RS256 JSON Web Token (RFC 7519, RFC 7518 section 3.3)
+//
+//   cargo run --example jwt
+//
+// RS256 is RSASSA-PKCS1-v1_5 with SHA-256 over "header.payload", every part base64url
+// without padding, which is signature::sign with Emsa::Pkcs1v15. The token is then checked
+// the way a verifier would, and a token with an edited payload is rejected.
+use naive_rsa::hash_alg::HashAlg;
+use naive_rsa::signature::{sign, verify, Emsa};
+use naive_rsa::{gen_keys, PrivateKey, PublicKey};
+
+const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn base64url(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, &b)| acc | u32::from(b) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(ALPHABET[(group >> (18 - 6 * i) & 63) as usize] as char);
+        }
+    }
+    out
+}
+
+fn base64url_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let value = ALPHABET.iter().position(|&a| a == c)? as u32;
+        acc = acc << 6 | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+fn issue(pub_key: &PublicKey, priv_key: &PrivateKey, claims: &str) -> String {
+    let header = base64url(br#"{"alg":"RS256","typ":"JWT"}"#);
+    let signing_input = format!("{}.{}", header, base64url(claims.as_bytes()));
+    let sig = sign(
+        pub_key,
+        priv_key,
+        signing_input.as_bytes(),
+        Emsa::Pkcs1v15,
+        HashAlg::Sha256,
+    )
+    .expect("gen_keys moduli are long enough for SHA-256");
+    format!("{}.{}", signing_input, base64url(&sig))
+}
+
+// check: the claims of a token signed by pub_key's owner, or None
+fn check(pub_key: &PublicKey, token: &str) -> Option<String> {
+    let (signing_input, sig) = token.rsplit_once('.')?;
+    let (header, payload) = signing_input.split_once('.')?;
+    if base64url_decode(header)? != br#"{"alg":"RS256","typ":"JWT"}"# {
+        return None;
+    }
+    let sig = base64url_decode(sig)?;
+    verify(
+        pub_key,
+        signing_input.as_bytes(),
+        &sig,
+        Emsa::Pkcs1v15,
+        HashAlg::Sha256,
+    )
+    .ok()?;
+    String::from_utf8(base64url_decode(payload)?).ok()
+}
+
+fn main() {
+    let (pub_key, priv_key) = gen_keys();
+    let token = issue(&pub_key, &priv_key, r#"{"sub":"alice","admin":false}"#);
+    println!("{}\n", token);
+    let claims = check(&pub_key, &token).expect("a freshly issued token verifies");
+    println!("claims: {}", claims);
+
+    // Editing the payload keeps the old signature, which no longer matches
+    let parts: Vec<&str> = token.split('.').collect();
+    let forged = format!(
+        "{}.{}.{}",
+        parts[0],
+        base64url(br#"{"sub":"alice","admin":true}"#),
+        parts[2]
+    );
+    assert!(check(&pub_key, &forged).is_none());
+    println!("a token with an edited payload is rejected");
+}
@@ -0,0 +1,54 @@
+// Generate a key pair, seal a file to it and open it again
+//
+//   cargo run --example keygen_and_encrypt_file [FILE]
+//
+// The key pair is written next to the file as FILE.key in the paper format, the sealed
+// contents as FILE.sealed. Without a FILE, the example seals a short message in a temporary
+// directory instead. Sealing is kem's hybrid encryption, so files of any size work.
+use naive_rsa::keypair::KeyPair;
+use naive_rsa::report::describe;
+use naive_rsa::{kem, paper};
+use std::path::PathBuf;
+
+fn main() -> std::io::Result<()> {
+    let path = match std::env::args().nth(1) {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let path = std::env::temp_dir().join("naive-rsa-example.txt");
+            std::fs::write(&path, "attack at dawn\n")?;
+            path
+        }
+    };
+    let with_extension = |ext: &str| {
+        let mut name = path.clone().into_os_string();
+        name.push(ext);
+        PathBuf::from(name)
+    };
+
+    let key_pair = KeyPair::generate(100);
+    println!("{}\n", describe(&key_pair));
+    let key_path = with_extension(".key");
+    std::fs::write(&key_path, paper::encode_key_pair(&key_pair))?;
+
+    let contents = std::fs::read(&path)?;
+    let sealed = kem::seal(key_pair.public(), &contents);
+    let sealed_path = with_extension(".sealed");
+    std::fs::write(&sealed_path, &sealed)?;
+    println!(
+        "sealed {} bytes of {} into {} ({} bytes)",
+        contents.len(),
+        path.display(),
+        sealed_path.display(),
+        sealed.len()
+    );
+
+    // Read everything back from disk, as the recipient would
+    let text = std::fs::read_to_string(&key_path)?;
+    let key_pair = paper::decode_key_pair(&text).expect("the key file was just written");
+    let sealed = std::fs::read(&sealed_path)?;
+    let opened = kem::open(key_pair.public(), key_pair.private(), &sealed)
+        .expect("the sealed file was just written");
+    assert_eq!(opened, contents);
+    println!("opened it again with {}", key_path.display());
+    Ok(())
+}
@@ -0,0 +1,37 @@
+// Mediated (2-of-2) signing with a split private exponent
+//
+//   cargo run --example threshold_signing
+//
+// This is mediated RSA, not a general t-of-n threshold scheme: both shares are always
+// needed and there is no other quorum. mrsa splits d into a user share and a mediator
+// share, d = d_user + d_mediator mod phi(n). Signing H(m)^d then needs both: each party
+// raises the hashed message to its own share and the two parts are multiplied. The
+// signature verifies with fdh against the ordinary public key, so a verifier cannot tell
+// it was made in two halves. Once the mediator revokes the user, the user's share alone
+// signs nothing.
+use naive_rsa::fdh;
+use naive_rsa::mrsa::{self, Mediator};
+
+fn main() {
+    let (pub_key, user_share, mediator_share) = mrsa::gen_keys(50);
+    let mut mediator = Mediator::new();
+    mediator.enroll("alice", pub_key.clone(), mediator_share);
+
+    let m = b"transfer 10 coins to bob";
+    let point = fdh::message_point(&pub_key, m);
+    let user_part = user_share.partial_decrypt(&pub_key, &point);
+    let mediator_part = mediator
+        .partial_decrypt("alice", &point)
+        .expect("alice is enrolled");
+    let sig = mrsa::combine(&pub_key, &user_part, &mediator_part);
+    assert!(fdh::verify(&pub_key, m, &sig));
+    println!("signature from both shares verifies: {}", sig);
+
+    // The user's part on its own is not a signature
+    assert!(!fdh::verify(&pub_key, m, &user_part));
+    println!("the user's part alone does not verify");
+
+    mediator.revoke("alice");
+    assert!(mediator.partial_decrypt("alice", &point).is_none());
+    println!("after revocation the mediator refuses to help alice sign");
+}
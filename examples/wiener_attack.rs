@@ -0,0 +1,99 @@
+// Wiener's attack on a small private exponent
+//
+//   cargo run --example wiener_attack
+//
+// If d < n^(1/4) / 3, then k/d is a convergent of the continued fraction of e/n, where
+// ed = 1 + k phi(n). For each convergent the example guesses phi = (ed - 1) / k and checks
+// it by solving x^2 - (n - phi + 1) x + n = 0, whose roots would be p and q. The example
+// builds such a key on purpose through the public API, then recovers d from (n, e) alone.
+// https://en.wikipedia.org/wiki/Wiener%27s_attack
+use naive_rsa::error::RsaError;
+use naive_rsa::keypair::KeyPair;
+use naive_rsa::{decrypt, encrypt, mod_inverse, os2ip, random_prime_with_digits};
+use naive_rsa::{Plaintext, PublicKey};
+use num_bigint::{BigInt, RandBigInt};
+use num_traits::{One, Zero};
+
+// vulnerable_key: a key pair whose d is a random number below n^(1/4) / 3
+fn vulnerable_key(ndigits: u32) -> KeyPair {
+    loop {
+        let (p, q) = (
+            random_prime_with_digits(ndigits),
+            random_prime_with_digits(ndigits),
+        );
+        let n = &p * &q;
+        let phi: BigInt = (&p - 1) * (&q - 1);
+        let bound = n.nth_root(4) / 3;
+        let d = rand::thread_rng().gen_bigint_range(&BigInt::from(3), &bound);
+        if p == q {
+            continue;
+        }
+        // mod_inverse rejects a d that shares a factor with phi
+        if let Ok(e) = mod_inverse(d, phi) {
+            return KeyPair::from_primes(p, q, e);
+        }
+    }
+}
+
+// convergents: the successive convergents h/k of the continued fraction of a/b
+fn convergents(mut a: BigInt, mut b: BigInt) -> Vec<(BigInt, BigInt)> {
+    let (mut h, mut h_prev) = (BigInt::one(), BigInt::zero());
+    let (mut k, mut k_prev) = (BigInt::zero(), BigInt::one());
+    let mut out = Vec::new();
+    while !b.is_zero() {
+        let quotient = &a / &b;
+        (a, b) = (b.clone(), a - &quotient * &b);
+        (h, h_prev) = (&quotient * &h + &h_prev, h);
+        (k, k_prev) = (&quotient * &k + &k_prev, k);
+        out.push((h.clone(), k.clone()));
+    }
+    out
+}
+
+// wiener: d, if it is small enough for the attack
+fn wiener(pub_key: &PublicKey) -> Option<BigInt> {
+    let n = os2ip(&pub_key.modulus_bytes());
+    let e = os2ip(&pub_key.exponent_bytes());
+    for (k, d) in convergents(e.clone(), n.clone()) {
+        if k.is_zero() || !((&e * &d - 1u32) % &k).is_zero() {
+            continue;
+        }
+        let phi = (&e * &d - 1u32) / &k;
+        let sum = &n - &phi + 1u32;
+        let discriminant = &sum * &sum - 4u32 * &n;
+        if discriminant < BigInt::zero() {
+            continue;
+        }
+        let root = discriminant.sqrt();
+        if &root * &root == discriminant && ((&sum + &root) % 2u32).is_zero() {
+            return Some(d);
+        }
+    }
+    None
+}
+
+fn main() -> Result<(), RsaError> {
+    let key_pair = vulnerable_key(30);
+    let pub_key = key_pair.public();
+    println!(
+        "modulus: {} bits, private exponent: {} bits",
+        pub_key.modulus_bytes().len() * 8,
+        key_pair.private().exponent().bits()
+    );
+    let d = wiener(pub_key).expect("d is below the Wiener bound");
+    assert_eq!(&d, key_pair.private().exponent());
+    println!("recovered d = {}", d);
+
+    // The recovered exponent decrypts like the real one
+    let m = Plaintext::from(BigInt::from(42));
+    let c = encrypt(pub_key, m.clone())?;
+    assert_eq!(decrypt(pub_key, key_pair.private(), c.clone())?, m);
+    let recovered = BigInt::from(c).modpow(&d, &os2ip(&pub_key.modulus_bytes()));
+    assert_eq!(recovered, BigInt::from(42));
+    println!("and decrypts a ciphertext to {}", recovered);
+
+    // A key from KeyPair::generate has a d about as long as n and is out of reach
+    assert!(wiener(KeyPair::generate(30).public()).is_none());
+    println!("an ordinary key resists the attack");
+    Ok(())
+}
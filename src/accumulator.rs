@@ -0,0 +1,143 @@
+// RSA accumulators
+//
+// An accumulator commits to a set in a single group element, acc = g^(x_1 x_2 ... x_k) mod n,
+// where x_i is a prime representative of the i-th element: its SHA-256 hash with the top bit
+// set, rounded up with next_prime. The witness for x_i is the same power without x_i, and
+// w^x_i = acc proves membership. Forging a witness for a non-member means taking roots in
+// the group, so n must be a modulus whose factors nobody knows, such as one from
+// challenge_modulus. verify_batch checks many witnesses at once with the small exponents
+// test of the batch module: prod w_i^(x_i r_i) = acc^(r_1 + ... + r_k) for random r_i.
+use crate::batch::randomizers;
+use crate::hash::{self, TranscriptHash};
+use crate::modexp::multi_modpow;
+use crate::next_prime;
+use num_bigint::{BigInt, Sign};
+use num_traits::{One, Zero};
+
+#[derive(Clone, Debug)]
+pub struct Accumulator {
+    n: BigInt,
+    g: BigInt,
+    value: BigInt,
+    members: Vec<BigInt>,
+}
+
+// representative: the prime an element is accumulated as
+pub fn representative(element: &[u8]) -> BigInt {
+    let digest = TranscriptHash::new("naive-rsa accumulator element")
+        .bytes(element)
+        .finish();
+    let x = BigInt::from_bytes_be(Sign::Plus, &digest) | (BigInt::one() << 255);
+    next_prime(&x)
+}
+
+impl Accumulator {
+    // new: the empty accumulator over n, with a generator derived by hashing n
+    pub fn new(n: BigInt) -> Self {
+        let seed = TranscriptHash::new("naive-rsa accumulator generator")
+            .int(&n)
+            .finish();
+        let x = hash::full_domain(&seed, &n);
+        let g = &x * &x % &n;
+        Accumulator {
+            value: g.clone(),
+            n,
+            g,
+            members: Vec::new(),
+        }
+    }
+
+    pub fn modulus(&self) -> &BigInt {
+        &self.n
+    }
+
+    pub fn value(&self) -> &BigInt {
+        &self.value
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    // add: accumulate element; adding one that is already a member changes nothing
+    pub fn add(&mut self, element: &[u8]) {
+        let x = representative(element);
+        if !self.members.contains(&x) {
+            self.value = self.value.modpow(&x, &self.n);
+            self.members.push(x);
+        }
+    }
+
+    // witness: g raised to the other members, or None if element is not a member
+    pub fn witness(&self, element: &[u8]) -> Option<BigInt> {
+        let x = representative(element);
+        if !self.members.contains(&x) {
+            return None;
+        }
+        let others: BigInt = self.members.iter().filter(|&m| *m != x).product();
+        Some(self.g.modpow(&others, &self.n))
+    }
+}
+
+pub fn verify(n: &BigInt, acc: &BigInt, element: &[u8], witness: &BigInt) -> bool {
+    *witness > BigInt::zero() && witness < n && witness.modpow(&representative(element), n) == *acc
+}
+
+// verify_batch: whether every witness proves its element a member, up to factors of small
+// order as explained in the batch module
+pub fn verify_batch(n: &BigInt, acc: &BigInt, proofs: &[(&[u8], BigInt)]) -> bool {
+    if !proofs.iter().all(|(_, w)| *w > BigInt::zero() && w < n) {
+        return false;
+    }
+    let r = randomizers(proofs.len());
+    let pairs: Vec<(BigInt, BigInt)> = proofs
+        .iter()
+        .zip(&r)
+        .map(|((element, w), r)| (w.clone(), representative(element) * r))
+        .collect();
+    multi_modpow(&pairs, n) == acc.modpow(&r.iter().sum(), n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+
+    fn accumulator(elements: &[&[u8]]) -> Accumulator {
+        // Fine for a test; a real accumulator needs a modulus without a known private key
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let mut acc = Accumulator::new(pub_key.n.clone());
+        for element in elements {
+            acc.add(element);
+        }
+        acc
+    }
+
+    #[test]
+    fn members_have_witnesses() {
+        let acc = accumulator(&[b"alice", b"bob", b"carol", b"bob"]);
+        assert_eq!(acc.len(), 3);
+        let (n, value) = (acc.modulus(), acc.value());
+        let w = acc.witness(b"bob").unwrap();
+        assert!(verify(n, value, b"bob", &w));
+        assert!(!verify(n, value, b"mallory", &w));
+        assert_eq!(acc.witness(b"mallory"), None);
+    }
+
+    #[test]
+    fn witnesses_verify_in_batches() {
+        let acc = accumulator(&[b"alice", b"bob", b"carol"]);
+        let (n, value) = (acc.modulus(), acc.value());
+        let mut proofs: Vec<(&[u8], BigInt)> = [&b"alice"[..], b"bob", b"carol"]
+            .into_iter()
+            .map(|e| (e, acc.witness(e).unwrap()))
+            .collect();
+        assert!(verify_batch(n, value, &proofs));
+        proofs[0].0 = b"mallory";
+        assert!(!verify_batch(n, value, &proofs));
+    }
+}
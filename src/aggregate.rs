@@ -0,0 +1,242 @@
+// Sequential aggregate signatures from trapdoor permutations (Lysyanskaya, Micali, Reyzin
+// and Shacham, 2004)
+//
+// Signers take turns: the i-th signer hashes every public key and message so far into h_i
+// and replaces the aggregate s with g_i^-1(h_i xor s), where g_i is their trapdoor
+// permutation (RSA by default) extended to a fixed b-bit domain as in the ring signatures.
+// The aggregate stays one domain element however many signers there are. Verification
+// walks the chain backward, s <- g_i(s) xor h_i, and accepts if it ends at the all-zero
+// starting value. Each signer checks the aggregate they were handed before adding to it.
+// https://eprint.iacr.org/2003/091
+use crate::error::ParseError;
+use crate::hash::{self, TranscriptHash};
+use crate::ring::{from_block, to_block, xor, DOMAIN_SLACK_BITS};
+use crate::trapdoor::{extended_forward, extended_inverse, TrapdoorPermutation};
+use crate::wire::{Reader, Writer};
+use crate::{checked_public_key, PublicKey};
+use num_bigint::BigInt;
+
+// The domain is fixed in advance, since a signer cannot know the keys that come after them
+const MAX_MODULUS_BITS: u64 = 4096;
+const DOMAIN_BYTES: usize = (MAX_MODULUS_BITS + DOMAIN_SLACK_BITS).div_ceil(8) as usize;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AggregateSignature<P = PublicKey> {
+    signers: Vec<P>,
+    messages: Vec<Vec<u8>>,
+    sigma: BigInt,
+}
+
+impl<P> AggregateSignature<P> {
+    // new: the empty aggregate that the first signer starts from
+    pub fn new() -> Self {
+        AggregateSignature {
+            signers: Vec::new(),
+            messages: Vec::new(),
+            sigma: BigInt::from(0),
+        }
+    }
+
+    pub fn signers(&self) -> &[P] {
+        &self.signers
+    }
+
+    pub fn messages(&self) -> &[Vec<u8>] {
+        &self.messages
+    }
+
+    pub fn len(&self) -> usize {
+        self.signers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signers.is_empty()
+    }
+}
+
+impl AggregateSignature<PublicKey> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::new("naive-rsa aggregate signature");
+        w.int(&self.sigma);
+        w.bytes(&(self.signers.len() as u32).to_be_bytes());
+        for (key, m) in self.signers.iter().zip(&self.messages) {
+            w.int(&key.n).int(&key.e).bytes(m);
+        }
+        w.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        const FORMAT: &str = "naive-rsa aggregate signature";
+        let mut r = Reader::new(bytes, FORMAT)?;
+        let sigma = r.int()?;
+        let len = r.count()?;
+        let (mut signers, mut messages) = (Vec::new(), Vec::new());
+        for _ in 0..len {
+            let offset = r.offset();
+            let (n, e) = (r.int()?, r.int()?);
+            let invalid = |reason| ParseError::invalid(FORMAT, offset, reason);
+            if e < BigInt::from(3) {
+                return Err(invalid("a signer's public exponent must be at least 3"));
+            }
+            signers.push(checked_public_key(n, e).map_err(invalid)?);
+            messages.push(r.bytes()?.to_vec());
+        }
+        r.finish()?;
+        Ok(AggregateSignature {
+            signers,
+            messages,
+            sigma,
+        })
+    }
+}
+
+impl<P> Default for AggregateSignature<P> {
+    fn default() -> Self {
+        AggregateSignature::new()
+    }
+}
+
+// chain_hash: h_i, binding the first i keys and messages of the aggregate
+fn chain_hash<P: TrapdoorPermutation>(signers: &[P], messages: &[Vec<u8>]) -> Vec<u8> {
+    let mut h = TranscriptHash::new("naive-rsa aggregate signature");
+    for (key, m) in signers.iter().zip(messages) {
+        h.bytes(&key.encode()).bytes(m);
+    }
+    hash::expand(&h.finish(), DOMAIN_BYTES)
+}
+
+// usable_signer: whether a key fits the fixed domain and is a well-formed permutation
+fn usable_signer<P: TrapdoorPermutation>(key: &P) -> bool {
+    key.domain().bits() <= MAX_MODULUS_BITS
+        && *key.domain() >= BigInt::from(3)
+        && key.is_well_formed()
+}
+
+// sign: add a signature on m to the aggregate; None if prev does not verify or pub_key
+// cannot join it, e.g. a modulus above MAX_MODULUS_BITS
+pub fn sign<P: TrapdoorPermutation + Clone>(
+    prev: &AggregateSignature<P>,
+    pub_key: &P,
+    trapdoor: &P::Trapdoor,
+    m: &[u8],
+) -> Option<AggregateSignature<P>> {
+    if !usable_signer(pub_key) || !verify(prev) {
+        return None;
+    }
+    let mut next = prev.clone();
+    next.signers.push(pub_key.clone());
+    next.messages.push(m.to_vec());
+    let h = chain_hash(&next.signers, &next.messages);
+    let y = from_block(&xor(&h, &to_block(&prev.sigma, DOMAIN_BYTES)));
+    next.sigma = extended_inverse(pub_key, trapdoor, &y, DOMAIN_BYTES);
+    Some(next)
+}
+
+pub fn verify<P: TrapdoorPermutation>(agg: &AggregateSignature<P>) -> bool {
+    let bound = BigInt::from(1) << (8 * DOMAIN_BYTES);
+    if agg.signers.len() != agg.messages.len()
+        || agg.sigma < BigInt::from(0)
+        || agg.sigma >= bound
+        || !agg.signers.iter().all(usable_signer)
+    {
+        return false;
+    }
+    let mut sigma = agg.sigma.clone();
+    for i in (0..agg.signers.len()).rev() {
+        let h = chain_hash(&agg.signers[..=i], &agg.messages[..=i]);
+        let y = extended_forward(&agg.signers[i], &sigma, DOMAIN_BYTES);
+        sigma = from_block(&xor(&to_block(&y, DOMAIN_BYTES), &h));
+    }
+    sigma == BigInt::from(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gen_keys_with_digits, PrivateKey};
+
+    fn chain(keys: &[(PublicKey, PrivateKey)], messages: &[&[u8]]) -> AggregateSignature {
+        keys.iter().zip(messages).fold(
+            AggregateSignature::new(),
+            |agg, ((pub_key, priv_key), m)| sign(&agg, pub_key, priv_key, m).unwrap(),
+        )
+    }
+
+    #[test]
+    fn signers_fold_into_one_aggregate() {
+        let keys: Vec<_> = (0..3).map(|_| gen_keys_with_digits(20)).collect();
+        let agg = chain(&keys, &[b"issued", b"reviewed", b"approved"]);
+        assert_eq!(agg.len(), 3);
+        assert!(verify(&agg));
+        assert!(verify(&AggregateSignature::<PublicKey>::new()));
+        let mut edited = agg.clone();
+        edited.messages[1] = b"rejected".to_vec();
+        assert!(!verify(&edited));
+        let mut reordered = agg.clone();
+        reordered.signers.swap(0, 2);
+        reordered.messages.swap(0, 2);
+        assert!(!verify(&reordered));
+    }
+
+    #[test]
+    fn a_bad_link_breaks_the_chain() {
+        let (alice_pub, alice_priv) = gen_keys_with_digits(20);
+        let (bob_pub, _) = gen_keys_with_digits(20);
+        let (carol_pub, carol_priv) = gen_keys_with_digits(20);
+        let agg = sign(&AggregateSignature::new(), &alice_pub, &alice_priv, b"m").unwrap();
+        // Bob's turn signed with Alice's private key
+        let forged = sign(&agg, &bob_pub, &alice_priv, b"m").unwrap();
+        assert!(!verify(&forged));
+        assert_eq!(sign(&forged, &carol_pub, &carol_priv, b"m"), None);
+    }
+
+    #[test]
+    fn oversized_or_degenerate_keys_cannot_sign() {
+        let (alice_pub, alice_priv) = gen_keys_with_digits(20);
+        let empty = AggregateSignature::new();
+        let big = PublicKey {
+            n: BigInt::from(1) << MAX_MODULUS_BITS,
+            e: BigInt::from(65537),
+        };
+        assert_eq!(sign(&empty, &big, &alice_priv, b"m"), None);
+        let identity = PublicKey {
+            n: alice_pub.n.clone(),
+            e: BigInt::from(1),
+        };
+        assert_eq!(
+            sign(&empty, &identity, &PrivateKey::new(BigInt::from(1)), b"m"),
+            None
+        );
+        // An aggregate built in code is held to the same rules as a decoded one
+        let mut agg = sign(&empty, &alice_pub, &alice_priv, b"m").unwrap();
+        agg.signers[0] = identity;
+        assert!(!verify(&agg));
+    }
+
+    #[test]
+    fn aggregate_round_trips_through_bytes() {
+        let keys: Vec<_> = (0..2).map(|_| gen_keys_with_digits(20)).collect();
+        let agg = chain(&keys, &[b"one", b"two"]);
+        let decoded = AggregateSignature::from_bytes(&agg.to_bytes()).unwrap();
+        assert_eq!(decoded, agg);
+        assert!(verify(&decoded));
+    }
+
+    #[test]
+    fn degenerate_signers_are_rejected() {
+        let keys: Vec<_> = (0..2).map(|_| gen_keys_with_digits(20)).collect();
+        let agg = chain(&keys, &[b"one", b"two"]);
+        let n = keys[1].0.n.clone();
+        for (n, e) in [(BigInt::from(0), 65537), (BigInt::from(1), 65537), (n, 1)] {
+            let mut bad = agg.clone();
+            bad.signers[1].n = n;
+            bad.signers[1].e = BigInt::from(e);
+            assert!(AggregateSignature::from_bytes(&bad.to_bytes()).is_err());
+        }
+        let mut tiny = agg.clone();
+        tiny.signers[0].n = BigInt::from(1);
+        assert!(!verify(&tiny));
+        tiny.signers[0].n = BigInt::from(0);
+        assert!(!verify(&tiny));
+    }
+}
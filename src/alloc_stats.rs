@@ -0,0 +1,192 @@
+// Allocation counting, behind the alloc-stats feature
+//
+// CountingAllocator forwards to the system allocator and counts, per thread, how many
+// allocations were made, how many bytes they asked for and the most bytes live at once.
+// measure runs a closure and returns the counts it added, so that changes meant to avoid
+// clones or to work in place can be checked with numbers rather than by eye. The counts
+// only move once a program installs the allocator:
+//
+//   use naive_rsa::alloc_stats::CountingAllocator;
+//   #[global_allocator]
+//   static ALLOCATOR: CountingAllocator = CountingAllocator;
+//
+// The crate's own tests do so when the feature is on, and KeyGenStats then breaks down what
+// key generation allocated. Counters are per thread, so tests running in parallel do not
+// disturb each other; memory freed on another thread than it was allocated on is not
+// subtracted from the live bytes of either.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt;
+use std::ops::AddAssign;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    // Calls to alloc and realloc
+    pub allocations: u64,
+    // Bytes requested by those calls, whether or not they were freed since
+    pub bytes: u64,
+    // The most bytes live at once above what was live at the start
+    pub peak_bytes: u64,
+}
+
+impl AddAssign for AllocStats {
+    // add_assign: totals of sequential runs; their peak is the larger of the two
+    fn add_assign(&mut self, other: AllocStats) {
+        self.allocations += other.allocations;
+        self.bytes += other.bytes;
+        self.peak_bytes = self.peak_bytes.max(other.peak_bytes);
+    }
+}
+
+impl fmt::Display for AllocStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} allocations, {} bytes, peak {} bytes",
+            self.allocations, self.bytes, self.peak_bytes
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Counters {
+    allocations: u64,
+    bytes: u64,
+    live: u64,
+    peak: u64,
+}
+
+impl Counters {
+    fn allocated(&mut self, size: usize) {
+        self.allocations += 1;
+        self.bytes += size as u64;
+        self.live += size as u64;
+        self.peak = self.peak.max(self.live);
+    }
+
+    fn freed(&mut self, size: usize) {
+        self.live = self.live.saturating_sub(size as u64);
+    }
+}
+
+thread_local! {
+    static COUNTERS: Cell<Counters> = const {
+        Cell::new(Counters {
+            allocations: 0,
+            bytes: 0,
+            live: 0,
+            peak: 0,
+        })
+    };
+}
+
+// record: update this thread's counters; a no-op while the thread is being torn down
+fn record(update: impl FnOnce(&mut Counters)) {
+    let _ = COUNTERS.try_with(|cell| {
+        let mut counters = cell.get();
+        update(&mut counters);
+        cell.set(counters);
+    });
+}
+
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record(|c| c.allocated(layout.size()));
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record(|c| c.allocated(layout.size()));
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        record(|c| c.freed(layout.size()));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record(|c| {
+                c.freed(layout.size());
+                c.allocated(new_size);
+            });
+        }
+        new_ptr
+    }
+}
+
+#[cfg(test)]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// measure: f's result and what it allocated on this thread; calls nest
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, AllocStats) {
+    let before = COUNTERS.with(Cell::get);
+    COUNTERS.with(|cell| {
+        cell.set(Counters {
+            peak: before.live,
+            ..before
+        })
+    });
+    let value = f();
+    let after = COUNTERS.with(Cell::get);
+    COUNTERS.with(|cell| {
+        cell.set(Counters {
+            peak: after.peak.max(before.peak),
+            ..after
+        })
+    });
+    let stats = AllocStats {
+        allocations: after.allocations - before.allocations,
+        bytes: after.bytes - before.bytes,
+        peak_bytes: after.peak - before.live,
+    };
+    (value, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::KeyGenParams;
+
+    #[test]
+    fn allocations_are_counted() {
+        let (v, stats) = measure(|| vec![0u8; 1000]);
+        assert_eq!(stats.allocations, 1);
+        assert_eq!(stats.bytes, 1000);
+        assert_eq!(stats.peak_bytes, 1000);
+        let ((), stats) = measure(|| drop(v));
+        assert_eq!(stats, AllocStats::default());
+    }
+
+    #[test]
+    fn nested_measurements_add_up() {
+        let ((_, inner), outer) = measure(|| {
+            let first = vec![0u8; 100];
+            drop(first);
+            measure(|| vec![0u8; 50])
+        });
+        assert_eq!(inner.bytes, 50);
+        assert_eq!(outer.allocations, 2);
+        assert_eq!(outer.bytes, 150);
+        assert_eq!(outer.peak_bytes, 100);
+    }
+
+    #[test]
+    fn key_generation_reports_its_allocations() {
+        let (_, stats) = KeyGenParams::new(20).generate().unwrap();
+        assert!(stats.prime_search.allocations > 0);
+        assert!(stats.derivation.allocations > 0);
+        assert!(stats.to_string().contains("allocations"));
+    }
+}
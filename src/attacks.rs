@@ -0,0 +1,64 @@
+// Attacks on textbook RSA
+//
+// Encryption without padding is deterministic: the same message always gives the same
+// ciphertext, so anyone holding the public key can test guesses. These functions turn that
+// observation into working decryptors for low-entropy plaintexts such as SSNs or salaries.
+use crate::{invert, PublicKey};
+use num_bigint::BigInt;
+use std::collections::HashMap;
+
+// brute_force_small_space: encrypt every candidate until one matches the ciphertext
+pub fn brute_force_small_space(
+    c: &BigInt,
+    pub_key: &PublicKey,
+    candidates: impl IntoIterator<Item = BigInt>,
+) -> Option<BigInt> {
+    candidates
+        .into_iter()
+        .find(|m| m.modpow(&pub_key.e, &pub_key.n) == *c)
+}
+
+// meet_in_the_middle: recover m = a b with 1 <= a <= a_max and 1 <= b <= b_max in about
+// a_max + b_max exponentiations instead of a_max b_max. Since c = a^e b^e, tabulate a^e and
+// look up c / b^e (Boneh, Joux and Nguyen). Returns the split (a, b).
+pub fn meet_in_the_middle(
+    c: &BigInt,
+    pub_key: &PublicKey,
+    a_max: u64,
+    b_max: u64,
+) -> Option<(BigInt, BigInt)> {
+    let (n, e) = (&pub_key.n, &pub_key.e);
+    let table: HashMap<BigInt, u64> = (1..=a_max)
+        .map(|a| (BigInt::from(a).modpow(e, n), a))
+        .collect();
+    (1..=b_max).find_map(|b| {
+        let b = BigInt::from(b);
+        let target = c * invert(b.modpow(e, n), n.clone()) % n;
+        table.get(&target).map(|&a| (BigInt::from(a), b))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encrypt, gen_keys_with_digits};
+
+    #[test]
+    fn low_entropy_values_are_recovered() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let salary = BigInt::from(73_500);
+        let c = encrypt(&pub_key, salary.clone().into()).unwrap().into();
+        let guesses = (0..100_000u32).step_by(500).map(BigInt::from);
+        assert_eq!(brute_force_small_space(&c, &pub_key, guesses), Some(salary));
+    }
+
+    #[test]
+    fn products_of_small_factors_meet_in_the_middle() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let m = BigInt::from(1234 * 5678);
+        let c = encrypt(&pub_key, m.clone().into()).unwrap().into();
+        let (a, b) = meet_in_the_middle(&c, &pub_key, 1 << 13, 1 << 13).unwrap();
+        assert_eq!(a * b, m);
+        assert_eq!(meet_in_the_middle(&c, &pub_key, 100, 100), None);
+    }
+}
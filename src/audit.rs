@@ -0,0 +1,293 @@
+// A signed, hash-chained log of private-key operations
+//
+// A PrivateKey made with with_audit records every use of d in an AuditLog: which
+// operation it was, when, and SHA-256 digests of the input and the result, so that the
+// log shows what the key did without holding the plaintexts themselves. Each entry names
+// the digest of the one before it, and the key signs every entry with a full-domain hash
+// signature as soon as it is written, so that dropping, reordering or editing entries
+// breaks either the chain or a signature. AuditLog::verify checks both against the public
+// key. Cutting entries off the end leaves a shorter log that still verifies; a reader who
+// kept AuditLog::head from an earlier look passes it to verify_from to catch that too.
+// Whoever holds the key could still rewrite the whole log and sign it again; the log is for
+// accountability demos and debugging, not for evidence against the key holder.
+// audit_log returns a copy of the entries so far.
+use crate::fdh;
+use crate::hash::TranscriptHash;
+use crate::{private_permutation, PrivateKey, PublicKey};
+use num_bigint::BigInt;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+// Operation: the use of the private permutation an entry records
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Decrypt,
+    Sign,
+    // Any other use of x^d mod n, such as blind signing, ring signatures or an oracle
+    Exponentiate,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub timestamp: SystemTime,
+    pub operation: Operation,
+    pub input_digest: Vec<u8>,
+    pub result_digest: Vec<u8>,
+    // The digest of the entry before, all zeros for the first
+    pub prev: Vec<u8>,
+    pub signature: BigInt,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuditLog {
+    entries: Vec<AuditEntry>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditError {
+    // The entry's sequence number is not its position in the log
+    OutOfOrder { seq: u64 },
+    // The entry does not name the digest of the one before it
+    BrokenChain { seq: u64 },
+    // The entry's signature does not verify under the public key
+    BadSignature { seq: u64 },
+    // The log ends before the entry whose digest verify_from was given
+    Truncated,
+}
+
+impl Operation {
+    pub fn name(self) -> &'static str {
+        match self {
+            Operation::Decrypt => "decrypt",
+            Operation::Sign => "sign",
+            Operation::Exponentiate => "exponentiate",
+        }
+    }
+}
+
+fn digest_int(x: &BigInt) -> Vec<u8> {
+    Sha256::digest(x.to_signed_bytes_be()).to_vec()
+}
+
+impl AuditEntry {
+    // digest: what the next entry chains to, and what the key signs
+    pub fn digest(&self) -> Vec<u8> {
+        let millis = self
+            .timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_millis() as u64;
+        TranscriptHash::new("naive-rsa audit entry")
+            .bytes(&self.seq.to_be_bytes())
+            .bytes(&millis.to_be_bytes())
+            .bytes(self.operation.name().as_bytes())
+            .bytes(&self.input_digest)
+            .bytes(&self.result_digest)
+            .bytes(&self.prev)
+            .finish()
+    }
+}
+
+impl AuditLog {
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // head: the digest of the last entry, all zeros for an empty log
+    pub fn head(&self) -> Vec<u8> {
+        self.entries
+            .last()
+            .map_or_else(|| vec![0u8; 32], AuditEntry::digest)
+    }
+
+    // record: append and sign an entry for x^d = y; the signature uses the permutation
+    // directly so that signing the entry is not itself recorded
+    pub(crate) fn record(
+        &mut self,
+        pub_key: &PublicKey,
+        priv_key: &PrivateKey,
+        operation: Operation,
+        x: &BigInt,
+        y: &BigInt,
+    ) {
+        let prev = self.head();
+        let mut entry = AuditEntry {
+            seq: self.entries.len() as u64,
+            timestamp: SystemTime::now(),
+            operation,
+            input_digest: digest_int(x),
+            result_digest: digest_int(y),
+            prev,
+            signature: BigInt::from(0),
+        };
+        let point = fdh::message_point(pub_key, &entry.digest());
+        entry.signature = private_permutation(pub_key, priv_key, &point);
+        self.entries.push(entry);
+    }
+
+    // verify: check the sequence numbers, the chain and every signature
+    pub fn verify(&self, pub_key: &PublicKey) -> Result<(), AuditError> {
+        let mut prev = vec![0u8; 32];
+        for (i, entry) in self.entries.iter().enumerate() {
+            let seq = entry.seq;
+            if seq != i as u64 {
+                return Err(AuditError::OutOfOrder { seq });
+            }
+            if entry.prev != prev {
+                return Err(AuditError::BrokenChain { seq });
+            }
+            prev = entry.digest();
+            if !fdh::verify(pub_key, &prev, &entry.signature) {
+                return Err(AuditError::BadSignature { seq });
+            }
+        }
+        Ok(())
+    }
+
+    // verify_from: verify, and check that the log still reaches the entry whose digest an
+    // earlier head returned
+    pub fn verify_from(&self, pub_key: &PublicKey, head: &[u8]) -> Result<(), AuditError> {
+        self.verify(pub_key)?;
+        let reached = head == [0u8; 32] || self.entries.iter().any(|e| e.digest() == head);
+        if !reached {
+            return Err(AuditError::Truncated);
+        }
+        Ok(())
+    }
+}
+
+fn short_hex(bytes: &[u8]) -> String {
+    bytes[..bytes.len().min(8)]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// One line per entry: sequence number, Unix time in milliseconds, operation and the
+// leading bytes of the input and result digests
+impl fmt::Display for AuditLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            let millis = entry
+                .timestamp
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or(Duration::ZERO)
+                .as_millis();
+            writeln!(
+                f,
+                "{} {} {} in={} out={}",
+                entry.seq,
+                millis,
+                entry.operation.name(),
+                short_hex(&entry.input_digest),
+                short_hex(&entry.result_digest)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for AuditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuditError::OutOfOrder { seq } => write!(f, "entry {} is out of order", seq),
+            AuditError::BrokenChain { seq } => {
+                write!(f, "entry {} does not follow the one before it", seq)
+            }
+            AuditError::BadSignature { seq } => write!(f, "entry {} is not signed by the key", seq),
+            AuditError::Truncated => write!(f, "the log ends before the entry it should reach"),
+        }
+    }
+}
+
+impl std::error::Error for AuditError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash_alg::HashAlg;
+    use crate::signature::{self, Emsa};
+    use crate::{decrypt, encrypt, gen_keys_with_digits};
+
+    #[test]
+    fn operations_are_recorded_in_order() {
+        let (pub_key, priv_key) = gen_keys_with_digits(40);
+        assert!(priv_key.audit_log().is_none());
+        let priv_key = priv_key.with_audit();
+        let c = encrypt(&pub_key, BigInt::from(1234).into()).unwrap();
+        let m = decrypt(&pub_key, &priv_key, c.clone()).unwrap();
+        signature::sign(&pub_key, &priv_key, b"m", Emsa::Pkcs1v15, HashAlg::Sha256).unwrap();
+        let log = priv_key.audit_log().unwrap();
+        let ops: Vec<Operation> = log.entries().iter().map(|e| e.operation).collect();
+        assert_eq!(ops, [Operation::Decrypt, Operation::Sign]);
+        assert_eq!(log.entries()[0].input_digest, digest_int(c.as_bigint()));
+        assert_eq!(log.entries()[0].result_digest, digest_int(m.as_bigint()));
+        assert_eq!(log.verify(&pub_key), Ok(()));
+        assert_eq!(log.to_string().lines().count(), 2);
+    }
+
+    #[test]
+    fn tampering_with_the_log_is_detected() {
+        let (pub_key, priv_key) = gen_keys_with_digits(40);
+        let priv_key = priv_key.with_audit();
+        for x in 2..5 {
+            decrypt(&pub_key, &priv_key, BigInt::from(x).into()).unwrap();
+        }
+        let log = priv_key.audit_log().unwrap();
+        let mut edited = log.clone();
+        edited.entries[1].operation = Operation::Sign;
+        assert_eq!(
+            edited.verify(&pub_key),
+            Err(AuditError::BadSignature { seq: 1 })
+        );
+        let mut dropped = log.clone();
+        dropped.entries.remove(1);
+        assert_eq!(
+            dropped.verify(&pub_key),
+            Err(AuditError::OutOfOrder { seq: 2 })
+        );
+        let mut swapped = log.clone();
+        swapped.entries.swap(1, 2);
+        swapped.entries[1].seq = 1;
+        swapped.entries[2].seq = 2;
+        assert_eq!(
+            swapped.verify(&pub_key),
+            Err(AuditError::BrokenChain { seq: 1 })
+        );
+        let (other_pub, _) = gen_keys_with_digits(40);
+        assert!(log.verify(&other_pub).is_err());
+    }
+
+    #[test]
+    fn truncation_is_caught_given_an_earlier_head() {
+        let (pub_key, priv_key) = gen_keys_with_digits(40);
+        let priv_key = priv_key.with_audit();
+        let empty = priv_key.audit_log().unwrap().head();
+        for x in 2..4 {
+            decrypt(&pub_key, &priv_key, BigInt::from(x).into()).unwrap();
+        }
+        let head = priv_key.audit_log().unwrap().head();
+        decrypt(&pub_key, &priv_key, BigInt::from(4).into()).unwrap();
+        let log = priv_key.audit_log().unwrap();
+        assert_eq!(log.verify_from(&pub_key, &head), Ok(()));
+        let mut truncated = log.clone();
+        truncated.entries.truncate(1);
+        // A plain verify cannot tell, but the head seen earlier is gone
+        assert_eq!(truncated.verify(&pub_key), Ok(()));
+        assert_eq!(
+            truncated.verify_from(&pub_key, &head),
+            Err(AuditError::Truncated)
+        );
+        assert_eq!(truncated.verify_from(&pub_key, &empty), Ok(()));
+    }
+}
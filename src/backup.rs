@@ -0,0 +1,339 @@
+// Key backups split into Shamir shares
+//
+// split serializes a key pair and shares it byte by byte over GF(256) (Shamir, 1979): each
+// byte is the constant term of a random polynomial of degree threshold - 1, and share i
+// holds every polynomial's value at x = i. Any threshold shares give the key back by
+// Lagrange interpolation at 0; fewer say nothing about it. Each share records its index,
+// the threshold, the fingerprint (pinning::fingerprint) of the key it belongs to and a
+// CRC-32 of itself, and can be written out in the paper format or drawn as a QR code:
+//
+//   NAIVE-RSA KEY SHARE
+//   01 AAAAA E3OMF UXMZJ NOJZW  01
+//   02 CIDLM V4SA4 3IMFZ GKAAA  CC
+//   ...
+//
+// restore checks that the shares agree with each other, including any beyond the threshold,
+// that the result is the key their fingerprint names and that its d undoes e. Only n, e and
+// d are shared: the primes and CRT parameters are dropped, and a restored KeyPair recovers
+// the primes from d when asked for them.
+use crate::error::ParseError;
+use crate::keypair::KeyPair;
+use crate::paper::{self, crc32};
+use crate::pinning::{fingerprint, Pin};
+use crate::wire::{Reader, Writer};
+use crate::{checked_public_key, PrivateKey};
+use num_bigint::BigInt;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+
+const FORMAT: &str = "naive-rsa key backup";
+const SHARE_TAG: &str = "naive-rsa key share";
+const SECRET_TAG: &str = "naive-rsa key pair";
+const HEADER: &str = "NAIVE-RSA KEY SHARE";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Share {
+    index: u8,
+    threshold: u8,
+    fingerprint: Pin,
+    data: Vec<u8>,
+}
+
+// gf_mul: multiplication in GF(2^8) modulo the AES polynomial x^8 + x^4 + x^3 + x + 1
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        a = if a & 0x80 != 0 { a << 1 ^ 0x1b } else { a << 1 };
+        b >>= 1;
+    }
+    product
+}
+
+// gf_inv: a^254 = a^-1 for nonzero a
+fn gf_inv(a: u8) -> u8 {
+    (0..254).fold(1, |acc, _| gf_mul(acc, a))
+}
+
+// eval: the polynomial with the given coefficients, constant term first, at x
+fn eval(coefficients: &[u8], x: u8) -> u8 {
+    coefficients
+        .iter()
+        .rev()
+        .fold(0, |acc, &c| gf_mul(acc, x) ^ c)
+}
+
+// interpolate: the value at x of the polynomial through the points (xs[j], ys[j])
+fn interpolate(xs: &[u8], ys: &[u8], x: u8) -> u8 {
+    xs.iter()
+        .zip(ys)
+        .enumerate()
+        .fold(0, |acc, (j, (&xj, &yj))| {
+            // Lagrange basis: the product of (x - x_m) / (x_j - x_m); subtraction is XOR
+            let basis = xs
+                .iter()
+                .enumerate()
+                .filter(|&(m, _)| m != j)
+                .fold(1, |b, (_, &xm)| gf_mul(b, gf_mul(x ^ xm, gf_inv(xj ^ xm))));
+            acc ^ gf_mul(yj, basis)
+        })
+}
+
+// split: count shares of the key pair, any threshold of which restore it
+pub fn split(key_pair: &KeyPair, threshold: u8, count: u8) -> Vec<Share> {
+    split_with_rng(key_pair, threshold, count, &mut OsRng)
+}
+
+pub fn split_with_rng(
+    key_pair: &KeyPair,
+    threshold: u8,
+    count: u8,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Vec<Share> {
+    if threshold == 0 || threshold > count {
+        panic!(
+            "a threshold of {} does not work with {} shares",
+            threshold, count
+        );
+    }
+    let (public, private) = (key_pair.public(), key_pair.private());
+    let secret = Writer::new(SECRET_TAG)
+        .int(&public.n)
+        .int(&public.e)
+        .int(&private.d)
+        .finish();
+    let mut shares: Vec<Share> = (1..=count)
+        .map(|index| Share {
+            index,
+            threshold,
+            fingerprint: fingerprint(public),
+            data: Vec::with_capacity(secret.len()),
+        })
+        .collect();
+    let mut coefficients = vec![0u8; threshold as usize];
+    for &byte in &secret {
+        coefficients[0] = byte;
+        rng.fill_bytes(&mut coefficients[1..]);
+        for share in &mut shares {
+            share.data.push(eval(&coefficients, share.index));
+        }
+    }
+    shares
+}
+
+// restore: the key pair behind the shares, from at least a threshold of them
+pub fn restore(shares: &[Share]) -> Result<KeyPair, ParseError> {
+    let invalid = |reason: String| ParseError::invalid(FORMAT, 0, reason);
+    let first = shares
+        .first()
+        .ok_or_else(|| invalid("no shares were given".to_string()))?;
+    for (i, share) in shares.iter().enumerate() {
+        if share.fingerprint != first.fingerprint {
+            return Err(invalid(format!(
+                "share {} is for a different key",
+                share.index
+            )));
+        }
+        if share.threshold != first.threshold || share.data.len() != first.data.len() {
+            return Err(invalid(format!(
+                "share {} is from a different split",
+                share.index
+            )));
+        }
+        if shares[..i].iter().any(|other| other.index == share.index) {
+            return Err(invalid(format!("share {} was given twice", share.index)));
+        }
+    }
+    if shares.len() < first.threshold as usize {
+        return Err(invalid(format!(
+            "{} of {} needed shares were given",
+            shares.len(),
+            first.threshold
+        )));
+    }
+    let (shares, extra) = shares.split_at(first.threshold as usize);
+    let xs: Vec<u8> = shares.iter().map(|share| share.index).collect();
+    let mut secret = Vec::with_capacity(first.data.len());
+    for i in 0..first.data.len() {
+        let ys: Vec<u8> = shares.iter().map(|share| share.data[i]).collect();
+        // The first threshold shares fix each polynomial, so the rest must lie on it
+        if let Some(share) = extra
+            .iter()
+            .find(|share| interpolate(&xs, &ys, share.index) != share.data[i])
+        {
+            return Err(invalid(format!(
+                "share {} disagrees with the others",
+                share.index
+            )));
+        }
+        secret.push(interpolate(&xs, &ys, 0));
+    }
+    let wrong_key = || invalid("the shares do not combine to the key they name".to_string());
+    let mut r = Reader::new(&secret, SECRET_TAG).map_err(|_| wrong_key())?;
+    let (n, e, d) = (r.int(), r.int(), r.int());
+    let (n, e, d) = match (n, e, d, r.finish()) {
+        (Ok(n), Ok(e), Ok(d), Ok(())) => (n, e, d),
+        _ => return Err(wrong_key()),
+    };
+    let public = checked_public_key(n, e).map_err(|reason| invalid(reason.to_string()))?;
+    if fingerprint(&public) != first.fingerprint {
+        return Err(wrong_key());
+    }
+    // The fingerprint covers n and e only; 2^(ed) = 2 mod n checks d
+    let two = BigInt::from(2);
+    if two.modpow(&(&public.e * &d), &public.n) != two % &public.n {
+        return Err(invalid("the restored d does not match e".to_string()));
+    }
+    let private = PrivateKey::new(d);
+    Ok(KeyPair::new(public, private))
+}
+
+impl Share {
+    // index: the x coordinate of the share, from 1
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    // fingerprint: the pin of the public key the share belongs to
+    pub fn fingerprint(&self) -> &Pin {
+        &self.fingerprint
+    }
+
+    // to_bytes: the share followed by a CRC-32 of everything before it
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Writer::new(SHARE_TAG)
+            .bytes(&[self.index, self.threshold])
+            .bytes(&self.fingerprint)
+            .bytes(&self.data)
+            .finish();
+        let check = crc32(&bytes);
+        bytes.extend_from_slice(&check.to_be_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let (body, check) = bytes
+            .split_at_checked(bytes.len().wrapping_sub(4))
+            .ok_or_else(|| ParseError::invalid(SHARE_TAG, 0, "the share is too short"))?;
+        if crc32(body).to_be_bytes() != check {
+            return Err(ParseError::invalid(
+                SHARE_TAG,
+                body.len(),
+                "the checksum does not match",
+            ));
+        }
+        let mut r = Reader::new(body, SHARE_TAG)?;
+        let offset = r.offset();
+        let (index, threshold) = match r.bytes()? {
+            &[index, threshold] if index != 0 && threshold != 0 => (index, threshold),
+            _ => {
+                return Err(ParseError::invalid(
+                    SHARE_TAG,
+                    offset,
+                    "expected a nonzero index and threshold",
+                ))
+            }
+        };
+        let offset = r.offset();
+        let fingerprint = r.bytes()?.try_into().map_err(|_| {
+            ParseError::invalid(SHARE_TAG, offset, "a fingerprint must be 32 bytes")
+        })?;
+        let data = r.bytes()?.to_vec();
+        r.finish()?;
+        Ok(Share {
+            index,
+            threshold,
+            fingerprint,
+            data,
+        })
+    }
+
+    // to_armored: the share in the hand-copyable format of the paper module
+    pub fn to_armored(&self) -> String {
+        paper::encode(HEADER, &self.to_bytes())
+    }
+
+    pub fn from_armored(text: &str) -> Result<Self, ParseError> {
+        Share::from_bytes(&paper::decode(text, HEADER)?)
+    }
+
+    // render_qr: the share as a QR code, drawn as in compact::render_qr
+    #[cfg(feature = "qr")]
+    pub fn render_qr(&self, level: crate::compact::QrLevel) -> String {
+        crate::compact::render(self.to_bytes(), level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn key_pair() -> KeyPair {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        KeyPair::new(pub_key, priv_key)
+    }
+
+    #[test]
+    fn any_threshold_of_shares_restores_the_key() {
+        let pair = key_pair();
+        let shares = split_with_rng(&pair, 3, 5, &mut StdRng::seed_from_u64(7));
+        for picked in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let subset: Vec<Share> = picked.iter().map(|&i| shares[i].clone()).collect();
+            let restored = restore(&subset).unwrap();
+            assert_eq!(restored.public(), pair.public());
+            assert_eq!(restored.private().exponent(), pair.private().exponent());
+        }
+        assert!(restore(&shares[..2]).is_err());
+        assert!(restore(&[shares[0].clone(), shares[0].clone(), shares[1].clone()]).is_err());
+    }
+
+    #[test]
+    fn shares_from_different_keys_do_not_mix() {
+        let (a, b) = (split(&key_pair(), 2, 2), split(&key_pair(), 2, 2));
+        assert!(restore(&[a[0].clone(), b[1].clone()]).is_err());
+        // A share whose data was tampered with restores to something else, which is caught
+        let mut tampered = a.clone();
+        tampered[1].data[10] ^= 1;
+        assert!(restore(&tampered).is_err());
+        // So is a share beyond the threshold that disagrees with the others
+        let pair = key_pair();
+        let mut shares = split(&pair, 2, 3);
+        assert!(restore(&shares).is_ok());
+        shares[2].data[0] ^= 1;
+        assert!(restore(&shares).is_err());
+        // And a d that does not belong to the key the fingerprint names
+        let wrong_d = PrivateKey::new(pair.private().exponent() + 1);
+        let shares = split(&KeyPair::new(pair.public().clone(), wrong_d), 2, 2);
+        assert!(restore(&shares).is_err());
+    }
+
+    #[test]
+    fn shares_round_trip_through_paper_and_bytes() {
+        let shares = split(&key_pair(), 2, 3);
+        let text = shares[1].to_armored();
+        assert!(text.starts_with("NAIVE-RSA KEY SHARE\n01 "));
+        assert_eq!(Share::from_armored(&text).unwrap(), shares[1]);
+        let mut bytes = shares[2].to_bytes();
+        assert_eq!(Share::from_bytes(&bytes).unwrap(), shares[2]);
+        bytes[30] ^= 1;
+        assert!(Share::from_bytes(&bytes).is_err());
+        assert!(Share::from_bytes(&[1, 2]).is_err());
+    }
+
+    #[test]
+    fn field_inverses_are_inverses() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+        }
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+    }
+}
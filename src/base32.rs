@@ -0,0 +1,80 @@
+// Base32 (RFC 4648) without padding, for keys meant to be copied by hand
+//
+// Decoding is case-insensitive and reads the digits 0 and 1, which the alphabet leaves out,
+// as the letters O and I they are easily mistaken for.
+
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    let (mut acc, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        acc = acc << 8 | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[(acc >> bits & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[(acc << (5 - bits) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+fn value(c: u8) -> Option<u32> {
+    match c.to_ascii_uppercase() {
+        c @ b'A'..=b'Z' => Some((c - b'A') as u32),
+        c @ b'2'..=b'7' => Some((c - b'2' + 26) as u32),
+        b'0' => Some((b'O' - b'A') as u32),
+        b'1' => Some((b'I' - b'A') as u32),
+        _ => None,
+    }
+}
+
+// decode: None for characters outside the alphabet or nonzero bits after the last byte
+pub(crate) fn decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in s.bytes() {
+        acc = (acc << 5 | value(c)?) & 0xffff;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    if acc & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_rfc_4648_vectors() {
+        for (plain, encoded) in [
+            ("", ""),
+            ("f", "MY"),
+            ("fo", "MZXQ"),
+            ("foo", "MZXW6"),
+            ("foob", "MZXW6YQ"),
+            ("fooba", "MZXW6YTB"),
+            ("foobar", "MZXW6YTBOI"),
+        ] {
+            assert_eq!(encode(plain.as_bytes()), encoded);
+            assert_eq!(decode(encoded).unwrap(), plain.as_bytes());
+        }
+    }
+
+    #[test]
+    fn decoding_forgives_case_and_lookalike_digits() {
+        assert_eq!(decode("mzxw6ytb0i").unwrap(), b"foobar");
+        assert_eq!(decode("MZXW6YTB8I"), None);
+        // "MZ" carries a nonzero bit past the single byte it encodes
+        assert_eq!(decode("MZ"), None);
+    }
+}
@@ -0,0 +1,91 @@
+// Base64 (RFC 4648) for PEM, JWK and OpenSSH keys and fingerprints
+//
+// Decoding accepts both the standard and the URL-safe alphabet, with or without '='
+// padding, and skips ASCII whitespace so that wrapped PEM bodies decode directly.
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// encode: the standard alphabet, with or without padding
+pub(crate) fn encode(bytes: &[u8], pad: bool) -> String {
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let mut block = [0u8; 3];
+        block[..chunk.len()].copy_from_slice(chunk);
+        let acc = u32::from_be_bytes([0, block[0], block[1], block[2]]);
+        for i in 0..=chunk.len() {
+            out.push(ALPHABET[(acc >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+        if pad {
+            out.push_str(&"=="[..3 - chunk.len()]);
+        }
+    }
+    out
+}
+
+fn value(c: u8) -> Option<u32> {
+    match c {
+        b'A'..=b'Z' => Some((c - b'A') as u32),
+        b'a'..=b'z' => Some((c - b'a' + 26) as u32),
+        b'0'..=b'9' => Some((c - b'0' + 52) as u32),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    }
+}
+
+// decode: None for characters outside the alphabets or a dangling final character
+pub(crate) fn decode(s: &str) -> Option<Vec<u8>> {
+    let chars: Vec<u8> = s.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+    let data = match chars.iter().position(|&c| c == b'=') {
+        Some(pad) if chars[pad..].iter().all(|&c| c == b'=') && chars.len().is_multiple_of(4) => {
+            &chars[..pad]
+        }
+        Some(_) => return None,
+        None => &chars[..],
+    };
+    if data.len() % 4 == 1 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(data.len() * 3 / 4);
+    for chunk in data.chunks(4) {
+        let mut acc = 0u32;
+        for &c in chunk {
+            acc = (acc << 6) | value(c)?;
+        }
+        acc <<= 6 * (4 - chunk.len()) as u32;
+        let bytes = acc.to_be_bytes();
+        out.extend_from_slice(&bytes[1..chunk.len()]);
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_rfc_4648_vectors() {
+        for (encoded, decoded) in [
+            ("", ""),
+            ("Zg==", "f"),
+            ("Zm8=", "fo"),
+            ("Zm9v", "foo"),
+            ("Zm9vYg", "foob"),
+            ("Zm9vYmE=", "fooba"),
+            ("Zm9v\nYmFy", "foobar"),
+        ] {
+            assert_eq!(decode(encoded).unwrap(), decoded.as_bytes());
+        }
+        assert_eq!(decode("-_8"), decode("+/8"));
+        assert_eq!(decode("Zm9vY"), None);
+        assert_eq!(decode("Zm=9v"), None);
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        assert_eq!(encode(b"fooba", true), "Zm9vYmE=");
+        assert_eq!(encode(b"foob", false), "Zm9vYg");
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode(&encode(&bytes, true)).unwrap(), bytes);
+    }
+}
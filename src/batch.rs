@@ -0,0 +1,77 @@
+// Batch verification of textbook RSA signatures
+//
+// The small exponents test of Bellare, Garay and Rabin (1998): for signatures s_i on m_i
+// under one key, draw random r_i of SECURITY_BITS bits and check
+//   (s_1^r_1 ... s_k^r_k)^e = m_1^r_1 ... m_k^r_k  (mod n)
+// with two multi-exponentiations. A batch with an invalid signature passes with probability
+// about 2^-SECURITY_BITS, except for factors of small order such as -1: s and -s can swap
+// in and out unnoticed, which is why this is screening rather than one-by-one verification.
+// With e = 65537 the random exponents are longer than e, so the saving only shows for
+// large public exponents.
+use crate::modexp::multi_modpow;
+use crate::PublicKey;
+use num_bigint::{BigInt, RandBigInt};
+use num_traits::Zero;
+
+pub(crate) const SECURITY_BITS: u64 = 64;
+
+// randomizers: count independent nonzero SECURITY_BITS-bit exponents
+pub(crate) fn randomizers(count: usize) -> Vec<BigInt> {
+    let mut rng = rand::thread_rng();
+    let (low, high) = (BigInt::from(1), BigInt::from(1) << SECURITY_BITS);
+    (0..count)
+        .map(|_| rng.gen_bigint_range(&low, &high))
+        .collect()
+}
+
+// verify_batch: whether every s is a signature s = m^d on its m, up to sign
+pub fn verify_batch(pub_key: &PublicKey, signed: &[(BigInt, BigInt)]) -> bool {
+    let n = &pub_key.n;
+    let in_range = |x: &BigInt| *x > BigInt::zero() && x < n;
+    if !signed.iter().all(|(m, s)| in_range(m) && in_range(s)) {
+        return false;
+    }
+    let r = randomizers(signed.len());
+    let (messages, signatures): (Vec<_>, Vec<_>) = signed
+        .iter()
+        .zip(&r)
+        .map(|((m, s), r)| ((m.clone(), r.clone()), (s.clone(), r.clone())))
+        .unzip();
+    multi_modpow(&signatures, n).modpow(&pub_key.e, n) == multi_modpow(&messages, n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gen_keys_with_digits, rsa_private};
+
+    #[test]
+    fn valid_batches_pass() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let signed: Vec<(BigInt, BigInt)> = (1..=8)
+            .map(|i| {
+                let m = BigInt::from(1000 + i);
+                let s = rsa_private(&pub_key, &priv_key, &m);
+                (m, s)
+            })
+            .collect();
+        assert!(verify_batch(&pub_key, &signed));
+        assert!(verify_batch(&pub_key, &[]));
+    }
+
+    #[test]
+    fn a_bad_signature_spoils_the_batch() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let sign = |m: u32| {
+            let m = BigInt::from(m);
+            let s = rsa_private(&pub_key, &priv_key, &m);
+            (m, s)
+        };
+        let (mut a, mut b) = (sign(5), sign(6));
+        // Swapped signatures have the same product, so only the randomizers catch them
+        std::mem::swap(&mut a.1, &mut b.1);
+        assert!(!verify_batch(&pub_key, &[a, b, sign(7)]));
+        let (m, s) = sign(8);
+        assert!(!verify_batch(&pub_key, &[(m, s + 1)]));
+    }
+}
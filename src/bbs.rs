@@ -0,0 +1,129 @@
+// Blum-Blum-Shub pseudorandom generator
+//
+// The state is squared modulo a Blum integer n = pq (p ≡ q ≡ 3 mod 4) and the least
+// significant bit of every new state is emitted. Predicting the next bit is as hard as
+// factoring n. https://en.wikipedia.org/wiki/Blum_Blum_Shub
+use crate::{extended_gcd, random_blum_prime};
+use num_bigint::{BigInt, RandBigInt};
+use rand::RngCore;
+
+pub struct BlumBlumShub {
+    n: BigInt,
+    state: BigInt,
+}
+
+impl BlumBlumShub {
+    // Generate a fresh Blum modulus from two ndigits primes and seed it from the thread rng
+    pub fn new(ndigits: u32) -> Self {
+        let p = random_blum_prime(ndigits);
+        let mut q = random_blum_prime(ndigits);
+        while q == p {
+            q = random_blum_prime(ndigits);
+        }
+        let n = &p * &q;
+        let mut rng = rand::thread_rng();
+        let mut seed = rng.gen_bigint_range(&BigInt::from(2), &n);
+        while !is_valid_seed(&seed, &n) {
+            seed = rng.gen_bigint_range(&BigInt::from(2), &n);
+        }
+        BlumBlumShub { n, state: seed }
+    }
+
+    // Use an existing Blum modulus with an explicit seed, e.g. to reproduce a stream
+    pub fn from_modulus(n: BigInt, seed: BigInt) -> Self {
+        let mut bbs = BlumBlumShub {
+            n,
+            state: BigInt::from(0),
+        };
+        bbs.reseed(seed);
+        bbs
+    }
+
+    pub fn modulus(&self) -> &BigInt {
+        &self.n
+    }
+
+    // The seed must be coprime to n and must not be 0 or 1, which would be fixed points
+    pub fn reseed(&mut self, seed: BigInt) {
+        let seed = seed % &self.n;
+        if !is_valid_seed(&seed, &self.n) {
+            panic!("{} is not a valid seed for modulus {}", seed, self.n);
+        }
+        self.state = seed;
+    }
+
+    pub fn next_bit(&mut self) -> bool {
+        self.state = self.state.modpow(&BigInt::from(2), &self.n);
+        self.state.bit(0)
+    }
+
+    // next_bits: the next k bits packed most significant first into an integer
+    pub fn next_bits(&mut self, k: usize) -> BigInt {
+        let mut out = BigInt::from(0);
+        for _ in 0..k {
+            out <<= 1;
+            if self.next_bit() {
+                out += 1;
+            }
+        }
+        out
+    }
+}
+
+fn is_valid_seed(seed: &BigInt, n: &BigInt) -> bool {
+    let (gcd, _, _) = extended_gcd(seed.clone(), n.clone());
+    *seed > BigInt::from(1) && gcd == BigInt::from(1)
+}
+
+impl RngCore for BlumBlumShub {
+    fn next_u32(&mut self) -> u32 {
+        (0..32).fold(0, |acc, _| (acc << 1) | self.next_bit() as u32)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        (0..64).fold(0, |acc, _| (acc << 1) | self.next_bit() as u64)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest.iter_mut() {
+            *byte = (0..8).fold(0, |acc, _| (acc << 1) | self.next_bit() as u8);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn matches_the_textbook_sequence() {
+        // p = 11, q = 23, seed 3: states 9, 81, 236, 36, 31, 202
+        let mut bbs = BlumBlumShub::from_modulus(BigInt::from(253), BigInt::from(3));
+        let bits: Vec<bool> = (0..6).map(|_| bbs.next_bit()).collect();
+        assert_eq!(bits, vec![true, true, false, false, true, false]);
+    }
+
+    #[test]
+    fn same_seed_gives_same_stream() {
+        let n = BlumBlumShub::new(10).modulus().clone();
+        let mut a = BlumBlumShub::from_modulus(n.clone(), BigInt::from(12345));
+        let mut b = BlumBlumShub::from_modulus(n, BigInt::from(12345));
+        let (mut x, mut y) = ([0u8; 16], [0u8; 16]);
+        a.fill_bytes(&mut x);
+        b.fill_bytes(&mut y);
+        assert_eq!(x, y);
+        assert_eq!(a.gen::<u64>(), b.gen::<u64>());
+    }
+
+    #[test]
+    fn blum_primes_are_3_mod_4() {
+        let p = random_blum_prime(10);
+        assert_eq!(p % 4, BigInt::from(3));
+    }
+}
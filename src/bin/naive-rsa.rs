@@ -0,0 +1,408 @@
+// naive-rsa: a command-line front end to the library, built with --features cli
+//
+//   naive-rsa bench [--message TEXT] [--digits 20,50,100] [--schemes raw,pkcs1v15,oaep,hybrid]
+//   naive-rsa describe KEY_FILE
+//   naive-rsa backup split --threshold T --shares N KEY_FILE
+//   naive-rsa backup restore SHARE_FILE...
+//   naive-rsa vectors --scheme raw|pkcs1v15|oaep --count N --out FILE [--digits D] [--seed HEX]
+//   naive-rsa keygen [--bits 2048] [--out KEY_FILE] [--pubout PUB_FILE]
+//   naive-rsa encrypt --pubkey FILE [--scheme oaep|pkcs1v15] [--hash sha256] [--in F] [--out F]
+//   naive-rsa decrypt --key FILE [--scheme oaep|pkcs1v15] [--hash sha256] [--in F] [--out F]
+//   naive-rsa sign --key FILE [--scheme pkcs1v15|pss] [--hash sha256] [--in F] [--out F]
+//   naive-rsa verify --pubkey FILE --sig FILE [--scheme pkcs1v15|pss] [--hash sha256] [--in F]
+//
+// bench prints compare_schemes as a table, and describe prints report::describe for a
+// private key file in any format keyfile reads. backup split writes the shares of such a
+// file to KEY_FILE.share1 to KEY_FILE.shareN in the paper format; backup restore prints the
+// key they combine to as a paper key. vectors writes known-answer vectors as JSON; the same
+// 64-hex-digit seed regenerates the same file, and without one a random seed is used and
+// recorded in the output.
+//
+// keygen writes the private key as PKCS #8 PEM and the public key as SubjectPublicKeyInfo
+// PEM, as openssl genpkey and openssl pkey -pubout do. The other four read --in or stdin and
+// write --out or stdout, raw bytes as openssl pkeyutl and dgst do, so that their output can
+// be checked with OpenSSL:
+//   encrypt      pkeyutl -decrypt -inkey K -pkeyopt rsa_padding_mode:oaep
+//                        -pkeyopt rsa_oaep_md:sha256 -pkeyopt rsa_mgf1_md:sha256
+//   sign         dgst -sha256 -verify PUB -signature SIG, adding
+//                -sigopt rsa_padding_mode:pss -sigopt rsa_pss_saltlen:digest for PSS
+// and the other way around for decrypt and verify. Note that OpenSSL's OAEP defaults to
+// SHA-1, and this tool's to SHA-256. --pubkey accepts a private key file too. verify prints
+// "Verified OK" and exits with 0, or says why it failed and exits with 1. Arguments are
+// parsed by hand, in keeping with the crate's habit of writing things out rather than
+// pulling in a dependency.
+use naive_rsa::backup::{self, Share};
+use naive_rsa::compare::{compare_schemes, Scheme};
+use naive_rsa::hash_alg::HashAlg;
+use naive_rsa::keygen::KeyGenParams;
+use naive_rsa::keypair::KeyPair;
+use naive_rsa::padding::Padding;
+use naive_rsa::report::describe;
+use naive_rsa::signature::{self, Emsa};
+use naive_rsa::vectors::{self, VectorScheme};
+use naive_rsa::{paper, PublicKey};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: naive-rsa bench [--message TEXT] [--digits N,...] [--schemes NAME,...]
+       naive-rsa describe KEY_FILE
+       naive-rsa backup split --threshold T --shares N KEY_FILE
+       naive-rsa backup restore SHARE_FILE...
+       naive-rsa vectors --scheme NAME --count N --out FILE [--digits D] [--seed HEX]
+       naive-rsa keygen [--bits N] [--out KEY_FILE] [--pubout PUB_FILE]
+       naive-rsa encrypt --pubkey FILE [--scheme NAME] [--hash NAME] [--in FILE] [--out FILE]
+       naive-rsa decrypt --key FILE [--scheme NAME] [--hash NAME] [--in FILE] [--out FILE]
+       naive-rsa sign --key FILE [--scheme NAME] [--hash NAME] [--in FILE] [--out FILE]
+       naive-rsa verify --pubkey FILE --sig FILE [--scheme NAME] [--hash NAME] [--in FILE]";
+
+fn list<T>(value: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Vec<T>, String> {
+    value
+        .split(',')
+        .map(|item| parse(item.trim()).ok_or_else(|| format!("unrecognized value {:?}", item)))
+        .collect()
+}
+
+fn bench(args: &[String]) -> Result<(), String> {
+    let mut message = b"attack at dawn".to_vec();
+    let mut digits = vec![20, 50, 100];
+    let mut schemes = Scheme::ALL.to_vec();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", flag))?;
+        match flag.as_str() {
+            "--message" => message = value.as_bytes().to_vec(),
+            "--digits" => digits = list(value, |d| d.parse().ok().filter(|&d| d >= 2))?,
+            "--schemes" => schemes = list(value, Scheme::from_name)?,
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+    }
+    println!("{}", compare_schemes(&message, &digits, &schemes));
+    Ok(())
+}
+
+fn read(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|err| format!("cannot read {}: {}", path, err))
+}
+
+fn read_key_pair(path: &str) -> Result<KeyPair, String> {
+    KeyPair::try_from(read(path)?.as_slice()).map_err(|err| format!("{}: {}", path, err))
+}
+
+fn describe_key(args: &[String]) -> Result<(), String> {
+    match args {
+        [path] => {
+            println!("{}", describe(&read_key_pair(path)?));
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn backup_split(args: &[String]) -> Result<(), String> {
+    let (mut threshold, mut count, mut key_file) = (None, None, None);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .and_then(|v| v.parse::<u8>().ok())
+                .ok_or_else(|| format!("{} needs a number from 1 to 255", flag))
+        };
+        match arg.as_str() {
+            "--threshold" => threshold = Some(value(arg)?),
+            "--shares" => count = Some(value(arg)?),
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path if key_file.is_none() => key_file = Some(path),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let (Some(threshold), Some(count), Some(key_file)) = (threshold, count, key_file) else {
+        return Err(USAGE.to_string());
+    };
+    if threshold == 0 || threshold > count {
+        return Err(format!(
+            "the threshold must be between 1 and the number of shares, {}",
+            count
+        ));
+    }
+    let key_pair = read_key_pair(key_file)?;
+    for share in backup::split(&key_pair, threshold, count) {
+        let path = format!("{}.share{}", key_file, share.index());
+        std::fs::write(&path, share.to_armored())
+            .map_err(|err| format!("cannot write {}: {}", path, err))?;
+        println!("{}", path);
+    }
+    Ok(())
+}
+
+fn backup_restore(paths: &[String]) -> Result<(), String> {
+    if paths.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    let shares = paths
+        .iter()
+        .map(|path| {
+            let text = String::from_utf8(read(path)?)
+                .map_err(|_| format!("{} is not a text file", path))?;
+            Share::from_armored(&text).map_err(|err| format!("{}: {}", path, err))
+        })
+        .collect::<Result<Vec<Share>, String>>()?;
+    let key_pair = backup::restore(&shares).map_err(|err| err.to_string())?;
+    print!("{}", paper::encode_key_pair(&key_pair));
+    Ok(())
+}
+
+fn parse_seed(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut seed = [0u8; 32];
+    for (byte, pair) in seed.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(seed)
+}
+
+fn write_vectors(args: &[String]) -> Result<(), String> {
+    let (mut scheme, mut count, mut out, mut digits, mut seed) = (None, None, None, 50, None);
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", flag))?;
+        let number = || {
+            value
+                .parse()
+                .map_err(|_| format!("{} needs a number, not {:?}", flag, value))
+        };
+        match flag.as_str() {
+            "--scheme" => {
+                scheme = Some(
+                    VectorScheme::from_name(value)
+                        .ok_or_else(|| format!("unrecognized scheme {:?}", value))?,
+                )
+            }
+            "--count" => count = Some(number()?),
+            "--out" => out = Some(value),
+            "--digits" => {
+                digits = value
+                    .parse()
+                    .ok()
+                    .filter(|&d| d >= 2)
+                    .ok_or_else(|| format!("--digits needs a number from 2, not {:?}", value))?
+            }
+            "--seed" => {
+                seed = Some(
+                    parse_seed(value).ok_or_else(|| "--seed needs 64 hex digits".to_string())?,
+                )
+            }
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+    }
+    let (Some(scheme), Some(count), Some(out)) = (scheme, count, out) else {
+        return Err(USAGE.to_string());
+    };
+    let seed = seed.unwrap_or_else(|| {
+        let mut seed = [0u8; 32];
+        OsRng.fill_bytes(&mut seed);
+        seed
+    });
+    let set = vectors::generate(scheme, count, digits, &seed).ok_or_else(|| {
+        format!(
+            "{}-digit primes are too small for {} vectors",
+            digits,
+            scheme.name()
+        )
+    })?;
+    std::fs::write(out, set.to_json()).map_err(|err| format!("cannot write {}: {}", out, err))?;
+    println!("{}", out);
+    Ok(())
+}
+
+// options: the --flag value pairs of args, each flag one of allowed
+fn options<'a>(args: &'a [String], allowed: &[&str]) -> Result<HashMap<&'a str, &'a str>, String> {
+    let mut options = HashMap::new();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        if !allowed.contains(&flag.as_str()) {
+            return Err(format!("unknown option {}", flag));
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", flag))?;
+        options.insert(flag.as_str(), value.as_str());
+    }
+    Ok(options)
+}
+
+fn required<'a>(options: &HashMap<&str, &'a str>, flag: &str) -> Result<&'a str, String> {
+    options
+        .get(flag)
+        .copied()
+        .ok_or_else(|| format!("{} is required", flag))
+}
+
+fn hash_option(options: &HashMap<&str, &str>) -> Result<HashAlg, String> {
+    let name = options.get("--hash").copied().unwrap_or("sha256");
+    HashAlg::from_name(name).ok_or_else(|| format!("unrecognized hash {:?}", name))
+}
+
+// read_input: the file given with --in, or stdin without one or for "-"
+fn read_input(options: &HashMap<&str, &str>) -> Result<Vec<u8>, String> {
+    match options.get("--in") {
+        Some(&path) if path != "-" => read(path),
+        _ => {
+            let mut input = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut input)
+                .map_err(|err| format!("cannot read stdin: {}", err))?;
+            Ok(input)
+        }
+    }
+}
+
+fn write_output(options: &HashMap<&str, &str>, bytes: &[u8]) -> Result<(), String> {
+    match options.get("--out") {
+        Some(&path) if path != "-" => {
+            std::fs::write(path, bytes).map_err(|err| format!("cannot write {}: {}", path, err))
+        }
+        _ => std::io::stdout()
+            .write_all(bytes)
+            .map_err(|err| format!("cannot write stdout: {}", err)),
+    }
+}
+
+fn read_public_key(path: &str) -> Result<PublicKey, String> {
+    PublicKey::try_from(read(path)?.as_slice()).map_err(|err| format!("{}: {}", path, err))
+}
+
+fn keygen(args: &[String]) -> Result<(), String> {
+    let options = options(args, &["--bits", "--out", "--pubout"])?;
+    let bits = match options.get("--bits") {
+        Some(bits) => bits
+            .parse()
+            .ok()
+            .filter(|&bits: &u64| bits >= 16 && bits.is_multiple_of(2))
+            .ok_or_else(|| format!("--bits needs an even number from 16, not {:?}", bits))?,
+        None => 2048,
+    };
+    let (key_pair, _) = KeyGenParams::fips_bits(bits)
+        .generate()
+        .ok_or("key generation gave up")?;
+    let pem = key_pair
+        .to_pkcs8_pem()
+        .expect("generated keys keep their primes");
+    write_output(&options, pem.as_bytes())?;
+    if let Some(path) = options.get("--pubout") {
+        std::fs::write(path, key_pair.public().to_spki_pem())
+            .map_err(|err| format!("cannot write {}: {}", path, err))?;
+    }
+    Ok(())
+}
+
+fn padding_option(options: &HashMap<&str, &str>) -> Result<Padding, String> {
+    match options.get("--scheme").copied().unwrap_or("oaep") {
+        "oaep" => Ok(Padding::Oaep(hash_option(options)?)),
+        "pkcs1v15" => Ok(Padding::Pkcs1v15),
+        scheme => Err(format!("unrecognized scheme {:?}", scheme)),
+    }
+}
+
+fn encrypt(args: &[String]) -> Result<(), String> {
+    let allowed = ["--pubkey", "--scheme", "--hash", "--in", "--out"];
+    let options = options(args, &allowed)?;
+    let pub_key = read_public_key(required(&options, "--pubkey")?)?;
+    let padding = padding_option(&options)?;
+    let m = read_input(&options)?;
+    let c = pub_key
+        .encrypt_bytes(&m, padding)
+        .map_err(|err| err.to_string())?;
+    write_output(&options, &c)
+}
+
+fn decrypt(args: &[String]) -> Result<(), String> {
+    let allowed = ["--key", "--scheme", "--hash", "--in", "--out"];
+    let options = options(args, &allowed)?;
+    let key_pair = read_key_pair(required(&options, "--key")?)?;
+    let padding = padding_option(&options)?;
+    let c = read_input(&options)?;
+    let m = key_pair
+        .private()
+        .decrypt_bytes(key_pair.public(), &c, padding)
+        .map_err(|err| err.to_string())?;
+    write_output(&options, &m)
+}
+
+fn emsa_option(options: &HashMap<&str, &str>) -> Result<Emsa, String> {
+    match options.get("--scheme").copied().unwrap_or("pkcs1v15") {
+        "pkcs1v15" => Ok(Emsa::Pkcs1v15),
+        "pss" => Ok(Emsa::Pss),
+        scheme => Err(format!("unrecognized scheme {:?}", scheme)),
+    }
+}
+
+fn sign(args: &[String]) -> Result<(), String> {
+    let allowed = ["--key", "--scheme", "--hash", "--in", "--out"];
+    let options = options(args, &allowed)?;
+    let key_pair = read_key_pair(required(&options, "--key")?)?;
+    let (emsa, hash) = (emsa_option(&options)?, hash_option(&options)?);
+    let m = read_input(&options)?;
+    let sig = signature::sign(key_pair.public(), key_pair.private(), &m, emsa, hash)
+        .map_err(|e| e.to_string())?;
+    write_output(&options, &sig)
+}
+
+// verify: Ok(false) for a signature that does not verify, Err for anything that kept the
+// check from running
+fn verify(args: &[String]) -> Result<bool, String> {
+    let allowed = ["--pubkey", "--sig", "--scheme", "--hash", "--in"];
+    let options = options(args, &allowed)?;
+    let pub_key = read_public_key(required(&options, "--pubkey")?)?;
+    let sig = read(required(&options, "--sig")?)?;
+    let (emsa, hash) = (emsa_option(&options)?, hash_option(&options)?);
+    let m = read_input(&options)?;
+    match signature::verify(&pub_key, &m, &sig, emsa, hash) {
+        Ok(()) => {
+            println!("Verified OK");
+            Ok(true)
+        }
+        Err(err) => {
+            println!("Verification failure: {}", err);
+            Ok(false)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("bench") => bench(&args[1..]),
+        Some("describe") => describe_key(&args[1..]),
+        Some("backup") => match args.get(1).map(String::as_str) {
+            Some("split") => backup_split(&args[2..]),
+            Some("restore") => backup_restore(&args[2..]),
+            _ => Err(USAGE.to_string()),
+        },
+        Some("vectors") => write_vectors(&args[1..]),
+        Some("keygen") => keygen(&args[1..]),
+        Some("encrypt") => encrypt(&args[1..]),
+        Some("decrypt") => decrypt(&args[1..]),
+        Some("sign") => sign(&args[1..]),
+        Some("verify") => match verify(&args[1..]) {
+            Ok(false) => return ExitCode::FAILURE,
+            result => result.map(|_| ()),
+        },
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("naive-rsa: {}", message);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,67 @@
+// Chaum blind signatures
+//
+// The requester hashes m as for FDH, multiplies the point by f(r) for a random r and
+// sends that; the signer applies f^-1 and gets f^-1(H(m)) r back, which the requester
+// divides by r. The result is an ordinary FDH signature on m, which the signer never saw
+// and cannot link to the signing request, so this needs a permutation with
+// f(a) f(b) = f(ab). That the signer signs blindly is the point, and also the danger: with
+// RSA, a key used for blind signing must not sign or decrypt anything else, since a
+// "blinded message" can just as well be someone else's ciphertext.
+use crate::fdh::message_point;
+use crate::trapdoor::Multiplicative;
+use crate::{invert, random_unit};
+use num_bigint::BigInt;
+
+// BlindingFactor: the r a request was blinded with, needed to unblind the answer
+pub struct BlindingFactor {
+    r: BigInt,
+}
+
+// blind: the request to hand the signer, and the factor to keep
+pub fn blind<P: Multiplicative>(perm: &P, m: &[u8]) -> (BigInt, BlindingFactor) {
+    let n = perm.domain();
+    let r = random_unit(n);
+    let blinded = message_point(perm, m) * perm.forward(&r) % n;
+    (blinded, BlindingFactor { r })
+}
+
+// sign_blinded: the signer's side, f^-1 of whatever it is given
+pub fn sign_blinded<P: Multiplicative>(
+    perm: &P,
+    trapdoor: &P::Trapdoor,
+    blinded: &BigInt,
+) -> BigInt {
+    perm.inverse(trapdoor, &(blinded % perm.domain()))
+}
+
+// unblind: the FDH signature on m, to be checked with fdh::verify
+pub fn unblind<P: Multiplicative>(perm: &P, blind_sig: &BigInt, factor: BlindingFactor) -> BigInt {
+    let n = perm.domain();
+    blind_sig * invert(factor.r, n.clone()) % n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fdh;
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn unblinded_signatures_are_fdh_signatures() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let (blinded, factor) = blind(&pub_key, b"coin 1234");
+        assert_ne!(blinded, message_point(&pub_key, b"coin 1234"));
+        let blind_sig = sign_blinded(&pub_key, &priv_key, &blinded);
+        let s = unblind(&pub_key, &blind_sig, factor);
+        assert!(fdh::verify(&pub_key, b"coin 1234", &s));
+        assert_eq!(s, fdh::sign(&pub_key, &priv_key, b"coin 1234"));
+    }
+
+    #[test]
+    fn the_same_message_blinds_differently_each_time() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let (a, _) = blind(&pub_key, b"coin");
+        let (b, _) = blind(&pub_key, b"coin");
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,303 @@
+// RSA moduli whose factors nobody kept, by cut-and-choose
+//
+// Accumulators, VDFs and "trusted setup" demos need an n that nobody can factor, including
+// whoever generated it. No proof can show that a number was forgotten, but cut-and-choose
+// can show that the generator followed the recipe: it derives CANDIDATES moduli from
+// independent random seeds and commits to each seed, then a challenge picks one modulus to
+// keep and every other seed is opened, so the verifier can rederive those moduli. A
+// generator that deviated for the kept modulus is caught with probability 1 - 1/CANDIDATES.
+// The kept seed goes out of scope with the ceremony; it is dropped, not securely wiped.
+//
+// The challenge must come from the verifier or a public beacon after the commitments are
+// out. gen_public_modulus_only derives it from the commitments alone (Fiat-Shamir), which
+// is convenient but lets a dishonest generator retry until the pick lands where it wants.
+use crate::error::ParseError;
+use crate::hash::{self, TranscriptHash};
+use crate::is_probable_prime;
+use crate::wire::{Reader, Writer};
+use num_bigint::{BigInt, Sign};
+use rand::RngCore;
+
+pub const CANDIDATES: usize = 16;
+const MR_ROUNDS: usize = 40;
+// The smallest modulus that splits into two primes with their top two bits set
+const MIN_BITS: u64 = 16;
+const FORMAT: &str = "naive-rsa modulus proof";
+
+// Commitments: what the generator publishes before seeing the challenge
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commitments {
+    bits: u64,
+    moduli: Vec<BigInt>,
+    seed_hashes: Vec<Vec<u8>>,
+}
+
+pub struct ModulusCeremony {
+    seeds: Vec<[u8; 32]>,
+    commitments: Commitments,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModulusProof {
+    commitments: Commitments,
+    chosen: usize,
+    // Every seed but the chosen one
+    opened: Vec<Option<[u8; 32]>>,
+}
+
+fn seed_hash(index: usize, seed: &[u8]) -> Vec<u8> {
+    TranscriptHash::new("naive-rsa challenge modulus seed")
+        .bytes(&(index as u32).to_be_bytes())
+        .bytes(seed)
+        .finish()
+}
+
+// derive_prime: the first probable prime among bits-bit candidates hashed from seed and
+// label, each with its top two bits set so that the product has exactly the bits asked for
+fn derive_prime(seed: &[u8], label: &str, bits: u64) -> BigInt {
+    let top = (BigInt::from(3) << (bits - 2)) | BigInt::from(1);
+    let mask = (BigInt::from(1) << bits) - 1;
+    for i in 0u32.. {
+        let h = TranscriptHash::new("naive-rsa challenge modulus candidate")
+            .bytes(seed)
+            .bytes(label.as_bytes())
+            .bytes(&i.to_be_bytes())
+            .finish();
+        let bytes = hash::expand(&h, bits.div_ceil(8) as usize);
+        let c = (BigInt::from_bytes_be(Sign::Plus, &bytes) & &mask) | &top;
+        if is_probable_prime(c.clone(), MR_ROUNDS) {
+            return c;
+        }
+    }
+    unreachable!()
+}
+
+fn derive_modulus(seed: &[u8], bits: u64) -> BigInt {
+    let p = derive_prime(seed, "p", bits.div_ceil(2));
+    let q = derive_prime(seed, "q", bits / 2);
+    p * q
+}
+
+fn chosen_index(commitments: &Commitments, challenge: &[u8]) -> usize {
+    let mut h = TranscriptHash::new("naive-rsa challenge modulus");
+    h.bytes(&commitments.bits.to_be_bytes());
+    for (n, seed_hash) in commitments.moduli.iter().zip(&commitments.seed_hashes) {
+        h.int(n).bytes(seed_hash);
+    }
+    let digest = h.bytes(challenge).finish();
+    (BigInt::from_bytes_be(Sign::Plus, &digest) % CANDIDATES)
+        .try_into()
+        .unwrap()
+}
+
+impl Commitments {
+    pub fn moduli(&self) -> &[BigInt] {
+        &self.moduli
+    }
+}
+
+impl ModulusCeremony {
+    // new: derive and commit to CANDIDATES moduli of exactly bits bits
+    pub fn new(bits: u64) -> Self {
+        if bits < MIN_BITS {
+            panic!(
+                "a {} bit modulus is too small to split into two primes",
+                bits
+            );
+        }
+        let mut rng = rand::thread_rng();
+        let seeds: Vec<[u8; 32]> = (0..CANDIDATES)
+            .map(|_| {
+                let mut seed = [0u8; 32];
+                rng.fill_bytes(&mut seed);
+                seed
+            })
+            .collect();
+        let commitments = Commitments {
+            bits,
+            moduli: seeds
+                .iter()
+                .map(|seed| derive_modulus(seed, bits))
+                .collect(),
+            seed_hashes: seeds
+                .iter()
+                .enumerate()
+                .map(|(i, seed)| seed_hash(i, seed))
+                .collect(),
+        };
+        ModulusCeremony { seeds, commitments }
+    }
+
+    pub fn commitments(&self) -> &Commitments {
+        &self.commitments
+    }
+
+    // reveal: keep the modulus the challenge picks and open every other seed
+    pub fn reveal(self, challenge: &[u8]) -> ModulusProof {
+        let chosen = chosen_index(&self.commitments, challenge);
+        let opened = self
+            .seeds
+            .into_iter()
+            .enumerate()
+            .map(|(i, seed)| (i != chosen).then_some(seed))
+            .collect();
+        ModulusProof {
+            commitments: self.commitments,
+            chosen,
+            opened,
+        }
+    }
+}
+
+// gen_public_modulus_only: a bits-bit modulus and the proof of how it was made, with a
+// Fiat-Shamir challenge; verify it with an empty challenge
+pub fn gen_public_modulus_only(bits: u64) -> (BigInt, ModulusProof) {
+    let proof = ModulusCeremony::new(bits).reveal(b"");
+    (proof.modulus().clone(), proof)
+}
+
+impl ModulusProof {
+    pub fn modulus(&self) -> &BigInt {
+        &self.commitments.moduli[self.chosen]
+    }
+
+    pub fn commitments(&self) -> &Commitments {
+        &self.commitments
+    }
+
+    // verify: the challenge picked the kept modulus, and every opened seed matches its
+    // commitment and rederives its modulus
+    pub fn verify(&self, challenge: &[u8]) -> bool {
+        let c = &self.commitments;
+        if c.moduli.len() != CANDIDATES
+            || c.seed_hashes.len() != CANDIDATES
+            || self.opened.len() != CANDIDATES
+            || self.chosen != chosen_index(c, challenge)
+            || self.modulus().bits() != c.bits
+        {
+            return false;
+        }
+        self.opened.iter().enumerate().all(|(i, seed)| match seed {
+            None => i == self.chosen,
+            Some(seed) => {
+                i != self.chosen
+                    && seed_hash(i, seed) == c.seed_hashes[i]
+                    && derive_modulus(seed, c.bits) == c.moduli[i]
+            }
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::new(FORMAT);
+        w.bytes(&(self.commitments.bits as u32).to_be_bytes())
+            .bytes(&(self.chosen as u32).to_be_bytes());
+        for i in 0..CANDIDATES {
+            let seed = self.opened[i].as_ref().map_or(&[][..], |seed| &seed[..]);
+            w.int(&self.commitments.moduli[i])
+                .bytes(&self.commitments.seed_hashes[i])
+                .bytes(seed);
+        }
+        w.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut r = Reader::new(bytes, FORMAT)?;
+        let offset = r.offset();
+        let bits = r.count()? as u64;
+        if bits < MIN_BITS {
+            return Err(ParseError::invalid(
+                FORMAT,
+                offset,
+                format!(
+                    "a {} bit modulus is below the minimum of {}",
+                    bits, MIN_BITS
+                ),
+            ));
+        }
+        let offset = r.offset();
+        let chosen = r.count()? as usize;
+        if chosen >= CANDIDATES {
+            return Err(ParseError::invalid(
+                FORMAT,
+                offset,
+                format!("candidate {} of {}", chosen, CANDIDATES),
+            ));
+        }
+        let mut commitments = Commitments {
+            bits,
+            moduli: Vec::new(),
+            seed_hashes: Vec::new(),
+        };
+        let mut opened = Vec::new();
+        for _ in 0..CANDIDATES {
+            commitments.moduli.push(r.int()?);
+            commitments.seed_hashes.push(r.bytes()?.to_vec());
+            let offset = r.offset();
+            let seed = r.bytes()?;
+            opened.push(match seed.len() {
+                0 => None,
+                32 => Some(seed.try_into().unwrap()),
+                len => {
+                    return Err(ParseError::invalid(
+                        FORMAT,
+                        offset,
+                        format!("a seed must be 32 bytes, not {}", len),
+                    ))
+                }
+            });
+        }
+        r.finish()?;
+        Ok(ModulusProof {
+            commitments,
+            chosen,
+            opened,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_kept_modulus_is_vouched_for_by_the_others() {
+        let (n, proof) = gen_public_modulus_only(128);
+        assert_eq!(n.bits(), 128);
+        assert!(!is_probable_prime(n.clone(), 20));
+        assert!(proof.verify(b""));
+        assert!(proof.opened[proof.chosen].is_none());
+        let decoded = ModulusProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(decoded, proof);
+    }
+
+    #[test]
+    fn the_verifier_picks_the_modulus() {
+        let ceremony = ModulusCeremony::new(64);
+        let commitments = ceremony.commitments().clone();
+        let proof = ceremony.reveal(b"verifier nonce");
+        assert_eq!(proof.commitments(), &commitments);
+        assert!(proof.verify(b"verifier nonce"));
+        // With another challenge the pick would (almost always) differ
+        let other = (0..8)
+            .map(|i| format!("nonce {}", i))
+            .find(|c| chosen_index(&commitments, c.as_bytes()) != proof.chosen)
+            .unwrap();
+        assert!(!proof.verify(other.as_bytes()));
+    }
+
+    #[test]
+    fn a_modulus_not_derived_from_its_seed_is_caught() {
+        let (_, mut proof) = gen_public_modulus_only(64);
+        let opened = (proof.chosen + 1) % CANDIDATES;
+        proof.commitments.moduli[opened] += 2;
+        assert!(!proof.verify(b""));
+    }
+
+    #[test]
+    fn tiny_sizes_are_refused_when_decoding() {
+        let (_, mut proof) = gen_public_modulus_only(64);
+        proof.commitments.bits = 3;
+        proof.commitments.moduli.fill(BigInt::from(5));
+        assert!(ModulusProof::from_bytes(&proof.to_bytes()).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+// Conversions between application bytes and RSA plaintexts
+//
+// Textbook implementations disagree on how bytes become a number mod n: big- or
+// little-endian, whether decryption gives the shortest byte string or one zero-padded to
+// the modulus length (losing or keeping leading zeros), and whether a length is stored
+// alongside. A MessageCodec spells those choices out. TEXTBOOK is the crate's own
+// convention, big-endian OS2IP with the shortest output. Length prefixing stores the message
+// length in four bytes in front of it and fills the rest of a fixed (k - 1)-byte block with
+// zeros, so that decoding gives back exactly the bytes that went in, zeros included.
+use crate::{modulus_len, Plaintext, PublicKey};
+use num_bigint::{BigInt, Sign};
+
+const LENGTH_LEN: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    BigEndian,
+    LittleEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MessageCodec {
+    order: ByteOrder,
+    zero_pad: bool,
+    length_prefix: bool,
+}
+
+impl MessageCodec {
+    pub const TEXTBOOK: MessageCodec = MessageCodec::new(ByteOrder::BigEndian);
+
+    pub const fn new(order: ByteOrder) -> Self {
+        MessageCodec {
+            order,
+            zero_pad: false,
+            length_prefix: false,
+        }
+    }
+
+    // zero_padded: decode to exactly as many bytes as the modulus has, like I2OSP(x, k)
+    pub const fn zero_padded(self) -> Self {
+        MessageCodec {
+            zero_pad: true,
+            ..self
+        }
+    }
+
+    pub const fn length_prefixed(self) -> Self {
+        MessageCodec {
+            length_prefix: true,
+            ..self
+        }
+    }
+
+    pub fn byte_order(&self) -> ByteOrder {
+        self.order
+    }
+
+    // max_message_len: the longest message that always fits; without a length prefix
+    // longer ones fit too as long as their value stays below n
+    pub fn max_message_len(&self, pub_key: &PublicKey) -> usize {
+        let block = modulus_len(pub_key).saturating_sub(1);
+        if self.length_prefix {
+            block.saturating_sub(LENGTH_LEN)
+        } else {
+            block
+        }
+    }
+
+    fn read_int(&self, bytes: &[u8]) -> BigInt {
+        match self.order {
+            ByteOrder::BigEndian => BigInt::from_bytes_be(Sign::Plus, bytes),
+            ByteOrder::LittleEndian => BigInt::from_bytes_le(Sign::Plus, bytes),
+        }
+    }
+
+    // write_int: x in width bytes, zeros going at the most significant end; None if x does
+    // not fit
+    fn write_int(&self, x: &BigInt, width: Option<usize>) -> Option<Vec<u8>> {
+        let (_, mut bytes) = x.to_bytes_le();
+        if x.sign() == Sign::NoSign {
+            bytes.clear();
+        }
+        if let Some(width) = width {
+            if bytes.len() > width {
+                return None;
+            }
+            bytes.resize(width, 0);
+        }
+        if self.order == ByteOrder::BigEndian {
+            bytes.reverse();
+        }
+        Some(bytes)
+    }
+
+    fn length_field(&self, len: usize) -> [u8; LENGTH_LEN] {
+        match self.order {
+            ByteOrder::BigEndian => (len as u32).to_be_bytes(),
+            ByteOrder::LittleEndian => (len as u32).to_le_bytes(),
+        }
+    }
+
+    // encode: the plaintext for m, or None if it is too long for this key
+    pub fn encode(&self, pub_key: &PublicKey, m: &[u8]) -> Option<Plaintext> {
+        let x = if self.length_prefix {
+            if m.len() > self.max_message_len(pub_key) {
+                return None;
+            }
+            let mut block = [&self.length_field(m.len())[..], m].concat();
+            block.resize(modulus_len(pub_key) - 1, 0);
+            self.read_int(&block)
+        } else {
+            self.read_int(m)
+        };
+        (x < pub_key.n).then(|| x.into())
+    }
+
+    // decode: None if the plaintext is not something encode could have produced
+    pub fn decode(&self, pub_key: &PublicKey, m: &Plaintext) -> Option<Vec<u8>> {
+        let k = modulus_len(pub_key);
+        if self.length_prefix {
+            let block = self.write_int(m.as_bigint(), Some(k - 1))?;
+            let (field, rest) = block.split_at_checked(LENGTH_LEN)?;
+            let len = self.read_int(field).try_into().ok()?;
+            if len > rest.len() || rest[len..].iter().any(|&b| b != 0) {
+                return None;
+            }
+            Some(rest[..len].to_vec())
+        } else {
+            self.write_int(m.as_bigint(), self.zero_pad.then_some(k))
+        }
+    }
+}
+
+impl Default for MessageCodec {
+    fn default() -> Self {
+        MessageCodec::TEXTBOOK
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decrypt, encrypt, gen_keys_with_digits};
+
+    #[test]
+    fn byte_order_matches_other_conventions() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let le = MessageCodec::new(ByteOrder::LittleEndian);
+        // int.from_bytes(b"\x01\x02", "big") == 258 and (..., "little") == 513 in Python
+        let be_value = MessageCodec::TEXTBOOK.encode(&pub_key, &[1, 2]).unwrap();
+        assert_eq!(be_value.as_bigint(), &BigInt::from(258));
+        assert_eq!(
+            le.encode(&pub_key, &[1, 2]).unwrap().as_bigint(),
+            &BigInt::from(513)
+        );
+        assert_eq!(
+            le.decode(&pub_key, &BigInt::from(513).into()).unwrap(),
+            [1, 2]
+        );
+        let k = modulus_len(&pub_key);
+        let padded = le
+            .zero_padded()
+            .decode(&pub_key, &BigInt::from(513).into())
+            .unwrap();
+        assert_eq!(padded.len(), k);
+        assert_eq!(padded[..3], [1, 2, 0]);
+        assert_eq!(
+            MessageCodec::TEXTBOOK.encode(&pub_key, &vec![0xff; k]),
+            None
+        );
+    }
+
+    #[test]
+    fn length_prefixes_keep_leading_zeros() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let m = [0u8, 0, 7, 0];
+        // The shortest output drops the leading zeros...
+        let plain = MessageCodec::TEXTBOOK.encode(&pub_key, &m).unwrap();
+        assert_eq!(
+            MessageCodec::TEXTBOOK.decode(&pub_key, &plain).unwrap(),
+            [7, 0]
+        );
+        // ...a length prefix keeps them, in either byte order
+        for order in [ByteOrder::BigEndian, ByteOrder::LittleEndian] {
+            let codec = MessageCodec::new(order).length_prefixed();
+            let c = encrypt(&pub_key, codec.encode(&pub_key, &m).unwrap()).unwrap();
+            let decoded = codec.decode(&pub_key, &decrypt(&pub_key, &priv_key, c).unwrap());
+            assert_eq!(decoded.unwrap(), m);
+            let too_long = vec![1; codec.max_message_len(&pub_key) + 1];
+            assert_eq!(codec.encode(&pub_key, &too_long), None);
+        }
+        let codec = MessageCodec::TEXTBOOK.length_prefixed();
+        assert_eq!(codec.decode(&pub_key, &BigInt::from(1).into()), None);
+    }
+}
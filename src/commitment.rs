@@ -0,0 +1,184 @@
+// Integer commitments over an RSA group
+//
+// commit(m, r) = g^m h^r mod n, with g and h squares mod an RSA modulus. The commitment is
+// statistically hiding because r is drawn from a range much wider than the group order,
+// and binding for anyone who knows neither the factors of n nor log_h(g): opening one
+// commitment two ways reveals a multiple of the group order, which factors n.
+use crate::error::ParseError;
+use crate::hash::{self, TranscriptHash};
+use crate::wire::{Reader, Writer};
+use crate::{gen_keys_with_digits, is_even, PublicKey};
+use num_bigint::{BigInt, RandBigInt};
+
+pub struct CommitmentParams {
+    n: BigInt,
+    g: BigInt,
+    h: BigInt,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commitment {
+    c: BigInt,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Opening {
+    m: BigInt,
+    r: BigInt,
+}
+
+// Bits of slack on top of the modulus size when sampling r, for statistical hiding
+const HIDING_BITS: u64 = 128;
+
+impl CommitmentParams {
+    // setup: generate a fresh modulus and throw the private key away, so nobody can equivocate
+    pub fn setup(ndigits: u32) -> Self {
+        let (pub_key, _) = gen_keys_with_digits(ndigits);
+        CommitmentParams::from_public_key(&pub_key)
+    }
+
+    // from_public_key: derive g and h by hashing an existing modulus. Whoever holds the
+    // matching private key can open commitments to anything, so only use keys whose
+    // factors are unknown to the committer.
+    pub fn from_public_key(pub_key: &PublicKey) -> Self {
+        let n = pub_key.n.clone();
+        let g = hash_to_square(&n, "g");
+        let h = hash_to_square(&n, "h");
+        CommitmentParams { n, g, h }
+    }
+
+    pub fn modulus(&self) -> &BigInt {
+        &self.n
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Writer::new("naive-rsa commitment params")
+            .int(&self.n)
+            .int(&self.g)
+            .int(&self.h)
+            .finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut r = Reader::new(bytes, "naive-rsa commitment params")?;
+        let (n, g, h) = (r.int()?, r.int()?, r.int()?);
+        r.finish()?;
+        // An RSA modulus is odd and greater than 2; anything else makes commit meaningless
+        if n <= BigInt::from(2) || is_even(n.clone()) {
+            return Err(ParseError::invalid(
+                "naive-rsa commitment params",
+                0,
+                "the modulus must be odd and greater than 2",
+            ));
+        }
+        Ok(CommitmentParams { n, g, h })
+    }
+}
+
+// hash_to_square: a square mod n nobody knows a discrete logarithm of
+fn hash_to_square(n: &BigInt, label: &str) -> BigInt {
+    let seed = TranscriptHash::new("naive-rsa commitment generator")
+        .int(n)
+        .bytes(label.as_bytes())
+        .finish();
+    let x = hash::full_domain(&seed, n);
+    &x * &x % n
+}
+
+impl Commitment {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Writer::new("naive-rsa commitment").int(&self.c).finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut r = Reader::new(bytes, "naive-rsa commitment")?;
+        let c = r.int()?;
+        r.finish()?;
+        Ok(Commitment { c })
+    }
+}
+
+impl Opening {
+    pub fn message(&self) -> &BigInt {
+        &self.m
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Writer::new("naive-rsa opening")
+            .int(&self.m)
+            .int(&self.r)
+            .finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut reader = Reader::new(bytes, "naive-rsa opening")?;
+        let (m, r) = (reader.int()?, reader.int()?);
+        reader.finish()?;
+        Ok(Opening { m, r })
+    }
+}
+
+// commit: commit to a non-negative integer m with fresh randomness
+pub fn commit(params: &CommitmentParams, m: &BigInt) -> (Commitment, Opening) {
+    let bound = BigInt::from(1) << (params.n.bits() + HIDING_BITS);
+    let r = rand::thread_rng().gen_bigint_range(&BigInt::from(0), &bound);
+    (commit_with(params, m, &r), Opening { m: m.clone(), r })
+}
+
+pub fn commit_with(params: &CommitmentParams, m: &BigInt, r: &BigInt) -> Commitment {
+    if *m < BigInt::from(0) || *r < BigInt::from(0) {
+        panic!("commitments are defined for non-negative m and r");
+    }
+    let c = params.g.modpow(m, &params.n) * params.h.modpow(r, &params.n) % &params.n;
+    Commitment { c }
+}
+
+pub fn verify(params: &CommitmentParams, commitment: &Commitment, opening: &Opening) -> bool {
+    opening.m >= BigInt::from(0)
+        && opening.r >= BigInt::from(0)
+        && commit_with(params, &opening.m, &opening.r) == *commitment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commitments_open_only_to_their_message() {
+        let params = CommitmentParams::setup(20);
+        let (c, opening) = commit(&params, &BigInt::from(42));
+        assert!(verify(&params, &c, &opening));
+        let forged = Opening {
+            m: BigInt::from(43),
+            r: opening.r.clone(),
+        };
+        assert!(!verify(&params, &c, &forged));
+    }
+
+    #[test]
+    fn commitments_are_hiding() {
+        let params = CommitmentParams::setup(20);
+        let (c1, _) = commit(&params, &BigInt::from(7));
+        let (c2, _) = commit(&params, &BigInt::from(7));
+        assert_ne!(c1, c2);
+    }
+
+    #[test]
+    fn values_round_trip_through_bytes() {
+        let params = CommitmentParams::setup(20);
+        let (c, opening) = commit(&params, &BigInt::from(1000));
+        let params = CommitmentParams::from_bytes(&params.to_bytes()).unwrap();
+        let c = Commitment::from_bytes(&c.to_bytes()).unwrap();
+        let opening = Opening::from_bytes(&opening.to_bytes()).unwrap();
+        assert!(verify(&params, &c, &opening));
+        assert!(Commitment::from_bytes(&opening.to_bytes()).is_err());
+        for n in [0, 1, 2, 4, 1000] {
+            let bytes = Writer::new("naive-rsa commitment params")
+                .int(&BigInt::from(n))
+                .int(&BigInt::from(1))
+                .int(&BigInt::from(1))
+                .finish();
+            assert!(CommitmentParams::from_bytes(&bytes).is_err());
+        }
+    }
+}
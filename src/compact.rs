@@ -0,0 +1,167 @@
+// A compact public key encoding for QR codes
+//
+// Layout: a version byte, one byte giving the length of e (0 standing for the usual 65537,
+// which is then omitted), e, and the modulus in big-endian bytes up to the end. A 2048-bit
+// key with e = 65537 takes 258 bytes, a version 12 QR code at level M. qr_version says how
+// large a code a payload needs; with the qr feature, render_qr draws one as text.
+use crate::error::ParseError;
+use crate::wire::Reader;
+use crate::{os2ip, PublicKey};
+use num_bigint::BigInt;
+
+const FORMAT: &str = "naive-rsa compact public key";
+const VERSION: u8 = 1;
+const DEFAULT_E: u32 = 65537;
+
+// QrLevel: how much of a damaged code can be recovered, at the cost of capacity
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QrLevel {
+    // About 7%
+    L,
+    // About 15%
+    M,
+    // About 25%
+    Q,
+    // About 30%
+    H,
+}
+
+// Byte-mode capacities of QR code versions 1 to 40 (ISO/IEC 18004, table 7)
+#[rustfmt::skip]
+const CAPACITY: [[u16; 40]; 4] = [
+    [17, 32, 53, 78, 106, 134, 154, 192, 230, 271, 321, 367, 425, 458, 520, 586, 644, 718, 792,
+     858, 929, 1003, 1091, 1171, 1273, 1367, 1465, 1528, 1628, 1732, 1840, 1952, 2068, 2188,
+     2303, 2431, 2563, 2699, 2809, 2953],
+    [14, 26, 42, 62, 84, 106, 122, 152, 180, 213, 251, 287, 331, 362, 412, 450, 504, 560, 624,
+     666, 711, 779, 857, 911, 997, 1059, 1125, 1190, 1264, 1370, 1452, 1538, 1628, 1722, 1809,
+     1911, 1989, 2099, 2213, 2331],
+    [11, 20, 32, 46, 60, 74, 86, 108, 130, 151, 177, 203, 241, 258, 292, 322, 364, 394, 442,
+     482, 509, 565, 611, 661, 715, 751, 805, 868, 908, 982, 1030, 1112, 1168, 1228, 1283, 1351,
+     1423, 1499, 1579, 1663],
+    [7, 14, 24, 34, 44, 58, 64, 84, 98, 119, 137, 155, 177, 194, 220, 250, 280, 310, 338, 382,
+     403, 439, 461, 511, 535, 593, 625, 658, 698, 742, 790, 842, 898, 958, 983, 1051, 1093,
+     1139, 1219, 1273],
+];
+
+pub fn to_compact(pub_key: &PublicKey) -> Vec<u8> {
+    let e = if pub_key.e == BigInt::from(DEFAULT_E) {
+        Vec::new()
+    } else {
+        pub_key.e.to_bytes_be().1
+    };
+    if e.len() > u8::MAX as usize {
+        panic!(
+            "a {} byte public exponent does not fit the compact encoding",
+            e.len()
+        );
+    }
+    [
+        &[VERSION, e.len() as u8][..],
+        &e,
+        &pub_key.n.to_bytes_be().1,
+    ]
+    .concat()
+}
+
+pub fn from_compact(bytes: &[u8]) -> Result<PublicKey, ParseError> {
+    let mut r = Reader::untagged(bytes, FORMAT);
+    let version = r.raw(1)?[0];
+    if version != VERSION {
+        return Err(ParseError::invalid(
+            FORMAT,
+            0,
+            format!("unknown version {}", version),
+        ));
+    }
+    let e_len = r.raw(1)?[0] as usize;
+    let e = if e_len == 0 {
+        BigInt::from(DEFAULT_E)
+    } else {
+        os2ip(r.raw(e_len)?)
+    };
+    let offset = r.offset();
+    let n = os2ip(r.raw(bytes.len() - offset)?);
+    if n < BigInt::from(3) {
+        return Err(ParseError::invalid(
+            FORMAT,
+            offset,
+            "the modulus is missing",
+        ));
+    }
+    Ok(PublicKey { n, e })
+}
+
+// qr_version: the smallest QR code version that holds len bytes at the given level
+pub fn qr_version(len: usize, level: QrLevel) -> Option<u8> {
+    CAPACITY[level as usize]
+        .iter()
+        .position(|&capacity| len <= capacity as usize)
+        .map(|i| i as u8 + 1)
+}
+
+// render_qr: the compact encoding as a QR code drawn with Unicode half blocks, two rows
+// per line of text
+#[cfg(feature = "qr")]
+pub fn render_qr(pub_key: &PublicKey, level: QrLevel) -> String {
+    render(to_compact(pub_key), level)
+}
+
+#[cfg(feature = "qr")]
+pub(crate) fn render(bytes: Vec<u8>, level: QrLevel) -> String {
+    use qrcode::render::unicode::Dense1x2;
+    use qrcode::{EcLevel, QrCode};
+    let level = match level {
+        QrLevel::L => EcLevel::L,
+        QrLevel::M => EcLevel::M,
+        QrLevel::Q => EcLevel::Q,
+        QrLevel::H => EcLevel::H,
+    };
+    let code = QrCode::with_error_correction_level(bytes, level)
+        .unwrap_or_else(|err| panic!("the data does not fit a QR code: {}", err));
+    code.render::<Dense1x2>().build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn keys_round_trip_compactly() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let bytes = to_compact(&pub_key);
+        assert_eq!(bytes.len(), 2 + pub_key.n.to_bytes_be().1.len());
+        assert_eq!(from_compact(&bytes).unwrap(), pub_key);
+        let odd_e = PublicKey {
+            n: pub_key.n.clone(),
+            e: BigInt::from(3),
+        };
+        assert_eq!(to_compact(&odd_e)[..3], [VERSION, 1, 3]);
+        assert_eq!(from_compact(&to_compact(&odd_e)).unwrap(), odd_e);
+        assert!(from_compact(&[VERSION, 0]).is_err());
+        assert!(from_compact(&[2, 0, 1, 2, 3]).is_err());
+        assert!(from_compact(&[VERSION, 4, 1]).is_err());
+    }
+
+    #[test]
+    fn qr_versions_follow_the_capacity_table() {
+        assert_eq!(qr_version(17, QrLevel::L), Some(1));
+        assert_eq!(qr_version(18, QrLevel::L), Some(2));
+        // A 2048-bit key with the default exponent
+        assert_eq!(qr_version(258, QrLevel::M), Some(12));
+        assert_eq!(qr_version(2954, QrLevel::L), None);
+    }
+
+    #[cfg(feature = "qr")]
+    #[test]
+    fn rendered_codes_have_the_predicted_version() {
+        let (pub_key, _) = gen_keys_with_digits(50);
+        let bytes = to_compact(&pub_key);
+        for level in [QrLevel::L, QrLevel::M, QrLevel::Q, QrLevel::H] {
+            let version = qr_version(bytes.len(), level).unwrap() as usize;
+            // 4v + 17 modules, a quiet zone of 4 on each side, two modules per text line
+            let lines = render_qr(&pub_key, level).lines().count();
+            assert_eq!(lines, (4 * version + 17 + 8).div_ceil(2));
+        }
+    }
+}
@@ -0,0 +1,279 @@
+// Side-by-side comparison of the encryption schemes
+//
+// compare_schemes encrypts one message under each scheme at each key size and records what
+// a lecture slide would: how much longer the ciphertext is than the message, how long
+// encryption and decryption take, and two properties found by experiment rather than
+// asserted. Deterministic means that encrypting twice gave the same ciphertext; malleable
+// means that multiplying the RSA block of the ciphertext by 2^e produced a valid encryption
+// of 2m. Textbook RSA is both, the padded and hybrid schemes neither. Timings are averages
+// over a few runs on a freshly generated key, so read them as orders of magnitude.
+use crate::codec::MessageCodec;
+use crate::{
+    decrypt, encrypt, gen_keys_with_digits, i2osp, kem, modulus_len, oaep, os2ip, pkcs1v15,
+    PrivateKey, PublicKey,
+};
+use num_bigint::BigInt;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+const REPETITIONS: u32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    Raw,
+    Pkcs1v15,
+    Oaep,
+    Hybrid,
+}
+
+#[derive(Clone, Debug)]
+pub struct Measurement {
+    pub scheme: Scheme,
+    pub ndigits: u32,
+    pub modulus_bits: u64,
+    // None when the message is too long for the scheme at this key size
+    pub metrics: Option<Metrics>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    pub ciphertext_len: usize,
+    // expansion: ciphertext length over message length
+    pub expansion: f64,
+    pub encrypt_time: Duration,
+    pub decrypt_time: Duration,
+    pub deterministic: bool,
+    pub malleable: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    message_len: usize,
+    measurements: Vec<Measurement>,
+}
+
+impl Scheme {
+    pub const ALL: [Scheme; 4] = [Scheme::Raw, Scheme::Pkcs1v15, Scheme::Oaep, Scheme::Hybrid];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Scheme::Raw => "raw",
+            Scheme::Pkcs1v15 => "pkcs1v15",
+            Scheme::Oaep => "oaep",
+            Scheme::Hybrid => "hybrid",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Scheme> {
+        Scheme::ALL.into_iter().find(|scheme| scheme.name() == name)
+    }
+
+    fn fits(self, pub_key: &PublicKey, m: &[u8]) -> bool {
+        match self {
+            Scheme::Raw => MessageCodec::TEXTBOOK.encode(pub_key, m).is_some(),
+            Scheme::Pkcs1v15 => m.len() <= pkcs1v15::max_message_len(pub_key),
+            // The encoder itself knows when the modulus is too short for OAEP at all
+            Scheme::Oaep => oaep::encrypt(pub_key, m, b"").is_some(),
+            Scheme::Hybrid => true,
+        }
+    }
+
+    fn encrypt(self, pub_key: &PublicKey, m: &[u8]) -> Vec<u8> {
+        match self {
+            Scheme::Raw => {
+                let m = MessageCodec::TEXTBOOK.encode(pub_key, m).unwrap();
+                let c = encrypt(pub_key, m).expect("the codec keeps messages below n");
+                i2osp(c.as_bigint(), modulus_len(pub_key))
+            }
+            Scheme::Pkcs1v15 => pkcs1v15::encrypt(pub_key, m),
+            Scheme::Oaep => oaep::encrypt(pub_key, m, b"").expect("fits checked the length"),
+            Scheme::Hybrid => kem::seal(pub_key, m),
+        }
+    }
+
+    fn decrypt(self, pub_key: &PublicKey, priv_key: &PrivateKey, c: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            Scheme::Raw => {
+                let m = decrypt(pub_key, priv_key, os2ip(c).into()).ok()?;
+                MessageCodec::TEXTBOOK.decode(pub_key, &m)
+            }
+            Scheme::Pkcs1v15 => pkcs1v15::decrypt(pub_key, priv_key, c),
+            Scheme::Oaep => oaep::decrypt(pub_key, priv_key, c, b""),
+            Scheme::Hybrid => kem::open(pub_key, priv_key, c),
+        }
+    }
+}
+
+impl fmt::Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+fn average<T>(mut f: impl FnMut() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let mut out = f();
+    for _ in 1..REPETITIONS {
+        out = f();
+    }
+    (out, start.elapsed() / REPETITIONS)
+}
+
+// doubled: c with its leading RSA block multiplied by 2^e, which textbook RSA decrypts to 2m
+fn doubled(pub_key: &PublicKey, c: &[u8]) -> Vec<u8> {
+    let k = modulus_len(pub_key);
+    let factor = encrypt(pub_key, BigInt::from(2).into()).expect("2 is below n");
+    let block = os2ip(&c[..k]) * factor.as_bigint() % &pub_key.n;
+    [i2osp(&block, k), c[k..].to_vec()].concat()
+}
+
+fn measure(scheme: Scheme, pub_key: &PublicKey, priv_key: &PrivateKey, m: &[u8]) -> Metrics {
+    let (c, encrypt_time) = average(|| scheme.encrypt(pub_key, m));
+    let (decrypted, decrypt_time) = average(|| scheme.decrypt(pub_key, priv_key, &c));
+    if decrypted.as_deref().map(os2ip) != Some(os2ip(m)) {
+        panic!("{} did not decrypt its own ciphertext", scheme);
+    }
+    let twice = (os2ip(m) * 2u8) % &pub_key.n;
+    let malleable = scheme
+        .decrypt(pub_key, priv_key, &doubled(pub_key, &c))
+        .is_some_and(|m2| os2ip(&m2) == twice);
+    Metrics {
+        ciphertext_len: c.len(),
+        expansion: c.len() as f64 / m.len().max(1) as f64,
+        encrypt_time,
+        decrypt_time,
+        deterministic: scheme.encrypt(pub_key, m) == c,
+        malleable,
+    }
+}
+
+// compare_schemes: one measurement per scheme and key size; key sizes are in decimal digits
+// per prime, as for gen_keys_with_digits, and each size gets one key shared by all schemes
+pub fn compare_schemes(message: &[u8], key_sizes: &[u32], schemes: &[Scheme]) -> Comparison {
+    let mut measurements = Vec::new();
+    for &ndigits in key_sizes {
+        let (pub_key, priv_key) = gen_keys_with_digits(ndigits);
+        for &scheme in schemes {
+            let metrics = scheme
+                .fits(&pub_key, message)
+                .then(|| measure(scheme, &pub_key, &priv_key, message));
+            measurements.push(Measurement {
+                scheme,
+                ndigits,
+                modulus_bits: pub_key.n.bits(),
+                metrics,
+            });
+        }
+    }
+    Comparison {
+        message_len: message.len(),
+        measurements,
+    }
+}
+
+impl Comparison {
+    pub fn message_len(&self) -> usize {
+        self.message_len
+    }
+
+    pub fn measurements(&self) -> &[Measurement] {
+        &self.measurements
+    }
+
+    pub fn get(&self, scheme: Scheme, ndigits: u32) -> Option<&Measurement> {
+        self.measurements
+            .iter()
+            .find(|m| m.scheme == scheme && m.ndigits == ndigits)
+    }
+}
+
+fn yes_no(b: bool) -> &'static str {
+    if b {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+// A plain-text table, one row per measurement
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "message: {} bytes", self.message_len)?;
+        write!(
+            f,
+            "{:<10} {:>9} {:>11} {:>10} {:>12} {:>12} {:>14} {:>10}",
+            "scheme",
+            "key bits",
+            "ciphertext",
+            "expansion",
+            "encrypt",
+            "decrypt",
+            "deterministic",
+            "malleable"
+        )?;
+        for m in &self.measurements {
+            write!(f, "\n{:<10} {:>9}", m.scheme.name(), m.modulus_bits)?;
+            match &m.metrics {
+                Some(x) => write!(
+                    f,
+                    " {:>11} {:>10} {:>12} {:>12} {:>14} {:>10}",
+                    format!("{} B", x.ciphertext_len),
+                    format!("{:.1}x", x.expansion),
+                    format!("{:.1?}", x.encrypt_time),
+                    format!("{:.1?}", x.decrypt_time),
+                    yes_no(x.deterministic),
+                    yes_no(x.malleable)
+                )?,
+                None => write!(f, " {:>11}", "too long")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_textbook_rsa_is_deterministic_and_malleable() {
+        let report = compare_schemes(b"hi", &[20], &Scheme::ALL);
+        assert_eq!(report.measurements().len(), 4);
+        let raw = report
+            .get(Scheme::Raw, 20)
+            .unwrap()
+            .metrics
+            .clone()
+            .unwrap();
+        assert!(raw.deterministic && raw.malleable);
+        for scheme in [Scheme::Pkcs1v15, Scheme::Hybrid] {
+            let metrics = report.get(scheme, 20).unwrap().metrics.clone().unwrap();
+            assert!(!metrics.deterministic && !metrics.malleable, "{}", scheme);
+        }
+        let hybrid = report
+            .get(Scheme::Hybrid, 20)
+            .unwrap()
+            .metrics
+            .clone()
+            .unwrap();
+        assert!(hybrid.ciphertext_len > raw.ciphertext_len);
+        // Twenty-digit primes leave no room for OAEP's two SHA-256 hashes
+        assert!(report.get(Scheme::Oaep, 20).unwrap().metrics.is_none());
+    }
+
+    #[test]
+    fn report_renders_as_a_table() {
+        let report = compare_schemes(b"lecture", &[20], &[Scheme::Raw, Scheme::Oaep]);
+        let table = report.to_string();
+        assert!(table.starts_with("message: 7 bytes\n"));
+        assert_eq!(table.lines().count(), 4);
+        assert!(table.contains("too long"));
+        assert_eq!(Scheme::from_name("pkcs1v15"), Some(Scheme::Pkcs1v15));
+    }
+
+    #[test]
+    fn empty_messages_skip_oaep_on_small_keys() {
+        let report = compare_schemes(b"", &[20], &[Scheme::Oaep]);
+        assert!(report.get(Scheme::Oaep, 20).unwrap().metrics.is_none());
+    }
+}
@@ -0,0 +1,159 @@
+// Keys from the decimal or hex strings of their components
+//
+// What a textbook, a test vector file or another library prints is usually n, e and d as
+// bare numbers. from_str_components parses them in any radix from 2 to 36 and checks what
+// can be checked without the factors, so a mistyped digit or swapped field is reported
+// against the field it is in rather than surfacing later as a wrong decryption. Surrounding
+// whitespace is ignored, as is a 0x prefix in radix 16.
+use crate::error::ParseError;
+use crate::{is_even, is_probable_prime, PrivateKey, PublicKey};
+use num_bigint::BigInt;
+
+const PUBLIC_FORMAT: &str = "public key components";
+const PRIVATE_FORMAT: &str = "private key components";
+
+// parse_component: the value of one field; offsets in errors are into that field's string
+fn parse_component(
+    format: &str,
+    field: &str,
+    text: &str,
+    radix: u32,
+) -> Result<BigInt, ParseError> {
+    if !(2..=36).contains(&radix) {
+        return Err(ParseError::invalid(
+            format,
+            0,
+            format!("radix must be from 2 to 36, not {}", radix),
+        ));
+    }
+    let start = text.len() - text.trim_start().len();
+    let mut digits = text.trim();
+    let mut offset = start;
+    if radix == 16 {
+        if let Some(rest) = digits
+            .strip_prefix("0x")
+            .or_else(|| digits.strip_prefix("0X"))
+        {
+            digits = rest;
+            offset += 2;
+        }
+    }
+    if digits.is_empty() {
+        return Err(ParseError::invalid(
+            format,
+            offset,
+            format!("{} is empty", field),
+        ));
+    }
+    if let Some((i, c)) = digits.char_indices().find(|(_, c)| !c.is_digit(radix)) {
+        let reason = match c {
+            '-' if i == 0 => format!("{} must be positive", field),
+            _ => format!(
+                "{} contains {:?}, which is not a base {} digit",
+                field, c, radix
+            ),
+        };
+        return Err(ParseError::invalid(format, offset + i, reason));
+    }
+    Ok(BigInt::parse_bytes(digits.as_bytes(), radix).unwrap())
+}
+
+impl PublicKey {
+    // from_str_components: the key (n, e), with n an odd composite and e odd, from 3 to n - 1
+    pub fn from_str_components(n: &str, e: &str, radix: u32) -> Result<PublicKey, ParseError> {
+        let n = parse_component(PUBLIC_FORMAT, "n", n, radix)?;
+        let e = parse_component(PUBLIC_FORMAT, "e", e, radix)?;
+        let invalid = |reason: &str| Err(ParseError::invalid(PUBLIC_FORMAT, 0, reason));
+        if n < BigInt::from(15) || is_even(n.clone()) {
+            return invalid("n must be an odd number of at least 15");
+        }
+        if is_probable_prime(n.clone(), 20) {
+            return invalid("n is prime, so anyone can compute d from e");
+        }
+        if e < BigInt::from(3) || e >= n {
+            return invalid("e must be from 3 to n - 1");
+        }
+        if is_even(e.clone()) {
+            return invalid("e is even, so it has no inverse mod phi(n)");
+        }
+        Ok(PublicKey { n, e })
+    }
+}
+
+impl PrivateKey {
+    // from_str_components: the private exponent d for pub_key, checked by a test decryption
+    pub fn from_str_components(
+        pub_key: &PublicKey,
+        d: &str,
+        radix: u32,
+    ) -> Result<PrivateKey, ParseError> {
+        let d = parse_component(PRIVATE_FORMAT, "d", d, radix)?;
+        let invalid = |reason: &str| Err(ParseError::invalid(PRIVATE_FORMAT, 0, reason));
+        if d < BigInt::from(3) || d >= pub_key.n {
+            return invalid("d must be from 3 to n - 1");
+        }
+        // A wrong d, or one for another modulus, almost never maps 2^e back to 2
+        let two = BigInt::from(2);
+        if two.modpow(&pub_key.e, &pub_key.n).modpow(&d, &pub_key.n) != two {
+            return invalid("d does not invert e for this modulus");
+        }
+        Ok(PrivateKey::new(d))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ParseErrorKind;
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn components_round_trip_in_any_radix() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        for radix in [10, 16, 36] {
+            let n = pub_key.n.to_str_radix(radix);
+            let e = pub_key.e.to_str_radix(radix);
+            let parsed = PublicKey::from_str_components(&n, &e, radix).unwrap();
+            assert_eq!(parsed, pub_key);
+            let d = priv_key.d.to_str_radix(radix);
+            let parsed = PrivateKey::from_str_components(&pub_key, &d, radix).unwrap();
+            assert_eq!(parsed.d, priv_key.d);
+        }
+        let n = format!("  0x{}\n", pub_key.n.to_str_radix(16));
+        assert!(PublicKey::from_str_components(&n, "10001", 16).is_ok());
+    }
+
+    #[test]
+    fn bad_digits_are_located() {
+        let err = PublicKey::from_str_components("3233", "1 7", 10).unwrap_err();
+        assert_eq!(err.offset(), 1);
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::Invalid("e contains ' ', which is not a base 10 digit".into())
+        );
+        let err = PublicKey::from_str_components("-3233", "17", 10).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::Invalid("n must be positive".into())
+        );
+        assert!(PublicKey::from_str_components("", "17", 10).is_err());
+        let err = PublicKey::from_str_components("3233", "17", 37).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::Invalid("radix must be from 2 to 36, not 37".into())
+        );
+    }
+
+    #[test]
+    fn implausible_keys_are_rejected() {
+        // 3233 = 61 * 53, phi = 3120, d = 2753 is the textbook example
+        assert!(PublicKey::from_str_components("3233", "17", 10).is_ok());
+        assert!(PublicKey::from_str_components("3232", "17", 10).is_err());
+        assert!(PublicKey::from_str_components("3229", "17", 10).is_err());
+        assert!(PublicKey::from_str_components("3233", "16", 10).is_err());
+        assert!(PublicKey::from_str_components("17", "3233", 10).is_err());
+        let pub_key = PublicKey::from_str_components("3233", "17", 10).unwrap();
+        assert!(PrivateKey::from_str_components(&pub_key, "2753", 10).is_ok());
+        assert!(PrivateKey::from_str_components(&pub_key, "2754", 10).is_err());
+    }
+}
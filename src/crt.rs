@@ -0,0 +1,103 @@
+// Decryption by the Chinese Remainder Theorem
+//
+// A private key that knows p and q can exponentiate mod each prime separately, with the
+// exponents dP = d mod p-1 and dQ = d mod q-1, and recombine with qInv = q^-1 mod p
+// (Garner's formula, RFC 8017 section 5.1.2): m = m2 + q (qInv (m1 - m2) mod p). Each half
+// works with numbers half the size and an exponent half the length, so the two together
+// take about a quarter of the time of x^d mod n. Keys built from d alone, as most of the
+// crate's own formats store them, fall back to the full exponentiation.
+use crate::exponents::crt_exponents;
+use crate::mod_inverse;
+use crate::mod_ring::ModRing;
+use num_bigint::BigInt;
+
+pub(crate) struct Crt {
+    pub(crate) p: BigInt,
+    pub(crate) q: BigInt,
+    pub(crate) dp: BigInt,
+    pub(crate) dq: BigInt,
+    pub(crate) qinv: BigInt,
+}
+
+impl Crt {
+    // new: the parameters for p and q, or None unless both are above 1 and coprime, as
+    // recombination needs q to have an inverse mod p
+    pub(crate) fn new(d: &BigInt, p: BigInt, q: BigInt) -> Option<Self> {
+        let one = BigInt::from(1);
+        if p <= one || q <= one {
+            return None;
+        }
+        let qinv = mod_inverse(q.clone(), p.clone()).ok()?;
+        let (dp, dq) = crt_exponents(d, &p, &q);
+        Some(Crt { p, q, dp, dq, qinv })
+    }
+
+    // pow: x^d mod pq
+    pub(crate) fn pow(&self, x: &BigInt) -> BigInt {
+        self.pow_with(x, |x, exp, modulus| {
+            ModRing::new(modulus.clone())
+                .elem(x.clone())
+                .pow(exp)
+                .into()
+        })
+    }
+
+    // pow_with: x^d mod pq, with each half computed as half(x, dP, p) and half(x, dQ, q)
+    pub(crate) fn pow_with(
+        &self,
+        x: &BigInt,
+        half: impl Fn(&BigInt, &BigInt, &BigInt) -> BigInt,
+    ) -> BigInt {
+        let m1 = half(x, &self.dp, &self.p);
+        let m2 = half(x, &self.dq, &self.q);
+        self.recombine(m1, &m2)
+    }
+
+    // recombine: the x mod pq that is m1 mod p and m2 mod q
+    pub(crate) fn recombine(&self, m1: BigInt, m2: &BigInt) -> BigInt {
+        let h = ((m1 - m2) % &self.p + &self.p) * &self.qinv % &self.p;
+        m2 + h * &self.q
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{decrypt, encrypt, gen_keys_with_digits, rsa_private, PrivateKey, PublicKey};
+    use num_bigint::BigInt;
+
+    const RSA_PRIVATE_PEM: &str = include_str!("../testdata/rsa_private.pem");
+
+    #[test]
+    fn crt_agrees_with_the_full_exponentiation() {
+        let pub_key = PublicKey::from_pem(RSA_PRIVATE_PEM).unwrap();
+        let priv_key = PrivateKey::from_pem(RSA_PRIVATE_PEM).unwrap();
+        assert!(priv_key.has_crt());
+        let plain = PrivateKey::new(priv_key.exponent().clone());
+        assert!(!plain.has_crt());
+        for x in [0, 1, 2, 65537, 123456789] {
+            let x = BigInt::from(x);
+            assert_eq!(
+                rsa_private(&pub_key, &priv_key, &x),
+                rsa_private(&pub_key, &plain, &x)
+            );
+        }
+        let n_minus_1 = &pub_key.n - 1;
+        assert_eq!(
+            rsa_private(&pub_key, &priv_key, &n_minus_1),
+            rsa_private(&pub_key, &plain, &n_minus_1)
+        );
+    }
+
+    #[test]
+    fn generated_keys_decrypt_by_crt() {
+        let (pub_key, priv_key) = gen_keys_with_digits(30);
+        assert!(priv_key.has_crt());
+        let (p, q) = priv_key.primes().unwrap();
+        assert_eq!(p * q, pub_key.n);
+        let m: crate::Plaintext = BigInt::from(4242).into();
+        assert_eq!(
+            decrypt(&pub_key, &priv_key, encrypt(&pub_key, m.clone()).unwrap()).unwrap(),
+            m
+        );
+    }
+}
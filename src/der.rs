@@ -0,0 +1,230 @@
+// Just enough DER (X.690) to read and write RSA key structures
+//
+// Every value is a tag byte, a length and that many content bytes. Only the handful of
+// universal types that RSA keys use are named here; anything else is reported by number.
+use crate::error::{ParseError, ParseErrorKind};
+use num_bigint::BigInt;
+
+pub(crate) const INTEGER: u8 = 0x02;
+pub(crate) const BIT_STRING: u8 = 0x03;
+pub(crate) const OCTET_STRING: u8 = 0x04;
+pub(crate) const NULL: u8 = 0x05;
+pub(crate) const OID: u8 = 0x06;
+pub(crate) const SEQUENCE: u8 = 0x30;
+
+pub(crate) fn tag_name(tag: u8) -> String {
+    let name = match tag {
+        INTEGER => "INTEGER",
+        BIT_STRING => "BIT STRING",
+        OCTET_STRING => "OCTET STRING",
+        NULL => "NULL",
+        OID => "OBJECT IDENTIFIER",
+        SEQUENCE => "SEQUENCE",
+        _ => return format!("tag 0x{:02x}", tag),
+    };
+    format!("{} (0x{:02x})", name, tag)
+}
+
+// encode_oid: the content bytes of an OBJECT IDENTIFIER, base-128 arcs after the first two
+pub(crate) fn encode_oid(arcs: &[u64]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let first = arcs[0] * 40 + arcs[1];
+    for &arc in std::iter::once(&first).chain(&arcs[2..]) {
+        let mut groups = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            groups.push((rest & 0x7f) as u8 | 0x80);
+            rest >>= 7;
+        }
+        out.extend(groups.iter().rev());
+    }
+    out
+}
+
+// encode: one value, tag then definite length then content
+pub(crate) fn encode(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    if content.len() < 0x80 {
+        out.push(content.len() as u8);
+    } else {
+        let len = content.len().to_be_bytes();
+        let skip = len.iter().take_while(|&&b| b == 0).count();
+        out.push(0x80 | (len.len() - skip) as u8);
+        out.extend_from_slice(&len[skip..]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+pub(crate) fn integer(x: &BigInt) -> Vec<u8> {
+    encode(INTEGER, &x.to_signed_bytes_be())
+}
+
+pub(crate) fn sequence(items: &[Vec<u8>]) -> Vec<u8> {
+    encode(SEQUENCE, &items.concat())
+}
+
+// Reader: errors name the format and give offsets from the start of the outermost value
+pub(crate) struct Reader<'a> {
+    buf: &'a [u8],
+    offset: usize,
+    format: &'a str,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(buf: &'a [u8], format: &'a str) -> Self {
+        Reader {
+            buf,
+            offset: 0,
+            format,
+        }
+    }
+
+    fn invalid(&self, reason: impl Into<String>) -> ParseError {
+        ParseError::invalid(self.format, self.offset, reason)
+    }
+
+    pub(crate) fn peek_tag(&self) -> Option<u8> {
+        self.buf.first().copied()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    // read: the next value's tag, content, and the offset of the content
+    fn read(&mut self) -> Result<(u8, &'a [u8], usize), ParseError> {
+        let truncated = |needed, available| {
+            ParseError::new(
+                self.format,
+                self.offset,
+                ParseErrorKind::Truncated { needed, available },
+            )
+        };
+        if self.buf.len() < 2 {
+            return Err(truncated(2, self.buf.len()));
+        }
+        let tag = self.buf[0];
+        let (len, header) = match self.buf[1] {
+            short @ 0..=0x7f => (short as usize, 2),
+            0x80 => return Err(self.invalid("indefinite lengths are not allowed in DER")),
+            long => {
+                let count = (long & 0x7f) as usize;
+                if count > 4 {
+                    return Err(self.invalid(format!("a {}-byte length is too long", count)));
+                }
+                if self.buf.len() < 2 + count {
+                    return Err(truncated(2 + count, self.buf.len()));
+                }
+                let len = self.buf[2..2 + count]
+                    .iter()
+                    .fold(0usize, |acc, &b| (acc << 8) | b as usize);
+                (len, 2 + count)
+            }
+        };
+        if self.buf.len() - header < len {
+            return Err(truncated(header + len, self.buf.len()));
+        }
+        let content = &self.buf[header..header + len];
+        let content_offset = self.offset + header;
+        self.buf = &self.buf[header + len..];
+        self.offset += header + len;
+        Ok((tag, content, content_offset))
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(&'a [u8], usize), ParseError> {
+        match self.peek_tag() {
+            Some(tag) if tag != expected => Err(ParseError::new(
+                self.format,
+                self.offset,
+                ParseErrorKind::UnexpectedTag {
+                    expected: tag_name(expected),
+                    actual: tag_name(tag),
+                },
+            )),
+            _ => {
+                let (_, content, offset) = self.read()?;
+                Ok((content, offset))
+            }
+        }
+    }
+
+    // sequence: a reader over the contents of the next SEQUENCE
+    pub(crate) fn sequence(&mut self) -> Result<Reader<'a>, ParseError> {
+        let (content, offset) = self.expect(SEQUENCE)?;
+        Ok(Reader {
+            buf: content,
+            offset,
+            format: self.format,
+        })
+    }
+
+    pub(crate) fn integer(&mut self) -> Result<BigInt, ParseError> {
+        let (content, _) = self.expect(INTEGER)?;
+        if content.is_empty() {
+            return Err(self.invalid("an INTEGER needs at least one content byte"));
+        }
+        Ok(BigInt::from_signed_bytes_be(content))
+    }
+
+    pub(crate) fn octet_string(&mut self) -> Result<&'a [u8], ParseError> {
+        Ok(self.expect(OCTET_STRING)?.0)
+    }
+
+    // bit_string: the contents of a BIT STRING, which for keys is always whole bytes
+    pub(crate) fn bit_string(&mut self) -> Result<&'a [u8], ParseError> {
+        let (content, _) = self.expect(BIT_STRING)?;
+        match content.split_first() {
+            Some((0, bytes)) => Ok(bytes),
+            _ => Err(self.invalid("expected a BIT STRING of whole bytes")),
+        }
+    }
+
+    pub(crate) fn null(&mut self) -> Result<(), ParseError> {
+        let (content, _) = self.expect(NULL)?;
+        if !content.is_empty() {
+            return Err(self.invalid("NULL must be empty"));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn oid(&mut self) -> Result<Vec<u64>, ParseError> {
+        let (content, _) = self.expect(OID)?;
+        let mut values = Vec::new();
+        let mut acc = 0u64;
+        for &byte in content {
+            if acc >> 57 != 0 {
+                return Err(self.invalid("an OID arc does not fit in 64 bits"));
+            }
+            acc = (acc << 7) | (byte & 0x7f) as u64;
+            if byte & 0x80 == 0 {
+                values.push(acc);
+                acc = 0;
+            }
+        }
+        if content.last().is_none_or(|b| b & 0x80 != 0) {
+            return Err(self.invalid("an OID must end with a complete arc"));
+        }
+        let first = values[0];
+        let (a, b) = if first < 80 {
+            (first / 40, first % 40)
+        } else {
+            (2, first - 80)
+        };
+        Ok([vec![a, b], values[1..].to_vec()].concat())
+    }
+
+    pub(crate) fn finish(self) -> Result<(), ParseError> {
+        if self.buf.is_empty() {
+            Ok(())
+        } else {
+            Err(ParseError::new(
+                self.format,
+                self.offset,
+                ParseErrorKind::TrailingBytes {
+                    count: self.buf.len(),
+                },
+            ))
+        }
+    }
+}
@@ -0,0 +1,257 @@
+// Looking up recipients' public keys by identity
+//
+// Schemes that encrypt to someone need their public key, and where it comes from (a
+// config file, LDAP, a key server, a pinned set shipped with the application) is the
+// application's business. KeyDirectory is the seam: lookup by identity returns the key and
+// whatever metadata the directory keeps about it, such as an expiry date or a contact
+// address. The crate uses it in the handshake and in multi-recipient encryption, and
+// ships two directories: MemoryDirectory, filled in code, and FileDirectory, a text file
+// of "identity base64-DER-key [name=value ...]" lines that is read again on every lookup,
+// so that edits to it apply without restarting.
+use crate::error::ParseError;
+use crate::{base64, PublicKey};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirectoryEntry {
+    pub key: PublicKey,
+    pub metadata: BTreeMap<String, String>,
+}
+
+#[derive(Debug)]
+pub enum DirectoryError {
+    // No key is listed for the identity
+    NotFound(String),
+    // The directory could not be read or its contents did not parse
+    Unavailable(io::Error),
+}
+
+pub trait KeyDirectory {
+    fn lookup(&self, identity: &str) -> Result<DirectoryEntry, DirectoryError>;
+}
+
+impl DirectoryEntry {
+    pub fn new(key: PublicKey) -> Self {
+        DirectoryEntry {
+            key,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    // with: the entry with one more metadata field, replacing any of the same name
+    pub fn with(mut self, name: &str, value: &str) -> Self {
+        check_token("metadata name", name);
+        if name.contains('=') {
+            panic!("metadata name {:?} must not contain '='", name);
+        }
+        check_token("metadata value", value);
+        self.metadata.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.metadata.get(name).map(String::as_str)
+    }
+}
+
+fn check_token(what: &str, token: &str) {
+    if token.is_empty() || token.contains(char::is_whitespace) || token.contains('#') {
+        panic!(
+            "{} {:?} must be non-empty and free of whitespace and '#'",
+            what, token
+        );
+    }
+}
+
+impl fmt::Display for DirectoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DirectoryError::NotFound(identity) => write!(f, "no key listed for {:?}", identity),
+            DirectoryError::Unavailable(err) => write!(f, "key directory unavailable: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for DirectoryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DirectoryError::NotFound(_) => None,
+            DirectoryError::Unavailable(err) => Some(err),
+        }
+    }
+}
+
+// MemoryDirectory: entries added in code, e.g. from the application's own configuration
+#[derive(Clone, Debug, Default)]
+pub struct MemoryDirectory {
+    entries: HashMap<String, DirectoryEntry>,
+}
+
+impl MemoryDirectory {
+    pub fn new() -> Self {
+        MemoryDirectory::default()
+    }
+
+    // insert: list entry for identity, replacing any earlier one
+    pub fn insert(&mut self, identity: &str, entry: DirectoryEntry) -> &mut Self {
+        check_token("identity", identity);
+        self.entries.insert(identity.to_string(), entry);
+        self
+    }
+
+    pub fn remove(&mut self, identity: &str) -> bool {
+        self.entries.remove(identity).is_some()
+    }
+}
+
+impl KeyDirectory for MemoryDirectory {
+    fn lookup(&self, identity: &str) -> Result<DirectoryEntry, DirectoryError> {
+        self.entries
+            .get(identity)
+            .cloned()
+            .ok_or_else(|| DirectoryError::NotFound(identity.to_string()))
+    }
+}
+
+// FileDirectory: the entries in a text file, one per line; blank lines and # comments are
+// skipped, and a later line for an identity overrides an earlier one
+#[derive(Clone, Debug)]
+pub struct FileDirectory {
+    path: PathBuf,
+}
+
+impl FileDirectory {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        FileDirectory {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    // parse: every entry in the text of a directory file
+    pub fn parse(text: &str) -> Result<Vec<(String, DirectoryEntry)>, ParseError> {
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            let content = line.split('#').next().unwrap_or("").trim();
+            if !content.is_empty() {
+                entries.push(parse_entry(content, offset)?);
+            }
+            offset += line.len();
+        }
+        Ok(entries)
+    }
+
+    // format: the line parse reads back as identity and entry
+    pub fn format(identity: &str, entry: &DirectoryEntry) -> String {
+        check_token("identity", identity);
+        let mut line = format!(
+            "{} {}",
+            identity,
+            base64::encode(&entry.key.to_spki_der(), true)
+        );
+        for (name, value) in &entry.metadata {
+            line.push_str(&format!(" {}={}", name, value));
+        }
+        line
+    }
+}
+
+fn parse_entry(line: &str, offset: usize) -> Result<(String, DirectoryEntry), ParseError> {
+    const FORMAT: &str = "key directory";
+    let mut fields = line.split_whitespace();
+    let (identity, encoded) = match (fields.next(), fields.next()) {
+        (Some(identity), Some(encoded)) => (identity, encoded),
+        _ => {
+            return Err(ParseError::invalid(
+                FORMAT,
+                offset,
+                "expected \"identity key [name=value ...]\"",
+            ))
+        }
+    };
+    let der = base64::decode(encoded)
+        .ok_or_else(|| ParseError::invalid(FORMAT, offset, "the key is not base64"))?;
+    let mut entry = DirectoryEntry::new(PublicKey::from_der(&der)?);
+    for field in fields {
+        let (name, value) = field
+            .split_once('=')
+            .filter(|(name, value)| !name.is_empty() && !value.is_empty())
+            .ok_or_else(|| ParseError::invalid(FORMAT, offset, "expected name=value"))?;
+        entry.metadata.insert(name.to_string(), value.to_string());
+    }
+    Ok((identity.to_string(), entry))
+}
+
+impl KeyDirectory for FileDirectory {
+    fn lookup(&self, identity: &str) -> Result<DirectoryEntry, DirectoryError> {
+        let text = std::fs::read_to_string(&self.path).map_err(DirectoryError::Unavailable)?;
+        let entries = FileDirectory::parse(&text).map_err(|e| {
+            DirectoryError::Unavailable(io::Error::new(io::ErrorKind::InvalidData, e))
+        })?;
+        entries
+            .into_iter()
+            .rev()
+            .find(|(listed, _)| listed == identity)
+            .map(|(_, entry)| entry)
+            .ok_or_else(|| DirectoryError::NotFound(identity.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn memory_directory_finds_what_was_inserted() {
+        let (alice, _) = gen_keys_with_digits(20);
+        let mut directory = MemoryDirectory::new();
+        directory.insert(
+            "alice",
+            DirectoryEntry::new(alice.clone()).with("expires", "2030-01-01"),
+        );
+        let entry = directory.lookup("alice").unwrap();
+        assert_eq!(entry.key, alice);
+        assert_eq!(entry.get("expires"), Some("2030-01-01"));
+        assert!(matches!(
+            directory.lookup("bob"),
+            Err(DirectoryError::NotFound(identity)) if identity == "bob"
+        ));
+        assert!(directory.remove("alice"));
+        assert!(directory.lookup("alice").is_err());
+    }
+
+    #[test]
+    fn file_directory_reads_the_file_on_every_lookup() {
+        let (alice, _) = gen_keys_with_digits(20);
+        let (rotated, _) = gen_keys_with_digits(20);
+        let path = std::env::temp_dir().join(format!("naive-rsa-dir-{}", std::process::id()));
+        let directory = FileDirectory::new(&path);
+        assert!(matches!(
+            directory.lookup("alice"),
+            Err(DirectoryError::Unavailable(_))
+        ));
+        let first = DirectoryEntry::new(alice).with("owner", "alice@example.com");
+        let text = format!("# team keys\n{}\n", FileDirectory::format("alice", &first));
+        std::fs::write(&path, &text).unwrap();
+        assert_eq!(directory.lookup("alice").unwrap(), first);
+        let second = DirectoryEntry::new(rotated);
+        let text = format!("{}{}\n", text, FileDirectory::format("alice", &second));
+        std::fs::write(&path, text).unwrap();
+        assert_eq!(directory.lookup("alice").unwrap(), second);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn malformed_lines_report_their_offset() {
+        let err = FileDirectory::parse("\nalice !!!\n").unwrap_err();
+        assert_eq!(err.offset(), 1);
+        let (alice, _) = gen_keys_with_digits(20);
+        let line = FileDirectory::format("alice", &DirectoryEntry::new(alice));
+        let err = FileDirectory::parse(&format!("{} expires\n", line)).unwrap_err();
+        assert_eq!(err.offset(), 0);
+    }
+}
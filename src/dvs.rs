@@ -0,0 +1,209 @@
+// Designated-verifier signatures
+//
+// A designated-verifier signature on m is a non-interactive OR-proof: "I know the RSA
+// signature H(m)^d_S mod n_S, or I know the verifier's e_V-th root of H(m) mod n_V". It is
+// built from the Guillou-Quisquater rounds of plaintext_proof: the known branch is proven
+// honestly and the other one is simulated, with the two challenges XOR-ing to the hashed
+// one. The designated verifier knows they did not produce it, so it convinces them; anyone
+// else sees a proof the verifier could have made with their own private key.
+// https://en.wikipedia.org/wiki/Designated_verifier_signature
+use crate::error::ParseError;
+use crate::hash::{self, TranscriptHash};
+use crate::plaintext_proof::{challenge_bits, split_challenges, SECURITY_BITS};
+use crate::wire::{Reader, Writer};
+use crate::{invert, random_unit, rsa_private, PrivateKey, PublicKey};
+use num_bigint::{BigInt, RandBigInt};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DvSignature {
+    // Per round: commitments and responses for the signer (0) and verifier (1) branches,
+    // plus the signer branch challenge; the verifier's is implied by the hash
+    t: [Vec<BigInt>; 2],
+    z: [Vec<BigInt>; 2],
+    ch: Vec<BigInt>,
+}
+
+// message_point: the value whose e-th root is the ordinary RSA-FDH signature under key
+fn message_point(key: &PublicKey, m: &[u8]) -> BigInt {
+    let seed = TranscriptHash::new("naive-rsa dvs message")
+        .int(&key.n)
+        .int(&key.e)
+        .bytes(m)
+        .finish();
+    hash::full_domain(&seed, &key.n)
+}
+
+fn params(keys: [&PublicKey; 2]) -> (u64, usize) {
+    let b = challenge_bits(&keys[0].e).min(challenge_bits(&keys[1].e));
+    (b, SECURITY_BITS.div_ceil(b) as usize)
+}
+
+fn challenges(keys: [&PublicKey; 2], t: &[Vec<BigInt>; 2], m: &[u8]) -> Vec<BigInt> {
+    let (b, rounds) = params(keys);
+    let mut h = TranscriptHash::new("naive-rsa dvs");
+    for key in keys {
+        h.int(&key.n).int(&key.e);
+    }
+    for t in t.iter().flatten() {
+        h.int(t);
+    }
+    h.bytes(m);
+    split_challenges(&h.finish(), rounds, b)
+}
+
+// prove: OR-proof where the e-th root x for branch `known` is available
+fn prove(keys: [&PublicKey; 2], known: usize, x: &BigInt, m: &[u8]) -> DvSignature {
+    let other = 1 - known;
+    let (b, rounds) = params(keys);
+    let y_other = message_point(keys[other], m);
+    let (nk, ek) = (&keys[known].n, &keys[known].e);
+    let (no, eo) = (&keys[other].n, &keys[other].e);
+    let mut rng = rand::thread_rng();
+    let mut t: [Vec<BigInt>; 2] = Default::default();
+    let mut z: [Vec<BigInt>; 2] = Default::default();
+    let mut r = Vec::with_capacity(rounds);
+    let mut ch_other = Vec::with_capacity(rounds);
+    for _ in 0..rounds {
+        // Real commitment for the known branch
+        let ri = random_unit(nk);
+        t[known].push(ri.modpow(ek, nk));
+        r.push(ri);
+        // Simulated transcript for the other branch: pick the answer, solve for t
+        let ch = rng.gen_bigint_range(&BigInt::from(0), &(BigInt::from(1) << b));
+        let zi = random_unit(no);
+        let y_inv = invert(y_other.modpow(&ch, no), no.clone());
+        t[other].push(zi.modpow(eo, no) * y_inv % no);
+        z[other].push(zi);
+        ch_other.push(ch);
+    }
+    let ch_known: Vec<BigInt> = challenges(keys, &t, m)
+        .iter()
+        .zip(&ch_other)
+        .map(|(ch, cho)| ch ^ cho)
+        .collect();
+    z[known] = r
+        .iter()
+        .zip(&ch_known)
+        .map(|(r, ch)| r * x.modpow(ch, nk) % nk)
+        .collect();
+    let ch = if known == 0 { ch_known } else { ch_other };
+    DvSignature { t, z, ch }
+}
+
+// sign: a signature on m that only the holder of verifier_pub's private key is convinced by
+pub fn sign(
+    signer_pub: &PublicKey,
+    signer_priv: &PrivateKey,
+    verifier_pub: &PublicKey,
+    m: &[u8],
+) -> DvSignature {
+    let x = rsa_private(signer_pub, signer_priv, &message_point(signer_pub, m));
+    prove([signer_pub, verifier_pub], 0, &x, m)
+}
+
+// simulate: what the designated verifier can produce on their own, indistinguishable
+// from a real signature to third parties; this is why the signature is non-transferable
+pub fn simulate(
+    signer_pub: &PublicKey,
+    verifier_pub: &PublicKey,
+    verifier_priv: &PrivateKey,
+    m: &[u8],
+) -> DvSignature {
+    let x = rsa_private(verifier_pub, verifier_priv, &message_point(verifier_pub, m));
+    prove([signer_pub, verifier_pub], 1, &x, m)
+}
+
+pub fn verify(
+    signer_pub: &PublicKey,
+    verifier_pub: &PublicKey,
+    m: &[u8],
+    sig: &DvSignature,
+) -> bool {
+    let keys = [signer_pub, verifier_pub];
+    let (_, rounds) = params(keys);
+    if sig.ch.len() != rounds
+        || sig.t.iter().chain(&sig.z).any(|v| v.len() != rounds)
+        || sig.t.iter().flatten().any(|t| *t <= BigInt::from(0))
+    {
+        return false;
+    }
+    let ys = [message_point(signer_pub, m), message_point(verifier_pub, m)];
+    challenges(keys, &sig.t, m)
+        .iter()
+        .enumerate()
+        .all(|(i, ch)| {
+            let chs = [sig.ch[i].clone(), ch ^ &sig.ch[i]];
+            (0..2).all(|j| {
+                let (n, e) = (&keys[j].n, &keys[j].e);
+                sig.z[j][i].modpow(e, n) == &sig.t[j][i] * ys[j].modpow(&chs[j], n) % n
+            })
+        })
+}
+
+impl DvSignature {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::new("naive-rsa dv signature");
+        w.bytes(&(self.ch.len() as u32).to_be_bytes());
+        for i in 0..self.ch.len() {
+            w.int(&self.t[0][i])
+                .int(&self.t[1][i])
+                .int(&self.z[0][i])
+                .int(&self.z[1][i])
+                .int(&self.ch[i]);
+        }
+        w.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut r = Reader::new(bytes, "naive-rsa dv signature")?;
+        let rounds = r.count()?;
+        let mut sig = DvSignature {
+            t: Default::default(),
+            z: Default::default(),
+            ch: Vec::new(),
+        };
+        for _ in 0..rounds {
+            sig.t[0].push(r.int()?);
+            sig.t[1].push(r.int()?);
+            sig.z[0].push(r.int()?);
+            sig.z[1].push(r.int()?);
+            sig.ch.push(r.int()?);
+        }
+        r.finish()?;
+        Ok(sig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn designated_verifier_accepts_signature() {
+        let (alice_pub, alice_priv) = gen_keys_with_digits(20);
+        let (bob_pub, _) = gen_keys_with_digits(20);
+        let sig = sign(&alice_pub, &alice_priv, &bob_pub, b"meet at noon");
+        assert!(verify(&alice_pub, &bob_pub, b"meet at noon", &sig));
+        assert!(!verify(&alice_pub, &bob_pub, b"meet at one", &sig));
+        let decoded = DvSignature::from_bytes(&sig.to_bytes()).unwrap();
+        assert_eq!(decoded, sig);
+    }
+
+    #[test]
+    fn verifier_can_simulate_a_signature() {
+        let (alice_pub, _) = gen_keys_with_digits(20);
+        let (bob_pub, bob_priv) = gen_keys_with_digits(20);
+        let fake = simulate(&alice_pub, &bob_pub, &bob_priv, b"alice owes bob");
+        assert!(verify(&alice_pub, &bob_pub, b"alice owes bob", &fake));
+    }
+
+    #[test]
+    fn signature_is_bound_to_the_verifier() {
+        let (alice_pub, alice_priv) = gen_keys_with_digits(20);
+        let (bob_pub, _) = gen_keys_with_digits(20);
+        let (carol_pub, _) = gen_keys_with_digits(20);
+        let sig = sign(&alice_pub, &alice_priv, &bob_pub, b"hi");
+        assert!(!verify(&alice_pub, &carol_pub, b"hi", &sig));
+    }
+}
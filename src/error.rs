@@ -0,0 +1,204 @@
+// Errors for decoding serialized values, and for the core RSA operations
+//
+// A ParseError says which format was being read, the byte offset where reading stopped and
+// what was wrong there, so that a rejected key file or signature can be traced to the
+// exact field instead of a bare "invalid input". RsaError is what the core API returns
+// instead of panicking or computing garbage: an input outside the range of the key, a key
+// that does not fit together, numbers without an inverse, a padding or parsing failure, an
+// argument out of range. Conveniences meant for arguments written into the source (bi!,
+// gen_keys_with_digits, random_prime, i2osp, jacobi, KeyPair::with_primes) still panic on
+// bad ones; parse_int and the try_ variants are their fallible forms.
+use crate::padding::PaddingError;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    // The input announced a different type, e.g. a signature where a key was expected
+    UnexpectedTag { expected: String, actual: String },
+    // The input ended while a field still needed more bytes
+    Truncated { needed: usize, available: usize },
+    TrailingBytes { count: usize },
+    // A field was complete but its contents are not acceptable
+    Invalid(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    format: String,
+    offset: usize,
+    kind: ParseErrorKind,
+}
+
+impl ParseError {
+    pub fn new(format: &str, offset: usize, kind: ParseErrorKind) -> Self {
+        ParseError {
+            format: format.to_string(),
+            offset,
+            kind,
+        }
+    }
+
+    pub(crate) fn invalid(format: &str, offset: usize, reason: impl Into<String>) -> Self {
+        ParseError::new(format, offset, ParseErrorKind::Invalid(reason.into()))
+    }
+
+    // format: the name of the format being decoded, e.g. "naive-rsa ring signature"
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+
+    // offset: where in the input the problem was found, in bytes from the start
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnexpectedTag { expected, actual } => {
+                write!(f, "expected {}, found {}", expected, actual)
+            }
+            ParseErrorKind::Truncated { needed, available } => write!(
+                f,
+                "input ends early, {} bytes needed but only {} left",
+                needed, available
+            ),
+            ParseErrorKind::TrailingBytes { count } => {
+                write!(f, "{} unexpected bytes after the end", count)
+            }
+            ParseErrorKind::Invalid(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid {}: {} (at byte {})",
+            self.format, self.kind, self.offset
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RsaError {
+    // The key cannot be used as it is, e.g. its primes do not multiply to the modulus
+    InvalidKey(&'static str),
+    // A plaintext that is negative or not below the modulus
+    MessageTooLarge,
+    // A ciphertext that is negative or not below the modulus
+    CiphertextOutOfRange,
+    Padding(PaddingError),
+    Parse(ParseError),
+    // An inverse was needed of a value that shares a factor with the modulus
+    NotCoprime,
+    // Key generation gave up under the constraints it was given
+    KeyGeneration,
+    // An argument outside what the function accepts, e.g. a prime of fewer than 8 bits
+    InvalidArgument(&'static str),
+}
+
+pub type Result<T> = std::result::Result<T, RsaError>;
+
+impl fmt::Display for RsaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RsaError::InvalidKey(reason) => write!(f, "invalid key: {}", reason),
+            RsaError::MessageTooLarge => write!(f, "the message is not in the range 0..n"),
+            RsaError::CiphertextOutOfRange => {
+                write!(f, "the ciphertext is not in the range 0..n")
+            }
+            RsaError::Padding(err) => err.fmt(f),
+            RsaError::Parse(err) => err.fmt(f),
+            RsaError::NotCoprime => write!(f, "the value has no inverse modulo m"),
+            RsaError::KeyGeneration => {
+                write!(f, "no key pair met the key generation constraints")
+            }
+            RsaError::InvalidArgument(reason) => write!(f, "invalid argument: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for RsaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RsaError::Padding(err) => Some(err),
+            RsaError::Parse(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseError> for RsaError {
+    fn from(err: ParseError) -> Self {
+        RsaError::Parse(err)
+    }
+}
+
+impl From<PaddingError> for RsaError {
+    fn from(err: PaddingError) -> Self {
+        RsaError::Padding(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commitment::{commit, Commitment, CommitmentParams};
+    use crate::{decrypt, encrypt, gen_keys_with_digits, mod_inverse, parse_int};
+    use num_bigint::BigInt;
+
+    #[test]
+    fn errors_locate_the_problem() {
+        let params = CommitmentParams::setup(20);
+        let (c, opening) = commit(&params, &BigInt::from(1));
+        let bytes = c.to_bytes();
+        let err = Commitment::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(err.format(), "naive-rsa commitment");
+        assert_eq!(err.offset(), 24);
+        assert!(matches!(err.kind(), ParseErrorKind::Truncated { .. }));
+        let err = Commitment::from_bytes(&[&bytes[..], &[0]].concat()).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::TrailingBytes { count: 1 });
+        let err = Commitment::from_bytes(&opening.to_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid naive-rsa commitment: expected naive-rsa commitment, found naive-rsa opening (at byte 0)"
+        );
+    }
+
+    #[test]
+    fn core_operations_return_errors() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let n = parse_int(&pub_key.n.to_string(), 10).unwrap();
+        assert_eq!(
+            encrypt(&pub_key, n.clone().into()),
+            Err(RsaError::MessageTooLarge)
+        );
+        assert_eq!(
+            decrypt(&pub_key, &priv_key, (-n).into()),
+            Err(RsaError::CiphertextOutOfRange)
+        );
+        assert_eq!(
+            mod_inverse(BigInt::from(6), BigInt::from(9)),
+            Err(RsaError::NotCoprime)
+        );
+        let err = parse_int("12x4", 10).unwrap_err();
+        assert!(matches!(err, RsaError::Parse(_)));
+        assert!(std::error::Error::source(&err).is_some());
+        let (other_pub, _) = gen_keys_with_digits(20);
+        assert_eq!(
+            decrypt(&other_pub, &priv_key, BigInt::from(2).into()),
+            Err(RsaError::InvalidKey(
+                "the private key's primes belong to another modulus"
+            ))
+        );
+    }
+}
@@ -0,0 +1,188 @@
+// Alternative private exponents
+//
+// Decryption only needs m^(ed) = m for every m, which holds exactly when
+// e d = 1 mod lambda(n), the Carmichael function lcm(p-1, q-1). So besides the d computed
+// mod phi(n) at key generation there is a minimal exponent e^-1 mod lambda(n), the whole
+// family d + k lambda(n), and the CRT pair (d mod p-1, d mod q-1) that works one prime at a
+// time. These helpers compute and check them.
+use crate::{extended_gcd, factor_out_twos, invert, is_even, PublicKey};
+use num_bigint::{BigInt, RandBigInt};
+use std::fmt;
+
+// Attempts of the randomized factoring in factor_from_exponents; each succeeds with
+// probability at least 1/2
+const FACTORING_ATTEMPTS: usize = 100;
+
+pub fn carmichael(p: &BigInt, q: &BigInt) -> BigInt {
+    let (p1, q1): (BigInt, BigInt) = (p - 1, q - 1);
+    let (gcd, _, _) = extended_gcd(p1.clone(), q1.clone());
+    p1 * q1 / gcd
+}
+
+// crt_exponents: (d mod p-1, d mod q-1), enough to decrypt modulo each prime separately
+pub fn crt_exponents(d: &BigInt, p: &BigInt, q: &BigInt) -> (BigInt, BigInt) {
+    (d % (p - 1), d % (q - 1))
+}
+
+// minimal_exponent: the smallest positive exponent that decrypts, e^-1 mod lambda(n)
+pub fn minimal_exponent(e: &BigInt, p: &BigInt, q: &BigInt) -> BigInt {
+    invert(e.clone(), carmichael(p, q))
+}
+
+// equivalent_exponent: d + k lambda(n), which decrypts exactly like d
+pub fn equivalent_exponent(d: &BigInt, p: &BigInt, q: &BigInt, k: &BigInt) -> BigInt {
+    d + k * carmichael(p, q)
+}
+
+// factor_from_exponents: any working d reveals the factors. e d - 1 is a multiple of
+// lambda(n); writing it as 2^s t, some g^(t 2^i) is a square root of 1 other than +-1,
+// and such a root shares a factor with n.
+pub fn factor_from_exponents(pub_key: &PublicKey, d: &BigInt) -> Option<(BigInt, BigInt)> {
+    let n = &pub_key.n;
+    let one = BigInt::from(1);
+    let k: BigInt = &pub_key.e * d - 1;
+    if k <= BigInt::from(0) || !is_even(k.clone()) {
+        return None;
+    }
+    let (s, t) = factor_out_twos(k);
+    let mut rng = rand::thread_rng();
+    let split = |p: BigInt| {
+        let q = n / &p;
+        if p < q {
+            (p, q)
+        } else {
+            (q, p)
+        }
+    };
+    for _ in 0..FACTORING_ATTEMPTS {
+        let g = rng.gen_bigint_range(&BigInt::from(2), &(n - 1));
+        // A base sharing a factor with n never squares to 1, but gives the factor directly
+        let (common, _, _) = extended_gcd(g.clone(), n.clone());
+        if common != one {
+            return Some(split(common));
+        }
+        let mut x = g.modpow(&t, n);
+        for _ in 0..s {
+            if x == one || x == n - 1 {
+                break;
+            }
+            let y = &x * &x % n;
+            if y == one {
+                let (p, _, _) = extended_gcd(&x - 1, n.clone());
+                return Some(split(p));
+            }
+            x = y;
+        }
+    }
+    None
+}
+
+// check_exponent: a black-box test that a candidate decrypts random messages correctly
+pub fn check_exponent(pub_key: &PublicKey, candidate: &BigInt, samples: usize) -> bool {
+    let mut rng = rand::thread_rng();
+    (0..samples).all(|_| {
+        let m = rng.gen_bigint_range(&BigInt::from(2), &pub_key.n);
+        m.modpow(&pub_key.e, &pub_key.n)
+            .modpow(candidate, &pub_key.n)
+            == m
+    })
+}
+
+pub struct ExponentReport {
+    pub candidate: BigInt,
+    pub lambda: BigInt,
+    // The candidate reduced mod lambda(n); exponents that agree here decrypt identically
+    pub reduced: BigInt,
+    pub crt: (BigInt, BigInt),
+    // e * candidate mod lambda(n), which must be 1 for the candidate to decrypt
+    pub residue: BigInt,
+    pub valid: bool,
+}
+
+// explain_exponent: exactly why a candidate does or does not decrypt, given the factors
+pub fn explain_exponent(
+    pub_key: &PublicKey,
+    p: &BigInt,
+    q: &BigInt,
+    candidate: &BigInt,
+) -> ExponentReport {
+    let lambda = carmichael(p, q);
+    let reduced = ((candidate % &lambda) + &lambda) % &lambda;
+    let residue = &pub_key.e * &reduced % &lambda;
+    ExponentReport {
+        candidate: candidate.clone(),
+        crt: crt_exponents(&reduced, p, q),
+        valid: residue == BigInt::from(1),
+        lambda,
+        reduced,
+        residue,
+    }
+}
+
+impl fmt::Display for ExponentReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "candidate exponent: {}", self.candidate)?;
+        writeln!(f, "lambda(n) = lcm(p-1, q-1) = {}", self.lambda)?;
+        writeln!(f, "candidate mod lambda(n) = {}", self.reduced)?;
+        writeln!(f, "CRT exponents: dP = {}, dQ = {}", self.crt.0, self.crt.1)?;
+        writeln!(f, "e * candidate mod lambda(n) = {}", self.residue)?;
+        if self.valid {
+            write!(
+                f,
+                "valid: e d = 1 mod lambda(n), so (m^e)^d = m for every m"
+            )
+        } else {
+            write!(
+                f,
+                "invalid: e d != 1 mod lambda(n), so decryption fails for most m"
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+
+    // The textbook example: p = 61, q = 53, e = 17, d = 2753
+    fn textbook() -> (PublicKey, BigInt, BigInt, BigInt) {
+        let pub_key = PublicKey {
+            n: BigInt::from(3233),
+            e: BigInt::from(17),
+        };
+        (
+            pub_key,
+            BigInt::from(61),
+            BigInt::from(53),
+            BigInt::from(2753),
+        )
+    }
+
+    #[test]
+    fn alternative_exponents_match_the_textbook() {
+        let (pub_key, p, q, d) = textbook();
+        assert_eq!(carmichael(&p, &q), BigInt::from(780));
+        assert_eq!(minimal_exponent(&pub_key.e, &p, &q), BigInt::from(413));
+        assert_eq!(
+            crt_exponents(&d, &p, &q),
+            (BigInt::from(53), BigInt::from(49))
+        );
+        let d2 = equivalent_exponent(&BigInt::from(413), &p, &q, &BigInt::from(5));
+        assert!(check_exponent(&pub_key, &d2, 20));
+        assert!(explain_exponent(&pub_key, &p, &q, &d2).valid);
+        assert!(!explain_exponent(&pub_key, &p, &q, &BigInt::from(414)).valid);
+    }
+
+    #[test]
+    fn factors_are_recovered_from_d() {
+        let (pub_key, p, q, d) = textbook();
+        assert_eq!(factor_from_exponents(&pub_key, &d), Some((q, p)));
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let (p, q) = factor_from_exponents(&pub_key, priv_key.exponent()).unwrap();
+        assert_eq!(&p * &q, pub_key.n);
+        let minimal = minimal_exponent(&pub_key.e, &p, &q);
+        assert!(minimal <= *priv_key.exponent());
+        assert!(check_exponent(&pub_key, &minimal, 5));
+    }
+}
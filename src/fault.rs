@@ -0,0 +1,102 @@
+// Fault attacks on CRT signing (Boneh, DeMillo and Lipton, 1997)
+//
+// CRT signing computes s_p = m^dP mod p and s_q = m^dQ mod q and recombines them. If a
+// glitch corrupts one half, say s_p, the result is still right mod q but wrong mod p, so
+// s^e - m is a multiple of q and not of p, and gcd(s^e - m, n) = q: one faulty signature
+// on a known message factors n (Lenstra's refinement of the original attack, which needed
+// a correct signature on the same message too). sign_with_fault simulates the glitch by
+// flipping a random bit of one half. The standard countermeasure is to verify each
+// signature before releasing it, which sign_verified does.
+use crate::keypair::KeyPair;
+use crate::{extended_gcd, PublicKey};
+use num_bigint::BigInt;
+use rand::Rng;
+
+// FaultSite: the half-exponentiation the fault strikes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultSite {
+    Mp,
+    Mq,
+}
+
+// crt_sign: m^d mod n by Garner's recombination, with an optional bit flipped in one half
+fn crt_sign(key: &KeyPair, m: &BigInt, fault: Option<FaultSite>) -> BigInt {
+    let crt = key
+        .crt()
+        .unwrap_or_else(|| panic!("CRT signing needs a key pair whose primes are known"));
+    let mut s_p = m.modpow(&crt.dp, &crt.p);
+    let mut s_q = m.modpow(&crt.dq, &crt.q);
+    let mut rng = rand::thread_rng();
+    match fault {
+        Some(FaultSite::Mp) => s_p ^= BigInt::from(1) << rng.gen_range(0..crt.p.bits()),
+        Some(FaultSite::Mq) => s_q ^= BigInt::from(1) << rng.gen_range(0..crt.q.bits()),
+        None => {}
+    }
+    crt.recombine(s_p, &s_q)
+}
+
+pub fn sign_crt(key: &KeyPair, m: &BigInt) -> BigInt {
+    crt_sign(key, m, None)
+}
+
+// sign_with_fault: a CRT signature with one bit flipped in the half named by flip_bit_in
+pub fn sign_with_fault(key: &KeyPair, m: &BigInt, flip_bit_in: FaultSite) -> BigInt {
+    crt_sign(key, m, Some(flip_bit_in))
+}
+
+// sign_verified: sign, possibly with a fault, but release the signature only if it verifies
+pub fn sign_verified(key: &KeyPair, m: &BigInt, fault: Option<FaultSite>) -> Option<BigInt> {
+    let pub_key = key.public();
+    let s = crt_sign(key, m, fault);
+    (s.modpow(&pub_key.e, &pub_key.n) == m % &pub_key.n).then_some(s)
+}
+
+// boneh_demillo_lipton: the factors of n (smaller first) from a faulty signature s on m, or
+// None if s is a correct signature or fails mod both primes
+pub fn boneh_demillo_lipton(
+    pub_key: &PublicKey,
+    m: &BigInt,
+    s: &BigInt,
+) -> Option<(BigInt, BigInt)> {
+    let n = &pub_key.n;
+    let diff = (s.modpow(&pub_key.e, n) - m) % n;
+    let (g, _, _) = extended_gcd(diff, n.clone());
+    let g = g.magnitude().clone().into();
+    if g <= BigInt::from(1) || &g >= n {
+        return None;
+    }
+    let other = n / &g;
+    Some(if g < other { (g, other) } else { (other, g) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_faulty_signature_factors_n() {
+        let key = KeyPair::generate(20);
+        let (p, q) = key.primes().unwrap();
+        let (p, q) = if p < q { (p, q) } else { (q, p) };
+        let m = BigInt::from(123456789);
+        assert_eq!(
+            boneh_demillo_lipton(key.public(), &m, &sign_crt(&key, &m)),
+            None
+        );
+        for site in [FaultSite::Mp, FaultSite::Mq] {
+            let s = sign_with_fault(&key, &m, site);
+            let factors = boneh_demillo_lipton(key.public(), &m, &s);
+            assert_eq!(factors, Some((p.clone(), q.clone())));
+        }
+    }
+
+    #[test]
+    fn verifying_before_release_withholds_faulty_signatures() {
+        let key = KeyPair::generate(20);
+        let m = BigInt::from(42);
+        let pub_key = key.public();
+        let s = sign_verified(&key, &m, None).unwrap();
+        assert_eq!(s.modpow(&pub_key.e, &pub_key.n), m);
+        assert_eq!(sign_verified(&key, &m, Some(FaultSite::Mq)), None);
+    }
+}
@@ -0,0 +1,50 @@
+// Full-domain hash signatures (Bellare and Rogaway, 1996)
+//
+// The message is hashed onto the whole domain of a trapdoor permutation and the signature
+// is the preimage of that point, s = f^-1(H(m)); verification checks f(s) = H(m). Hashing
+// to the full domain, rather than to a short digest, is what makes the scheme provably as
+// hard to forge as f is to invert, in the random oracle model. The hash binds the
+// permutation too, so a signature is only valid under the key that made it.
+use crate::hash::{self, TranscriptHash};
+use crate::trapdoor::TrapdoorPermutation;
+use num_bigint::BigInt;
+
+// message_point: H(m), the point whose preimage signs m under perm
+pub fn message_point<P: TrapdoorPermutation>(perm: &P, m: &[u8]) -> BigInt {
+    let seed = TranscriptHash::new("naive-rsa fdh")
+        .bytes(&perm.encode())
+        .bytes(m)
+        .finish();
+    hash::full_domain(&seed, perm.domain())
+}
+
+pub fn sign<P: TrapdoorPermutation>(perm: &P, trapdoor: &P::Trapdoor, m: &[u8]) -> BigInt {
+    perm.inverse(trapdoor, &message_point(perm, m))
+}
+
+pub fn verify<P: TrapdoorPermutation>(perm: &P, m: &[u8], s: &BigInt) -> bool {
+    *s >= BigInt::from(0) && s < perm.domain() && perm.forward(s) == message_point(perm, m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn signatures_verify_under_their_key_only() {
+        let (alice_pub, alice_priv) = gen_keys_with_digits(20);
+        let (bob_pub, _) = gen_keys_with_digits(20);
+        let s = sign(&alice_pub, &alice_priv, b"pay bob 10");
+        assert!(verify(&alice_pub, b"pay bob 10", &s));
+        assert!(!verify(&alice_pub, b"pay bob 100", &s));
+        assert!(!verify(&bob_pub, b"pay bob 10", &s));
+    }
+
+    #[test]
+    fn out_of_range_signatures_are_rejected() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let s = sign(&pub_key, &priv_key, b"m");
+        assert!(!verify(&pub_key, b"m", &(s + pub_key.domain())));
+    }
+}
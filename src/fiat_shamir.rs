@@ -0,0 +1,286 @@
+// Feige-Fiat-Shamir identification
+//
+// The prover holds k secret square roots s_i mod an RSA modulus n and publishes v_i = s_i^2.
+// Each round it commits to x = r^2, receives k challenge bits e_i and answers with
+// y = r * prod(s_i^e_i); the verifier checks y^2 = x * prod(v_i^e_i). Extracting the s_i from
+// a cheating prover would factor n. Hashing the commitments instead of asking the verifier
+// for the challenge turns the protocol into a non-interactive proof (the Fiat-Shamir
+// heuristic). https://en.wikipedia.org/wiki/Feige%E2%80%93Fiat%E2%80%93Shamir_identification_scheme
+use crate::hash::{self, TranscriptHash};
+use crate::{random_unit, PublicKey};
+use num_bigint::BigInt;
+use rand::Rng;
+
+pub struct Identity {
+    n: BigInt,
+    v: Vec<BigInt>,
+}
+
+pub struct Secret {
+    n: BigInt,
+    s: Vec<BigInt>,
+}
+
+pub struct Commitment {
+    x: BigInt,
+}
+
+// CommitState: the prover's randomness for one round, consumed by answering the challenge
+pub struct CommitState {
+    r: BigInt,
+}
+
+pub struct Challenge {
+    bits: Vec<bool>,
+}
+
+pub struct Response {
+    y: BigInt,
+}
+
+pub struct Round {
+    pub commitment: Commitment,
+    pub challenge: Challenge,
+    pub response: Response,
+}
+
+#[derive(Default)]
+pub struct Transcript {
+    rounds: Vec<Round>,
+}
+
+// Proof: a non-interactive transcript whose challenges are derived by hashing
+pub struct Proof {
+    commitments: Vec<Commitment>,
+    responses: Vec<Response>,
+}
+
+impl Secret {
+    // Pick k secrets modulo the public modulus of an RSA key; its factors are not needed.
+    // Panics for k = 0, which would let anyone answer every challenge
+    pub fn generate(pub_key: &PublicKey, k: usize) -> Self {
+        if k == 0 {
+            panic!("Feige-Fiat-Shamir needs at least one secret");
+        }
+        let s = (0..k).map(|_| random_unit(&pub_key.n)).collect();
+        Secret {
+            n: pub_key.n.clone(),
+            s,
+        }
+    }
+
+    pub fn identity(&self) -> Identity {
+        let v = self.s.iter().map(|s| s * s % &self.n).collect();
+        Identity {
+            n: self.n.clone(),
+            v,
+        }
+    }
+
+    pub fn commit(&self) -> (Commitment, CommitState) {
+        let r = random_unit(&self.n);
+        (
+            Commitment {
+                x: &r * &r % &self.n,
+            },
+            CommitState { r },
+        )
+    }
+
+    pub fn respond(&self, state: CommitState, challenge: &Challenge) -> Response {
+        let y = self
+            .s
+            .iter()
+            .zip(&challenge.bits)
+            .filter(|(_, &bit)| bit)
+            .fold(state.r, |y, (s, _)| y * s % &self.n);
+        Response { y }
+    }
+}
+
+impl Identity {
+    pub fn num_secrets(&self) -> usize {
+        self.v.len()
+    }
+
+    pub fn challenge(&self) -> Challenge {
+        let mut rng = rand::thread_rng();
+        Challenge {
+            bits: (0..self.v.len()).map(|_| rng.gen()).collect(),
+        }
+    }
+
+    pub fn verify(
+        &self,
+        commitment: &Commitment,
+        challenge: &Challenge,
+        response: &Response,
+    ) -> bool {
+        if self.v.is_empty()
+            || challenge.bits.len() != self.v.len()
+            || commitment.x == BigInt::from(0)
+        {
+            return false;
+        }
+        let rhs = self
+            .v
+            .iter()
+            .zip(&challenge.bits)
+            .filter(|(_, &bit)| bit)
+            .fold(commitment.x.clone(), |acc, (v, _)| acc * v % &self.n);
+        &response.y * &response.y % &self.n == rhs
+    }
+
+    pub fn verify_transcript(&self, transcript: &Transcript) -> bool {
+        !transcript.rounds.is_empty()
+            && transcript
+                .rounds
+                .iter()
+                .all(|round| self.verify(&round.commitment, &round.challenge, &round.response))
+    }
+
+    // derive_challenges: one k-bit challenge per commitment, bound to the identity and message
+    fn derive_challenges(&self, message: &[u8], commitments: &[Commitment]) -> Vec<Challenge> {
+        let mut t = TranscriptHash::new("naive-rsa fiat-shamir");
+        t.int(&self.n);
+        self.v.iter().for_each(|v| {
+            t.int(v);
+        });
+        commitments.iter().for_each(|c| {
+            t.int(&c.x);
+        });
+        t.bytes(message);
+        let k = self.v.len();
+        let bits = hash::bits(
+            &hash::expand(&t.finish(), (k * commitments.len()).div_ceil(8)),
+            k * commitments.len(),
+        );
+        bits.chunks(k.max(1))
+            .map(|chunk| Challenge {
+                bits: chunk.to_vec(),
+            })
+            .collect()
+    }
+}
+
+impl Challenge {
+    pub fn from_bits(bits: Vec<bool>) -> Self {
+        Challenge { bits }
+    }
+
+    pub fn bits(&self) -> &[bool] {
+        &self.bits
+    }
+}
+
+impl Transcript {
+    pub fn rounds(&self) -> &[Round] {
+        &self.rounds
+    }
+}
+
+// identify: run the interactive protocol for the given number of rounds, recording everything
+pub fn identify(secret: &Secret, identity: &Identity, rounds: usize) -> Transcript {
+    let mut transcript = Transcript::default();
+    for _ in 0..rounds {
+        let (commitment, state) = secret.commit();
+        let challenge = identity.challenge();
+        let response = secret.respond(state, &challenge);
+        transcript.rounds.push(Round {
+            commitment,
+            challenge,
+            response,
+        });
+    }
+    transcript
+}
+
+// prove: a non-interactive proof of knowledge of the secrets, bound to message
+pub fn prove(secret: &Secret, message: &[u8], rounds: usize) -> Proof {
+    let identity = secret.identity();
+    let (commitments, states): (Vec<_>, Vec<_>) = (0..rounds).map(|_| secret.commit()).unzip();
+    let challenges = identity.derive_challenges(message, &commitments);
+    let responses = states
+        .into_iter()
+        .zip(&challenges)
+        .map(|(state, challenge)| secret.respond(state, challenge))
+        .collect();
+    Proof {
+        commitments,
+        responses,
+    }
+}
+
+pub fn verify(identity: &Identity, message: &[u8], proof: &Proof) -> bool {
+    if identity.v.is_empty()
+        || proof.commitments.is_empty()
+        || proof.commitments.len() != proof.responses.len()
+    {
+        return false;
+    }
+    let challenges = identity.derive_challenges(message, &proof.commitments);
+    proof
+        .commitments
+        .iter()
+        .zip(&challenges)
+        .zip(&proof.responses)
+        .all(|((commitment, challenge), response)| identity.verify(commitment, challenge, response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn honest_prover_is_accepted() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let secret = Secret::generate(&pub_key, 8);
+        let identity = secret.identity();
+        assert!(identity.verify_transcript(&identify(&secret, &identity, 4)));
+    }
+
+    #[test]
+    fn wrong_secret_is_rejected() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let secret = Secret::generate(&pub_key, 8);
+        let impostor = Secret::generate(&pub_key, 8);
+        let identity = secret.identity();
+        let (commitment, state) = impostor.commit();
+        let challenge = Challenge::from_bits(vec![true; 8]);
+        let response = impostor.respond(state, &challenge);
+        assert!(!identity.verify(&commitment, &challenge, &response));
+    }
+
+    #[test]
+    fn non_interactive_proof_is_bound_to_message() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let secret = Secret::generate(&pub_key, 16);
+        let identity = secret.identity();
+        let proof = prove(&secret, b"hello", 8);
+        assert!(verify(&identity, b"hello", &proof));
+        assert!(!verify(&identity, b"goodbye", &proof));
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one secret")]
+    fn zero_secrets_are_refused() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        Secret::generate(&pub_key, 0);
+    }
+
+    #[test]
+    fn identities_without_secrets_verify_nothing() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let empty = Secret {
+            n: pub_key.n.clone(),
+            s: Vec::new(),
+        };
+        let identity = empty.identity();
+        let (commitment, state) = empty.commit();
+        let challenge = Challenge::from_bits(Vec::new());
+        let response = empty.respond(state, &challenge);
+        assert!(!identity.verify(&commitment, &challenge, &response));
+        assert!(!verify(&identity, b"hello", &prove(&empty, b"hello", 4)));
+    }
+}
@@ -0,0 +1,208 @@
+// Bellare-Miner forward-secure signatures
+//
+// The public key fixes U_i = S_i^(2^(T+1)) mod a Blum integer n for l secrets S_i, and the
+// secret key of period j is S_i^(2^j). Moving to the next period squares every secret and
+// forgets the old ones; going back would need square roots mod n, i.e. its factors. So an
+// attacker who steals the key in period j can sign for j and later, but every signature
+// from an earlier period stays trustworthy. Signing is Fiat-Shamir over that period's key.
+use crate::error::ParseError;
+use crate::hash::{self, TranscriptHash};
+use crate::wire::{Reader, Writer};
+use crate::{random_blum_prime, random_unit};
+use num_bigint::BigInt;
+
+// Number of secrets, and of challenge bits per signature
+const CHALLENGE_BITS: usize = 128;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FsPublicKey {
+    n: BigInt,
+    periods: u32,
+    u: Vec<BigInt>,
+}
+
+pub struct FsSecretKey {
+    n: BigInt,
+    periods: u32,
+    period: u32,
+    s: Vec<BigInt>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FsSignature {
+    period: u32,
+    y: BigInt,
+    z: BigInt,
+}
+
+// square_times: x^(2^k) mod n
+fn square_times(x: &BigInt, k: u32, n: &BigInt) -> BigInt {
+    (0..k).fold(x.clone(), |x, _| &x * &x % n)
+}
+
+// gen_keys: a key valid for the given number of periods, numbered from 0
+pub fn gen_keys(ndigits: u32, periods: u32) -> (FsPublicKey, FsSecretKey) {
+    if periods == 0 {
+        panic!("a forward-secure key needs at least one period");
+    }
+    let p = random_blum_prime(ndigits);
+    let mut q = random_blum_prime(ndigits);
+    while q == p {
+        q = random_blum_prime(ndigits);
+    }
+    let n = &p * &q;
+    let s: Vec<BigInt> = (0..CHALLENGE_BITS).map(|_| random_unit(&n)).collect();
+    let u = s.iter().map(|s| square_times(s, periods + 1, &n)).collect();
+    (
+        FsPublicKey {
+            n: n.clone(),
+            periods,
+            u,
+        },
+        FsSecretKey {
+            n,
+            periods,
+            period: 0,
+            s,
+        },
+    )
+}
+
+fn challenge(pub_n: &BigInt, period: u32, y: &BigInt, m: &[u8]) -> Vec<bool> {
+    let seed = TranscriptHash::new("naive-rsa forward-secure signature")
+        .int(pub_n)
+        .bytes(&period.to_be_bytes())
+        .int(y)
+        .bytes(m)
+        .finish();
+    hash::bits(&hash::expand(&seed, CHALLENGE_BITS / 8), CHALLENGE_BITS)
+}
+
+impl FsSecretKey {
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.period >= self.periods
+    }
+
+    // update: evolve into the next period's key, erasing the current one; after the last
+    // period nothing is left to steal and the key can no longer sign
+    pub fn update(&mut self) {
+        if self.is_expired() {
+            return;
+        }
+        self.period += 1;
+        if self.is_expired() {
+            self.s.clear();
+        } else {
+            for s in self.s.iter_mut() {
+                *s = &*s * &*s % &self.n;
+            }
+        }
+    }
+
+    pub fn sign(&self, m: &[u8]) -> FsSignature {
+        if self.is_expired() {
+            panic!("the key expired after period {}", self.periods - 1);
+        }
+        let r = random_unit(&self.n);
+        let y = square_times(&r, self.periods + 1 - self.period, &self.n);
+        let c = challenge(&self.n, self.period, &y, m);
+        let z = self
+            .s
+            .iter()
+            .zip(&c)
+            .filter(|(_, &bit)| bit)
+            .fold(r, |z, (s, _)| z * s % &self.n);
+        FsSignature {
+            period: self.period,
+            y,
+            z,
+        }
+    }
+}
+
+impl FsPublicKey {
+    pub fn periods(&self) -> u32 {
+        self.periods
+    }
+
+    // verify: check a signature, which is only ever valid for the period it names
+    pub fn verify(&self, m: &[u8], sig: &FsSignature) -> bool {
+        if sig.period >= self.periods || sig.y == BigInt::from(0) {
+            return false;
+        }
+        let c = challenge(&self.n, sig.period, &sig.y, m);
+        let rhs = self
+            .u
+            .iter()
+            .zip(&c)
+            .filter(|(_, &bit)| bit)
+            .fold(sig.y.clone(), |acc, (u, _)| acc * u % &self.n);
+        square_times(&sig.z, self.periods + 1 - sig.period, &self.n) == rhs
+    }
+}
+
+impl FsSignature {
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Writer::new("naive-rsa forward-secure signature")
+            .bytes(&self.period.to_be_bytes())
+            .int(&self.y)
+            .int(&self.z)
+            .finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut r = Reader::new(bytes, "naive-rsa forward-secure signature")?;
+        let period = r.count()?;
+        let (y, z) = (r.int()?, r.int()?);
+        r.finish()?;
+        Ok(FsSignature { period, y, z })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_from_every_period_verify() {
+        let (pub_key, mut secret) = gen_keys(20, 3);
+        let mut sigs = Vec::new();
+        while !secret.is_expired() {
+            sigs.push(secret.sign(b"status report"));
+            secret.update();
+        }
+        assert_eq!(sigs.len(), 3);
+        for (period, sig) in sigs.iter().enumerate() {
+            assert_eq!(sig.period() as usize, period);
+            assert!(pub_key.verify(b"status report", sig));
+            assert!(!pub_key.verify(b"forged report", sig));
+        }
+    }
+
+    #[test]
+    fn stolen_key_cannot_sign_for_earlier_periods() {
+        let (pub_key, mut secret) = gen_keys(20, 4);
+        secret.update();
+        secret.update();
+        let mut sig = secret.sign(b"backdated");
+        sig.period = 0;
+        assert!(!pub_key.verify(b"backdated", &sig));
+    }
+
+    #[test]
+    fn signature_round_trips_through_bytes() {
+        let (pub_key, secret) = gen_keys(20, 2);
+        let sig = secret.sign(b"m");
+        let decoded = FsSignature::from_bytes(&sig.to_bytes()).unwrap();
+        assert_eq!(decoded, sig);
+        assert!(pub_key.verify(b"m", &decoded));
+    }
+}
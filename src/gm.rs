@@ -0,0 +1,110 @@
+// Goldwasser-Micali probabilistic encryption
+//
+// Every plaintext bit b is encrypted as c = y^2 x^b mod n for a fresh random y, where x is
+// a quadratic non-residue whose Jacobi symbol is 1. Distinguishing encryptions of 0 from 1
+// is the quadratic residuosity problem; with p the key holder just checks whether c is a
+// square mod p. https://en.wikipedia.org/wiki/Goldwasser%E2%80%93Micali_cryptosystem
+use crate::{jacobi, random_blum_prime, random_unit};
+use num_bigint::BigInt;
+
+pub struct GmPublicKey {
+    n: BigInt,
+    x: BigInt,
+}
+
+pub struct GmPrivateKey {
+    p: BigInt,
+}
+
+impl GmPublicKey {
+    pub fn modulus(&self) -> &BigInt {
+        &self.n
+    }
+}
+
+pub fn gen_keys(ndigits: u32) -> (GmPublicKey, GmPrivateKey) {
+    // With Blum primes -1 is a non-residue mod both p and q, so x = n - 1 always works
+    let p = random_blum_prime(ndigits);
+    let mut q = random_blum_prime(ndigits);
+    while q == p {
+        q = random_blum_prime(ndigits);
+    }
+    let n = &p * &q;
+    let x = &n - 1;
+    debug_assert_eq!(jacobi(&x, &n), 1);
+    (GmPublicKey { n, x }, GmPrivateKey { p })
+}
+
+pub fn encrypt_bit(pub_key: &GmPublicKey, bit: bool) -> BigInt {
+    let y = random_unit(&pub_key.n);
+    let c = &y * &y % &pub_key.n;
+    if bit {
+        c * &pub_key.x % &pub_key.n
+    } else {
+        c
+    }
+}
+
+// decrypt_bit: c encrypts 0 exactly when it is a quadratic residue mod p (Euler's criterion)
+pub fn decrypt_bit(priv_key: &GmPrivateKey, c: &BigInt) -> bool {
+    let exp = (&priv_key.p - 1) / 2;
+    c.modpow(&exp, &priv_key.p) != BigInt::from(1)
+}
+
+// encrypt: one ciphertext per message bit, most significant bit of each byte first
+pub fn encrypt(pub_key: &GmPublicKey, m: &[u8]) -> Vec<BigInt> {
+    m.iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1))
+        .map(|bit| encrypt_bit(pub_key, bit))
+        .collect()
+}
+
+pub fn decrypt(priv_key: &GmPrivateKey, c: &[BigInt]) -> Vec<u8> {
+    c.chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .fold(0u8, |acc, c| (acc << 1) | decrypt_bit(priv_key, c) as u8)
+        })
+        .collect()
+}
+
+// xor: the product of two ciphertexts encrypts the XOR of their plaintext bits
+pub fn xor(pub_key: &GmPublicKey, c1: &BigInt, c2: &BigInt) -> BigInt {
+    c1 * c2 % &pub_key.n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits_round_trip() {
+        let (pub_key, priv_key) = gen_keys(10);
+        for bit in [false, true] {
+            let c = encrypt_bit(&pub_key, bit);
+            assert_eq!(decrypt_bit(&priv_key, &c), bit);
+        }
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let (pub_key, priv_key) = gen_keys(10);
+        let c = encrypt(&pub_key, b"GM");
+        assert_eq!(c.len(), 16);
+        assert_eq!(decrypt(&priv_key, &c), b"GM".to_vec());
+    }
+
+    #[test]
+    fn ciphertexts_combine_by_xor() {
+        let (pub_key, priv_key) = gen_keys(10);
+        for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+            let c = xor(
+                &pub_key,
+                &encrypt_bit(&pub_key, a),
+                &encrypt_bit(&pub_key, b),
+            );
+            assert_eq!(decrypt_bit(&priv_key, &c), a ^ b);
+        }
+    }
+}
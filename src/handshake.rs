@@ -0,0 +1,335 @@
+// A toy RSA-authenticated handshake
+//
+//   client -> server  ClientHello    OAEP(server key, client nonce || premaster key)
+//   server -> client  ServerHello    server nonce, HMAC(finished key, "server finished" || transcript)
+//   client -> server  ClientFinished HMAC(finished key, "client finished" || transcript)
+//
+// Both sides derive the finished key and the two session keys with HKDF from the premaster
+// key, salted with both nonces. Only the holder of the server's private key can recover the
+// premaster key, so a correct ServerHello authenticates the server; the ClientFinished
+// tells the server that the client saw the same transcript. The client stays anonymous.
+// Each side is a small state machine: a value for every state, consumed by each step.
+// client_start_with finds the server's key by name in a KeyDirectory. Either fails with a
+// HandshakeError if the server key is too small to carry the nonce and premaster key.
+use crate::directory::{DirectoryError, KeyDirectory};
+use crate::error::ParseError;
+use crate::hash::{ct_eq, hkdf, hmac_sha256, TranscriptHash};
+use crate::wire::{Reader, Writer};
+use crate::{oaep, random_bytes, PrivateKey, PublicKey};
+use std::fmt;
+
+const NONCE_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const OAEP_LABEL: &[u8] = b"naive-rsa handshake";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientHello {
+    encrypted: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerHello {
+    nonce: Vec<u8>,
+    proof: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientFinished {
+    tag: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum HandshakeError {
+    // The server's modulus is too small for OAEP to carry a nonce and a premaster key
+    KeyTooSmall,
+    // The server's key could not be looked up
+    Directory(DirectoryError),
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HandshakeError::KeyTooSmall => {
+                write!(f, "the server key is too small to handshake with")
+            }
+            HandshakeError::Directory(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HandshakeError::KeyTooSmall => None,
+            HandshakeError::Directory(err) => Some(err),
+        }
+    }
+}
+
+impl From<DirectoryError> for HandshakeError {
+    fn from(err: DirectoryError) -> Self {
+        HandshakeError::Directory(err)
+    }
+}
+
+pub struct SessionKeys {
+    client_write: Vec<u8>,
+    server_write: Vec<u8>,
+}
+
+impl SessionKeys {
+    // client_write: the key for traffic from client to server
+    pub fn client_write(&self) -> &[u8] {
+        &self.client_write
+    }
+
+    pub fn server_write(&self) -> &[u8] {
+        &self.server_write
+    }
+}
+
+// ClientAwaitingServer: the client has sent its hello and waits for the server's proof
+pub struct ClientAwaitingServer {
+    hello: ClientHello,
+    client_nonce: Vec<u8>,
+    premaster: Vec<u8>,
+}
+
+// ServerAwaitingFinished: the server has answered and waits for the client's confirmation
+pub struct ServerAwaitingFinished {
+    finished_key: Vec<u8>,
+    transcript: Vec<u8>,
+    keys: SessionKeys,
+}
+
+// key_schedule: (finished key, transcript hash, session keys) shared by both sides
+fn key_schedule(
+    hello: &ClientHello,
+    client_nonce: &[u8],
+    server_nonce: &[u8],
+    premaster: &[u8],
+) -> (Vec<u8>, Vec<u8>, SessionKeys) {
+    let transcript = TranscriptHash::new("naive-rsa handshake")
+        .bytes(&hello.to_bytes())
+        .bytes(server_nonce)
+        .finish();
+    let salt = [client_nonce, server_nonce].concat();
+    let mut okm = hkdf(&salt, premaster, &transcript, 3 * KEY_LEN);
+    let server_write = okm.split_off(2 * KEY_LEN);
+    let client_write = okm.split_off(KEY_LEN);
+    (
+        okm,
+        transcript,
+        SessionKeys {
+            client_write,
+            server_write,
+        },
+    )
+}
+
+// client_start: open a handshake with the server owning server_pub
+pub fn client_start(
+    server_pub: &PublicKey,
+) -> Result<(ClientAwaitingServer, ClientHello), HandshakeError> {
+    if oaep::max_message_len(server_pub) < NONCE_LEN + KEY_LEN {
+        return Err(HandshakeError::KeyTooSmall);
+    }
+    let client_nonce = random_bytes(NONCE_LEN);
+    let premaster = random_bytes(KEY_LEN);
+    let plaintext = [&client_nonce[..], &premaster].concat();
+    let hello = ClientHello {
+        encrypted: oaep::encrypt(server_pub, &plaintext, OAEP_LABEL)
+            .ok_or(HandshakeError::KeyTooSmall)?,
+    };
+    let state = ClientAwaitingServer {
+        hello: hello.clone(),
+        client_nonce,
+        premaster,
+    };
+    Ok((state, hello))
+}
+
+// client_start_with: client_start with the key directory lists for server
+pub fn client_start_with(
+    directory: &(impl KeyDirectory + ?Sized),
+    server: &str,
+) -> Result<(ClientAwaitingServer, ClientHello), HandshakeError> {
+    let entry = directory.lookup(server)?;
+    client_start(&entry.key)
+}
+
+impl ClientAwaitingServer {
+    // receive: None if the server failed to prove it could decrypt the hello
+    pub fn receive(self, msg: &ServerHello) -> Option<(SessionKeys, ClientFinished)> {
+        let (finished_key, transcript, keys) =
+            key_schedule(&self.hello, &self.client_nonce, &msg.nonce, &self.premaster);
+        let expected = hmac_sha256(&finished_key, &[b"server finished", &transcript]);
+        if !ct_eq(&expected, &msg.proof) {
+            return None;
+        }
+        let tag = hmac_sha256(&finished_key, &[b"client finished", &transcript]);
+        Some((keys, ClientFinished { tag }))
+    }
+}
+
+// server_accept: answer a client hello; None if it does not decrypt under the server key
+pub fn server_accept(
+    pub_key: &PublicKey,
+    priv_key: &PrivateKey,
+    hello: &ClientHello,
+) -> Option<(ServerAwaitingFinished, ServerHello)> {
+    let plaintext = oaep::decrypt(pub_key, priv_key, &hello.encrypted, OAEP_LABEL)?;
+    if plaintext.len() != NONCE_LEN + KEY_LEN {
+        return None;
+    }
+    let (client_nonce, premaster) = plaintext.split_at(NONCE_LEN);
+    let server_nonce = random_bytes(NONCE_LEN);
+    let (finished_key, transcript, keys) =
+        key_schedule(hello, client_nonce, &server_nonce, premaster);
+    let proof = hmac_sha256(&finished_key, &[b"server finished", &transcript]);
+    let state = ServerAwaitingFinished {
+        finished_key,
+        transcript,
+        keys,
+    };
+    Some((
+        state,
+        ServerHello {
+            nonce: server_nonce,
+            proof,
+        },
+    ))
+}
+
+impl ServerAwaitingFinished {
+    pub fn finish(self, msg: &ClientFinished) -> Option<SessionKeys> {
+        let expected = hmac_sha256(&self.finished_key, &[b"client finished", &self.transcript]);
+        ct_eq(&expected, &msg.tag).then_some(self.keys)
+    }
+}
+
+impl ClientHello {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Writer::new("naive-rsa client hello")
+            .bytes(&self.encrypted)
+            .finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut r = Reader::new(bytes, "naive-rsa client hello")?;
+        let encrypted = r.bytes()?.to_vec();
+        r.finish()?;
+        Ok(ClientHello { encrypted })
+    }
+}
+
+impl ServerHello {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Writer::new("naive-rsa server hello")
+            .bytes(&self.nonce)
+            .bytes(&self.proof)
+            .finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut r = Reader::new(bytes, "naive-rsa server hello")?;
+        let (nonce, proof) = (r.bytes()?.to_vec(), r.bytes()?.to_vec());
+        r.finish()?;
+        Ok(ServerHello { nonce, proof })
+    }
+}
+
+impl ClientFinished {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Writer::new("naive-rsa client finished")
+            .bytes(&self.tag)
+            .finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut r = Reader::new(bytes, "naive-rsa client finished")?;
+        let tag = r.bytes()?.to_vec();
+        r.finish()?;
+        Ok(ClientFinished { tag })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+    use std::sync::OnceLock;
+
+    fn server_keys() -> &'static (PublicKey, PrivateKey) {
+        static KEYS: OnceLock<(PublicKey, PrivateKey)> = OnceLock::new();
+        KEYS.get_or_init(|| gen_keys_with_digits(100))
+    }
+
+    #[test]
+    fn handshake_agrees_on_session_keys() {
+        let (pub_key, priv_key) = server_keys();
+        let (client, hello) = client_start(pub_key).unwrap();
+        let hello = ClientHello::from_bytes(&hello.to_bytes()).unwrap();
+        let (server, server_hello) = server_accept(pub_key, priv_key, &hello).unwrap();
+        let server_hello = ServerHello::from_bytes(&server_hello.to_bytes()).unwrap();
+        let (client_keys, finished) = client.receive(&server_hello).unwrap();
+        let finished = ClientFinished::from_bytes(&finished.to_bytes()).unwrap();
+        let server_keys = server.finish(&finished).unwrap();
+        assert_eq!(client_keys.client_write(), server_keys.client_write());
+        assert_eq!(client_keys.server_write(), server_keys.server_write());
+        assert_ne!(client_keys.client_write(), client_keys.server_write());
+    }
+
+    #[test]
+    fn impostor_server_is_detected() {
+        let (pub_key, _) = server_keys();
+        let (client, _) = client_start(pub_key).unwrap();
+        let forged = ServerHello {
+            nonce: vec![0; NONCE_LEN],
+            proof: vec![0; 32],
+        };
+        assert!(client.receive(&forged).is_none());
+    }
+
+    #[test]
+    fn tampered_server_hello_is_rejected() {
+        let (pub_key, priv_key) = server_keys();
+        let (client, hello) = client_start(pub_key).unwrap();
+        let (_, mut server_hello) = server_accept(pub_key, priv_key, &hello).unwrap();
+        server_hello.nonce[0] ^= 1;
+        assert!(client.receive(&server_hello).is_none());
+    }
+
+    #[test]
+    fn finished_from_another_session_is_rejected() {
+        let (pub_key, priv_key) = server_keys();
+        let (_, hello) = client_start(pub_key).unwrap();
+        let (server, _) = server_accept(pub_key, priv_key, &hello).unwrap();
+        let (other_client, other_hello) = client_start(pub_key).unwrap();
+        let (_, other_server_hello) = server_accept(pub_key, priv_key, &other_hello).unwrap();
+        let (_, finished) = other_client.receive(&other_server_hello).unwrap();
+        assert!(server.finish(&finished).is_none());
+    }
+
+    #[test]
+    fn client_finds_the_server_key_in_a_directory() {
+        use crate::directory::{DirectoryEntry, MemoryDirectory};
+        let (pub_key, priv_key) = server_keys();
+        let mut directory = MemoryDirectory::new();
+        directory.insert("server.example", DirectoryEntry::new(pub_key.clone()));
+        let (client, hello) = client_start_with(&directory, "server.example").unwrap();
+        let (_, server_hello) = server_accept(pub_key, priv_key, &hello).unwrap();
+        assert!(client.receive(&server_hello).is_some());
+        assert!(matches!(
+            client_start_with(&directory, "other.example"),
+            Err(HandshakeError::Directory(DirectoryError::NotFound(_)))
+        ));
+        // A 20-digit modulus has no room for OAEP, let alone the nonce and premaster key
+        let (small, _) = gen_keys_with_digits(20);
+        directory.insert("small.example", DirectoryEntry::new(small));
+        assert!(matches!(
+            client_start_with(&directory, "small.example"),
+            Err(HandshakeError::KeyTooSmall)
+        ));
+    }
+}
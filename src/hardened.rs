@@ -0,0 +1,122 @@
+// Blinded, ladder-based private-key operations, for keys made with PrivateKey::hardened
+//
+// The threat model is an attacker who chooses ciphertexts or messages, watches how long
+// each decryption or signature takes (over a network, or from another process on the same
+// machine) and learns whether it succeeded, but cannot read memory or probe the hardware.
+// Against the plain path such an attacker has three handles:
+// - square-and-multiply does a multiplication for each set bit of d, so the time taken
+//   depends on d and, across many chosen inputs, leaks it (Kocher, 1996)
+// - the time of each step also depends on the input, which the attacker picks to steer the
+//   intermediate values; with CRT, inputs near a multiple of p time differently from inputs
+//   just above it, which recovers p bit by bit (Brumley and Boneh, 2003)
+// - padding checks that stop at the first bad byte, or compare digests with ==, tell
+//   malformed inputs apart, which is the oracle Bleichenbacher's and Manger's attacks need
+// A hardened key multiplies the input by r^e for a fresh random unit r before
+// exponentiating and the result by r^-1 after, so the exponentiation only ever sees
+// uniformly random values unrelated to anything the attacker sent. It exponentiates by a
+// Montgomery ladder, one multiplication and one squaring per bit of d whatever the bit is,
+// for the full exponent and for both CRT halves. The padding decoders and every digest or
+// tag comparison in the crate fold their checks through subtle's constant-time primitives
+// for all keys, hardened or not.
+//
+// What it does not cover: BigInt arithmetic is variable-time and allocates according to
+// the size of its values, the ladder's length shows the bit length of d (or dP and dQ),
+// nothing here resists cache or power analysis, and a fault in a CRT half is not caught
+// (see fault for that attack and its check). The point is to show where the leaks are and
+// what closes them, not to make this crate safe to deploy.
+use crate::modexp::{modpow_with, Backend};
+use crate::{invert, random_unit, rsa_public, PrivateKey, PublicKey};
+use num_bigint::BigInt;
+
+// Blinding: x r^e mod n for a fresh unit r, with r^-1 to undo it on the result; also used
+// by oracle to hide the input from a DecryptionOracle
+pub(crate) struct Blinding {
+    pub(crate) blinded: BigInt,
+    r_inv: BigInt,
+}
+
+impl Blinding {
+    pub(crate) fn new(pub_key: &PublicKey, x: &BigInt) -> Self {
+        let n = &pub_key.n;
+        let r = random_unit(n);
+        let blinded = x * rsa_public(pub_key, &r) % n;
+        Blinding {
+            blinded,
+            r_inv: invert(r, n.clone()),
+        }
+    }
+
+    // unblind: y r^-1 mod n, which is x^d for y = (x r^e)^d
+    pub(crate) fn unblind(&self, n: &BigInt, y: &BigInt) -> BigInt {
+        y * &self.r_inv % n
+    }
+}
+
+fn ladder(x: &BigInt, exp: &BigInt, modulus: &BigInt) -> BigInt {
+    modpow_with(x, exp, modulus, Backend::ConstantTime)
+}
+
+// permutation: x^d mod n, blinded, by ladder, and by CRT if the key has its primes
+pub(crate) fn permutation(pub_key: &PublicKey, priv_key: &PrivateKey, x: &BigInt) -> BigInt {
+    let n = &pub_key.n;
+    let blinding = Blinding::new(pub_key, x);
+    let y = match &priv_key.crt {
+        Some(crt) => crt.pow_with(&blinding.blinded, ladder),
+        None => ladder(&blinding.blinded, &priv_key.d, n),
+    };
+    blinding.unblind(n, &y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash_alg::HashAlg;
+    use crate::signature::{self, Emsa};
+    use crate::{decrypt, encrypt, gen_keys_with_digits, oaep, private_permutation};
+
+    #[test]
+    fn hardened_keys_agree_with_plain_ones() {
+        for crt in [true, false] {
+            let (pub_key, priv_key) = gen_keys_with_digits(40);
+            let priv_key = if crt {
+                priv_key
+            } else {
+                priv_key.without_crt()
+            };
+            let c = encrypt(&pub_key, BigInt::from(424242).into()).unwrap();
+            let plain = decrypt(&pub_key, &priv_key, c.clone()).unwrap();
+            let priv_key = priv_key.hardened();
+            assert!(priv_key.is_hardened());
+            assert_eq!(priv_key.has_crt(), crt);
+            assert_eq!(decrypt(&pub_key, &priv_key, c).unwrap(), plain);
+            let s = signature::sign(&pub_key, &priv_key, b"m", Emsa::Pkcs1v15, HashAlg::Sha256)
+                .unwrap();
+            assert!(signature::verify(&pub_key, b"m", &s, Emsa::Pkcs1v15, HashAlg::Sha256).is_ok());
+        }
+    }
+
+    #[test]
+    fn padded_decryption_runs_through_the_hardened_path() {
+        let (pub_key, priv_key) = gen_keys_with_digits(160);
+        let priv_key = priv_key.hardened();
+        let c = oaep::encrypt(&pub_key, b"attack at dawn", b"").unwrap();
+        assert_eq!(
+            oaep::decrypt(&pub_key, &priv_key, &c, b"").as_deref(),
+            Some(&b"attack at dawn"[..])
+        );
+    }
+
+    #[test]
+    fn blinding_hides_the_input_and_unblinds_exactly() {
+        let (pub_key, priv_key) = gen_keys_with_digits(40);
+        let x = BigInt::from(31337);
+        let first = Blinding::new(&pub_key, &x);
+        let second = Blinding::new(&pub_key, &x);
+        assert_ne!(first.blinded, second.blinded);
+        let expected = private_permutation(&pub_key, &priv_key, &x);
+        for blinding in [first, second] {
+            let y = private_permutation(&pub_key, &priv_key, &blinding.blinded);
+            assert_eq!(blinding.unblind(&pub_key.n, &y), expected);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+// Hashing helpers shared by the protocols that derive challenges from a transcript
+use crate::hash_alg::HashAlg;
+use num_bigint::{BigInt, Sign};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+// TranscriptHash: SHA-256 over a domain label followed by length-prefixed items, so that
+// different splits of the same bytes never collide
+pub(crate) struct TranscriptHash(Sha256);
+
+impl TranscriptHash {
+    pub(crate) fn new(label: &str) -> Self {
+        let mut t = TranscriptHash(Sha256::new());
+        t.bytes(label.as_bytes());
+        t
+    }
+
+    pub(crate) fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.0.update((bytes.len() as u32).to_be_bytes());
+        self.0.update(bytes);
+        self
+    }
+
+    pub(crate) fn int(&mut self, x: &BigInt) -> &mut Self {
+        self.bytes(&x.to_signed_bytes_be())
+    }
+
+    pub(crate) fn finish(&self) -> Vec<u8> {
+        self.0.clone().finalize().to_vec()
+    }
+}
+
+// expand: stretch a seed to len bytes with MGF1-SHA256
+pub(crate) fn expand(seed: &[u8], len: usize) -> Vec<u8> {
+    HashAlg::Sha256.mgf1(seed, len)
+}
+
+// bits: the first n bits of some bytes, most significant bit first
+pub(crate) fn bits(bytes: &[u8], n: usize) -> Vec<bool> {
+    (0..n)
+        .map(|i| (bytes[i / 8] >> (7 - i % 8)) & 1 == 1)
+        .collect()
+}
+
+// full_domain: map a seed to an integer mod n, with 128 extra bits to make the bias negligible
+pub(crate) fn full_domain(seed: &[u8], n: &BigInt) -> BigInt {
+    let len = (n.bits() as usize).div_ceil(8) + 16;
+    BigInt::from_bytes_be(Sign::Plus, &expand(seed, len)) % n
+}
+
+// hmac_sha256: HMAC (RFC 2104) over the concatenation of parts
+pub(crate) fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+    const BLOCK: usize = 64;
+    let mut k = if key.len() > BLOCK {
+        Sha256::digest(key).to_vec()
+    } else {
+        key.to_vec()
+    };
+    k.resize(BLOCK, 0);
+    let mut inner = Sha256::new();
+    inner.update(k.iter().map(|b| b ^ 0x36).collect::<Vec<u8>>());
+    for part in parts {
+        inner.update(part);
+    }
+    let mut outer = Sha256::new();
+    outer.update(k.iter().map(|b| b ^ 0x5c).collect::<Vec<u8>>());
+    outer.update(inner.finalize());
+    outer.finalize().to_vec()
+}
+
+// hkdf: HKDF-SHA256 (RFC 5869), extract then expand to len bytes
+pub(crate) fn hkdf(salt: &[u8], ikm: &[u8], info: &[u8], len: usize) -> Vec<u8> {
+    let prk = hmac_sha256(salt, &[ikm]);
+    let mut out = Vec::with_capacity(len);
+    let mut t = Vec::new();
+    let mut counter = 1u8;
+    while out.len() < len {
+        t = hmac_sha256(&prk, &[&t, info, &[counter]]);
+        out.extend_from_slice(&t);
+        counter += 1;
+    }
+    out.truncate(len);
+    out
+}
+
+// ct_eq: compare two byte strings without exiting early at the first difference; only
+// their lengths, which are public wherever the crate compares, decide the time taken
+pub(crate) fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        let tag = hmac_sha256(b"Jefe", &[b"what do ya want ", b"for nothing?"]);
+        assert_eq!(
+            hex(&tag),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn hkdf_matches_rfc_5869() {
+        let salt: Vec<u8> = (0x00..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+        let okm = hkdf(&salt, &[0x0b; 22], &info, 42);
+        assert_eq!(
+            hex(&okm),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+        );
+    }
+}
@@ -0,0 +1,239 @@
+// The hash functions the padding schemes can be instantiated with
+//
+// Every algorithm appears exactly once in TABLE, together with its output length and its
+// object identifier, so that the padding modules agree on which hashes exist and how they
+// are named on the wire. The DER DigestInfo prefix used by PKCS #1 v1.5 signatures is
+// derived from the OID rather than stored, which rules out a table whose prefix and OID
+// columns disagree.
+// https://www.rfc-editor.org/rfc/rfc8017#appendix-B.1
+use crate::der::encode_oid;
+use sha2::Digest;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HashAlg {
+    Sha1,
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+    Sha3_224,
+    Sha3_256,
+    Sha3_384,
+    Sha3_512,
+    Blake2b512,
+    Blake2s256,
+}
+
+struct Entry {
+    alg: HashAlg,
+    name: &'static str,
+    output_len: usize,
+    oid: &'static [u64],
+}
+
+// Ordered like the enum, so that an algorithm's entry is TABLE[alg as usize]
+const TABLE: [Entry; 11] = [
+    Entry {
+        alg: HashAlg::Sha1,
+        name: "SHA-1",
+        output_len: 20,
+        oid: &[1, 3, 14, 3, 2, 26],
+    },
+    Entry {
+        alg: HashAlg::Sha224,
+        name: "SHA-224",
+        output_len: 28,
+        oid: &[2, 16, 840, 1, 101, 3, 4, 2, 4],
+    },
+    Entry {
+        alg: HashAlg::Sha256,
+        name: "SHA-256",
+        output_len: 32,
+        oid: &[2, 16, 840, 1, 101, 3, 4, 2, 1],
+    },
+    Entry {
+        alg: HashAlg::Sha384,
+        name: "SHA-384",
+        output_len: 48,
+        oid: &[2, 16, 840, 1, 101, 3, 4, 2, 2],
+    },
+    Entry {
+        alg: HashAlg::Sha512,
+        name: "SHA-512",
+        output_len: 64,
+        oid: &[2, 16, 840, 1, 101, 3, 4, 2, 3],
+    },
+    Entry {
+        alg: HashAlg::Sha3_224,
+        name: "SHA3-224",
+        output_len: 28,
+        oid: &[2, 16, 840, 1, 101, 3, 4, 2, 7],
+    },
+    Entry {
+        alg: HashAlg::Sha3_256,
+        name: "SHA3-256",
+        output_len: 32,
+        oid: &[2, 16, 840, 1, 101, 3, 4, 2, 8],
+    },
+    Entry {
+        alg: HashAlg::Sha3_384,
+        name: "SHA3-384",
+        output_len: 48,
+        oid: &[2, 16, 840, 1, 101, 3, 4, 2, 9],
+    },
+    Entry {
+        alg: HashAlg::Sha3_512,
+        name: "SHA3-512",
+        output_len: 64,
+        oid: &[2, 16, 840, 1, 101, 3, 4, 2, 10],
+    },
+    Entry {
+        alg: HashAlg::Blake2b512,
+        name: "BLAKE2b-512",
+        output_len: 64,
+        oid: &[1, 3, 6, 1, 4, 1, 1722, 12, 2, 1, 16],
+    },
+    Entry {
+        alg: HashAlg::Blake2s256,
+        name: "BLAKE2s-256",
+        output_len: 32,
+        oid: &[1, 3, 6, 1, 4, 1, 1722, 12, 2, 2, 8],
+    },
+];
+
+impl HashAlg {
+    pub const ALL: [HashAlg; 11] = [
+        HashAlg::Sha1,
+        HashAlg::Sha224,
+        HashAlg::Sha256,
+        HashAlg::Sha384,
+        HashAlg::Sha512,
+        HashAlg::Sha3_224,
+        HashAlg::Sha3_256,
+        HashAlg::Sha3_384,
+        HashAlg::Sha3_512,
+        HashAlg::Blake2b512,
+        HashAlg::Blake2s256,
+    ];
+
+    fn entry(self) -> &'static Entry {
+        &TABLE[self as usize]
+    }
+
+    pub fn name(self) -> &'static str {
+        self.entry().name
+    }
+
+    pub fn output_len(self) -> usize {
+        self.entry().output_len
+    }
+
+    // oid: the object identifier as a list of arcs
+    pub fn oid(self) -> &'static [u64] {
+        self.entry().oid
+    }
+
+    pub fn from_oid(oid: &[u64]) -> Option<Self> {
+        TABLE.iter().find(|e| e.oid == oid).map(|e| e.alg)
+    }
+
+    // from_name: case-insensitive, and the dash may be left out ("sha256", "SHA3-256")
+    pub fn from_name(name: &str) -> Option<Self> {
+        let squash = |s: &str| s.replace('-', "").to_ascii_lowercase();
+        TABLE
+            .iter()
+            .find(|e| squash(e.name) == squash(name))
+            .map(|e| e.alg)
+    }
+
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlg::Sha1 => sha1::Sha1::digest(data).to_vec(),
+            HashAlg::Sha224 => sha2::Sha224::digest(data).to_vec(),
+            HashAlg::Sha256 => sha2::Sha256::digest(data).to_vec(),
+            HashAlg::Sha384 => sha2::Sha384::digest(data).to_vec(),
+            HashAlg::Sha512 => sha2::Sha512::digest(data).to_vec(),
+            HashAlg::Sha3_224 => sha3::Sha3_224::digest(data).to_vec(),
+            HashAlg::Sha3_256 => sha3::Sha3_256::digest(data).to_vec(),
+            HashAlg::Sha3_384 => sha3::Sha3_384::digest(data).to_vec(),
+            HashAlg::Sha3_512 => sha3::Sha3_512::digest(data).to_vec(),
+            HashAlg::Blake2b512 => blake2::Blake2b512::digest(data).to_vec(),
+            HashAlg::Blake2s256 => blake2::Blake2s256::digest(data).to_vec(),
+        }
+    }
+
+    // mgf1: the mask generation function of RFC 8017 B.2.1 over this hash
+    pub fn mgf1(self, seed: &[u8], len: usize) -> Vec<u8> {
+        let mut out = Vec::with_capacity(len);
+        let mut counter: u32 = 0;
+        while out.len() < len {
+            out.extend(self.digest(&[seed, &counter.to_be_bytes()].concat()));
+            counter += 1;
+        }
+        out.truncate(len);
+        out
+    }
+
+    // digest_info_prefix: DER of DigestInfo { AlgorithmIdentifier { oid, NULL }, OCTET STRING }
+    // up to the digest itself, which is appended to it
+    pub fn digest_info_prefix(self) -> Vec<u8> {
+        let oid = encode_oid(self.oid());
+        let algorithm_len = 2 + oid.len() + 2;
+        let total_len = 2 + algorithm_len + 2 + self.output_len();
+        let mut der = vec![0x30, total_len as u8, 0x30, algorithm_len as u8];
+        der.push(0x06);
+        der.push(oid.len() as u8);
+        der.extend(oid);
+        der.extend([0x05, 0x00, 0x04, self.output_len() as u8]);
+        der
+    }
+
+    pub fn digest_info(self, data: &[u8]) -> Vec<u8> {
+        [self.digest_info_prefix(), self.digest(data)].concat()
+    }
+}
+
+impl fmt::Display for HashAlg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn table_matches_the_enum() {
+        for (i, alg) in HashAlg::ALL.iter().enumerate() {
+            assert_eq!(TABLE[i].alg, *alg);
+            assert_eq!(alg.digest(b"abc").len(), alg.output_len());
+            assert_eq!(HashAlg::from_oid(alg.oid()), Some(*alg));
+            assert_eq!(HashAlg::from_name(alg.name()), Some(*alg));
+        }
+        assert_eq!(HashAlg::from_name("sha3256"), Some(HashAlg::Sha3_256));
+        assert_eq!(HashAlg::from_name("md5"), None);
+    }
+
+    // The DigestInfo prefixes listed in RFC 8017, section 9.2, note 1
+    #[test]
+    fn digest_info_prefixes_match_rfc_8017() {
+        assert_eq!(
+            hex(&HashAlg::Sha1.digest_info_prefix()),
+            "3021300906052b0e03021a05000414"
+        );
+        assert_eq!(
+            hex(&HashAlg::Sha256.digest_info_prefix()),
+            "3031300d060960864801650304020105000420"
+        );
+        assert_eq!(
+            hex(&HashAlg::Sha512.digest_info_prefix()),
+            "3051300d060960864801650304020305000440"
+        );
+    }
+}
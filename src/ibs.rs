@@ -0,0 +1,119 @@
+// Shamir's identity-based signatures (1984)
+//
+// A trusted authority holds an ordinary RSA key pair (n, e, d). A user's public key is
+// their identity string itself: the authority extracts the user's secret g = H(id)^d mod n.
+// To sign, the user picks r, sets t = r^e and s = g r^f(t, m); anyone checks
+// s^e = H(id) t^f(t, m) mod n against the identity and the authority's public key alone.
+use crate::error::ParseError;
+use crate::hash::{self, TranscriptHash};
+use crate::wire::{Reader, Writer};
+use crate::{random_unit, rsa_private, PrivateKey, PublicKey};
+use num_bigint::{BigInt, Sign};
+
+pub struct UserKey {
+    identity: String,
+    g: BigInt,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IbsSignature {
+    t: BigInt,
+    s: BigInt,
+}
+
+fn identity_point(master: &PublicKey, identity: &str) -> BigInt {
+    let seed = TranscriptHash::new("naive-rsa ibs identity")
+        .int(&master.n)
+        .bytes(identity.as_bytes())
+        .finish();
+    hash::full_domain(&seed, &master.n)
+}
+
+fn message_exponent(master: &PublicKey, t: &BigInt, m: &[u8]) -> BigInt {
+    let digest = TranscriptHash::new("naive-rsa ibs message")
+        .int(&master.n)
+        .int(t)
+        .bytes(m)
+        .finish();
+    BigInt::from_bytes_be(Sign::Plus, &digest)
+}
+
+// extract: the authority derives the signing key for an identity
+pub fn extract(master_pub: &PublicKey, master_priv: &PrivateKey, identity: &str) -> UserKey {
+    let g = rsa_private(
+        master_pub,
+        master_priv,
+        &identity_point(master_pub, identity),
+    );
+    UserKey {
+        identity: identity.to_string(),
+        g,
+    }
+}
+
+impl UserKey {
+    pub fn identity(&self) -> &str {
+        &self.identity
+    }
+}
+
+pub fn sign(master_pub: &PublicKey, user_key: &UserKey, m: &[u8]) -> IbsSignature {
+    let n = &master_pub.n;
+    let r = random_unit(n);
+    let t = r.modpow(&master_pub.e, n);
+    let f = message_exponent(master_pub, &t, m);
+    let s = &user_key.g * r.modpow(&f, n) % n;
+    IbsSignature { t, s }
+}
+
+pub fn verify(master_pub: &PublicKey, identity: &str, m: &[u8], sig: &IbsSignature) -> bool {
+    let n = &master_pub.n;
+    if sig.t <= BigInt::from(0) || sig.t >= *n {
+        return false;
+    }
+    let f = message_exponent(master_pub, &sig.t, m);
+    let rhs = identity_point(master_pub, identity) * sig.t.modpow(&f, n) % n;
+    sig.s.modpow(&master_pub.e, n) == rhs
+}
+
+impl IbsSignature {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Writer::new("naive-rsa ibs signature")
+            .int(&self.t)
+            .int(&self.s)
+            .finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut r = Reader::new(bytes, "naive-rsa ibs signature")?;
+        let (t, s) = (r.int()?, r.int()?);
+        r.finish()?;
+        Ok(IbsSignature { t, s })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn signatures_verify_against_the_identity() {
+        let (master_pub, master_priv) = gen_keys_with_digits(20);
+        let alice = extract(&master_pub, &master_priv, "alice@example.com");
+        let sig = sign(&master_pub, &alice, b"hello");
+        assert!(verify(&master_pub, "alice@example.com", b"hello", &sig));
+        assert!(!verify(&master_pub, "bob@example.com", b"hello", &sig));
+        assert!(!verify(&master_pub, "alice@example.com", b"bye", &sig));
+    }
+
+    #[test]
+    fn signature_round_trips_through_bytes() {
+        let (master_pub, master_priv) = gen_keys_with_digits(20);
+        let alice = extract(&master_pub, &master_priv, "alice");
+        let sig = sign(&master_pub, &alice, b"m");
+        let decoded = IbsSignature::from_bytes(&sig.to_bytes()).unwrap();
+        assert_eq!(decoded, sig);
+        assert!(verify(&master_pub, alice.identity(), b"m", &decoded));
+    }
+}
@@ -0,0 +1,251 @@
+// An in-memory cache of private keys, kept wrapped
+//
+// A long-running service that decrypts on demand has to keep its private keys somewhere.
+// KeyCache stores each one wrapped under a random key that never leaves the process: the
+// private exponent and any primes are XORed with an HMAC-SHA256-derived keystream for a
+// fresh nonce and then authenticated, encrypt-then-MAC as in kem::seal. The rest of the
+// key, its audit log and whether it is hardened, stays in the entry as it was. with_key
+// unwraps a key just for the duration of a closure and wraps it again under a new nonce
+// afterwards, and a key that has not been used for the TTL is dropped. BigInt gives no way
+// to wipe its buffer, so the unwrapped exponent may linger in freed memory; the wrapped
+// form is what stays resident.
+use crate::error::ParseError;
+use crate::hash::{self, ct_eq, hmac_sha256};
+use crate::wire::{Reader, Writer};
+use crate::{PrivateKey, PublicKey};
+use num_bigint::BigInt;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 16;
+const SECRETS_TAG: &str = "naive-rsa cached key";
+
+struct Entry {
+    public: PublicKey,
+    // The private key with d and the primes taken out
+    shell: PrivateKey,
+    nonce: [u8; NONCE_LEN],
+    wrapped: Vec<u8>,
+    tag: Vec<u8>,
+    expires: Instant,
+}
+
+fn read_secrets(bytes: &[u8]) -> Result<(BigInt, Option<(BigInt, BigInt)>), ParseError> {
+    let mut r = Reader::new(bytes, SECRETS_TAG)?;
+    let d = r.int()?;
+    let primes = if r.is_empty() {
+        None
+    } else {
+        Some((r.int()?, r.int()?))
+    };
+    r.finish()?;
+    Ok((d, primes))
+}
+
+pub struct KeyCache<K> {
+    enc_key: [u8; KEY_LEN],
+    mac_key: [u8; KEY_LEN],
+    ttl: Duration,
+    entries: HashMap<K, Entry>,
+}
+
+impl<K: Eq + Hash> KeyCache<K> {
+    // new: an empty cache whose keys expire ttl after they were last used
+    pub fn new(ttl: Duration) -> Self {
+        let (mut enc_key, mut mac_key) = ([0u8; KEY_LEN], [0u8; KEY_LEN]);
+        OsRng.fill_bytes(&mut enc_key);
+        OsRng.fill_bytes(&mut mac_key);
+        KeyCache {
+            enc_key,
+            mac_key,
+            ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn keystream(&self, nonce: &[u8], len: usize) -> Vec<u8> {
+        hash::expand(&hmac_sha256(&self.enc_key, &[nonce]), len)
+    }
+
+    fn wrap(&self, public: PublicKey, private: PrivateKey, expires: Instant) -> Entry {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let (shell, d, primes) = private.take_secrets();
+        let mut w = Writer::new(SECRETS_TAG);
+        w.int(&d);
+        if let Some((p, q)) = &primes {
+            w.int(p).int(q);
+        }
+        let mut bytes = w.finish();
+        let wrapped: Vec<u8> = bytes
+            .iter()
+            .zip(self.keystream(&nonce, bytes.len()))
+            .map(|(b, k)| b ^ k)
+            .collect();
+        bytes.fill(0);
+        let tag = hmac_sha256(&self.mac_key, &[&nonce, &wrapped]);
+        Entry {
+            public,
+            shell,
+            nonce,
+            wrapped,
+            tag,
+            expires,
+        }
+    }
+
+    // unwrap: d and the primes, if any, back from the entry
+    fn unwrap(&self, entry: &Entry) -> (BigInt, Option<(BigInt, BigInt)>) {
+        let tag = hmac_sha256(&self.mac_key, &[&entry.nonce, &entry.wrapped]);
+        if !ct_eq(&tag, &entry.tag) {
+            panic!("a wrapped key in the cache has been corrupted");
+        }
+        let mut bytes: Vec<u8> = entry
+            .wrapped
+            .iter()
+            .zip(self.keystream(&entry.nonce, entry.wrapped.len()))
+            .map(|(b, k)| b ^ k)
+            .collect();
+        let secrets = read_secrets(&bytes).expect("the cache wraps only what it can read back");
+        bytes.fill(0);
+        secrets
+    }
+
+    // insert: cache a key pair under id, replacing any key already there
+    pub fn insert(&mut self, id: K, public: PublicKey, private: PrivateKey) {
+        self.insert_at(id, public, private, Instant::now())
+    }
+
+    fn insert_at(&mut self, id: K, public: PublicKey, private: PrivateKey, now: Instant) {
+        let entry = self.wrap(public, private, now + self.ttl);
+        self.entries.insert(id, entry);
+    }
+
+    pub fn remove(&mut self, id: &K) -> bool {
+        self.entries.remove(id).is_some()
+    }
+
+    pub fn public_key(&self, id: &K) -> Option<&PublicKey> {
+        self.entries.get(id).map(|entry| &entry.public)
+    }
+
+    // with_key: run f with the unwrapped key, or None if id is absent or has expired
+    pub fn with_key<R>(
+        &mut self,
+        id: &K,
+        f: impl FnOnce(&PublicKey, &PrivateKey) -> R,
+    ) -> Option<R> {
+        self.with_key_at(id, Instant::now(), f)
+    }
+
+    fn with_key_at<R>(
+        &mut self,
+        id: &K,
+        now: Instant,
+        f: impl FnOnce(&PublicKey, &PrivateKey) -> R,
+    ) -> Option<R> {
+        let (id, entry) = self.entries.remove_entry(id)?;
+        if entry.expires <= now {
+            return None;
+        }
+        let (d, primes) = self.unwrap(&entry);
+        let private = entry.shell.with_secrets(d, primes);
+        let result = f(&entry.public, &private);
+        let rewrapped = self.wrap(entry.public, private, now + self.ttl);
+        self.entries.insert(id, rewrapped);
+        Some(result)
+    }
+
+    // evict_expired: drop every key whose TTL has run out; returns how many went
+    pub fn evict_expired(&mut self) -> usize {
+        self.evict_expired_at(Instant::now())
+    }
+
+    fn evict_expired_at(&mut self, now: Instant) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| entry.expires > now);
+        before - self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decrypt, encrypt, gen_keys_with_digits};
+
+    #[test]
+    fn cached_keys_decrypt_and_are_rewrapped() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let mut cache = KeyCache::new(Duration::from_secs(60));
+        cache.insert("service", pub_key.clone(), priv_key);
+        let c = encrypt(&pub_key, BigInt::from(31337).into()).unwrap();
+        let before = cache.entries["service"].wrapped.clone();
+        let m = cache.with_key(&"service", |public, private| {
+            decrypt(public, private, c).unwrap()
+        });
+        assert_eq!(m, Some(BigInt::from(31337).into()));
+        assert_ne!(cache.entries["service"].wrapped, before);
+        assert_eq!(cache.public_key(&"service"), Some(&pub_key));
+        assert_eq!(cache.with_key(&"other", |_, _| ()), None);
+    }
+
+    #[test]
+    fn keys_expire_after_the_ttl_since_last_use() {
+        let mut cache = KeyCache::new(Duration::from_secs(60));
+        let start = Instant::now();
+        for id in [1, 2] {
+            let (pub_key, priv_key) = gen_keys_with_digits(20);
+            cache.insert_at(id, pub_key, priv_key, start);
+        }
+        let later = start + Duration::from_secs(45);
+        assert!(cache.with_key_at(&1, later, |_, _| ()).is_some());
+        // Key 1 was used at 45s and lives until 105s; key 2 expired at 60s
+        assert_eq!(cache.evict_expired_at(start + Duration::from_secs(90)), 1);
+        assert_eq!(cache.len(), 1);
+        assert!(cache
+            .with_key_at(&1, start + Duration::from_secs(110), |_, _| ())
+            .is_none());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "corrupted")]
+    fn tampered_entries_are_detected() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let mut cache = KeyCache::new(Duration::from_secs(60));
+        cache.insert(0, pub_key, priv_key);
+        cache.entries.get_mut(&0).unwrap().wrapped[0] ^= 1;
+        cache.with_key(&0, |_, _| ());
+    }
+
+    #[test]
+    fn cached_keys_keep_crt_hardening_and_audit_log() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let mut cache = KeyCache::new(Duration::from_secs(60));
+        cache.insert("service", pub_key.clone(), priv_key.hardened().with_audit());
+        for x in [2, 3] {
+            let c = encrypt(&pub_key, BigInt::from(x).into()).unwrap();
+            let flags = cache.with_key(&"service", |public, private| {
+                decrypt(public, private, c).unwrap();
+                (
+                    private.is_hardened(),
+                    private.has_crt(),
+                    private.audit_log().unwrap().len(),
+                )
+            });
+            assert_eq!(flags, Some((true, true, x - 1)));
+        }
+    }
+}
@@ -0,0 +1,954 @@
+// Loading RSA keys from whatever format they arrive in
+//
+// PublicKey and PrivateKey implement FromStr and TryFrom<&[u8]>, which sniff the input:
+//   PEM      "-----BEGIN <label>-----", dispatched on the label
+//   DER      a SEQUENCE tag (0x30); PKCS #1 and PKCS #8 / SubjectPublicKeyInfo are told
+//            apart by the shape of their first fields
+//   JWK      a JSON object with "kty": "RSA" (RFC 7518, section 6.3)
+//   OpenSSH  "ssh-rsa AAAA..." public keys and unencrypted "openssh-key-v1" private keys
+// A public key can be read from a private key file too, since those carry n and e. A
+// PrivateKey keeps d and, when the file has them, p and q for CRT decryption. The primes
+// are checked against n, the CRT parameters are recomputed from them, and the rest of the
+// file is dropped.
+// from_der and from_pem do the same but insist on that one encoding.
+//
+// Keys are written back out as PKCS #1 or SubjectPublicKeyInfo (public) and PKCS #1 or
+// PKCS #8 (private) DER and PEM, byte for byte as OpenSSL writes them. A private key file
+// needs p, q and the CRT parameters: KeyPair writes its stored primes in their order, and a
+// bare PrivateKey writes its own primes, or else has them recovered from d, smaller first.
+use crate::crt::Crt;
+use crate::der::{self, Reader as DerReader};
+use crate::error::{ParseError, ParseErrorKind};
+use crate::exponents::factor_from_exponents;
+use crate::keypair::KeyPair;
+use crate::wire::Reader as SshReader;
+use crate::{base64, extended_gcd, PrivateKey, PublicKey};
+use num_bigint::{BigInt, Sign};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyFormat {
+    Pem,
+    Der,
+    Jwk,
+    OpenSsh,
+}
+
+const RSA_ENCRYPTION: [u64; 7] = [1, 2, 840, 113549, 1, 1, 1];
+const OPENSSH_MAGIC: &[u8] = b"openssh-key-v1\0";
+// Characters per line of a PEM body, as OpenSSL wraps them
+const PEM_LINE_LEN: usize = 64;
+
+// detect: which parser a key file would be handed to, from its first bytes
+pub fn detect(input: &[u8]) -> Option<KeyFormat> {
+    let text = input.trim_ascii_start();
+    if input.first() == Some(&der::SEQUENCE) {
+        Some(KeyFormat::Der)
+    } else if text.starts_with(b"-----BEGIN ") {
+        Some(KeyFormat::Pem)
+    } else if text.starts_with(b"{") {
+        Some(KeyFormat::Jwk)
+    } else if text.starts_with(b"ssh-rsa ") {
+        Some(KeyFormat::OpenSsh)
+    } else {
+        None
+    }
+}
+
+// The fields every format provides, whatever else it carries
+struct Parsed {
+    format: &'static str,
+    n: BigInt,
+    e: BigInt,
+    d: Option<BigInt>,
+    primes: Option<(BigInt, BigInt)>,
+}
+
+impl Parsed {
+    fn check(self) -> Result<Self, ParseError> {
+        let one = BigInt::from(1);
+        if self.n <= one || self.e <= one || self.d.as_ref().is_some_and(|d| *d <= one) {
+            return Err(ParseError::invalid(
+                self.format,
+                0,
+                "key integers must be greater than 1",
+            ));
+        }
+        if self.n < BigInt::from(3) {
+            return Err(ParseError::invalid(
+                self.format,
+                0,
+                "the modulus must be at least 3",
+            ));
+        }
+        if let Some((p, q)) = &self.primes {
+            if p * q != self.n {
+                return Err(ParseError::invalid(
+                    self.format,
+                    0,
+                    "p * q does not equal n",
+                ));
+            }
+            // CRT needs q invertible mod p, which also rules out p == q
+            if *p <= one || *q <= one || extended_gcd(p.clone(), q.clone()).0 != one {
+                return Err(ParseError::invalid(
+                    self.format,
+                    0,
+                    "p and q must be coprime and greater than 1",
+                ));
+            }
+        }
+        Ok(self)
+    }
+
+    // private_key: d, with the primes for CRT decryption when the format stores them
+    fn private_key(&self) -> Result<PrivateKey, ParseError> {
+        let d = self.private_exponent()?;
+        Ok(match &self.primes {
+            Some((p, q)) => PrivateKey::with_primes(d, p.clone(), q.clone()),
+            None => PrivateKey::new(d),
+        })
+    }
+
+    fn private_exponent(&self) -> Result<BigInt, ParseError> {
+        self.d.clone().ok_or_else(|| {
+            ParseError::invalid(
+                self.format,
+                0,
+                "this is a public key, it has no private exponent",
+            )
+        })
+    }
+}
+
+fn parse(input: &[u8]) -> Result<Parsed, ParseError> {
+    let text = || {
+        std::str::from_utf8(input).map_err(|e| {
+            ParseError::invalid("key file", e.valid_up_to(), "text formats must be UTF-8")
+        })
+    };
+    let parsed = match detect(input) {
+        Some(KeyFormat::Der) => parse_der(input),
+        Some(KeyFormat::Pem) => parse_pem(text()?),
+        Some(KeyFormat::Jwk) => parse_jwk(text()?),
+        Some(KeyFormat::OpenSsh) => parse_openssh_public(text()?),
+        None => Err(ParseError::invalid(
+            "key file",
+            0,
+            "not a PEM, DER, JWK or OpenSSH key",
+        )),
+    }?;
+    parsed.check()
+}
+
+// parse_as: parse, but only input in the given format
+fn parse_as(input: &[u8], format: KeyFormat) -> Result<Parsed, ParseError> {
+    if detect(input) != Some(format) {
+        let name = match format {
+            KeyFormat::Der => "DER",
+            _ => "PEM",
+        };
+        return Err(ParseError::invalid(
+            "key file",
+            0,
+            format!("not a {} key", name),
+        ));
+    }
+    parse(input)
+}
+
+fn parse_pem(text: &str) -> Result<Parsed, ParseError> {
+    let start = text.find("-----BEGIN ").unwrap_or(0);
+    let after_begin = start + "-----BEGIN ".len();
+    let label_len = text[after_begin..]
+        .find("-----")
+        .ok_or_else(|| ParseError::invalid("PEM", after_begin, "unterminated BEGIN line"))?;
+    let label = &text[after_begin..after_begin + label_len];
+    let body_start = after_begin + label_len + "-----".len();
+    let end_line = format!("-----END {}-----", label);
+    let body_len = text[body_start..].find(&end_line).ok_or_else(|| {
+        ParseError::new(
+            "PEM",
+            text.len(),
+            ParseErrorKind::Truncated {
+                needed: end_line.len(),
+                available: 0,
+            },
+        )
+    })?;
+    let body = &text[body_start..body_start + body_len];
+    if body.contains(':') {
+        return Err(ParseError::invalid(
+            "PEM",
+            body_start,
+            "encrypted PEM (with Proc-Type headers) is not supported",
+        ));
+    }
+    let bytes = base64::decode(body)
+        .ok_or_else(|| ParseError::invalid("PEM", body_start, "the body is not valid base64"))?;
+    match label {
+        "RSA PUBLIC KEY" => pkcs1_public(&bytes, "PKCS #1 RSAPublicKey"),
+        "PUBLIC KEY" => spki(&bytes),
+        "RSA PRIVATE KEY" => pkcs1_private(&bytes, "PKCS #1 RSAPrivateKey"),
+        "PRIVATE KEY" => pkcs8(&bytes),
+        "OPENSSH PRIVATE KEY" => parse_openssh_private(&bytes),
+        _ => Err(ParseError::new(
+            "PEM",
+            after_begin,
+            ParseErrorKind::UnexpectedTag {
+                expected: "an RSA key label".to_string(),
+                actual: label.to_string(),
+            },
+        )),
+    }
+}
+
+// parse_der: look at the first fields to pick the structure, then parse it properly
+fn parse_der(bytes: &[u8]) -> Result<Parsed, ParseError> {
+    let mut probe = DerReader::new(bytes, "DER key").sequence()?;
+    if probe.peek_tag() == Some(der::SEQUENCE) {
+        return spki(bytes);
+    }
+    probe.integer()?;
+    if probe.peek_tag() == Some(der::SEQUENCE) {
+        return pkcs8(bytes);
+    }
+    probe.integer()?;
+    if probe.is_empty() {
+        pkcs1_public(bytes, "PKCS #1 RSAPublicKey")
+    } else {
+        pkcs1_private(bytes, "PKCS #1 RSAPrivateKey")
+    }
+}
+
+// pkcs1_public: RSAPublicKey ::= SEQUENCE { modulus, publicExponent }
+fn pkcs1_public(bytes: &[u8], format: &'static str) -> Result<Parsed, ParseError> {
+    let mut outer = DerReader::new(bytes, format);
+    let mut seq = outer.sequence()?;
+    let (n, e) = (seq.integer()?, seq.integer()?);
+    seq.finish()?;
+    outer.finish()?;
+    Ok(Parsed {
+        format,
+        n,
+        e,
+        d: None,
+        primes: None,
+    })
+}
+
+// algorithm: AlgorithmIdentifier, which must be rsaEncryption with NULL parameters
+fn algorithm(r: &mut DerReader) -> Result<(), ParseError> {
+    let mut alg = r.sequence()?;
+    let oid = alg.oid()?;
+    if oid != RSA_ENCRYPTION {
+        let dotted: Vec<String> = oid.iter().map(u64::to_string).collect();
+        return Err(ParseError::invalid(
+            "AlgorithmIdentifier",
+            0,
+            format!("{} is not rsaEncryption", dotted.join(".")),
+        ));
+    }
+    if !alg.is_empty() {
+        alg.null()?;
+    }
+    alg.finish()
+}
+
+fn rsa_encryption() -> Vec<u8> {
+    der::sequence(&[
+        der::encode(der::OID, &der::encode_oid(&RSA_ENCRYPTION)),
+        der::encode(der::NULL, &[]),
+    ])
+}
+
+// spki_der: the DER SubjectPublicKeyInfo of a key, the form fingerprints are taken over
+pub(crate) fn spki_der(pub_key: &PublicKey) -> Vec<u8> {
+    let rsa_public_key = der::sequence(&[der::integer(&pub_key.n), der::integer(&pub_key.e)]);
+    let key = der::encode(der::BIT_STRING, &[&[0][..], &rsa_public_key].concat());
+    der::sequence(&[rsa_encryption(), key])
+}
+
+// pkcs1_private_der: RSAPrivateKey with the CRT parameters for p and q in that order, if
+// they have any
+fn pkcs1_private_der(pub_key: &PublicKey, d: &BigInt, p: &BigInt, q: &BigInt) -> Option<Vec<u8>> {
+    let crt = Crt::new(d, p.clone(), q.clone())?;
+    let fields = [
+        &BigInt::from(0),
+        &pub_key.n,
+        &pub_key.e,
+        d,
+        p,
+        q,
+        &crt.dp,
+        &crt.dq,
+        &crt.qinv,
+    ];
+    Some(der::sequence(&fields.map(der::integer)))
+}
+
+fn pkcs8_der(pkcs1: &[u8]) -> Vec<u8> {
+    der::sequence(&[
+        der::integer(&BigInt::from(0)),
+        rsa_encryption(),
+        der::encode(der::OCTET_STRING, pkcs1),
+    ])
+}
+
+fn pem(label: &str, der: &[u8]) -> String {
+    let body = base64::encode(der, true);
+    let mut out = format!("-----BEGIN {}-----\n", label);
+    for line in body.as_bytes().chunks(PEM_LINE_LEN) {
+        out.push_str(std::str::from_utf8(line).unwrap());
+        out.push('\n');
+    }
+    out + &format!("-----END {}-----\n", label)
+}
+
+// spki: SubjectPublicKeyInfo ::= SEQUENCE { algorithm, BIT STRING { RSAPublicKey } }
+fn spki(bytes: &[u8]) -> Result<Parsed, ParseError> {
+    let mut outer = DerReader::new(bytes, "SubjectPublicKeyInfo");
+    let mut seq = outer.sequence()?;
+    algorithm(&mut seq)?;
+    let key = seq.bit_string()?;
+    seq.finish()?;
+    outer.finish()?;
+    pkcs1_public(key, "SubjectPublicKeyInfo RSAPublicKey")
+}
+
+// pkcs1_private: RSAPrivateKey ::= SEQUENCE { version, n, e, d, p, q, dP, dQ, qInv }
+fn pkcs1_private(bytes: &[u8], format: &'static str) -> Result<Parsed, ParseError> {
+    let mut outer = DerReader::new(bytes, format);
+    let mut seq = outer.sequence()?;
+    if seq.integer()? != BigInt::from(0) {
+        return Err(ParseError::invalid(
+            format,
+            0,
+            "only two-prime keys (version 0) are supported",
+        ));
+    }
+    let (n, e, d) = (seq.integer()?, seq.integer()?, seq.integer()?);
+    let (p, q) = (seq.integer()?, seq.integer()?);
+    for _ in 0..3 {
+        seq.integer()?;
+    }
+    seq.finish()?;
+    outer.finish()?;
+    Ok(Parsed {
+        format,
+        n,
+        e,
+        d: Some(d),
+        primes: Some((p, q)),
+    })
+}
+
+// pkcs8: PrivateKeyInfo ::= SEQUENCE { version, algorithm, OCTET STRING { RSAPrivateKey }, ... }
+fn pkcs8(bytes: &[u8]) -> Result<Parsed, ParseError> {
+    let mut outer = DerReader::new(bytes, "PKCS #8 PrivateKeyInfo");
+    let mut seq = outer.sequence()?;
+    seq.integer()?;
+    algorithm(&mut seq)?;
+    let key = seq.octet_string()?;
+    // Optional attributes and public key may follow; they are not needed
+    outer.finish()?;
+    pkcs1_private(key, "PKCS #8 RSAPrivateKey")
+}
+
+fn base64url_int(value: &str, field: &str) -> Result<BigInt, ParseError> {
+    let bytes = base64::decode(value)
+        .ok_or_else(|| ParseError::invalid("JWK", 0, format!("\"{}\" is not base64url", field)))?;
+    Ok(BigInt::from_bytes_be(Sign::Plus, &bytes))
+}
+
+fn parse_jwk(text: &str) -> Result<Parsed, ParseError> {
+    let members = json::string_members(text)?;
+    let get = |name: &str| {
+        members
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    };
+    let kty = get("kty").unwrap_or("(missing)");
+    if kty != "RSA" {
+        return Err(ParseError::new(
+            "JWK",
+            0,
+            ParseErrorKind::UnexpectedTag {
+                expected: "\"kty\": \"RSA\"".to_string(),
+                actual: format!("\"kty\": \"{}\"", kty),
+            },
+        ));
+    }
+    let required = |name: &str| {
+        get(name).ok_or_else(|| ParseError::invalid("JWK", 0, format!("\"{}\" is missing", name)))
+    };
+    Ok(Parsed {
+        format: "JWK",
+        n: base64url_int(required("n")?, "n")?,
+        e: base64url_int(required("e")?, "e")?,
+        d: get("d").map(|d| base64url_int(d, "d")).transpose()?,
+        primes: match (get("p"), get("q")) {
+            (Some(p), Some(q)) => Some((base64url_int(p, "p")?, base64url_int(q, "q")?)),
+            _ => None,
+        },
+    })
+}
+
+// openssh_public_blob: string "ssh-rsa", mpint e, mpint n (RFC 4253, section 6.6)
+fn openssh_public_blob(blob: &[u8]) -> Result<(BigInt, BigInt), ParseError> {
+    let mut r = SshReader::new(blob, "ssh-rsa")?;
+    let (e, n) = (r.int()?, r.int()?);
+    r.finish()?;
+    Ok((n, e))
+}
+
+fn parse_openssh_public(text: &str) -> Result<Parsed, ParseError> {
+    let mut fields = text.split_ascii_whitespace().skip(1);
+    let encoded = fields.next().unwrap_or("");
+    let blob = base64::decode(encoded).ok_or_else(|| {
+        ParseError::invalid("OpenSSH public key", 8, "the key is not valid base64")
+    })?;
+    let (n, e) = openssh_public_blob(&blob)?;
+    Ok(Parsed {
+        format: "OpenSSH public key",
+        n,
+        e,
+        d: None,
+        primes: None,
+    })
+}
+
+// parse_openssh_private: the "openssh-key-v1" container (PROTOCOL.key in OpenSSH) holding
+// one unencrypted RSA key
+fn parse_openssh_private(bytes: &[u8]) -> Result<Parsed, ParseError> {
+    const FORMAT: &str = "OpenSSH private key";
+    let mut r = SshReader::untagged(bytes, FORMAT);
+    if r.raw(OPENSSH_MAGIC.len())? != OPENSSH_MAGIC {
+        return Err(ParseError::new(
+            FORMAT,
+            0,
+            ParseErrorKind::UnexpectedTag {
+                expected: "openssh-key-v1".to_string(),
+                actual: String::from_utf8_lossy(&bytes[..OPENSSH_MAGIC.len()]).into_owned(),
+            },
+        ));
+    }
+    let cipher_offset = r.offset();
+    if r.bytes()? != b"none" {
+        return Err(ParseError::invalid(
+            FORMAT,
+            cipher_offset,
+            "passphrase-protected keys are not supported",
+        ));
+    }
+    r.bytes()?; // kdfname
+    r.bytes()?; // kdfoptions
+    let count_offset = r.offset();
+    if r.raw_u32()? != 1 {
+        return Err(ParseError::invalid(
+            FORMAT,
+            count_offset,
+            "expected exactly one key",
+        ));
+    }
+    let (n, e) = openssh_public_blob(r.bytes()?)?;
+    let private = r.bytes()?;
+    r.finish()?;
+    let mut p = SshReader::untagged(private, "OpenSSH private key section");
+    if p.raw_u32()? != p.raw_u32()? {
+        return Err(ParseError::invalid(
+            "OpenSSH private key section",
+            0,
+            "check integers differ",
+        ));
+    }
+    let key_type_offset = p.offset();
+    let key_type = p.bytes()?;
+    if key_type != b"ssh-rsa" {
+        return Err(ParseError::new(
+            "OpenSSH private key section",
+            key_type_offset,
+            ParseErrorKind::UnexpectedTag {
+                expected: "ssh-rsa".to_string(),
+                actual: String::from_utf8_lossy(key_type).into_owned(),
+            },
+        ));
+    }
+    let (n2, e2, d) = (p.int()?, p.int()?, p.int()?);
+    let (_iqmp, prime_p, prime_q) = (p.int()?, p.int()?, p.int()?);
+    p.bytes()?; // comment, followed by padding 1, 2, 3, ...
+    if n2 != n || e2 != e {
+        return Err(ParseError::invalid(
+            "OpenSSH private key section",
+            key_type_offset,
+            "the private key does not match the public key",
+        ));
+    }
+    Ok(Parsed {
+        format: FORMAT,
+        n,
+        e,
+        d: Some(d),
+        primes: Some((prime_p, prime_q)),
+    })
+}
+
+impl TryFrom<&[u8]> for PublicKey {
+    type Error = ParseError;
+
+    fn try_from(input: &[u8]) -> Result<Self, ParseError> {
+        let Parsed { n, e, .. } = parse(input)?;
+        Ok(PublicKey { n, e })
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        PublicKey::try_from(s.as_bytes())
+    }
+}
+
+impl TryFrom<&[u8]> for PrivateKey {
+    type Error = ParseError;
+
+    fn try_from(input: &[u8]) -> Result<Self, ParseError> {
+        parse(input)?.private_key()
+    }
+}
+
+impl FromStr for PrivateKey {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        PrivateKey::try_from(s.as_bytes())
+    }
+}
+
+impl PublicKey {
+    pub fn from_der(bytes: &[u8]) -> Result<Self, ParseError> {
+        let Parsed { n, e, .. } = parse_as(bytes, KeyFormat::Der)?;
+        Ok(PublicKey { n, e })
+    }
+
+    pub fn from_pem(text: &str) -> Result<Self, ParseError> {
+        let Parsed { n, e, .. } = parse_as(text.as_bytes(), KeyFormat::Pem)?;
+        Ok(PublicKey { n, e })
+    }
+
+    // to_pkcs1_der: RSAPublicKey, "RSA PUBLIC KEY" in PEM
+    pub fn to_pkcs1_der(&self) -> Vec<u8> {
+        der::sequence(&[der::integer(&self.n), der::integer(&self.e)])
+    }
+
+    pub fn to_pkcs1_pem(&self) -> String {
+        pem("RSA PUBLIC KEY", &self.to_pkcs1_der())
+    }
+
+    // to_spki_der: SubjectPublicKeyInfo, "PUBLIC KEY" in PEM, what openssl rsa -pubout writes
+    pub fn to_spki_der(&self) -> Vec<u8> {
+        spki_der(self)
+    }
+
+    pub fn to_spki_pem(&self) -> String {
+        pem("PUBLIC KEY", &self.to_spki_der())
+    }
+}
+
+impl PrivateKey {
+    pub fn from_der(bytes: &[u8]) -> Result<Self, ParseError> {
+        parse_as(bytes, KeyFormat::Der)?.private_key()
+    }
+
+    pub fn from_pem(text: &str) -> Result<Self, ParseError> {
+        parse_as(text.as_bytes(), KeyFormat::Pem)?.private_key()
+    }
+
+    // to_pkcs1_der: RSAPrivateKey for this d and pub_key, with the key's own primes if it
+    // has them for this modulus and otherwise those d factors n into; None if it does not
+    pub fn to_pkcs1_der(&self, pub_key: &PublicKey) -> Option<Vec<u8>> {
+        let (p, q) = match self.primes() {
+            Some((p, q)) if p * q == pub_key.n => (p.clone(), q.clone()),
+            _ => factor_from_exponents(pub_key, &self.d)?,
+        };
+        pkcs1_private_der(pub_key, &self.d, &p, &q)
+    }
+
+    pub fn to_pkcs1_pem(&self, pub_key: &PublicKey) -> Option<String> {
+        Some(pem("RSA PRIVATE KEY", &self.to_pkcs1_der(pub_key)?))
+    }
+
+    pub fn to_pkcs8_der(&self, pub_key: &PublicKey) -> Option<Vec<u8>> {
+        Some(pkcs8_der(&self.to_pkcs1_der(pub_key)?))
+    }
+
+    pub fn to_pkcs8_pem(&self, pub_key: &PublicKey) -> Option<String> {
+        Some(pem("PRIVATE KEY", &self.to_pkcs8_der(pub_key)?))
+    }
+}
+
+impl KeyPair {
+    // to_pkcs1_der: RSAPrivateKey with the primes as stored, or recovered from d; None if
+    // there are none and d does not factor n
+    pub fn to_pkcs1_der(&self) -> Option<Vec<u8>> {
+        let (p, q) = self.primes()?;
+        pkcs1_private_der(self.public(), self.private().exponent(), &p, &q)
+    }
+
+    pub fn to_pkcs1_pem(&self) -> Option<String> {
+        Some(pem("RSA PRIVATE KEY", &self.to_pkcs1_der()?))
+    }
+
+    pub fn to_pkcs8_der(&self) -> Option<Vec<u8>> {
+        Some(pkcs8_der(&self.to_pkcs1_der()?))
+    }
+
+    pub fn to_pkcs8_pem(&self) -> Option<String> {
+        Some(pem("PRIVATE KEY", &self.to_pkcs8_der()?))
+    }
+}
+
+// KeyPair keeps the primes of a private key file, in the order the file has them
+impl TryFrom<&[u8]> for KeyPair {
+    type Error = ParseError;
+
+    fn try_from(input: &[u8]) -> Result<Self, ParseError> {
+        let parsed = parse(input)?;
+        let private = parsed.private_key()?;
+        let public = PublicKey {
+            n: parsed.n,
+            e: parsed.e,
+        };
+        Ok(match parsed.primes {
+            Some((p, q)) => KeyPair::with_primes(public, private, p, q),
+            None => KeyPair::new(public, private),
+        })
+    }
+}
+
+impl FromStr for KeyPair {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        KeyPair::try_from(s.as_bytes())
+    }
+}
+
+// Just enough JSON to read the string members of a JWK object; everything else is skipped
+mod json {
+    use crate::error::ParseError;
+
+    struct Parser<'a> {
+        s: &'a [u8],
+        pos: usize,
+    }
+
+    impl Parser<'_> {
+        fn error(&self, reason: &str) -> ParseError {
+            ParseError::invalid("JWK", self.pos, reason)
+        }
+
+        fn skip_ws(&mut self) {
+            while self.s.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+                self.pos += 1;
+            }
+        }
+
+        fn eat(&mut self, c: u8) -> Result<(), ParseError> {
+            self.skip_ws();
+            if self.s.get(self.pos) != Some(&c) {
+                return Err(self.error(&format!("expected '{}'", c as char)));
+            }
+            self.pos += 1;
+            Ok(())
+        }
+
+        fn peek(&mut self) -> Option<u8> {
+            self.skip_ws();
+            self.s.get(self.pos).copied()
+        }
+
+        fn string(&mut self) -> Result<String, ParseError> {
+            self.eat(b'"')?;
+            let mut out = Vec::new();
+            loop {
+                let c = *self
+                    .s
+                    .get(self.pos)
+                    .ok_or_else(|| self.error("unterminated string"))?;
+                self.pos += 1;
+                match c {
+                    b'"' => break,
+                    b'\\' => {
+                        let escaped = *self
+                            .s
+                            .get(self.pos)
+                            .ok_or_else(|| self.error("bad escape"))?;
+                        self.pos += 1;
+                        let decoded = match escaped {
+                            b'"' | b'\\' | b'/' => escaped as char,
+                            b'b' => '\u{8}',
+                            b'f' => '\u{c}',
+                            b'n' => '\n',
+                            b'r' => '\r',
+                            b't' => '\t',
+                            b'u' => {
+                                let hex = self
+                                    .s
+                                    .get(self.pos..self.pos + 4)
+                                    .ok_or_else(|| self.error("bad escape"))?;
+                                self.pos += 4;
+                                let code = std::str::from_utf8(hex)
+                                    .ok()
+                                    .and_then(|h| u32::from_str_radix(h, 16).ok());
+                                code.and_then(char::from_u32).unwrap_or('\u{fffd}')
+                            }
+                            _ => return Err(self.error("bad escape")),
+                        };
+                        out.extend_from_slice(decoded.encode_utf8(&mut [0; 4]).as_bytes());
+                    }
+                    _ => out.push(c),
+                }
+            }
+            String::from_utf8(out).map_err(|_| self.error("string is not UTF-8"))
+        }
+
+        // skip_value: step over any value, checking only that brackets and quotes balance
+        fn skip_value(&mut self) -> Result<(), ParseError> {
+            match self.peek() {
+                Some(b'"') => self.string().map(|_| ()),
+                Some(open @ (b'{' | b'[')) => {
+                    let close = if open == b'{' { b'}' } else { b']' };
+                    self.pos += 1;
+                    if self.peek() == Some(close) {
+                        self.pos += 1;
+                        return Ok(());
+                    }
+                    loop {
+                        if open == b'{' {
+                            self.string()?;
+                            self.eat(b':')?;
+                        }
+                        self.skip_value()?;
+                        if self.peek() == Some(b',') {
+                            self.pos += 1;
+                        } else {
+                            return self.eat(close);
+                        }
+                    }
+                }
+                Some(_) => {
+                    let start = self.pos;
+                    while self
+                        .s
+                        .get(self.pos)
+                        .is_some_and(|c| c.is_ascii_alphanumeric() || b"+-.".contains(c))
+                    {
+                        self.pos += 1;
+                    }
+                    if self.pos == start {
+                        return Err(self.error("expected a value"));
+                    }
+                    Ok(())
+                }
+                None => Err(self.error("expected a value")),
+            }
+        }
+    }
+
+    // string_members: the (name, value) pairs of a top-level object whose values are strings
+    pub(super) fn string_members(text: &str) -> Result<Vec<(String, String)>, ParseError> {
+        let mut p = Parser {
+            s: text.as_bytes(),
+            pos: 0,
+        };
+        let mut members = Vec::new();
+        p.eat(b'{')?;
+        if p.peek() == Some(b'}') {
+            p.pos += 1;
+        } else {
+            loop {
+                let name = p.string()?;
+                p.eat(b':')?;
+                if p.peek() == Some(b'"') {
+                    members.push((name, p.string()?));
+                } else {
+                    p.skip_value()?;
+                }
+                if p.peek() == Some(b',') {
+                    p.pos += 1;
+                } else {
+                    p.eat(b'}')?;
+                    break;
+                }
+            }
+        }
+        if p.peek().is_some() {
+            return Err(p.error("unexpected text after the object"));
+        }
+        Ok(members)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decrypt, encrypt, Plaintext};
+
+    // The same 1024-bit key in every supported format, exported by OpenSSL and ssh-keygen
+    const RSA_PUBLIC_PEM: &str = include_str!("../testdata/rsa_public.pem");
+    const SPKI_PUBLIC_PEM: &str = include_str!("../testdata/spki_public.pem");
+    const SPKI_PUBLIC_DER: &[u8] = include_bytes!("../testdata/spki_public.der");
+    const RSA_PRIVATE_PEM: &str = include_str!("../testdata/rsa_private.pem");
+    const RSA_PRIVATE_DER: &[u8] = include_bytes!("../testdata/rsa_private.der");
+    const PKCS8_PRIVATE_PEM: &str = include_str!("../testdata/pkcs8_private.pem");
+    const PUBLIC_JWK: &str = include_str!("../testdata/public.jwk");
+    const PRIVATE_JWK: &str = include_str!("../testdata/private.jwk");
+    const OPENSSH_PUBLIC: &str = include_str!("../testdata/openssh_public.pub");
+    const OPENSSH_PRIVATE: &str = include_str!("../testdata/openssh_private");
+    const EXPECTED: &str = include_str!("../testdata/expected.txt");
+
+    fn expected(name: &str) -> BigInt {
+        let line = EXPECTED
+            .lines()
+            .find(|l| l.starts_with(&format!("{} = ", name)))
+            .unwrap();
+        line[name.len() + 3..].parse().unwrap()
+    }
+
+    #[test]
+    fn every_format_yields_the_same_public_key() {
+        let want = PublicKey {
+            n: expected("n"),
+            e: expected("e"),
+        };
+        for text in [
+            RSA_PUBLIC_PEM,
+            SPKI_PUBLIC_PEM,
+            RSA_PRIVATE_PEM,
+            PKCS8_PRIVATE_PEM,
+            PUBLIC_JWK,
+            PRIVATE_JWK,
+            OPENSSH_PUBLIC,
+            OPENSSH_PRIVATE,
+        ] {
+            assert_eq!(text.parse::<PublicKey>().unwrap(), want, "{}", text);
+        }
+        for bytes in [SPKI_PUBLIC_DER, RSA_PRIVATE_DER] {
+            assert_eq!(PublicKey::try_from(bytes).unwrap(), want);
+        }
+    }
+
+    #[test]
+    fn private_keys_load_and_decrypt() {
+        let pub_key: PublicKey = SPKI_PUBLIC_PEM.parse().unwrap();
+        let m = Plaintext::from(BigInt::from(424242));
+        let c = encrypt(&pub_key, m.clone()).unwrap();
+        for text in [
+            RSA_PRIVATE_PEM,
+            PKCS8_PRIVATE_PEM,
+            PRIVATE_JWK,
+            OPENSSH_PRIVATE,
+        ] {
+            let priv_key: PrivateKey = text.parse().unwrap();
+            assert_eq!(priv_key.exponent(), &expected("d"));
+            assert_eq!(decrypt(&pub_key, &priv_key, c.clone()).unwrap(), m);
+        }
+        assert!(PrivateKey::try_from(RSA_PRIVATE_DER).is_ok());
+        let err = PUBLIC_JWK.parse::<PrivateKey>().err().unwrap();
+        assert!(err.to_string().contains("no private exponent"));
+    }
+
+    #[test]
+    fn key_pairs_survive_a_trip_through_every_format() {
+        let reference: KeyPair = RSA_PRIVATE_PEM.parse().unwrap();
+        for text in [PKCS8_PRIVATE_PEM, OPENSSH_PRIVATE, PRIVATE_JWK] {
+            let diff = reference.diff(&text.parse().unwrap());
+            assert!(diff.same_key(), "{}", diff);
+        }
+    }
+
+    #[test]
+    fn spki_encoding_matches_openssl() {
+        let pub_key: PublicKey = RSA_PUBLIC_PEM.parse().unwrap();
+        assert_eq!(spki_der(&pub_key), SPKI_PUBLIC_DER);
+    }
+
+    #[test]
+    fn exports_match_openssl_byte_for_byte() {
+        let pair: KeyPair = RSA_PRIVATE_PEM.parse().unwrap();
+        assert_eq!(pair.to_pkcs1_pem().unwrap(), RSA_PRIVATE_PEM);
+        assert_eq!(pair.to_pkcs8_pem().unwrap(), PKCS8_PRIVATE_PEM);
+        // rsa_private.der holds the PKCS #8 form
+        assert_eq!(pair.to_pkcs8_der().unwrap(), RSA_PRIVATE_DER);
+        let pub_key = pair.public();
+        assert_eq!(pub_key.to_pkcs1_pem(), RSA_PUBLIC_PEM);
+        assert_eq!(pub_key.to_spki_pem(), SPKI_PUBLIC_PEM);
+        assert_eq!(pub_key.to_spki_der(), SPKI_PUBLIC_DER);
+    }
+
+    #[test]
+    fn generated_keys_round_trip_through_pem_and_der() {
+        let (pub_key, priv_key) = crate::gen_keys_with_digits(20);
+        assert_eq!(
+            PublicKey::from_pem(&pub_key.to_spki_pem()).unwrap(),
+            pub_key
+        );
+        assert_eq!(
+            PublicKey::from_der(&pub_key.to_pkcs1_der()).unwrap(),
+            pub_key
+        );
+        let pem = priv_key.to_pkcs8_pem(&pub_key).unwrap();
+        assert_eq!(PrivateKey::from_pem(&pem).unwrap().d, priv_key.d);
+        let der = priv_key.to_pkcs1_der(&pub_key).unwrap();
+        assert_eq!(PrivateKey::from_der(&der).unwrap().d, priv_key.d);
+        let pair = KeyPair::try_from(der.as_slice()).unwrap();
+        assert_eq!(
+            pair.crt_params(),
+            KeyPair::new(pub_key.clone(), priv_key).crt_params()
+        );
+        // Each constructor takes only its own encoding
+        assert!(PublicKey::from_der(pem.as_bytes()).is_err());
+        assert!(PublicKey::from_pem(PUBLIC_JWK).is_err());
+    }
+
+    #[test]
+    fn formats_are_detected() {
+        assert_eq!(detect(RSA_PUBLIC_PEM.as_bytes()), Some(KeyFormat::Pem));
+        assert_eq!(detect(SPKI_PUBLIC_DER), Some(KeyFormat::Der));
+        assert_eq!(detect(PUBLIC_JWK.as_bytes()), Some(KeyFormat::Jwk));
+        assert_eq!(detect(OPENSSH_PUBLIC.as_bytes()), Some(KeyFormat::OpenSsh));
+        assert_eq!(detect(b"hello"), None);
+    }
+
+    #[test]
+    fn damaged_files_report_where() {
+        let truncated = &SPKI_PUBLIC_DER[..SPKI_PUBLIC_DER.len() - 10];
+        let err = PublicKey::try_from(truncated).unwrap_err();
+        assert!(matches!(err.kind(), ParseErrorKind::Truncated { .. }));
+        let err = SPKI_PUBLIC_PEM
+            .replace("PUBLIC KEY", "CERTIFICATE")
+            .parse::<PublicKey>()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid PEM: expected an RSA key label, found CERTIFICATE (at byte 11)"
+        );
+        let err = PUBLIC_JWK
+            .replace("\"RSA\"", "\"EC\"")
+            .parse::<PublicKey>()
+            .unwrap_err();
+        assert_eq!(err.format(), "JWK");
+    }
+
+    #[test]
+    fn repeated_primes_are_an_error() {
+        // p = q = 1000003
+        let jwk = r#"{"kty":"RSA","n":"6NUAnYk","e":"AQAB","d":"MDk","p":"D0JD","q":"D0JD"}"#;
+        let err = jwk.parse::<PrivateKey>().err().unwrap();
+        assert_eq!(err.format(), "JWK");
+        assert!(jwk.parse::<KeyPair>().is_err());
+    }
+}
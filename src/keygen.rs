@@ -0,0 +1,416 @@
+// Key generation with constraints on how far apart the primes are
+//
+// If p and q are close, n is close to a square and Fermat's method factors it quickly, so
+// FIPS 186-4 (appendix B.3.1) requires |p - q| > 2^(nlen/2 - 100) for an nlen-bit modulus.
+// KeyGenParams sets a minimum distance of 2^bits and what to do when a pair falls short:
+// draw a new q, draw both primes again, or give up. The fips preset always enforces the
+// FIPS bound for the modulus that comes out, on top of any stricter minimum; elsewhere the
+// constraint is off unless asked for. generate reports the constraints it applied and how
+// many pairs it went through in a KeyGenStats; with the alloc-stats feature, also what the
+// prime search and the derivation of the key allocated. Primes are sized in decimal digits
+// as in gen_keys_with_digits, or by the bit length of the modulus for standard sizes such
+// as 2048 bits, and generate_with_rng draws every random choice from a caller's RNG.
+// safe_primes asks for primes p = 2p' + 1 with p' prime, at many times the search cost.
+#[cfg(feature = "alloc-stats")]
+use crate::alloc_stats::{self, AllocStats};
+use crate::error::RsaError;
+use crate::keypair::KeyPair;
+use crate::{extended_gcd, prime_bit_range, prime_range, random_prime_in, random_safe_prime_in};
+use num_bigint::BigInt;
+use num_traits::{One, Signed};
+use rand::{CryptoRng, RngCore};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+// Bits below half the modulus size that FIPS 186-4 lets |p - q| fall to
+const FIPS_DISTANCE_SLACK: u64 = 100;
+
+// OnFailure: what generate does with a prime pair that violates the distance constraint
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnFailure {
+    RegenerateQ,
+    RegenerateBoth,
+    Fail,
+}
+
+// PrimeSize: how big each prime is, in decimal digits or in bits with the top two set
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrimeSize {
+    Digits(u32),
+    Bits(u64),
+}
+
+#[derive(Clone, Debug)]
+pub struct KeyGenParams {
+    size: PrimeSize,
+    e: BigInt,
+    min_distance_bits: Option<u64>,
+    on_failure: OnFailure,
+    max_attempts: Option<u32>,
+    fips: bool,
+    safe_primes: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct KeyGenStats {
+    pub prime_size: PrimeSize,
+    pub modulus_bits: u64,
+    // The minimum applied, as bits b in |p - q| > 2^b; for fips, the larger of the FIPS
+    // bound and the requested minimum
+    pub min_distance_bits: Option<u64>,
+    pub fips: bool,
+    pub safe_primes: bool,
+    pub on_failure: OnFailure,
+    // Prime pairs examined, including the one that was kept
+    pub attempts: u32,
+    // Size of the |p - q| that was accepted, in bits
+    pub distance_bits: u64,
+    pub elapsed: Duration,
+    // Allocations made drawing primes, over all attempts
+    #[cfg(feature = "alloc-stats")]
+    pub prime_search: AllocStats,
+    // Allocations made computing n, d and the rest of the key pair
+    #[cfg(feature = "alloc-stats")]
+    pub derivation: AllocStats,
+}
+
+// Tally: what the closures it runs allocated, if the alloc-stats feature is on
+#[derive(Default)]
+struct Tally {
+    #[cfg(feature = "alloc-stats")]
+    stats: AllocStats,
+}
+
+impl Tally {
+    fn run<T>(&mut self, f: impl FnOnce() -> T) -> T {
+        #[cfg(feature = "alloc-stats")]
+        {
+            let (value, stats) = alloc_stats::measure(f);
+            self.stats += stats;
+            value
+        }
+        #[cfg(not(feature = "alloc-stats"))]
+        f()
+    }
+}
+
+impl KeyGenParams {
+    // new: ndigits primes as in gen_keys_with_digits, e = 65537, no distance constraint
+    pub fn new(ndigits: u32) -> Self {
+        KeyGenParams::with_size(PrimeSize::Digits(ndigits))
+    }
+
+    fn with_size(size: PrimeSize) -> Self {
+        KeyGenParams {
+            size,
+            e: BigInt::from(65537),
+            min_distance_bits: None,
+            on_failure: OnFailure::RegenerateQ,
+            max_attempts: None,
+            fips: false,
+            safe_primes: false,
+        }
+    }
+
+    // bits: a modulus of exactly bits bits, from two primes of half that size
+    pub fn bits(bits: u64) -> Self {
+        if !bits.is_multiple_of(2) || bits < 16 {
+            panic!("cannot split a {}-bit modulus into equal primes", bits);
+        }
+        KeyGenParams::with_size(PrimeSize::Bits(bits / 2))
+    }
+
+    // fips: new with the FIPS 186-4 distance bound enforced
+    pub fn fips(ndigits: u32) -> Self {
+        KeyGenParams {
+            fips: true,
+            ..KeyGenParams::new(ndigits)
+        }
+    }
+
+    // fips_bits: bits with the FIPS 186-4 distance bound enforced
+    pub fn fips_bits(bits: u64) -> Self {
+        KeyGenParams {
+            fips: true,
+            ..KeyGenParams::bits(bits)
+        }
+    }
+
+    // exponent: the public exponent, which must be odd and at least 3 for any prime to
+    // leave it invertible
+    pub fn exponent(self, e: BigInt) -> Result<Self, RsaError> {
+        if e < BigInt::from(3) || !e.bit(0) {
+            return Err(RsaError::InvalidKey(
+                "the public exponent must be odd and at least 3",
+            ));
+        }
+        Ok(KeyGenParams { e, ..self })
+    }
+
+    // min_distance_bits: require |p - q| > 2^bits
+    pub fn min_distance_bits(self, bits: u64) -> Self {
+        KeyGenParams {
+            min_distance_bits: Some(bits),
+            ..self
+        }
+    }
+
+    pub fn on_failure(self, on_failure: OnFailure) -> Self {
+        KeyGenParams { on_failure, ..self }
+    }
+
+    // max_attempts: give up after this many prime pairs; unlimited by default
+    pub fn max_attempts(self, attempts: u32) -> Self {
+        KeyGenParams {
+            max_attempts: Some(attempts),
+            ..self
+        }
+    }
+
+    // safe_primes: draw both primes as safe primes
+    pub fn safe_primes(self) -> Self {
+        KeyGenParams {
+            safe_primes: true,
+            ..self
+        }
+    }
+
+    // required_distance_bits: the constraint a pair with modulus n has to meet
+    fn required_distance_bits(&self, n: &BigInt) -> Option<u64> {
+        let fips = (n.bits() / 2).saturating_sub(FIPS_DISTANCE_SLACK);
+        match (self.fips, self.min_distance_bits) {
+            (true, Some(bits)) => Some(bits.max(fips)),
+            (true, None) => Some(fips),
+            (false, bits) => bits,
+        }
+    }
+
+    fn prime(&self, rng: &mut (impl CryptoRng + RngCore)) -> BigInt {
+        loop {
+            let (low, high) = match self.size {
+                PrimeSize::Digits(ndigits) => prime_range(ndigits),
+                PrimeSize::Bits(bits) => prime_bit_range(bits),
+            };
+            let p = if self.safe_primes {
+                random_safe_prime_in(&low, &high, rng)
+            } else {
+                random_prime_in(&low, &high, rng)
+            };
+            // e must be invertible mod p - 1, not merely not divide it
+            if extended_gcd(&p - 1u32, self.e.clone()).0.is_one() {
+                return p;
+            }
+        }
+    }
+
+    // generate: a key pair meeting the constraints, or None once max_attempts pairs or,
+    // with OnFailure::Fail, the first pair have failed them
+    pub fn generate(&self) -> Option<(KeyPair, KeyGenStats)> {
+        self.generate_with_rng(&mut rand::thread_rng())
+    }
+
+    // generate_with_rng: generate with the primes and Miller-Rabin bases drawn from rng, so
+    // that a seeded RNG gives the same key pair every time
+    pub fn generate_with_rng(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Option<(KeyPair, KeyGenStats)> {
+        let start = Instant::now();
+        let (mut search, mut derivation) = (Tally::default(), Tally::default());
+        let mut p = search.run(|| self.prime(rng));
+        let mut attempts = 0;
+        loop {
+            let q = search.run(|| self.prime(rng));
+            if q == p {
+                continue;
+            }
+            attempts += 1;
+            let n = &p * &q;
+            let distance = (&p - &q).abs();
+            let required = self.required_distance_bits(&n);
+            if required.is_none_or(|bits| distance > BigInt::from(1) << bits) {
+                let e = self.e.clone();
+                let pair = derivation.run(|| KeyPair::from_primes(p, q, e));
+                let stats = KeyGenStats {
+                    prime_size: self.size,
+                    modulus_bits: n.bits(),
+                    min_distance_bits: required,
+                    fips: self.fips,
+                    safe_primes: self.safe_primes,
+                    on_failure: self.on_failure,
+                    attempts,
+                    distance_bits: distance.bits(),
+                    elapsed: start.elapsed(),
+                    #[cfg(feature = "alloc-stats")]
+                    prime_search: search.stats,
+                    #[cfg(feature = "alloc-stats")]
+                    derivation: derivation.stats,
+                };
+                return Some((pair, stats));
+            }
+            if self.on_failure == OnFailure::Fail || Some(attempts) == self.max_attempts {
+                return None;
+            }
+            if self.on_failure == OnFailure::RegenerateBoth {
+                p = search.run(|| self.prime(rng));
+            }
+        }
+    }
+}
+
+impl fmt::Display for PrimeSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PrimeSize::Digits(ndigits) => write!(f, "{}-digit", ndigits),
+            PrimeSize::Bits(bits) => write!(f, "{}-bit", bits),
+        }
+    }
+}
+
+impl fmt::Display for KeyGenStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "modulus: {} bits from {} {}primes",
+            self.modulus_bits,
+            self.prime_size,
+            if self.safe_primes { "safe " } else { "" }
+        )?;
+        match self.min_distance_bits {
+            Some(bits) => writeln!(
+                f,
+                "|p - q|: {} bits, required > 2^{}{}",
+                self.distance_bits,
+                bits,
+                if self.fips { " (FIPS 186-4)" } else { "" }
+            )?,
+            None => writeln!(f, "|p - q|: {} bits, unconstrained", self.distance_bits)?,
+        }
+        write!(
+            f,
+            "attempts: {} ({:?} on failure), {:?}",
+            self.attempts, self.on_failure, self.elapsed
+        )?;
+        #[cfg(feature = "alloc-stats")]
+        write!(
+            f,
+            "\nprime search: {}\nderivation: {}",
+            self.prime_search, self.derivation
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::is_probable_prime;
+
+    #[test]
+    fn distance_constraint_is_met() {
+        // 20-digit primes are below 2^127, so a third or so of pairs are 2^125 apart
+        for on_failure in [OnFailure::RegenerateQ, OnFailure::RegenerateBoth] {
+            let (pair, stats) = KeyGenParams::new(20)
+                .min_distance_bits(125)
+                .on_failure(on_failure)
+                .generate()
+                .unwrap();
+            let (p, q) = pair.primes().unwrap();
+            assert!((p - q).abs() > BigInt::from(1) << 125);
+            assert_eq!(stats.min_distance_bits, Some(125));
+            assert!(stats.attempts >= 1);
+        }
+    }
+
+    #[test]
+    fn impossible_constraints_give_up() {
+        let params = KeyGenParams::new(20).min_distance_bits(200);
+        assert!(params
+            .clone()
+            .on_failure(OnFailure::Fail)
+            .generate()
+            .is_none());
+        assert!(params.max_attempts(3).generate().is_none());
+    }
+
+    #[test]
+    fn bit_sizes_are_exact_and_seeded_rngs_reproduce_keys() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        let params = KeyGenParams::fips_bits(512)
+            .exponent(BigInt::from(3))
+            .unwrap();
+        let (a, stats) = params
+            .generate_with_rng(&mut StdRng::seed_from_u64(7))
+            .unwrap();
+        let (b, _) = params
+            .generate_with_rng(&mut StdRng::seed_from_u64(7))
+            .unwrap();
+        assert_eq!(a.public(), b.public());
+        assert_eq!(a.public().n.bits(), 512);
+        assert_eq!(a.public().e, BigInt::from(3));
+        assert_eq!(stats.prime_size, PrimeSize::Bits(256));
+        assert!(stats
+            .to_string()
+            .starts_with("modulus: 512 bits from 256-bit primes"));
+    }
+
+    #[test]
+    fn fips_mode_enforces_its_bound() {
+        let (pair, stats) = KeyGenParams::fips(40).generate().unwrap();
+        let bound = pair.public().n.bits() / 2 - FIPS_DISTANCE_SLACK;
+        assert!(stats.fips);
+        assert_eq!(stats.min_distance_bits, Some(bound));
+        let (_, stats) = KeyGenParams::fips(40)
+            .min_distance_bits(10)
+            .generate()
+            .unwrap();
+        assert!(stats.min_distance_bits.unwrap() > 10);
+        assert!(stats.to_string().contains("(FIPS 186-4)"));
+        assert_eq!(
+            KeyGenParams::new(20)
+                .generate()
+                .unwrap()
+                .1
+                .min_distance_bits,
+            None
+        );
+    }
+
+    #[test]
+    fn safe_primes_are_drawn_on_request() {
+        let (pair, stats) = KeyGenParams::bits(192).safe_primes().generate().unwrap();
+        let (p, q) = pair.primes().unwrap();
+        for prime in [p, q] {
+            assert_eq!(prime.bits(), 96);
+            assert!(is_probable_prime(prime / 2, 20));
+        }
+        assert!(stats.safe_primes);
+        assert!(stats.to_string().contains("96-bit safe primes"));
+    }
+
+    #[test]
+    fn exponents_must_be_odd_and_coprime_to_the_primes() {
+        for e in [0, 1, 2, 65536] {
+            assert!(matches!(
+                KeyGenParams::bits(64).exponent(BigInt::from(e)),
+                Err(RsaError::InvalidKey(_))
+            ));
+        }
+        // 15 is odd but shares 3 or 5 with many p - 1
+        let params = KeyGenParams::bits(64).exponent(BigInt::from(15)).unwrap();
+        for _ in 0..10 {
+            let (pair, _) = params.generate().unwrap();
+            let (p, q) = pair.primes().unwrap();
+            for prime in [p, q] {
+                assert!(extended_gcd(prime - 1u32, BigInt::from(15)).0.is_one());
+            }
+            let (pub_key, priv_key) = pair.into_keys();
+            let c = crate::encrypt(&pub_key, BigInt::from(42).into()).unwrap();
+            assert_eq!(
+                crate::decrypt(&pub_key, &priv_key, c).unwrap(),
+                BigInt::from(42).into()
+            );
+        }
+    }
+}
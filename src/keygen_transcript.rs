@@ -0,0 +1,309 @@
+// Reproducible key generation with an auditable transcript
+//
+// gen_keys_with_transcript draws a 32-byte seed and derives everything else from it: the
+// i-th prime candidate for p (or q) is hashed from the seed, the label and i, and so are
+// the Miller-Rabin bases each candidate is tested with. The KeyGenTranscript records the
+// seed, which candidate became each prime, the bases that prime passed and how d follows
+// from the primes; verify replays the whole derivation and checks that it arrives at the
+// same key. A hash over the contents seals the transcript, so an edited copy is rejected
+// before anything is replayed. It contains the seed, so it is exactly as secret as the
+// private key it describes.
+use crate::error::ParseError;
+use crate::hash::{full_domain, TranscriptHash};
+use crate::wire::{Reader, Writer};
+use crate::{invert, is_strong_probable_prime, prime_range, PrivateKey, PublicKey};
+use num_bigint::BigInt;
+use num_traits::{One, Zero};
+use rand::RngCore;
+use std::fmt;
+
+const MR_ROUNDS: u32 = 40;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrimeRecord {
+    // The index of the candidate that became the prime; every earlier one was rejected
+    candidate: u64,
+    prime: BigInt,
+    witnesses: Vec<BigInt>,
+}
+
+impl PrimeRecord {
+    pub fn candidate_index(&self) -> u64 {
+        self.candidate
+    }
+
+    pub fn prime(&self) -> &BigInt {
+        &self.prime
+    }
+
+    // witnesses: the Miller-Rabin bases the prime was tested with, all of which it passed
+    pub fn witnesses(&self) -> &[BigInt] {
+        &self.witnesses
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyGenTranscript {
+    seed: [u8; 32],
+    ndigits: u32,
+    e: BigInt,
+    p: PrimeRecord,
+    q: PrimeRecord,
+    phi: BigInt,
+    d: BigInt,
+    seal: Vec<u8>,
+}
+
+fn derived(seed: &[u8], label: &str, candidate: u64, round: u32, modulus: &BigInt) -> BigInt {
+    let h = TranscriptHash::new("naive-rsa keygen")
+        .bytes(seed)
+        .bytes(label.as_bytes())
+        .bytes(&candidate.to_be_bytes())
+        .bytes(&round.to_be_bytes())
+        .finish();
+    full_domain(&h, modulus)
+}
+
+// derive_prime: the first candidate with gcd(e, c - 1) = 1 that differs from avoid and passes
+// every Miller-Rabin base derived for it
+fn derive_prime(
+    seed: &[u8],
+    label: &str,
+    ndigits: u32,
+    e: &BigInt,
+    avoid: Option<&BigInt>,
+) -> PrimeRecord {
+    let (low, high) = prime_range(ndigits);
+    let width: BigInt = &high - &low;
+    for candidate in 0.. {
+        // Round 0 picks the candidate, rounds 1.. its bases in [2, c - 2]
+        let c = (&low + derived(seed, label, candidate, 0, &width)) | BigInt::one();
+        if avoid == Some(&c) || ((&c - 1u8) % e).is_zero() {
+            continue;
+        }
+        let base_range: BigInt = &c - 3u8;
+        let witness = |round| derived(seed, label, candidate, round, &base_range) + 2u8;
+        if (1..=MR_ROUNDS).all(|round| is_strong_probable_prime(&c, &witness(round))) {
+            return PrimeRecord {
+                candidate,
+                witnesses: (1..=MR_ROUNDS).map(witness).collect(),
+                prime: c,
+            };
+        }
+    }
+    unreachable!()
+}
+
+// gen_keys_with_transcript: like gen_keys_with_digits, plus the record of how the key came about
+pub fn gen_keys_with_transcript(ndigits: u32) -> (PublicKey, PrivateKey, KeyGenTranscript) {
+    let mut seed = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut seed);
+    gen_keys_from_seed(&seed, ndigits)
+}
+
+// gen_keys_from_seed: the key, and transcript, that a seed determines
+pub fn gen_keys_from_seed(
+    seed: &[u8; 32],
+    ndigits: u32,
+) -> (PublicKey, PrivateKey, KeyGenTranscript) {
+    let e = BigInt::from(65537);
+    let p = derive_prime(seed, "p", ndigits, &e, None);
+    let q = derive_prime(seed, "q", ndigits, &e, Some(&p.prime));
+    let phi: BigInt = (&p.prime - 1u8) * (&q.prime - 1u8);
+    let d = invert(e.clone(), phi.clone());
+    let mut transcript = KeyGenTranscript {
+        seed: *seed,
+        ndigits,
+        e,
+        p,
+        q,
+        phi,
+        d,
+        seal: Vec::new(),
+    };
+    transcript.seal = transcript.compute_seal();
+    let pub_key = PublicKey {
+        n: &transcript.p.prime * &transcript.q.prime,
+        e: transcript.e.clone(),
+    };
+    let priv_key = PrivateKey::with_primes(
+        transcript.d.clone(),
+        transcript.p.prime.clone(),
+        transcript.q.prime.clone(),
+    );
+    (pub_key, priv_key, transcript)
+}
+
+impl KeyGenTranscript {
+    fn compute_seal(&self) -> Vec<u8> {
+        let mut h = TranscriptHash::new("naive-rsa keygen transcript");
+        h.bytes(&self.seed)
+            .bytes(&self.ndigits.to_be_bytes())
+            .int(&self.e);
+        for record in [&self.p, &self.q] {
+            h.bytes(&record.candidate.to_be_bytes()).int(&record.prime);
+            for w in &record.witnesses {
+                h.int(w);
+            }
+        }
+        h.int(&self.phi).int(&self.d).finish()
+    }
+
+    pub fn seed(&self) -> &[u8; 32] {
+        &self.seed
+    }
+
+    pub fn primes(&self) -> (&PrimeRecord, &PrimeRecord) {
+        (&self.p, &self.q)
+    }
+
+    // phi: (p - 1)(q - 1), the modulus d was inverted under
+    pub fn phi(&self) -> &BigInt {
+        &self.phi
+    }
+
+    pub fn seal(&self) -> &[u8] {
+        &self.seal
+    }
+
+    // verify: the seal is intact, replaying the seed reproduces this transcript exactly, and
+    // the transcript describes the given key
+    pub fn verify(&self, pub_key: &PublicKey, priv_key: &PrivateKey) -> bool {
+        if self.seal != self.compute_seal() {
+            return false;
+        }
+        let (replayed_pub, replayed_priv, replayed) = gen_keys_from_seed(&self.seed, self.ndigits);
+        replayed == *self && replayed_pub == *pub_key && replayed_priv.d == priv_key.d
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::new("naive-rsa keygen transcript");
+        w.bytes(&self.seed)
+            .bytes(&self.ndigits.to_be_bytes())
+            .int(&self.e);
+        for record in [&self.p, &self.q] {
+            w.bytes(&record.candidate.to_be_bytes())
+                .int(&record.prime)
+                .bytes(&(record.witnesses.len() as u32).to_be_bytes());
+            for witness in &record.witnesses {
+                w.int(witness);
+            }
+        }
+        w.int(&self.phi).int(&self.d).bytes(&self.seal).finish()
+    }
+
+    // from_bytes: also rejects a transcript whose seal does not match its contents
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        const FORMAT: &str = "naive-rsa keygen transcript";
+        let mut r = Reader::new(bytes, FORMAT)?;
+        let fixed = |item: &[u8], offset| {
+            ParseError::invalid(
+                FORMAT,
+                offset,
+                format!("unexpected {}-byte field", item.len()),
+            )
+        };
+        let offset = r.offset();
+        let seed = r.bytes()?;
+        let seed: [u8; 32] = seed.try_into().map_err(|_| fixed(seed, offset))?;
+        let ndigits = r.count()?;
+        let e = r.int()?;
+        let mut record = || -> Result<PrimeRecord, ParseError> {
+            let offset = r.offset();
+            let index = r.bytes()?;
+            let candidate = u64::from_be_bytes(index.try_into().map_err(|_| fixed(index, offset))?);
+            let prime = r.int()?;
+            let witnesses = (0..r.count()?).map(|_| r.int()).collect::<Result<_, _>>()?;
+            Ok(PrimeRecord {
+                candidate,
+                prime,
+                witnesses,
+            })
+        };
+        let (p, q) = (record()?, record()?);
+        let (phi, d) = (r.int()?, r.int()?);
+        let seal_offset = r.offset();
+        let seal = r.bytes()?.to_vec();
+        r.finish()?;
+        let transcript = KeyGenTranscript {
+            seed,
+            ndigits,
+            e,
+            p,
+            q,
+            phi,
+            d,
+            seal,
+        };
+        if transcript.seal != transcript.compute_seal() {
+            return Err(ParseError::invalid(
+                FORMAT,
+                seal_offset,
+                "the seal does not match the contents",
+            ));
+        }
+        Ok(transcript)
+    }
+}
+
+impl fmt::Display for KeyGenTranscript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, record) in [("p", &self.p), ("q", &self.q)] {
+            writeln!(
+                f,
+                "{} = {} (candidate #{}, passed {} Miller-Rabin rounds)",
+                name,
+                record.prime,
+                record.candidate,
+                record.witnesses.len()
+            )?;
+        }
+        writeln!(f, "phi = (p - 1)(q - 1) = {}", self.phi)?;
+        write!(f, "d = {}^-1 mod phi = {}", self.e, self.d)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decrypt, encrypt, Plaintext};
+
+    #[test]
+    fn transcripts_replay_to_the_same_key() {
+        let (pub_key, priv_key, transcript) = gen_keys_with_transcript(20);
+        assert!(transcript.verify(&pub_key, &priv_key));
+        let m = Plaintext::from(BigInt::from(1234));
+        assert_eq!(
+            decrypt(&pub_key, &priv_key, encrypt(&pub_key, m.clone()).unwrap()).unwrap(),
+            m
+        );
+        let (again, _, _) = gen_keys_from_seed(transcript.seed(), 20);
+        assert_eq!(again, pub_key);
+        let (other, _, _) = gen_keys_from_seed(&[1; 32], 20);
+        assert_ne!(other, pub_key);
+        assert!(!transcript.verify(&other, &priv_key));
+    }
+
+    #[test]
+    fn edited_transcripts_are_rejected() {
+        let (pub_key, priv_key, transcript) = gen_keys_with_transcript(20);
+        let mut edited = transcript.clone();
+        edited.p.witnesses[0] += 1;
+        assert!(!edited.verify(&pub_key, &priv_key));
+        // Resealing does not help: replaying the seed gives the original witnesses
+        edited.seal = edited.compute_seal();
+        assert!(!edited.verify(&pub_key, &priv_key));
+    }
+
+    #[test]
+    fn transcripts_round_trip_through_bytes() {
+        let (pub_key, priv_key, transcript) = gen_keys_with_transcript(20);
+        let decoded = KeyGenTranscript::from_bytes(&transcript.to_bytes()).unwrap();
+        assert_eq!(decoded, transcript);
+        assert!(decoded.verify(&pub_key, &priv_key));
+        let mut bytes = transcript.to_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(KeyGenTranscript::from_bytes(&bytes).is_err());
+    }
+}
@@ -0,0 +1,309 @@
+// A public key together with its private key, and the primes when they are known
+//
+// Key files and tools disagree on details that leave the key itself unchanged: which prime
+// is called p, whether d is reduced mod lambda(n) or phi(n), whether CRT parameters are
+// stored at all. KeyPair::diff sorts the differences between two key pairs into those that
+// change the key and those that only change its representation, which is what one wants
+// to know when a key did not survive an export and re-import.
+use crate::crt::Crt;
+use crate::error::RsaError;
+use crate::exponents::{carmichael, factor_from_exponents};
+use crate::{gen_prime_pair, invert, PrivateKey, PublicKey};
+use num_bigint::BigInt;
+use std::fmt;
+
+pub struct KeyPair {
+    public: PublicKey,
+    private: PrivateKey,
+    // As given to with_primes, in that order; None otherwise, even when the private key
+    // keeps its own primes for CRT
+    primes: Option<(BigInt, BigInt)>,
+}
+
+// Match: how one component of two key pairs compares
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Match {
+    Same,
+    // Different numbers that act the same: private exponents congruent mod lambda(n)
+    Equivalent,
+    // The same two primes, in the opposite order
+    Swapped,
+    Different,
+    // Not enough information on one side to compare, e.g. d does not factor n
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyDiff {
+    pub n: Match,
+    pub e: Match,
+    pub d: Match,
+    pub primes: Match,
+    // The CRT parameters (dP, dQ, qInv) each side implies, recomputed from p, q and d
+    pub crt: Match,
+}
+
+impl KeyDiff {
+    // same_key: both pairs encrypt and decrypt identically
+    pub fn same_key(&self) -> bool {
+        self.n == Match::Same
+            && self.e == Match::Same
+            && matches!(self.d, Match::Same | Match::Equivalent)
+    }
+}
+
+impl KeyPair {
+    pub fn new(public: PublicKey, private: PrivateKey) -> Self {
+        KeyPair {
+            public,
+            private,
+            primes: None,
+        }
+    }
+
+    // with_primes: a key pair that also remembers p and q in the given order; panics unless
+    // p * q is the modulus
+    pub fn with_primes(public: PublicKey, private: PrivateKey, p: BigInt, q: BigInt) -> Self {
+        KeyPair::try_with_primes(public, private, p, q).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // try_with_primes: with_primes, or InvalidKey if p * q is not the modulus
+    pub fn try_with_primes(
+        public: PublicKey,
+        private: PrivateKey,
+        p: BigInt,
+        q: BigInt,
+    ) -> Result<Self, RsaError> {
+        if &p * &q != public.n {
+            return Err(RsaError::InvalidKey("p * q does not equal the modulus"));
+        }
+        // The private key gets the primes too, so that the pair decrypts by CRT
+        let private = match private.has_crt() {
+            true => private,
+            false => private.with_crt(p.clone(), q.clone()),
+        };
+        Ok(KeyPair {
+            public,
+            private,
+            primes: Some((p, q)),
+        })
+    }
+
+    // from_primes: the key pair with primes p and q and public exponent e, d taken mod phi(n)
+    pub fn from_primes(p: BigInt, q: BigInt, e: BigInt) -> Self {
+        let phi: BigInt = (&p - 1) * (&q - 1);
+        let d = invert(e.clone(), phi);
+        let public = PublicKey { n: &p * &q, e };
+        KeyPair::with_primes(public, PrivateKey::new(d), p, q)
+    }
+
+    pub fn generate(ndigits: u32) -> Self {
+        let e = BigInt::from(65537);
+        let (p, q) = gen_prime_pair(ndigits, &e);
+        KeyPair::from_primes(p, q, e)
+    }
+
+    pub fn public(&self) -> &PublicKey {
+        &self.public
+    }
+
+    pub fn private(&self) -> &PrivateKey {
+        &self.private
+    }
+
+    // into_keys: the public and private key, as gen_keys returns them
+    pub fn into_keys(self) -> (PublicKey, PrivateKey) {
+        (self.public, self.private)
+    }
+
+    // has_stored_primes: whether p and q were given or the private key keeps them for this
+    // modulus, rather than having to be recovered from d
+    pub fn has_stored_primes(&self) -> bool {
+        self.primes.is_some() || self.own_primes().is_some()
+    }
+
+    // primes: the stored primes, or the private key's own, or else the ones recovered from
+    // d (smaller first)
+    pub fn primes(&self) -> Option<(BigInt, BigInt)> {
+        self.primes
+            .clone()
+            .or_else(|| self.own_primes())
+            .or_else(|| factor_from_exponents(&self.public, &self.private.d))
+    }
+
+    // own_primes: the private key's CRT primes, if they belong to this modulus
+    fn own_primes(&self) -> Option<(BigInt, BigInt)> {
+        self.private
+            .primes()
+            .filter(|(p, q)| *p * *q == self.public.n)
+            .map(|(p, q)| (p.clone(), q.clone()))
+    }
+
+    // crt_params: (dP, dQ, qInv) for the primes in the order primes() gives them
+    pub fn crt_params(&self) -> Option<(BigInt, BigInt, BigInt)> {
+        let crt = self.crt()?;
+        Some((crt.dp, crt.dq, crt.qinv))
+    }
+
+    // crt: the CRT parameters for the primes in the order primes() gives them
+    pub(crate) fn crt(&self) -> Option<Crt> {
+        let (p, q) = self.primes()?;
+        Crt::new(&self.private.d, p, q)
+    }
+
+    pub fn diff(&self, other: &KeyPair) -> KeyDiff {
+        let same = |equal: bool| if equal { Match::Same } else { Match::Different };
+        let n = same(self.public.n == other.public.n);
+        let (primes, other_primes) = (self.primes(), other.primes());
+        let d = if self.private.d == other.private.d {
+            Match::Same
+        } else if n == Match::Different {
+            Match::Different
+        } else {
+            match primes.as_ref().or(other_primes.as_ref()) {
+                Some((p, q)) => {
+                    let lambda = carmichael(p, q);
+                    let congruent =
+                        (&self.private.d - &other.private.d) % lambda == BigInt::from(0);
+                    if congruent {
+                        Match::Equivalent
+                    } else {
+                        Match::Different
+                    }
+                }
+                None => Match::Unknown,
+            }
+        };
+        let primes_match = match (&primes, &other_primes) {
+            (Some((p1, q1)), Some((p2, q2))) if p1 == p2 && q1 == q2 => Match::Same,
+            (Some((p1, q1)), Some((p2, q2))) if p1 == q2 && q1 == p2 => Match::Swapped,
+            (Some(_), Some(_)) => Match::Different,
+            _ => Match::Unknown,
+        };
+        let crt = match (self.crt_params(), other.crt_params()) {
+            (Some(a), Some(b)) => same(a == b),
+            _ => Match::Unknown,
+        };
+        KeyDiff {
+            n,
+            e: same(self.public.e == other.public.e),
+            d,
+            primes: primes_match,
+            crt,
+        }
+    }
+}
+
+impl fmt::Display for Match {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Match::Same => "same",
+            Match::Equivalent => "different but equivalent",
+            Match::Swapped => "swapped",
+            Match::Different => "different",
+            Match::Unknown => "cannot tell",
+        })
+    }
+}
+
+impl fmt::Display for KeyDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "n: {}", self.n)?;
+        writeln!(f, "e: {}", self.e)?;
+        writeln!(f, "d: {}", self.d)?;
+        writeln!(f, "p, q: {}", self.primes)?;
+        writeln!(f, "CRT parameters: {}", self.crt)?;
+        if self.same_key() {
+            write!(f, "=> the same key")?;
+            if self.primes == Match::Swapped {
+                write!(
+                    f,
+                    "; the primes are swapped, so CRT parameters must be recomputed"
+                )?;
+            }
+            Ok(())
+        } else {
+            write!(f, "=> different keys")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_pairs_match_everywhere() {
+        let a = KeyPair::generate(20);
+        let (p, q) = a.primes().unwrap();
+        let b = KeyPair::from_primes(p, q, BigInt::from(65537));
+        let diff = a.diff(&b);
+        assert_eq!(
+            diff,
+            KeyDiff {
+                n: Match::Same,
+                e: Match::Same,
+                d: Match::Same,
+                primes: Match::Same,
+                crt: Match::Same,
+            }
+        );
+        assert!(diff.same_key());
+    }
+
+    #[test]
+    fn representation_differences_keep_the_key() {
+        let (p, q) = KeyPair::generate(20).primes().unwrap();
+        let (small, large) = if p < q { (p, q) } else { (q, p) };
+        let a = KeyPair::from_primes(small.clone(), large.clone(), BigInt::from(65537));
+        // d + lambda(n), as a tool that does not reduce d might store, and the primes the
+        // other way round
+        let d = &a.private.d + carmichael(&small, &large);
+        let b = KeyPair::with_primes(a.public.clone(), PrivateKey::new(d), large, small);
+        let diff = a.diff(&b);
+        assert_eq!(diff.d, Match::Equivalent);
+        assert_eq!(diff.primes, Match::Swapped);
+        assert_eq!(diff.crt, Match::Different);
+        assert!(diff.same_key());
+        // Without stored primes they are recovered from d, smaller first
+        let c = KeyPair::new(a.public.clone(), PrivateKey::new(a.private.d.clone()));
+        assert_eq!(a.diff(&c).primes, Match::Same);
+        assert_eq!(a.diff(&c).crt, Match::Same);
+        assert_eq!(b.diff(&c).primes, Match::Swapped);
+    }
+
+    #[test]
+    fn mismatched_primes_are_an_error() {
+        let (public, private) = KeyPair::generate(20).into_keys();
+        let (p, q) = private
+            .primes()
+            .map(|(p, q)| (p.clone(), q.clone()))
+            .unwrap();
+        let result = KeyPair::try_with_primes(public, private, p, q + 2);
+        assert!(matches!(result, Err(RsaError::InvalidKey(_))));
+    }
+
+    #[test]
+    fn adding_primes_keeps_the_rest_of_the_private_key() {
+        let (public, private) = KeyPair::generate(20).into_keys();
+        let (p, q) = private
+            .primes()
+            .map(|(p, q)| (p.clone(), q.clone()))
+            .unwrap();
+        let private = private.without_crt().hardened().with_audit();
+        let pair = KeyPair::with_primes(public, private, p, q);
+        assert!(pair.private().has_crt());
+        assert!(pair.private().is_hardened());
+        assert!(pair.private().audit_log().is_some());
+    }
+
+    #[test]
+    fn unrelated_keys_differ() {
+        let diff = KeyPair::generate(20).diff(&KeyPair::generate(20));
+        assert_eq!(diff.n, Match::Different);
+        assert_eq!(diff.d, Match::Different);
+        assert_eq!(diff.primes, Match::Different);
+        assert!(!diff.same_key());
+        assert!(diff.to_string().ends_with("=> different keys"));
+    }
+}
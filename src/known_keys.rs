@@ -0,0 +1,221 @@
+// Trust on first use, like SSH's known_hosts
+//
+// The first key seen for an identity is remembered; later keys for the same identity are
+// compared against it. A changed key is either a legitimate rotation or an impersonation,
+// and only the user can tell which, so Changed is reported rather than resolved. The store
+// keeps the SHA-256 SubjectPublicKeyInfo pin of each key, one "identity SHA256:..." line per
+// identity, the same fingerprint notation that ssh-keygen -l prints.
+use crate::error::{ParseError, ParseErrorKind};
+use crate::pinning::{fingerprint, Pin};
+use crate::{base64, PublicKey};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyStatus {
+    New,
+    Match,
+    Changed,
+}
+
+// InvalidIdentity: an identity the text form could not store, being empty or holding
+// whitespace or a '#'
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidIdentity {
+    pub identity: String,
+}
+
+impl fmt::Display for InvalidIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "identity {:?} must be non-empty and free of whitespace and '#'",
+            self.identity
+        )
+    }
+}
+
+impl std::error::Error for InvalidIdentity {}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KnownKeys {
+    keys: BTreeMap<String, Pin>,
+}
+
+// display_fingerprint: "SHA256:" and the unpadded base64 pin
+pub fn display_fingerprint(pub_key: &PublicKey) -> String {
+    format_pin(&fingerprint(pub_key))
+}
+
+fn format_pin(pin: &Pin) -> String {
+    format!("SHA256:{}", base64::encode(pin, false))
+}
+
+impl KnownKeys {
+    pub fn new() -> Self {
+        KnownKeys::default()
+    }
+
+    pub fn check(&self, identity: &str, pub_key: &PublicKey) -> KeyStatus {
+        match self.keys.get(identity) {
+            None => KeyStatus::New,
+            Some(pin) if *pin == fingerprint(pub_key) => KeyStatus::Match,
+            Some(_) => KeyStatus::Changed,
+        }
+    }
+
+    // trust: remember pub_key for identity, replacing any earlier key
+    pub fn trust(&mut self, identity: &str, pub_key: &PublicKey) -> Result<(), InvalidIdentity> {
+        if identity.is_empty() || identity.contains(|c: char| c.is_whitespace() || c == '#') {
+            return Err(InvalidIdentity {
+                identity: identity.to_string(),
+            });
+        }
+        self.keys.insert(identity.to_string(), fingerprint(pub_key));
+        Ok(())
+    }
+
+    pub fn forget(&mut self, identity: &str) -> bool {
+        self.keys.remove(identity).is_some()
+    }
+
+    // fingerprint: the remembered fingerprint for identity, for showing to the user
+    pub fn fingerprint(&self, identity: &str) -> Option<String> {
+        self.keys.get(identity).map(format_pin)
+    }
+
+    // parse: the text form written by Display; blank lines and # comments are skipped
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut known = KnownKeys::new();
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            let content = line.split('#').next().unwrap_or("").trim();
+            if !content.is_empty() {
+                known.parse_entry(content, offset)?;
+            }
+            offset += line.len();
+        }
+        Ok(known)
+    }
+
+    fn parse_entry(&mut self, line: &str, offset: usize) -> Result<(), ParseError> {
+        const FORMAT: &str = "known keys";
+        let mut fields = line.split_whitespace();
+        let (identity, pin) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(identity), Some(pin), None) => (identity, pin),
+            _ => {
+                return Err(ParseError::invalid(
+                    FORMAT,
+                    offset,
+                    "expected \"identity SHA256:fingerprint\"",
+                ))
+            }
+        };
+        let encoded = pin.strip_prefix("SHA256:").ok_or_else(|| {
+            ParseError::new(
+                FORMAT,
+                offset,
+                ParseErrorKind::UnexpectedTag {
+                    expected: "SHA256:".to_string(),
+                    actual: pin.split(':').next().unwrap_or("").to_string(),
+                },
+            )
+        })?;
+        let pin: Pin = base64::decode(encoded)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| ParseError::invalid(FORMAT, offset, "not a SHA-256 fingerprint"))?;
+        self.keys.insert(identity.to_string(), pin);
+        Ok(())
+    }
+
+    // load: the store in a file; a missing file is an empty store
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                KnownKeys::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(KnownKeys::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for KnownKeys {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (identity, pin) in &self.keys {
+            writeln!(f, "{} {}", identity, format_pin(pin))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn first_use_then_match_or_change() {
+        let (key, _) = gen_keys_with_digits(20);
+        let (other, _) = gen_keys_with_digits(20);
+        let mut known = KnownKeys::new();
+        assert_eq!(known.check("example.com", &key), KeyStatus::New);
+        known.trust("example.com", &key).unwrap();
+        assert_eq!(known.check("example.com", &key), KeyStatus::Match);
+        assert_eq!(known.check("example.com", &other), KeyStatus::Changed);
+        assert_eq!(
+            known.fingerprint("example.com"),
+            Some(display_fingerprint(&key))
+        );
+        assert!(known.forget("example.com"));
+        assert_eq!(known.check("example.com", &other), KeyStatus::New);
+    }
+
+    #[test]
+    fn identities_the_file_cannot_hold_are_refused() {
+        let (key, _) = gen_keys_with_digits(20);
+        let mut known = KnownKeys::new();
+        for identity in ["", "alice smith", "tab\there", "alice#work"] {
+            assert_eq!(
+                known.trust(identity, &key),
+                Err(InvalidIdentity {
+                    identity: identity.to_string()
+                })
+            );
+        }
+        assert_eq!(known, KnownKeys::new());
+    }
+
+    // openssl pkey -pubin -in spki_public.pem -outform der | openssl dgst -sha256 -binary | base64
+    #[test]
+    fn fingerprints_match_openssl() {
+        let key: PublicKey = include_str!("../testdata/spki_public.pem").parse().unwrap();
+        assert_eq!(
+            display_fingerprint(&key),
+            "SHA256:mBdWT8tHlxsRlQXs4VFpUIq2/Wc2RZoUV3gbgutCRHY"
+        );
+    }
+
+    #[test]
+    fn stores_persist_to_files() {
+        let (a, _) = gen_keys_with_digits(20);
+        let (b, _) = gen_keys_with_digits(20);
+        let mut known = KnownKeys::new();
+        known.trust("alice", &a).unwrap();
+        known.trust("bob", &b).unwrap();
+        let path = std::env::temp_dir().join(format!("naive-rsa-known-{}", std::process::id()));
+        known.save(&path).unwrap();
+        let loaded = KnownKeys::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, known);
+        assert_eq!(KnownKeys::load(&path).unwrap(), KnownKeys::new());
+        let err = KnownKeys::parse("# comment\n\nalice MD5:abc\n").unwrap_err();
+        assert_eq!(err.offset(), 11);
+    }
+}
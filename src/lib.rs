@@ -1,19 +1,111 @@
-use num_bigint::{BigInt, BigUint, ToBigInt, RandBigInt};
+use audit::{AuditLog, Operation};
+use crt::Crt;
+use error::{ParseError, RsaError};
+use keygen::KeyGenParams;
+use mod_ring::ModRing;
+use num_bigint::{BigInt, RandBigInt};
 use num_traits::Zero;
-use std::{cmp::PartialEq, ops::{Add, Mul, Neg}};
+use prime_search::{fips_rounds, Candidates};
+use rand::{CryptoRng, RngCore};
+use std::sync::Mutex;
+use uint::Uint;
 
-// A macro to create a BigInt from a string literal
+pub mod accumulator;
+pub mod aggregate;
+#[cfg(feature = "alloc-stats")]
+pub mod alloc_stats;
+pub mod attacks;
+pub mod audit;
+pub mod backup;
+mod base32;
+mod base64;
+pub mod batch;
+pub mod bbs;
+pub mod blind;
+pub mod challenge_modulus;
+pub mod codec;
+pub mod commitment;
+pub mod compact;
+pub mod compare;
+mod components;
+mod crt;
+mod der;
+pub mod directory;
+pub mod dvs;
+pub mod error;
+pub mod exponents;
+pub mod fault;
+pub mod fdh;
+pub mod fiat_shamir;
+pub mod forward_secure;
+pub mod gm;
+pub mod handshake;
+mod hardened;
+mod hash;
+pub mod hash_alg;
+pub mod ibs;
+pub mod kem;
+pub mod key_cache;
+pub mod keyfile;
+pub mod keygen;
+pub mod keygen_transcript;
+pub mod keypair;
+pub mod known_keys;
+pub mod limbs;
+pub mod malleability;
+pub mod metered;
+pub mod mod_ring;
+pub mod modexp;
+pub mod mrsa;
+pub mod multi_recipient;
+pub mod oaep;
+pub mod okamoto_uchiyama;
+pub mod oracle;
+pub mod padding;
+pub mod paper;
+pub mod pinning;
+pub mod pkcs1v15;
+pub mod plaintext_proof;
+pub mod primality;
+pub mod prime_pool;
+mod prime_search;
+pub mod proxy;
+pub mod report;
+pub mod ring;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod signature;
+#[cfg(all(test, feature = "timing-tests"))]
+mod timing_tests;
+pub mod trapdoor;
+mod uint;
+pub mod vectors;
+pub mod well_formed;
+pub mod wheel;
+mod wire;
+
+// A macro to create a BigInt from a string literal; it panics on a bad one, and parse_int
+// is its fallible form
 #[macro_export]
 macro_rules! bi {
     ($x: expr) => {
         $crate::bi!($x, 10)
     };
     ($x: expr, $base: literal) => {
-        num_bigint::BigInt::parse_bytes($x.as_bytes(), $base).unwrap()
+        num_bigint::BigInt::parse_bytes($x.as_bytes(), $base)
+            .unwrap_or_else(|| panic!("{:?} is not a base-{} integer", $x, $base))
     }
 }
 
+// parse_int: the fallible bi!, for digits that come from input rather than the source
+pub fn parse_int(digits: &str, radix: u32) -> Result<BigInt, RsaError> {
+    BigInt::parse_bytes(digits.as_bytes(), radix).ok_or_else(|| {
+        let reason = format!("{:?} is not a base-{} integer", digits, radix);
+        RsaError::Parse(ParseError::invalid("integer", 0, reason))
+    })
+}
 
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PublicKey {
     n: BigInt,
     e: BigInt,
@@ -21,6 +113,110 @@ pub struct PublicKey {
 
 pub struct PrivateKey {
     d: BigInt,
+    // p, q, dP, dQ and qInv, for keys generated here or read with their primes
+    crt: Option<Crt>,
+    // The operations so far, for keys made with with_audit
+    audit: Option<Mutex<AuditLog>>,
+    // Whether every use of d is blinded and run by ladder, for keys made with hardened
+    hardened: bool,
+}
+
+impl PrivateKey {
+    // new: a key holding d alone, which decrypts with one full exponentiation mod n
+    pub(crate) fn new(d: BigInt) -> Self {
+        PrivateKey {
+            d,
+            crt: None,
+            audit: None,
+            hardened: false,
+        }
+    }
+
+    // with_primes: a key that decrypts mod p and q separately, or with d alone if the two
+    // are not coprime
+    pub(crate) fn with_primes(d: BigInt, p: BigInt, q: BigInt) -> Self {
+        PrivateKey::new(d).with_crt(p, q)
+    }
+
+    // with_crt: the same key, audit log and hardening included, decrypting mod p and q
+    pub(crate) fn with_crt(self, p: BigInt, q: BigInt) -> Self {
+        PrivateKey {
+            crt: Crt::new(&self.d, p, q),
+            ..self
+        }
+    }
+
+    // take_secrets: d and the primes, and the key without them (d zero, no CRT) but with
+    // its audit log and hardening; with_secrets puts them back, as KeyCache does around
+    // keeping them wrapped
+    pub(crate) fn take_secrets(self) -> (PrivateKey, BigInt, Option<(BigInt, BigInt)>) {
+        let primes = self.crt.map(|crt| (crt.p, crt.q));
+        let shell = PrivateKey {
+            d: BigInt::zero(),
+            crt: None,
+            audit: self.audit,
+            hardened: self.hardened,
+        };
+        (shell, self.d, primes)
+    }
+
+    pub(crate) fn with_secrets(self, d: BigInt, primes: Option<(BigInt, BigInt)>) -> Self {
+        let key = PrivateKey {
+            d,
+            crt: None,
+            ..self
+        };
+        match primes {
+            Some((p, q)) => key.with_crt(p, q),
+            None => key,
+        }
+    }
+
+    // exponent: the private exponent d as stored in the key
+    pub fn exponent(&self) -> &BigInt {
+        &self.d
+    }
+
+    // primes: p and q, if the key keeps them for CRT decryption
+    pub fn primes(&self) -> Option<(&BigInt, &BigInt)> {
+        self.crt.as_ref().map(|crt| (&crt.p, &crt.q))
+    }
+
+    pub fn has_crt(&self) -> bool {
+        self.crt.is_some()
+    }
+
+    // without_crt: the same key with only d, e.g. to compare the two decryption paths
+    pub fn without_crt(self) -> Self {
+        PrivateKey { crt: None, ..self }
+    }
+
+    // with_audit: the same key, recording every operation with d in a signed audit log
+    pub fn with_audit(self) -> Self {
+        PrivateKey {
+            audit: Some(Mutex::new(AuditLog::default())),
+            ..self
+        }
+    }
+
+    // hardened: the same key, blinding every operation with d and exponentiating by a
+    // ladder whose steps do not depend on the bits of d (see hardened)
+    pub fn hardened(self) -> Self {
+        PrivateKey {
+            hardened: true,
+            ..self
+        }
+    }
+
+    pub fn is_hardened(&self) -> bool {
+        self.hardened
+    }
+
+    // audit_log: a copy of the log so far, if the key keeps one
+    pub fn audit_log(&self) -> Option<AuditLog> {
+        let audit = self.audit.as_ref()?;
+        Some(audit.lock().unwrap().clone())
+    }
 }
 
 pub fn is_even(n: BigInt) -> bool {
@@ -39,67 +235,415 @@ pub fn factor_out_twos(n: BigInt) -> (usize, BigInt) {
 
 // is_probable_prime: determine if a number is probably prime using Miller-Rabin test
 pub fn is_probable_prime(n: BigInt, num_rounds: usize) -> bool {
+    is_probable_prime_with_rng(&n, num_rounds, &mut rand::thread_rng())
+}
+
+// is_probable_prime_with_rng: is_probable_prime with the bases drawn from rng
+pub(crate) fn is_probable_prime_with_rng(
+    n: &BigInt,
+    num_rounds: usize,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> bool {
     // If n is even, it's not prime
     if is_even(n.clone()) {
         return false;
     }
-    let (s, d) = factor_out_twos(n.clone() - 1);
-    let n1: BigInt = n.clone() - 1;
-    for _ in 0..num_rounds {
-        let a = rand::thread_rng().gen_bigint_range(&BigInt::from(2), &n1);
-        let mut x = a.modpow(&d, &n.clone());
-        for _ in 0..s {
-            let y = x.modpow(&BigInt::from(2), &n.clone());
-            if y == BigInt::from(1) && x != BigInt::from(1) && x != n1 {
-                return false;
-            }
-            x = y;
-        }
-        if x != BigInt::from(1) {
+    // If we haven't found a witness, then n is probably prime
+    miller_rabin_witness(n, num_rounds, rng).is_none()
+}
+
+// miller_rabin_witness: the first of num_rounds random bases that proves odd n > 3
+// composite, if any does
+pub(crate) fn miller_rabin_witness(
+    n: &BigInt,
+    num_rounds: usize,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Option<BigInt> {
+    let n1: BigInt = n - 1;
+    (0..num_rounds)
+        .map(|_| rng.gen_bigint_range(&BigInt::from(2), &n1))
+        .find(|a| !is_strong_probable_prime(n, a))
+}
+
+// is_strong_probable_prime: one Miller-Rabin round for odd n > 3 with base a; false means a
+// is a witness that n is composite
+pub(crate) fn is_strong_probable_prime(n: &BigInt, a: &BigInt) -> bool {
+    let ring = ModRing::new(n.clone());
+    let (one, minus_one) = (ring.one(), -ring.one());
+    let (s, d) = factor_out_twos(n - 1);
+    let mut x = ring.elem(a.clone()).pow(&d);
+    for _ in 0..s {
+        let y = &x * &x;
+        if y == one && x != one && x != minus_one {
             return false;
         }
+        x = y;
     }
-    // If we haven't found a witness, then n is probably prime
-    true
+    x == one
 }
 
-pub fn random_prime(ndigits: u32) -> BigInt {
-    let mut rng = rand::thread_rng();
-    let low = BigInt::from(10).pow(ndigits - 1 as u32);
+// The smallest primes the generators draw, by decimal digits and by bits
+const MIN_PRIME_DIGITS: u32 = 2;
+const MIN_PRIME_BITS: u64 = 8;
+
+// prime_range: the interval random_prime_with_digits draws ndigits primes from
+pub(crate) fn prime_range(ndigits: u32) -> (BigInt, BigInt) {
+    if ndigits < MIN_PRIME_DIGITS {
+        panic!("random primes need at least 2 digits, not {}", ndigits);
+    }
+    let low = BigInt::from(10).pow(ndigits - 1);
     let high = low.clone().pow(2);
-    let mut p = rng.gen_bigint_range(&low, &high);
-    while !is_probable_prime(p.clone(), 100) {
-        p = rng.gen_bigint_range(&low, &high);
+    (low, high)
+}
+
+// next_prime: the smallest probable prime that is at least n
+pub fn next_prime(n: &BigInt) -> BigInt {
+    if let Some(p) = [2, 3, 5, 7].map(BigInt::from).into_iter().find(|p| p >= n) {
+        return p;
+    }
+    Candidates::starting_at(n)
+        .find(|c| is_probable_prime(c.clone(), 100))
+        .unwrap()
+}
+
+// random_prime_in: search upward through the sieved candidates from a random point in
+// [low, high), starting over if the search runs off the end of the range
+pub(crate) fn random_prime_in(
+    low: &BigInt,
+    high: &BigInt,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> BigInt {
+    let rounds = fips_rounds(low.bits());
+    loop {
+        let start = rng.gen_bigint_range(low, high);
+        let found = Candidates::starting_at(&start)
+            .take_while(|c| c < high)
+            .find(|c| is_probable_prime_with_rng(c, rounds, rng));
+        if let Some(p) = found {
+            return p;
+        }
+    }
+}
+
+// random_safe_prime_in: a prime p = 2q + 1 in [low, high) with q prime as well
+pub(crate) fn random_safe_prime_in(
+    low: &BigInt,
+    high: &BigInt,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> BigInt {
+    let (q_low, q_high): (BigInt, BigInt) = (low / 2u32, (high - 1u32) / 2u32);
+    let rounds = fips_rounds(low.bits());
+    loop {
+        let start = rng.gen_bigint_range(&q_low, &q_high);
+        let found = Candidates::safe_starting_at(&start)
+            .take_while(|q| q < &q_high)
+            .map(|q| 2u32 * q + 1u32)
+            .filter(|p| p >= low)
+            // One base-2 round rules out most composite p before the full tests
+            .filter(|p| is_strong_probable_prime(p, &BigInt::from(2)))
+            .find(|p| {
+                let q: BigInt = p / 2u32;
+                is_probable_prime_with_rng(&q, rounds, rng)
+                    && is_probable_prime_with_rng(p, rounds, rng)
+            });
+        if let Some(p) = found {
+            return p;
+        }
+    }
+}
+
+// prime_bit_range: the bits-bit integers with the top two bits set
+pub(crate) fn prime_bit_range(bits: u64) -> (BigInt, BigInt) {
+    if bits < MIN_PRIME_BITS {
+        panic!("random primes need at least 8 bits, not {}", bits);
     }
-    p
+    (BigInt::from(3) << (bits - 2), BigInt::from(1) << bits)
 }
 
+// random_prime: a bits-bit prime with its top two bits set, so that the product of two
+// of them has exactly 2 * bits bits
+pub fn random_prime(bits: u64, rng: &mut (impl CryptoRng + RngCore)) -> BigInt {
+    let (low, high) = prime_bit_range(bits);
+    random_prime_in(&low, &high, rng)
+}
+
+// try_random_prime: random_prime, or InvalidArgument for fewer than 8 bits
+pub fn try_random_prime(
+    bits: u64,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<BigInt, RsaError> {
+    if bits < MIN_PRIME_BITS {
+        return Err(RsaError::InvalidArgument(
+            "random primes need at least 8 bits",
+        ));
+    }
+    Ok(random_prime(bits, rng))
+}
+
+// random_safe_prime: random_prime for a safe prime p = 2q + 1, q prime, as used for
+// groups with a large prime-order subgroup and some older key generation rules
+pub fn random_safe_prime(bits: u64, rng: &mut (impl CryptoRng + RngCore)) -> BigInt {
+    let (low, high) = prime_bit_range(bits);
+    random_safe_prime_in(&low, &high, rng)
+}
+
+// random_prime_with_digits: a prime from prime_range(ndigits), using thread_rng
+pub fn random_prime_with_digits(ndigits: u32) -> BigInt {
+    let (low, high) = prime_range(ndigits);
+    random_prime_in(&low, &high, &mut rand::thread_rng())
+}
+
+// random_unit: a uniformly random element of the multiplicative group mod n
+pub(crate) fn random_unit(n: &BigInt) -> BigInt {
+    let mut rng = rand::thread_rng();
+    loop {
+        let x = BigInt::random_range(&BigInt::from_u64(1), n, &mut rng);
+        let (gcd, _, _) = extended_gcd(x.clone(), n.clone());
+        if gcd == BigInt::from(1) {
+            return x;
+        }
+    }
+}
+
+// random_bytes: len bytes from thread_rng
+pub(crate) fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+// random_blum_prime: a random prime p with p ≡ 3 mod 4, the shared building block for
+// Blum integers n = pq used by the BBS generator and other quadratic residuosity schemes
+pub fn random_blum_prime(ndigits: u32) -> BigInt {
+    loop {
+        let p = random_prime_with_digits(ndigits);
+        if &p % 4 == BigInt::from(3) {
+            return p;
+        }
+    }
+}
+
+// gen_keys: a key pair from 100-digit primes; it has no argument that could be wrong
 pub fn gen_keys() -> (PublicKey, PrivateKey) {
-    // Pick two large primes p and q
-    let p: BigInt = random_prime(100u32);
-    let q: BigInt = random_prime(100u32);
+    gen_keys_with_digits(100)
+}
+
+// gen_keys_with_rng: a key pair as params describe it, with every random choice drawn
+// from rng; KeyGeneration only if params allow giving up on the distance between the
+// primes
+pub fn gen_keys_with_rng<R: CryptoRng + RngCore>(
+    rng: &mut R,
+    params: &KeyGenParams,
+) -> Result<(PublicKey, PrivateKey), RsaError> {
+    params
+        .generate_with_rng(rng)
+        .map(|(pair, _)| pair.into_keys())
+        .ok_or(RsaError::KeyGeneration)
+}
+
+// gen_keys_with_digits: like gen_keys but with ndigits primes, handy for small test moduli;
+// panics for fewer than 2 digits
+pub fn gen_keys_with_digits(ndigits: u32) -> (PublicKey, PrivateKey) {
+    try_gen_keys_with_digits(ndigits).unwrap_or_else(|err| panic!("{}", err))
+}
+
+// try_gen_keys_with_digits: gen_keys_with_digits, or InvalidArgument for fewer than 2 digits
+pub fn try_gen_keys_with_digits(ndigits: u32) -> Result<(PublicKey, PrivateKey), RsaError> {
+    if ndigits < MIN_PRIME_DIGITS {
+        return Err(RsaError::InvalidArgument(
+            "key generation needs primes of at least 2 digits",
+        ));
+    }
+    let e: BigInt = BigInt::from(65537);
+    let (p, q) = gen_prime_pair(ndigits, &e);
     // Compute n = pq
     let n: BigInt = &p * &q;
     // Compute (p-1)(q-1)
     let phi: BigInt = (&p - 1) * (&q - 1);
-    // Find 'e' relatively prime to (p-1)(q-1)
-    let e: BigInt = BigInt::from(65537);
-    let d = mod_inverse(e, phi.clone());
-    (PublicKey { 
-        n: n.clone(), 
-        e: BigInt::from(65537),
-     },
-     PrivateKey {
-        d,
-     })
+    let d = invert(e.clone(), phi);
+    Ok((PublicKey { n, e }, PrivateKey::with_primes(d, p, q)))
+}
+
+// gen_prime_pair: two distinct large primes p and q, skipping any with gcd(e, p - 1) > 1 so
+// that e stays invertible mod (p-1)(q-1)
+pub(crate) fn gen_prime_pair(ndigits: u32, e: &BigInt) -> (BigInt, BigInt) {
+    let shares_factor = |p: &BigInt| extended_gcd(p - 1, e.clone()).0 != BigInt::from(1);
+    let mut p: BigInt = random_prime_with_digits(ndigits);
+    while shares_factor(&p) {
+        p = random_prime_with_digits(ndigits);
+    }
+    let mut q: BigInt = random_prime_with_digits(ndigits);
+    while q == p || shares_factor(&q) {
+        q = random_prime_with_digits(ndigits);
+    }
+    (p, q)
+}
+
+// Plaintext and Ciphertext: the two sides of textbook RSA, kept apart by type so that a
+// ciphertext cannot be encrypted again or a plaintext decrypted by mistake
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Plaintext(BigInt);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Ciphertext(BigInt);
+
+impl Plaintext {
+    pub fn as_bigint(&self) -> &BigInt {
+        &self.0
+    }
+}
+
+impl Ciphertext {
+    pub fn as_bigint(&self) -> &BigInt {
+        &self.0
+    }
+}
+
+impl From<BigInt> for Plaintext {
+    fn from(m: BigInt) -> Self {
+        Plaintext(m)
+    }
+}
+
+impl From<Plaintext> for BigInt {
+    fn from(m: Plaintext) -> Self {
+        m.0
+    }
+}
+
+// A received ciphertext starts out as an integer read off the wire
+impl From<BigInt> for Ciphertext {
+    fn from(c: BigInt) -> Self {
+        Ciphertext(c)
+    }
+}
+
+impl From<Ciphertext> for BigInt {
+    fn from(c: Ciphertext) -> Self {
+        c.0
+    }
+}
+
+// encrypt: m^e mod n, for 0 <= m < n; a larger m would decrypt to m mod n instead
+pub fn encrypt(pub_key: &PublicKey, m: Plaintext) -> Result<Ciphertext, RsaError> {
+    if !in_range(pub_key, &m.0) {
+        return Err(RsaError::MessageTooLarge);
+    }
+    Ok(Ciphertext(rsa_public(pub_key, &m.0)))
+}
+
+// decrypt: c^d mod n, for 0 <= c < n and a private key that belongs to pub_key as far as
+// its primes show
+pub fn decrypt(
+    pub_key: &PublicKey,
+    priv_key: &PrivateKey,
+    c: Ciphertext,
+) -> Result<Plaintext, RsaError> {
+    if !in_range(pub_key, &c.0) {
+        return Err(RsaError::CiphertextOutOfRange);
+    }
+    if let Some(crt) = &priv_key.crt {
+        if &crt.p * &crt.q != pub_key.n {
+            return Err(RsaError::InvalidKey(
+                "the private key's primes belong to another modulus",
+            ));
+        }
+    }
+    Ok(Plaintext(rsa_private_as(
+        pub_key,
+        priv_key,
+        &c.0,
+        Operation::Decrypt,
+    )))
+}
+
+fn in_range(pub_key: &PublicKey, x: &BigInt) -> bool {
+    *x >= BigInt::zero() && *x < pub_key.n
+}
+
+// checked_public_key: (n, e) as a key if the permutations can run on it, for constructors
+// that take n and e from outside; a modulus below 3 has no ring to work in, and a negative
+// e needs inverses that not every input has
+pub(crate) fn checked_public_key(n: BigInt, e: BigInt) -> Result<PublicKey, &'static str> {
+    if n < BigInt::from(3) {
+        return Err("the modulus must be at least 3");
+    }
+    if e < BigInt::from(1) {
+        return Err("the public exponent must be positive");
+    }
+    Ok(PublicKey { n, e })
+}
+
+// rsa_public and rsa_private: the bare permutations x^e and x^d mod n, for values that are
+// neither plaintexts nor ciphertexts, such as the hashed points signatures are built on
+pub(crate) fn rsa_public(pub_key: &PublicKey, x: &BigInt) -> BigInt {
+    let ring = ModRing::new(pub_key.n.clone());
+    ring.elem(x.clone()).pow(&pub_key.e).into()
+}
+
+pub(crate) fn rsa_private(pub_key: &PublicKey, priv_key: &PrivateKey, x: &BigInt) -> BigInt {
+    rsa_private_as(pub_key, priv_key, x, Operation::Exponentiate)
+}
+
+// rsa_private_as: rsa_private, recorded as operation if the key keeps an audit log
+pub(crate) fn rsa_private_as(
+    pub_key: &PublicKey,
+    priv_key: &PrivateKey,
+    x: &BigInt,
+    operation: Operation,
+) -> BigInt {
+    let y = private_permutation(pub_key, priv_key, x);
+    if let Some(audit) = &priv_key.audit {
+        let mut log = audit.lock().unwrap();
+        log.record(pub_key, priv_key, operation, x, &y);
+    }
+    y
+}
+
+// private_permutation: x^d mod n, by CRT if the key has its primes, blinded if the key is
+// hardened, and never audited
+pub(crate) fn private_permutation(
+    pub_key: &PublicKey,
+    priv_key: &PrivateKey,
+    x: &BigInt,
+) -> BigInt {
+    if priv_key.hardened {
+        return hardened::permutation(pub_key, priv_key, x);
+    }
+    if let Some(crt) = &priv_key.crt {
+        return crt.pow(x);
+    }
+    let ring = ModRing::new(pub_key.n.clone());
+    ring.elem(x.clone()).pow(&priv_key.d).into()
+}
+
+// modulus_len: k, the length of the modulus in bytes
+pub(crate) fn modulus_len(pub_key: &PublicKey) -> usize {
+    (pub_key.n.bits() as usize).div_ceil(8)
+}
+
+// i2osp: the integer x as a big-endian byte string of exactly len bytes (RFC 8017 I2OSP);
+// panics if x does not fit
+pub fn i2osp(x: &BigInt, len: usize) -> Vec<u8> {
+    try_i2osp(x, len).unwrap_or_else(|_| panic!("{} does not fit in {} bytes", x, len))
 }
 
-pub fn encrypt(pub_key: &PublicKey, m: BigInt) -> BigInt {
-    m.modpow(&pub_key.e, &pub_key.n)
+// try_i2osp: i2osp, or InvalidArgument where RFC 8017 says "integer too large"
+pub fn try_i2osp(x: &BigInt, len: usize) -> Result<Vec<u8>, RsaError> {
+    let bytes = Uint::to_be_bytes(x);
+    if bytes.len() > len {
+        return Err(RsaError::InvalidArgument(
+            "the integer does not fit in the length given",
+        ));
+    }
+    let mut out = vec![0u8; len - bytes.len()];
+    out.extend_from_slice(&bytes);
+    Ok(out)
 }
 
-pub fn decrypt(pub_key: &PublicKey, priv_key: &PrivateKey, c: BigInt) -> BigInt {
-    c.modpow(&priv_key.d, &pub_key.n)
+// os2ip: a big-endian byte string as a non-negative integer (RFC 8017 OS2IP)
+pub fn os2ip(bytes: &[u8]) -> BigInt {
+    <BigInt as Uint>::from_be_bytes(bytes)
 }
 
 // extended gcd: https://en.wikipedia.org/wiki/Extended_Euclidean_algorithm a helpful utility function
@@ -120,16 +664,55 @@ pub fn extended_gcd(a: BigInt, b: BigInt) -> (BigInt, (BigInt, BigInt), (BigInt,
     (old_r, (old_s, old_t), (s, t))
 }
 
-pub fn mod_inverse(a: BigInt, m: BigInt) -> BigInt {
-    let (gcd, (s, _), _) = extended_gcd(a.clone(), m.clone());
-    if gcd != BigInt::from(1) {
-        panic!("{} and {} are not coprime", a, m);
+// jacobi: the Jacobi symbol (a/n) for odd positive n, via quadratic reciprocity; panics
+// for any other n
+// https://en.wikipedia.org/wiki/Jacobi_symbol
+pub fn jacobi(a: &BigInt, n: &BigInt) -> i32 {
+    try_jacobi(a, n).unwrap_or_else(|_| {
+        panic!(
+            "the Jacobi symbol is only defined for odd positive n, got {}",
+            n
+        )
+    })
+}
+
+// try_jacobi: jacobi, or InvalidArgument for an even or non-positive n
+pub fn try_jacobi(a: &BigInt, n: &BigInt) -> Result<i32, RsaError> {
+    if *n <= BigInt::zero() || is_even(n.clone()) {
+        return Err(RsaError::InvalidArgument(
+            "the Jacobi symbol needs an odd positive n",
+        ));
     }
-    if s < BigInt::zero() {
-        m + s
-    } else {
-        s
+    let mut n = n.clone();
+    let mut a = ((a % &n) + &n) % &n;
+    let mut t = 1;
+    while a != BigInt::zero() {
+        while is_even(a.clone()) {
+            a /= 2;
+            let r = &n % 8;
+            if r == BigInt::from(3) || r == BigInt::from(5) {
+                t = -t;
+            }
+        }
+        std::mem::swap(&mut a, &mut n);
+        if &a % 4 == BigInt::from(3) && &n % 4 == BigInt::from(3) {
+            t = -t;
+        }
+        a %= &n;
     }
+    Ok(if n == BigInt::from(1) { t } else { 0 })
+}
+
+// mod_inverse: a^-1 mod m, or NotCoprime if gcd(a, m) != 1
+pub fn mod_inverse(a: BigInt, m: BigInt) -> Result<BigInt, RsaError> {
+    a.inv_mod(&m).ok_or(RsaError::NotCoprime)
+}
+
+// invert: mod_inverse for callers that have already made sure a and m are coprime, e.g.
+// e against phi(n) or one prime against another
+pub(crate) fn invert(a: BigInt, m: BigInt) -> BigInt {
+    a.inv_mod(&m)
+        .unwrap_or_else(|| panic!("{} and {} are not coprime", a, m))
 }
 
 #[cfg(test)]
@@ -149,13 +732,90 @@ mod tests {
         assert!(!is_probable_prime(bi!("355") * bi!("113"), 20));
     }
 
+    #[test]
+    fn next_prime_finds_the_following_prime() {
+        assert_eq!(next_prime(&bi!("0")), bi!("2"));
+        assert_eq!(next_prime(&bi!("8")), bi!("11"));
+        assert_eq!(next_prime(&bi!("11")), bi!("11"));
+        assert_eq!(next_prime(&bi!("1000000")), bi!("1000003"));
+    }
+
+    #[test]
+    fn random_primes_have_the_requested_bit_length() {
+        let mut rng = rand::thread_rng();
+        for bits in [8, 64, 129] {
+            let p = random_prime(bits, &mut rng);
+            assert_eq!(p.bits(), bits);
+            assert!(p.bit(bits - 2));
+            assert!(is_probable_prime(p, 20));
+        }
+    }
+
+    #[test]
+    fn safe_primes_are_twice_a_prime_plus_one() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(random_safe_prime(8, &mut rng), bi!("227"));
+        let p = random_safe_prime(96, &mut rng);
+        assert_eq!(p.bits(), 96);
+        assert!(is_probable_prime(p.clone(), 20));
+        assert!(is_probable_prime(p / 2, 20));
+    }
+
+    #[test]
+    fn gen_keys_with_rng_is_reproducible() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        let params = KeyGenParams::bits(256);
+        let (a, _) = gen_keys_with_rng(&mut StdRng::seed_from_u64(1), &params).unwrap();
+        let (b, _) = gen_keys_with_rng(&mut StdRng::seed_from_u64(1), &params).unwrap();
+        let (c, _) = gen_keys_with_rng(&mut StdRng::seed_from_u64(2), &params).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(a.n.bits(), 256);
+    }
+
+    #[test]
+    fn jacobi_symbol_matches_known_values() {
+        assert_eq!(jacobi(&bi!("1001"), &bi!("9907")), -1);
+        assert_eq!(jacobi(&bi!("19"), &bi!("45")), 1);
+        assert_eq!(jacobi(&bi!("8"), &bi!("21")), -1);
+        assert_eq!(jacobi(&bi!("6"), &bi!("15")), 0);
+        assert_eq!(jacobi(&bi!("-1"), &bi!("7")), -1);
+    }
+
+    #[test]
+    fn try_variants_report_bad_arguments() {
+        fn invalid<T>(result: Result<T, RsaError>) -> bool {
+            matches!(result, Err(RsaError::InvalidArgument(_)))
+        }
+        assert!(invalid(try_gen_keys_with_digits(0)));
+        assert!(invalid(try_gen_keys_with_digits(1)));
+        let (pub_key, priv_key) = try_gen_keys_with_digits(2).unwrap();
+        let c = encrypt(&pub_key, BigInt::from(7).into()).unwrap();
+        assert_eq!(
+            decrypt(&pub_key, &priv_key, c).unwrap(),
+            BigInt::from(7).into()
+        );
+        assert!(invalid(try_random_prime(7, &mut rand::thread_rng())));
+        assert_eq!(
+            try_random_prime(8, &mut rand::thread_rng()).unwrap().bits(),
+            8
+        );
+        assert!(invalid(try_i2osp(&BigInt::from(256), 1)));
+        assert_eq!(try_i2osp(&BigInt::from(255), 2), Ok(vec![0, 255]));
+        for n in [0, -3, 8] {
+            assert!(invalid(try_jacobi(&BigInt::from(2), &BigInt::from(n))));
+        }
+        assert_eq!(try_jacobi(&BigInt::from(2), &BigInt::from(7)), Ok(1));
+    }
+
     #[test]
     fn encryption_and_decryption_work_on_u8() {
         let (pub_key, priv_key) = gen_keys();
         for i in 0..255 {
-            let m = BigInt::from(i);
-            let c = encrypt(&pub_key, m.clone());
-            let m_prime = decrypt(&pub_key, &priv_key, c);
+            let m = Plaintext::from(BigInt::from(i));
+            let c = encrypt(&pub_key, m.clone()).unwrap();
+            let m_prime = decrypt(&pub_key, &priv_key, c).unwrap();
             assert_eq!(m, m_prime);
         }
     }
@@ -0,0 +1,129 @@
+// Key components as fixed-layout byte strings and machine-word limbs
+//
+// For wire formats and hardware interfaces that want the raw numbers rather than a decimal
+// string or one of the keyfile encodings. Bytes are big-endian (I2OSP); limbs are u64 words,
+// least significant first, as most bignum libraries and accelerators lay them out. The
+// modulus and the private exponent are padded to the width of the modulus, so a key's
+// encodings all have the same length and d's length gives nothing away. Beyond refusing a
+// modulus below 3, nothing here validates a key; parse a keyfile for that.
+use crate::error::RsaError;
+use crate::{checked_public_key, i2osp, modulus_len, os2ip, PrivateKey, PublicKey};
+use num_bigint::{BigInt, BigUint};
+
+pub type Limb = u64;
+
+fn limb_count(pub_key: &PublicKey) -> usize {
+    (pub_key.n.bits() as usize).div_ceil(64)
+}
+
+// to_limbs: the limbs of x, zero-extended to at least len
+fn to_limbs(x: &BigInt, len: usize) -> Vec<Limb> {
+    let mut limbs = x.magnitude().to_u64_digits();
+    if limbs.len() < len {
+        limbs.resize(len, 0);
+    }
+    limbs
+}
+
+fn from_limbs(limbs: &[Limb]) -> BigInt {
+    let digits: Vec<u32> = limbs
+        .iter()
+        .flat_map(|&limb| [limb as u32, (limb >> 32) as u32])
+        .collect();
+    BigUint::from_slice(&digits).into()
+}
+
+impl PublicKey {
+    // modulus_bytes: n as exactly ceil(bits / 8) bytes
+    pub fn modulus_bytes(&self) -> Vec<u8> {
+        i2osp(&self.n, modulus_len(self))
+    }
+
+    pub fn exponent_bytes(&self) -> Vec<u8> {
+        self.e.to_bytes_be().1
+    }
+
+    pub fn from_be_bytes(n: &[u8], e: &[u8]) -> Result<PublicKey, RsaError> {
+        checked_public_key(os2ip(n), os2ip(e)).map_err(RsaError::InvalidKey)
+    }
+
+    // to_limbs: (n, e), with e no longer than it needs to be
+    pub fn to_limbs(&self) -> (Vec<Limb>, Vec<Limb>) {
+        (to_limbs(&self.n, 0), to_limbs(&self.e, 0))
+    }
+
+    pub fn from_limbs(n: &[Limb], e: &[Limb]) -> Result<PublicKey, RsaError> {
+        checked_public_key(from_limbs(n), from_limbs(e)).map_err(RsaError::InvalidKey)
+    }
+}
+
+impl PrivateKey {
+    // to_be_bytes: d padded to the length of the modulus
+    pub fn to_be_bytes(&self, pub_key: &PublicKey) -> Vec<u8> {
+        i2osp(&self.d, modulus_len(pub_key))
+    }
+
+    pub fn from_be_bytes(d: &[u8]) -> PrivateKey {
+        PrivateKey::new(os2ip(d))
+    }
+
+    // to_limbs: d padded to as many limbs as the modulus has
+    pub fn to_limbs(&self, pub_key: &PublicKey) -> Vec<Limb> {
+        to_limbs(&self.d, limb_count(pub_key))
+    }
+
+    pub fn from_limbs(d: &[Limb]) -> PrivateKey {
+        PrivateKey::new(from_limbs(d))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decrypt, encrypt, gen_keys_with_digits, Plaintext};
+
+    #[test]
+    fn limbs_are_little_endian_words() {
+        let key = PublicKey {
+            n: (BigInt::from(1) << 64) + 3,
+            e: BigInt::from(65537),
+        };
+        assert_eq!(key.to_limbs(), (vec![3, 1], vec![65537]));
+        assert_eq!(PublicKey::from_limbs(&[3, 1], &[65537]).unwrap(), key);
+        assert_eq!(key.modulus_bytes(), [1, 0, 0, 0, 0, 0, 0, 0, 3]);
+        assert_eq!(key.exponent_bytes(), [1, 0, 1]);
+        let d = PrivateKey::new(BigInt::from(5));
+        assert_eq!(d.to_limbs(&key), [5, 0]);
+        assert_eq!(d.to_be_bytes(&key), [0, 0, 0, 0, 0, 0, 0, 0, 5]);
+    }
+
+    #[test]
+    fn moduli_too_small_to_encrypt_with_are_refused() {
+        assert!(matches!(
+            PublicKey::from_be_bytes(&[1], &[3]),
+            Err(RsaError::InvalidKey(_))
+        ));
+        assert!(PublicKey::from_limbs(&[2], &[3]).is_err());
+        assert!(PublicKey::from_limbs(&[5], &[0]).is_err());
+    }
+
+    #[test]
+    fn keys_survive_both_encodings() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let (n, e) = pub_key.to_limbs();
+        let from_limbs = PublicKey::from_limbs(&n, &e).unwrap();
+        assert_eq!(from_limbs, pub_key);
+        let from_bytes =
+            PublicKey::from_be_bytes(&pub_key.modulus_bytes(), &pub_key.exponent_bytes()).unwrap();
+        assert_eq!(from_bytes, pub_key);
+        let d_limbs = PrivateKey::from_limbs(&priv_key.to_limbs(&pub_key));
+        let d_bytes = PrivateKey::from_be_bytes(&priv_key.to_be_bytes(&pub_key));
+        let m = Plaintext::from(BigInt::from(31337));
+        for d in [d_limbs, d_bytes] {
+            assert_eq!(
+                decrypt(&pub_key, &d, encrypt(&pub_key, m.clone()).unwrap()).unwrap(),
+                m
+            );
+        }
+    }
+}
@@ -0,0 +1,196 @@
+// What an attacker can do with a given ciphertext or signature, for teaching tools
+//
+// analyze_ciphertext and analyze_signature list the manipulations that the scheme, the key
+// and the value itself allow, each with a one-line explanation in its Display, so that a
+// tool built on the crate can show students why textbook RSA is not a finished scheme.
+// Scheme-level weaknesses hold for every value (textbook RSA is always multiplicative);
+// value-level ones are checked on the number at hand, such as a ciphertext that is an exact
+// e-th power over the integers and so gives up its plaintext without the key.
+// Ciphertexts here are the textbook Ciphertext type; padded schemes are compared in
+// compare.
+use crate::{Ciphertext, PublicKey};
+use num_bigint::BigInt;
+use std::fmt;
+
+// Largest e for which collecting e encryptions of one message (Hastad) is worth reporting
+const BROADCAST_MAX_E: u32 = 257;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureScheme {
+    // s = m^d on the message as an integer
+    Textbook,
+    // s = H(m)^d with H onto the whole of Z_n, as in fdh
+    Fdh,
+    // s = EMSA-PKCS1-v1_5(m)^d, padding with 0xff bytes and a DigestInfo, as in signature
+    Pkcs1v15,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Weakness {
+    // c r^e decrypts to m r, and s1 s2 signs m1 m2: values can be combined without the key
+    Multiplicative,
+    // Equal messages give equal values, so a guess is checked by encrypting it
+    Deterministic,
+    // The value is 0, 1 or n - 1, which RSA maps to themselves
+    FixedPoint,
+    // The value is not below n, so it is not a valid output of the scheme at all
+    OutOfRange,
+    // x^e < n: the message is the integer e-th root of the ciphertext
+    IntegerRoot { m: BigInt },
+    // e encryptions of one message under different keys give it up by CRT and a root
+    Broadcast { copies: u32 },
+    // Any s is a valid signature on the message s^e mod n
+    ExistentialForgery,
+    // With e = 3, a verifier that does not check all of the padding accepts the cube root
+    // of a forged encoding (Bleichenbacher, 2006)
+    CubeRootForgery,
+}
+
+impl fmt::Display for Weakness {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Weakness::Multiplicative => write!(
+                f,
+                "multiplicative: multiplying by r^e (or by another signature) gives a valid \
+                 value for the product of the messages"
+            ),
+            Weakness::Deterministic => write!(
+                f,
+                "deterministic: a guessed message can be confirmed by encrypting it"
+            ),
+            Weakness::FixedPoint => write!(f, "fixed point: RSA leaves 0, 1 and n - 1 unchanged"),
+            Weakness::OutOfRange => write!(f, "out of range: the value is not below n"),
+            Weakness::IntegerRoot { m } => write!(
+                f,
+                "no wraparound: m^e < n, so m = {} is the integer e-th root",
+                m
+            ),
+            Weakness::Broadcast { copies } => write!(
+                f,
+                "broadcast: {} encryptions of one message under different keys reveal it",
+                copies
+            ),
+            Weakness::ExistentialForgery => write!(
+                f,
+                "existential forgery: any s signs s^e mod n, no key needed"
+            ),
+            Weakness::CubeRootForgery => write!(
+                f,
+                "cube root forgery: with e = 3, a lenient padding check accepts forged roots"
+            ),
+        }
+    }
+}
+
+fn small_exponent(pub_key: &PublicKey) -> Option<u32> {
+    u32::try_from(&pub_key.e).ok()
+}
+
+// value_weaknesses: what the number itself gives away, for either kind of value
+fn value_weaknesses(x: &BigInt, pub_key: &PublicKey) -> Vec<Weakness> {
+    let n = &pub_key.n;
+    if *x < BigInt::from(0) || x >= n {
+        return vec![Weakness::OutOfRange];
+    }
+    if *x <= BigInt::from(1) || *x == n - 1 {
+        return vec![Weakness::FixedPoint];
+    }
+    Vec::new()
+}
+
+pub fn analyze_ciphertext(c: &Ciphertext, pub_key: &PublicKey) -> Vec<Weakness> {
+    let c = c.as_bigint();
+    let mut found = value_weaknesses(c, pub_key);
+    if found.contains(&Weakness::OutOfRange) {
+        return found;
+    }
+    found.extend([Weakness::Multiplicative, Weakness::Deterministic]);
+    if let Some(e) = small_exponent(pub_key) {
+        let root = c.nth_root(e);
+        if root.pow(e) == *c && !found.contains(&Weakness::FixedPoint) {
+            found.push(Weakness::IntegerRoot { m: root });
+        }
+        if e <= BROADCAST_MAX_E {
+            found.push(Weakness::Broadcast { copies: e });
+        }
+    }
+    found
+}
+
+pub fn analyze_signature(
+    s: &BigInt,
+    pub_key: &PublicKey,
+    scheme: SignatureScheme,
+) -> Vec<Weakness> {
+    let mut found = value_weaknesses(s, pub_key);
+    if found.contains(&Weakness::OutOfRange) {
+        return found;
+    }
+    match scheme {
+        SignatureScheme::Textbook => {
+            found.extend([Weakness::Multiplicative, Weakness::ExistentialForgery]);
+        }
+        SignatureScheme::Fdh => {}
+        SignatureScheme::Pkcs1v15 => {
+            if pub_key.e == BigInt::from(3) {
+                found.push(Weakness::CubeRootForgery);
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encrypt, fdh, gen_keys_with_digits, Plaintext};
+
+    #[test]
+    fn textbook_ciphertexts_are_malleable_and_small_messages_leak() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let c = encrypt(&pub_key, Plaintext::from(BigInt::from(1) << 100)).unwrap();
+        let found = analyze_ciphertext(&c, &pub_key);
+        assert!(found.contains(&Weakness::Multiplicative));
+        assert!(found.contains(&Weakness::Deterministic));
+        assert!(!found
+            .iter()
+            .any(|w| matches!(w, Weakness::IntegerRoot { .. })));
+        assert!(!found
+            .iter()
+            .any(|w| matches!(w, Weakness::Broadcast { .. })));
+
+        // With e = 3 (n = 11 * 17), a short message never wraps around n
+        let small_e = PublicKey::from_str_components("187", "3", 10).unwrap();
+        let c = encrypt(&small_e, Plaintext::from(BigInt::from(5))).unwrap();
+        let found = analyze_ciphertext(&c, &small_e);
+        assert!(found.contains(&Weakness::IntegerRoot { m: BigInt::from(5) }));
+        assert!(found.contains(&Weakness::Broadcast { copies: 3 }));
+    }
+
+    #[test]
+    fn special_values_are_flagged() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let one = Ciphertext::from(BigInt::from(1));
+        assert!(analyze_ciphertext(&one, &pub_key).contains(&Weakness::FixedPoint));
+        let too_big = Ciphertext::from(pub_key.n.clone());
+        assert_eq!(
+            analyze_ciphertext(&too_big, &pub_key),
+            vec![Weakness::OutOfRange]
+        );
+    }
+
+    #[test]
+    fn signature_findings_depend_on_the_scheme() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let s = fdh::sign(&pub_key, &priv_key, b"m");
+        assert!(analyze_signature(&s, &pub_key, SignatureScheme::Fdh).is_empty());
+        let textbook = analyze_signature(&s, &pub_key, SignatureScheme::Textbook);
+        assert!(textbook.contains(&Weakness::ExistentialForgery));
+        assert!(!analyze_signature(&s, &pub_key, SignatureScheme::Pkcs1v15)
+            .contains(&Weakness::CubeRootForgery));
+        let e3 = PublicKey::from_str_components("187", "3", 10).unwrap();
+        let found = analyze_signature(&BigInt::from(5), &e3, SignatureScheme::Pkcs1v15);
+        assert_eq!(found, vec![Weakness::CubeRootForgery]);
+        assert!(found[0].to_string().starts_with("cube root forgery"));
+    }
+}
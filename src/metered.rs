@@ -0,0 +1,229 @@
+// Metered private keys
+//
+// MeteredKey wraps a DecryptionOracle, so it decrypts and signs through PrivateKeyOps like
+// any other oracle, and counts every private-key operation on the way. It can refuse
+// operations beyond a budget per interval, which is an operational guard and also the
+// defence that matters against adaptive chosen-ciphertext attacks: Bleichenbacher's attack
+// on PKCS #1 v1.5 (the "million message attack") needs up to a million queries, Manger's on
+// OAEP about as many as the modulus has bits. Callbacks registered with on_event hear when the
+// total crosses a registered threshold and when an operation is refused.
+use crate::oracle::{DecryptionOracle, LocalOracle, OracleError};
+use crate::{PrivateKey, PublicKey};
+use num_bigint::BigInt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeterEvent {
+    // The total number of operations reached the threshold
+    ThresholdCrossed { threshold: u64, total: u64 },
+    // An operation was refused; retry_after is the time left in the current interval
+    RateLimited { total: u64, retry_after: Duration },
+}
+
+// RateLimit: at most max_ops operations in each interval, counted from the first of them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    pub max_ops: u64,
+    pub interval: Duration,
+}
+
+type Listener = Box<dyn Fn(MeterEvent) + Send>;
+
+struct Meter {
+    total: u64,
+    refused: u64,
+    window_start: Option<Instant>,
+    in_window: u64,
+    thresholds: Vec<u64>,
+}
+
+// MeteredKey: the listeners sit apart from the counts, and are called only once the counts
+// are unlocked, so that a listener may call count or refused
+pub struct MeteredKey<O = LocalOracle> {
+    inner: O,
+    limit: Option<RateLimit>,
+    meter: Mutex<Meter>,
+    listeners: Mutex<Vec<Listener>>,
+}
+
+impl MeteredKey<LocalOracle> {
+    // from_key: a metered key doing its operations in process
+    pub fn from_key(public: PublicKey, private: PrivateKey) -> Self {
+        MeteredKey::new(LocalOracle::new(public, private))
+    }
+}
+
+impl<O: DecryptionOracle> MeteredKey<O> {
+    pub fn new(inner: O) -> Self {
+        MeteredKey {
+            inner,
+            limit: None,
+            meter: Mutex::new(Meter {
+                total: 0,
+                refused: 0,
+                window_start: None,
+                in_window: 0,
+                thresholds: Vec::new(),
+            }),
+            listeners: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn with_rate_limit(self, limit: RateLimit) -> Self {
+        MeteredKey {
+            limit: Some(limit),
+            ..self
+        }
+    }
+
+    // with_threshold: emit ThresholdCrossed once the total reaches threshold
+    pub fn with_threshold(self, threshold: u64) -> Self {
+        self.meter.lock().unwrap().thresholds.push(threshold);
+        self
+    }
+
+    pub fn on_event(&self, listener: impl Fn(MeterEvent) + Send + 'static) {
+        self.listeners.lock().unwrap().push(Box::new(listener));
+    }
+
+    // count: the operations performed so far, not counting refused ones
+    pub fn count(&self) -> u64 {
+        self.meter.lock().unwrap().total
+    }
+
+    pub fn refused(&self) -> u64 {
+        self.meter.lock().unwrap().refused
+    }
+
+    // admit: record one operation at now, or refuse it under the rate limit, then tell the
+    // listeners
+    fn admit(&self, now: Instant) -> Result<(), OracleError> {
+        let (result, events) = self.record(now);
+        let listeners = self.listeners.lock().unwrap();
+        for event in events {
+            listeners.iter().for_each(|listener| listener(event));
+        }
+        result
+    }
+
+    // record: the outcome of one operation at now and the events it raises
+    fn record(&self, now: Instant) -> (Result<(), OracleError>, Vec<MeterEvent>) {
+        let mut meter = self.meter.lock().unwrap();
+        if let Some(limit) = self.limit {
+            let start = match meter.window_start {
+                Some(start) if now.duration_since(start) < limit.interval => start,
+                _ => {
+                    meter.in_window = 0;
+                    now
+                }
+            };
+            meter.window_start = Some(start);
+            if meter.in_window >= limit.max_ops {
+                meter.refused += 1;
+                let retry_after = limit.interval - now.duration_since(start);
+                let event = MeterEvent::RateLimited {
+                    total: meter.total,
+                    retry_after,
+                };
+                return (Err(OracleError::RateLimited { retry_after }), vec![event]);
+            }
+            meter.in_window += 1;
+        }
+        meter.total += 1;
+        let total = meter.total;
+        let events = meter
+            .thresholds
+            .iter()
+            .filter(|&&threshold| threshold == total)
+            .map(|&threshold| MeterEvent::ThresholdCrossed { threshold, total })
+            .collect();
+        (Ok(()), events)
+    }
+}
+
+impl<O: DecryptionOracle> DecryptionOracle for MeteredKey<O> {
+    fn public_key(&self) -> &PublicKey {
+        self.inner.public_key()
+    }
+
+    fn apply(&self, x: &BigInt) -> Result<BigInt, OracleError> {
+        self.admit(Instant::now())?;
+        self.inner.apply(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+    use crate::oracle::PrivateKeyOps;
+    use std::sync::{mpsc, Arc};
+
+    fn key() -> MeteredKey {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        MeteredKey::from_key(pub_key, priv_key)
+    }
+
+    #[test]
+    fn operations_are_counted_and_thresholds_announced() {
+        let key = key().with_threshold(2).with_threshold(5);
+        let (tx, rx) = mpsc::channel();
+        key.on_event(move |event| tx.send(event).unwrap());
+        for m in 0..5 {
+            key.sign(&BigInt::from(m)).unwrap();
+        }
+        assert_eq!(key.count(), 5);
+        let events: Vec<MeterEvent> = rx.try_iter().collect();
+        assert_eq!(
+            events,
+            [
+                MeterEvent::ThresholdCrossed {
+                    threshold: 2,
+                    total: 2
+                },
+                MeterEvent::ThresholdCrossed {
+                    threshold: 5,
+                    total: 5
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn listeners_can_read_the_counts() {
+        let key = Arc::new(key().with_threshold(1));
+        let (tx, rx) = mpsc::channel();
+        let weak = Arc::downgrade(&key);
+        key.on_event(move |_| {
+            let key = weak.upgrade().unwrap();
+            tx.send((key.count(), key.refused())).unwrap();
+        });
+        key.sign(&BigInt::from(7)).unwrap();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [(1, 0)]);
+    }
+
+    #[test]
+    fn rate_limit_refuses_and_resets() {
+        let limit = RateLimit {
+            max_ops: 3,
+            interval: Duration::from_secs(60),
+        };
+        let key = key().with_rate_limit(limit);
+        let start = Instant::now();
+        for _ in 0..3 {
+            key.admit(start).unwrap();
+        }
+        let later = start + Duration::from_secs(20);
+        assert_eq!(
+            key.admit(later),
+            Err(OracleError::RateLimited {
+                retry_after: Duration::from_secs(40)
+            })
+        );
+        assert!(key.sign(&BigInt::from(1)).is_err());
+        assert_eq!((key.count(), key.refused()), (3, 2));
+        key.admit(start + Duration::from_secs(61)).unwrap();
+        assert_eq!(key.count(), 4);
+    }
+}
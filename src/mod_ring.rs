@@ -0,0 +1,201 @@
+// Arithmetic in Z/nZ with operator overloading
+//
+// A ModRing fixes a modulus, and its elements always hold a representative in [0, n), so
+// +, -, * and unary - can be written as they are on paper: (&a * &b + c).pow(&e). Mixing
+// elements of different rings panics, as does inverting or raising to a negative power an
+// element that shares a factor with n. encrypt, decrypt and Miller-Rabin are written in
+// terms of it, and it in terms of the Uint backend trait.
+use crate::uint::Uint;
+use num_bigint::BigInt;
+use num_traits::{Signed, Zero};
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModRing {
+    n: BigInt,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Elem<'a> {
+    ring: &'a ModRing,
+    x: BigInt,
+}
+
+impl ModRing {
+    pub fn new(n: BigInt) -> Self {
+        if n < BigInt::from(2) {
+            panic!("a ring needs a modulus of at least 2, not {}", n);
+        }
+        ModRing { n }
+    }
+
+    pub fn modulus(&self) -> &BigInt {
+        &self.n
+    }
+
+    // elem: the class of x, for any integer x including negative ones
+    pub fn elem(&self, x: impl Into<BigInt>) -> Elem<'_> {
+        let x = x.into() % &self.n;
+        let x = if x.is_negative() { x + &self.n } else { x };
+        Elem { ring: self, x }
+    }
+
+    pub fn zero(&self) -> Elem<'_> {
+        self.elem(0)
+    }
+
+    pub fn one(&self) -> Elem<'_> {
+        self.elem(1)
+    }
+
+    fn check(&self, other: &ModRing) {
+        if self.n != other.n {
+            panic!("cannot combine elements mod {} and mod {}", self.n, other.n);
+        }
+    }
+}
+
+impl<'a> Elem<'a> {
+    pub fn ring(&self) -> &'a ModRing {
+        self.ring
+    }
+
+    // value: the representative in [0, n)
+    pub fn value(&self) -> &BigInt {
+        &self.x
+    }
+
+    // inv: the multiplicative inverse, if gcd(x, n) = 1
+    pub fn inv(&self) -> Option<Elem<'a>> {
+        self.x
+            .inv_mod(&self.ring.n)
+            .map(|x| Elem { ring: self.ring, x })
+    }
+
+    // pow: x^e, where a negative e means a power of the inverse
+    pub fn pow(&self, e: &BigInt) -> Elem<'a> {
+        if e.is_negative() {
+            let inv = self
+                .inv()
+                .unwrap_or_else(|| panic!("{} has no inverse mod {}", self.x, self.ring.n));
+            return inv.pow(&-e);
+        }
+        Elem {
+            ring: self.ring,
+            x: self.x.pow_mod(e, &self.ring.n),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.x.is_zero()
+    }
+}
+
+impl From<Elem<'_>> for BigInt {
+    fn from(a: Elem<'_>) -> Self {
+        a.x
+    }
+}
+
+impl fmt::Display for Elem<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (mod {})", self.x, self.ring.n)
+    }
+}
+
+// Each operator for all four combinations of owned and borrowed operands
+macro_rules! impl_op {
+    ($trait:ident, $method:ident, $op:tt) => {
+        impl<'a> $trait<&Elem<'a>> for &Elem<'a> {
+            type Output = Elem<'a>;
+
+            fn $method(self, rhs: &Elem<'a>) -> Elem<'a> {
+                self.ring.check(rhs.ring);
+                self.ring.elem(&self.x $op &rhs.x)
+            }
+        }
+
+        impl<'a> $trait<Elem<'a>> for Elem<'a> {
+            type Output = Elem<'a>;
+
+            fn $method(self, rhs: Elem<'a>) -> Elem<'a> {
+                (&self).$method(&rhs)
+            }
+        }
+
+        impl<'a> $trait<&Elem<'a>> for Elem<'a> {
+            type Output = Elem<'a>;
+
+            fn $method(self, rhs: &Elem<'a>) -> Elem<'a> {
+                (&self).$method(rhs)
+            }
+        }
+
+        impl<'a> $trait<Elem<'a>> for &Elem<'a> {
+            type Output = Elem<'a>;
+
+            fn $method(self, rhs: Elem<'a>) -> Elem<'a> {
+                self.$method(&rhs)
+            }
+        }
+    };
+}
+
+impl_op!(Add, add, +);
+impl_op!(Sub, sub, -);
+impl_op!(Mul, mul, *);
+
+impl<'a> Neg for &Elem<'a> {
+    type Output = Elem<'a>;
+
+    fn neg(self) -> Elem<'a> {
+        self.ring.elem(-&self.x)
+    }
+}
+
+impl<'a> Neg for Elem<'a> {
+    type Output = Elem<'a>;
+
+    fn neg(self) -> Elem<'a> {
+        -&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic_wraps_around_the_modulus() {
+        let ring = ModRing::new(BigInt::from(7));
+        let (a, b) = (ring.elem(5), ring.elem(4));
+        assert_eq!(&a + &b, ring.elem(2));
+        assert_eq!(&b - &a, ring.elem(6));
+        assert_eq!(&a * &b, ring.elem(6));
+        assert_eq!(-&a, ring.elem(2));
+        assert_eq!(ring.elem(-1), ring.elem(6));
+        assert_eq!(a.pow(&BigInt::from(6)), ring.one());
+        assert_eq!(format!("{}", a * b), "6 (mod 7)");
+    }
+
+    #[test]
+    fn only_units_have_inverses() {
+        let ring = ModRing::new(BigInt::from(12));
+        let five = ring.elem(5);
+        assert_eq!(&five * five.inv().unwrap(), ring.one());
+        assert_eq!(five.pow(&BigInt::from(-1)), five.inv().unwrap());
+        assert_eq!(ring.elem(4).inv(), None);
+        assert!(ring.elem(24).is_zero());
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot combine")]
+    fn elements_of_different_rings_do_not_mix() {
+        let (r7, r11) = (
+            ModRing::new(BigInt::from(7)),
+            ModRing::new(BigInt::from(11)),
+        );
+        let _ = r7.one() + r11.one();
+    }
+}
@@ -0,0 +1,281 @@
+// Modular exponentiation strategies, side by side
+//
+// BigInt::modpow is what the rest of the crate uses. The backends here are written out by
+// hand so that they can be read, compared and timed against each other:
+//   Plain         left-to-right square-and-multiply, reducing with % after every step
+//   Montgomery    the same chain in Montgomery form, where reduction is a shift (odd moduli)
+//   Barrett       the same chain, reducing with a precomputed reciprocal of the modulus
+//   ConstantTime  a Montgomery ladder: one multiply and one square per exponent bit,
+//                 whatever the bit is
+// BigInt arithmetic is itself variable-time, so ConstantTime only shows the shape of a
+// side-channel-resistant exponentiation; it does not make this crate constant-time.
+//
+// multi_modpow computes a product of powers b_1^e_1 ... b_k^e_k with Straus' interleaved
+// windows: the exponents are cut into w-bit windows and walked together from the top, so
+// the k chains share their squarings and only one table of 2^w powers per base is needed.
+// Verifying a batch of signatures or accumulator witnesses is one such product.
+use crate::invert;
+use num_bigint::BigInt;
+use num_traits::{One, Zero};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Plain,
+    Montgomery,
+    Barrett,
+    ConstantTime,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 4] = [
+        Backend::Plain,
+        Backend::Montgomery,
+        Backend::Barrett,
+        Backend::ConstantTime,
+    ];
+}
+
+// modpow_with: base^exp mod modulus in [0, modulus), computed by the chosen backend
+pub fn modpow_with(base: &BigInt, exp: &BigInt, modulus: &BigInt, backend: Backend) -> BigInt {
+    if *modulus <= BigInt::zero() {
+        panic!("modulus must be positive, got {}", modulus);
+    }
+    if *exp < BigInt::zero() {
+        panic!("exponent must be non-negative, got {}", exp);
+    }
+    if modulus.is_one() {
+        return BigInt::zero();
+    }
+    if exp.is_zero() {
+        return BigInt::one();
+    }
+    let base = ((base % modulus) + modulus) % modulus;
+    match backend {
+        Backend::Plain => square_and_multiply(&base, exp, |x| x % modulus),
+        Backend::Barrett => {
+            let barrett = Barrett::new(modulus);
+            square_and_multiply(&base, exp, |x| barrett.reduce(x))
+        }
+        Backend::Montgomery => {
+            let mont = Montgomery::new(modulus);
+            let x = square_and_multiply(&mont.to_form(&base), exp, |x| mont.redc(x));
+            // The chain ran on x R throughout, so one more REDC leaves Montgomery form
+            mont.redc(&x)
+        }
+        Backend::ConstantTime => ladder(&base, exp, modulus),
+    }
+}
+
+// square_and_multiply: walk the exponent down from below its top bit, starting from base;
+// reduce maps a product below modulus^2 back into the residues
+fn square_and_multiply(base: &BigInt, exp: &BigInt, reduce: impl Fn(&BigInt) -> BigInt) -> BigInt {
+    let mut acc = base.clone();
+    for i in (0..exp.bits() - 1).rev() {
+        acc = reduce(&(&acc * &acc));
+        if exp.bit(i) {
+            acc = reduce(&(&acc * base));
+        }
+    }
+    acc
+}
+
+struct Barrett {
+    n: BigInt,
+    k: u64,
+    mu: BigInt,
+}
+
+impl Barrett {
+    fn new(n: &BigInt) -> Self {
+        let k = n.bits();
+        let mu = (BigInt::one() << (2 * k)) / n;
+        Barrett {
+            n: n.clone(),
+            k,
+            mu,
+        }
+    }
+
+    // reduce: x mod n for 0 <= x < n^2; the quotient estimate is off by at most two
+    fn reduce(&self, x: &BigInt) -> BigInt {
+        let q = ((x >> (self.k - 1)) * &self.mu) >> (self.k + 1);
+        let mut r = x - q * &self.n;
+        while r >= self.n {
+            r -= &self.n;
+        }
+        r
+    }
+}
+
+struct Montgomery {
+    n: BigInt,
+    k: u64,
+    n_prime: BigInt,
+}
+
+impl Montgomery {
+    fn new(n: &BigInt) -> Self {
+        if (n % 2u8).is_zero() {
+            panic!("Montgomery reduction needs an odd modulus, got {}", n);
+        }
+        let k = n.bits();
+        let r = BigInt::one() << k;
+        // n' = -n^-1 mod R
+        let n_prime = &r - invert(n.clone(), r.clone());
+        Montgomery {
+            n: n.clone(),
+            k,
+            n_prime,
+        }
+    }
+
+    fn to_form(&self, x: &BigInt) -> BigInt {
+        (x << self.k) % &self.n
+    }
+
+    // redc: x R^-1 mod n for 0 <= x < n R, using only masks and shifts by R = 2^k
+    fn redc(&self, x: &BigInt) -> BigInt {
+        let mask = (BigInt::one() << self.k) - 1;
+        let m = ((x & &mask) * &self.n_prime) & &mask;
+        let t = (x + m * &self.n) >> self.k;
+        if t >= self.n {
+            t - &self.n
+        } else {
+            t
+        }
+    }
+}
+
+// window_bits: the Straus window for exponents of the given size; larger windows trade a
+// bigger table per base for fewer multiplications
+fn window_bits(exp_bits: u64) -> u64 {
+    match exp_bits {
+        0..=32 => 2,
+        33..=256 => 4,
+        _ => 5,
+    }
+}
+
+// multi_modpow: the product of base^exp over the pairs, mod modulus, in [0, modulus)
+pub fn multi_modpow(pairs: &[(BigInt, BigInt)], modulus: &BigInt) -> BigInt {
+    if *modulus <= BigInt::zero() {
+        panic!("modulus must be positive, got {}", modulus);
+    }
+    if let Some((_, exp)) = pairs.iter().find(|(_, exp)| *exp < BigInt::zero()) {
+        panic!("exponent must be non-negative, got {}", exp);
+    }
+    let max_bits = pairs.iter().map(|(_, exp)| exp.bits()).max().unwrap_or(0);
+    let w = window_bits(max_bits);
+    // tables[j][i] = base_j^i for i < 2^w
+    let tables: Vec<Vec<BigInt>> = pairs
+        .iter()
+        .map(|(base, _)| {
+            let base = ((base % modulus) + modulus) % modulus;
+            let mut table = vec![BigInt::one() % modulus];
+            for i in 1..1usize << w {
+                table.push(&table[i - 1] * &base % modulus);
+            }
+            table
+        })
+        .collect();
+    let mut acc = BigInt::one() % modulus;
+    for window in (0..max_bits.div_ceil(w)).rev() {
+        for _ in 0..w {
+            acc = &acc * &acc % modulus;
+        }
+        for ((_, exp), table) in pairs.iter().zip(&tables) {
+            let digit = (0..w).fold(0, |d, i| d << 1 | exp.bit(window * w + w - 1 - i) as usize);
+            if digit != 0 {
+                acc = acc * &table[digit] % modulus;
+            }
+        }
+    }
+    acc
+}
+
+// ladder: keeps r1 = r0 * base throughout; each bit does one product and one square
+fn ladder(base: &BigInt, exp: &BigInt, n: &BigInt) -> BigInt {
+    let mut r = [BigInt::one(), base.clone()];
+    for i in (0..exp.bits()).rev() {
+        let bit = exp.bit(i) as usize;
+        r[1 - bit] = &r[0] * &r[1] % n;
+        r[bit] = &r[bit] * &r[bit] % n;
+    }
+    r[0].clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::RandBigInt;
+
+    #[test]
+    fn backends_agree_with_modpow() {
+        let mut rng = rand::thread_rng();
+        for bits in [8, 64, 300] {
+            let n = BigInt::from(rng.gen_biguint(bits)) | BigInt::one();
+            let base = rng.gen_bigint(bits + 10);
+            let exp = BigInt::from(rng.gen_biguint(bits));
+            let expected = ((&base % &n + &n) % &n).modpow(&exp, &n);
+            for backend in Backend::ALL {
+                assert_eq!(
+                    modpow_with(&base, &exp, &n, backend),
+                    expected,
+                    "{:?}",
+                    backend
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn edge_cases() {
+        let n = BigInt::from(101);
+        for backend in Backend::ALL {
+            assert_eq!(
+                modpow_with(&BigInt::from(5), &BigInt::zero(), &n, backend),
+                BigInt::one()
+            );
+            assert_eq!(
+                modpow_with(&BigInt::from(5), &BigInt::one(), &n, backend),
+                BigInt::from(5)
+            );
+            assert_eq!(
+                modpow_with(&BigInt::from(-1), &BigInt::from(3), &n, backend),
+                BigInt::from(100)
+            );
+            assert_eq!(
+                modpow_with(&BigInt::from(7), &BigInt::from(9), &BigInt::one(), backend),
+                BigInt::zero()
+            );
+        }
+    }
+
+    #[test]
+    fn multi_modpow_agrees_with_a_product_of_modpows() {
+        let mut rng = rand::thread_rng();
+        for (count, bits) in [(1, 8), (3, 64), (5, 300)] {
+            let n = BigInt::from(rng.gen_biguint(bits)) | BigInt::one();
+            let pairs: Vec<(BigInt, BigInt)> = (0..count)
+                .map(|_| (rng.gen_bigint(bits), BigInt::from(rng.gen_biguint(bits))))
+                .collect();
+            let expected = pairs.iter().fold(BigInt::one(), |acc, (base, exp)| {
+                acc * ((base % &n + &n) % &n).modpow(exp, &n) % &n
+            });
+            assert_eq!(multi_modpow(&pairs, &n), expected);
+        }
+        assert_eq!(multi_modpow(&[], &BigInt::from(7)), BigInt::one());
+        assert_eq!(multi_modpow(&[], &BigInt::one()), BigInt::zero());
+    }
+
+    #[test]
+    #[should_panic(expected = "odd modulus")]
+    fn montgomery_rejects_even_moduli() {
+        modpow_with(
+            &BigInt::from(3),
+            &BigInt::from(5),
+            &BigInt::from(100),
+            Backend::Montgomery,
+        );
+    }
+}
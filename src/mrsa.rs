@@ -0,0 +1,106 @@
+// Mediated RSA (mRSA)
+//
+// The private exponent is split additively, d = d_user + d_mediator mod phi(n), between the
+// user and an online mediator. Decryption (or signing) needs a partial result from each,
+// c^d_user * c^d_mediator = c^d mod n, so the mediator can revoke a user instantly by
+// refusing to answer. Neither half on its own reveals anything about d.
+use crate::{gen_prime_pair, invert, PublicKey};
+use num_bigint::{BigInt, RandBigInt};
+use std::collections::{HashMap, HashSet};
+
+pub struct KeyShare {
+    d: BigInt,
+}
+
+#[derive(Default)]
+pub struct Mediator {
+    shares: HashMap<String, (PublicKey, KeyShare)>,
+    revoked: HashSet<String>,
+}
+
+// gen_keys: a fresh key pair whose private exponent is split into a user and a mediator share
+pub fn gen_keys(ndigits: u32) -> (PublicKey, KeyShare, KeyShare) {
+    let e = BigInt::from(65537);
+    let (p, q) = gen_prime_pair(ndigits, &e);
+    let n = &p * &q;
+    let phi: BigInt = (&p - 1) * (&q - 1);
+    let d = invert(e.clone(), phi.clone());
+    let d_user = rand::thread_rng().gen_bigint_range(&BigInt::from(1), &phi);
+    let d_mediator = ((d - &d_user) % &phi + &phi) % &phi;
+    (
+        PublicKey { n, e },
+        KeyShare { d: d_user },
+        KeyShare { d: d_mediator },
+    )
+}
+
+impl KeyShare {
+    // partial_decrypt: this share's contribution c^d_share mod n; signing works the same way
+    pub fn partial_decrypt(&self, pub_key: &PublicKey, c: &BigInt) -> BigInt {
+        c.modpow(&self.d, &pub_key.n)
+    }
+}
+
+// combine: the full decryption (or signature) from the user's and the mediator's parts
+pub fn combine(pub_key: &PublicKey, user_part: &BigInt, mediator_part: &BigInt) -> BigInt {
+    user_part * mediator_part % &pub_key.n
+}
+
+impl Mediator {
+    pub fn new() -> Self {
+        Mediator::default()
+    }
+
+    pub fn enroll(&mut self, user: &str, pub_key: PublicKey, share: KeyShare) {
+        self.revoked.remove(user);
+        self.shares.insert(user.to_string(), (pub_key, share));
+    }
+
+    // revoke: from now on the mediator refuses to help this user, who can no longer decrypt
+    pub fn revoke(&mut self, user: &str) {
+        self.revoked.insert(user.to_string());
+    }
+
+    pub fn is_revoked(&self, user: &str) -> bool {
+        self.revoked.contains(user)
+    }
+
+    // partial_decrypt: None for unknown or revoked users
+    pub fn partial_decrypt(&self, user: &str, c: &BigInt) -> Option<BigInt> {
+        if self.is_revoked(user) {
+            return None;
+        }
+        let (pub_key, share) = self.shares.get(user)?;
+        Some(share.partial_decrypt(pub_key, c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encrypt;
+
+    #[test]
+    fn both_shares_together_decrypt() {
+        let (pub_key, user, mediator_share) = gen_keys(20);
+        let mut mediator = Mediator::new();
+        mediator.enroll("alice", pub_key.clone(), mediator_share);
+        let m = BigInt::from(4242);
+        let c = encrypt(&pub_key, m.clone().into()).unwrap().into();
+        let mediator_part = mediator.partial_decrypt("alice", &c).unwrap();
+        let user_part = user.partial_decrypt(&pub_key, &c);
+        assert_eq!(combine(&pub_key, &user_part, &mediator_part), m);
+        assert_ne!(user_part, m);
+    }
+
+    #[test]
+    fn revoked_users_get_no_help() {
+        let (pub_key, _, mediator_share) = gen_keys(20);
+        let mut mediator = Mediator::new();
+        mediator.enroll("alice", pub_key.clone(), mediator_share);
+        mediator.revoke("alice");
+        let c = encrypt(&pub_key, BigInt::from(1).into()).unwrap().into();
+        assert!(mediator.partial_decrypt("alice", &c).is_none());
+        assert!(mediator.partial_decrypt("bob", &c).is_none());
+    }
+}
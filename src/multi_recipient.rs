@@ -0,0 +1,217 @@
+// Hybrid encryption to several recipients at once
+//
+// A random content key encrypts the message once, with the SHA-256 keystream and HMAC tag
+// of kem::seal, and each recipient gets the content key sealed to their own public key in
+// a slot labelled with their identity. The message costs its own length once and each
+// recipient one sealed key. The tag covers the slots as well as the body, so slots cannot
+// be dropped or swapped without detection by outsiders; but every recipient holds the
+// content key, so any one of them can forge a message that the others accept, and the
+// format says nothing about who sent it. seal_for takes the recipients' keys from a
+// KeyDirectory.
+use crate::directory::{DirectoryError, KeyDirectory};
+use crate::error::ParseError;
+use crate::hash::{self, ct_eq, hmac_sha256};
+use crate::kem;
+use crate::ring::xor;
+use crate::wire::{Reader, Writer};
+use crate::{PrivateKey, PublicKey};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use std::fmt;
+
+const FORMAT: &str = "naive-rsa multi-recipient";
+const KEY_LEN: usize = 32;
+const TAG_LEN: usize = 32;
+
+#[derive(Debug)]
+pub enum SealError {
+    // seal_for was given no identities
+    NoRecipients,
+    // A recipient's key could not be looked up
+    Directory(DirectoryError),
+}
+
+impl fmt::Display for SealError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SealError::NoRecipients => write!(f, "a multi-recipient message needs a recipient"),
+            SealError::Directory(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for SealError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SealError::NoRecipients => None,
+            SealError::Directory(err) => Some(err),
+        }
+    }
+}
+
+impl From<DirectoryError> for SealError {
+    fn from(err: DirectoryError) -> Self {
+        SealError::Directory(err)
+    }
+}
+
+// seal: encrypt m so that each of the recipients, given by identity and key, can open it
+pub fn seal(recipients: &[(&str, &PublicKey)], m: &[u8]) -> Vec<u8> {
+    seal_with_rng(recipients, m, &mut OsRng)
+}
+
+pub fn seal_with_rng(
+    recipients: &[(&str, &PublicKey)],
+    m: &[u8],
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Vec<u8> {
+    if recipients.is_empty() {
+        panic!("a multi-recipient message needs at least one recipient");
+    }
+    let mut content_key = vec![0u8; 2 * KEY_LEN];
+    rng.fill_bytes(&mut content_key);
+    let (enc_key, mac_key) = content_key.split_at(KEY_LEN);
+    let mut w = Writer::new(FORMAT);
+    w.bytes(&(recipients.len() as u32).to_be_bytes());
+    for (identity, pub_key) in recipients {
+        w.bytes(identity.as_bytes())
+            .bytes(&kem::seal_with_rng(pub_key, &content_key, rng));
+    }
+    w.bytes(&xor(m, &hash::expand(enc_key, m.len())));
+    let body = w.finish();
+    let tag = hmac_sha256(mac_key, &[&body]);
+    [body, tag].concat()
+}
+
+// seal_for: seal with the recipients' keys looked up in directory
+pub fn seal_for(
+    directory: &(impl KeyDirectory + ?Sized),
+    identities: &[&str],
+    m: &[u8],
+) -> Result<Vec<u8>, SealError> {
+    if identities.is_empty() {
+        return Err(SealError::NoRecipients);
+    }
+    let keys = identities
+        .iter()
+        .map(|identity| directory.lookup(identity).map(|entry| entry.key))
+        .collect::<Result<Vec<_>, _>>()?;
+    let recipients: Vec<(&str, &PublicKey)> = identities.iter().copied().zip(&keys).collect();
+    Ok(seal(&recipients, m))
+}
+
+struct Parsed<'a> {
+    slots: Vec<(&'a [u8], &'a [u8])>,
+    ciphertext: &'a [u8],
+    body: &'a [u8],
+    tag: &'a [u8],
+}
+
+fn parse(sealed: &[u8]) -> Result<Parsed<'_>, ParseError> {
+    if sealed.len() < TAG_LEN {
+        return Err(ParseError::invalid(FORMAT, 0, "too short for the tag"));
+    }
+    let (body, tag) = sealed.split_at(sealed.len() - TAG_LEN);
+    let mut r = Reader::new(body, FORMAT)?;
+    let slots = (0..r.count()?)
+        .map(|_| Ok((r.bytes()?, r.bytes()?)))
+        .collect::<Result<_, ParseError>>()?;
+    let ciphertext = r.bytes()?;
+    r.finish()?;
+    Ok(Parsed {
+        slots,
+        ciphertext,
+        body,
+        tag,
+    })
+}
+
+// recipients: the identities a sealed message is addressed to
+pub fn recipients(sealed: &[u8]) -> Result<Vec<String>, ParseError> {
+    Ok(parse(sealed)?
+        .slots
+        .iter()
+        .map(|(identity, _)| String::from_utf8_lossy(identity).into_owned())
+        .collect())
+}
+
+// open: the message, if sealed has a slot for identity that opens with this key pair and
+// the whole message is untampered
+pub fn open(
+    identity: &str,
+    pub_key: &PublicKey,
+    priv_key: &PrivateKey,
+    sealed: &[u8],
+) -> Option<Vec<u8>> {
+    let parsed = parse(sealed).ok()?;
+    let content_key = parsed
+        .slots
+        .iter()
+        .filter(|(listed, _)| *listed == identity.as_bytes())
+        .find_map(|(_, wrapped)| kem::open(pub_key, priv_key, wrapped))
+        .filter(|key| key.len() == 2 * KEY_LEN)?;
+    let (enc_key, mac_key) = content_key.split_at(KEY_LEN);
+    if !ct_eq(&hmac_sha256(mac_key, &[parsed.body]), parsed.tag) {
+        return None;
+    }
+    let c = parsed.ciphertext;
+    Some(xor(c, &hash::expand(enc_key, c.len())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directory::{DirectoryEntry, MemoryDirectory};
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn every_recipient_opens_the_message() {
+        let keys: Vec<_> = (0..3).map(|_| gen_keys_with_digits(30)).collect();
+        let names = ["alice", "bob", "carol"];
+        let to: Vec<(&str, &PublicKey)> = names
+            .iter()
+            .copied()
+            .zip(keys.iter().map(|(p, _)| p))
+            .collect();
+        let sealed = seal(&to, b"meeting at noon");
+        assert_eq!(recipients(&sealed).unwrap(), names);
+        for (name, (pub_key, priv_key)) in names.iter().zip(&keys) {
+            assert_eq!(
+                open(name, pub_key, priv_key, &sealed).as_deref(),
+                Some(&b"meeting at noon"[..])
+            );
+        }
+        // A recipient's key does not open someone else's slot
+        let (alice_pub, alice_priv) = &keys[0];
+        assert!(open("bob", alice_pub, alice_priv, &sealed).is_none());
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let (pub_key, priv_key) = gen_keys_with_digits(30);
+        let sealed = seal(&[("alice", &pub_key)], b"hello");
+        for i in [sealed.len() - 1, sealed.len() - TAG_LEN - 1] {
+            let mut tampered = sealed.clone();
+            tampered[i] ^= 1;
+            assert!(open("alice", &pub_key, &priv_key, &tampered).is_none());
+        }
+        assert!(open("alice", &pub_key, &priv_key, &sealed[..10]).is_none());
+    }
+
+    #[test]
+    fn keys_come_from_the_directory() {
+        let (alice_pub, alice_priv) = gen_keys_with_digits(30);
+        let mut directory = MemoryDirectory::new();
+        directory.insert("alice", DirectoryEntry::new(alice_pub.clone()));
+        let sealed = seal_for(&directory, &["alice"], b"from the directory").unwrap();
+        assert!(open("alice", &alice_pub, &alice_priv, &sealed).is_some());
+        assert!(matches!(
+            seal_for(&directory, &["alice", "mallory"], b""),
+            Err(SealError::Directory(DirectoryError::NotFound(identity))) if identity == "mallory"
+        ));
+        assert!(matches!(
+            seal_for(&directory, &[], b""),
+            Err(SealError::NoRecipients)
+        ));
+    }
+}
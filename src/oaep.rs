@@ -0,0 +1,331 @@
+// RSAES-OAEP (RFC 8017, section 7.1) with MGF1
+//
+// Textbook RSA is deterministic and malleable. OAEP first runs the message through a
+// two-round Feistel network keyed by a random seed, so that equal messages encrypt
+// differently and any tampering with the ciphertext is detected on decryption. The free
+// functions use SHA-256; Oaep::new picks any other HashAlg, used for both the label hash
+// and MGF1.
+//
+// Encryption draws its seed from OsRng; the _with_rng variants take any CryptoRng instead,
+// e.g. a seeded one for reproducible test vectors. It returns None for a message longer
+// than max_message_len, and for any message under a modulus shorter than 2 hLen + 2 bytes,
+// which OAEP cannot use at all.
+//
+// DeterministicOaep is a separate, opt-in variant that derives the seed from an HMAC of the
+// message (as in SIV mode), so equal messages under the same key give equal ciphertexts.
+// That enables deduplication or equality search over ciphertexts, at the price of leaking
+// exactly that equality, and of being open to dictionary attacks by anyone who knows the
+// HMAC key.
+use crate::hash::{ct_eq, hmac_sha256};
+use crate::hash_alg::HashAlg;
+use crate::{
+    decrypt as rsa_decrypt, encrypt as rsa_encrypt, i2osp, modulus_len, os2ip, PrivateKey,
+    PublicKey,
+};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Oaep {
+    hash: HashAlg,
+}
+
+const SHA256: Oaep = Oaep {
+    hash: HashAlg::Sha256,
+};
+
+fn xor_in_place(dst: &mut [u8], mask: &[u8]) {
+    dst.iter_mut().zip(mask).for_each(|(d, m)| *d ^= m);
+}
+
+impl Oaep {
+    pub fn new(hash: HashAlg) -> Self {
+        Oaep { hash }
+    }
+
+    pub fn hash(&self) -> HashAlg {
+        self.hash
+    }
+
+    // max_message_len: the longest message that fits a k-byte modulus, k - 2 hLen - 2
+    pub fn max_message_len(&self, pub_key: &PublicKey) -> usize {
+        modulus_len(pub_key).saturating_sub(2 * self.hash.output_len() + 2)
+    }
+
+    // encode: EME-OAEP encoding of m into k bytes with the given seed, or None if m does
+    // not fit
+    pub(crate) fn encode(&self, k: usize, m: &[u8], label: &[u8], seed: &[u8]) -> Option<Vec<u8>> {
+        let h_len = self.hash.output_len();
+        if k < m.len() + 2 * h_len + 2 {
+            return None;
+        }
+        let mut db = self.hash.digest(label);
+        db.resize(k - h_len - 1 - m.len() - 1, 0);
+        db.push(0x01);
+        db.extend_from_slice(m);
+        let db_mask = self.hash.mgf1(seed, db.len());
+        xor_in_place(&mut db, &db_mask);
+        let mut masked_seed = seed.to_vec();
+        xor_in_place(&mut masked_seed, &self.hash.mgf1(&db, h_len));
+        Some([&[0x00][..], &masked_seed, &db].concat())
+    }
+
+    // decode: undo encode, returning (seed, message). Every check is folded into one flag so
+    // that all malformed encodings take the same path and report the same failure, which is
+    // what keeps Manger's attack from telling them apart.
+    pub(crate) fn decode(&self, em: &[u8], label: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        let h_len = self.hash.output_len();
+        let k = em.len();
+        if k < 2 * h_len + 2 {
+            return None;
+        }
+        let (masked_seed, masked_db) = em[1..].split_at(h_len);
+        let mut seed = masked_seed.to_vec();
+        xor_in_place(&mut seed, &self.hash.mgf1(masked_db, h_len));
+        let mut db = masked_db.to_vec();
+        let db_mask = self.hash.mgf1(&seed, db.len());
+        xor_in_place(&mut db, &db_mask);
+        let l_hash = self.hash.digest(label);
+        let mut bad = !em[0].ct_eq(&0x00) | !db[..h_len].ct_eq(&l_hash[..]);
+        // Find the 0x01 separator after the zero padding without branching on secret bytes
+        let mut looking = Choice::from(1);
+        let mut index = 0u64;
+        for (i, &byte) in db[h_len..].iter().enumerate() {
+            let is_one = byte.ct_eq(&0x01);
+            let is_zero = byte.ct_eq(&0x00);
+            index.conditional_assign(&(i as u64), looking & is_one);
+            bad |= looking & !is_one & !is_zero;
+            looking &= !is_one;
+        }
+        bad |= looking;
+        if bad.into() {
+            return None;
+        }
+        Some((seed, db[h_len + index as usize + 1..].to_vec()))
+    }
+
+    pub(crate) fn encrypt_with_seed(
+        &self,
+        pub_key: &PublicKey,
+        m: &[u8],
+        label: &[u8],
+        seed: &[u8],
+    ) -> Option<Vec<u8>> {
+        let k = modulus_len(pub_key);
+        let em = self.encode(k, m, label, seed)?;
+        let c = rsa_encrypt(pub_key, os2ip(&em).into()).expect("em starts with a zero byte");
+        Some(i2osp(c.as_bigint(), k))
+    }
+
+    fn decrypt_to_parts(
+        &self,
+        pub_key: &PublicKey,
+        priv_key: &PrivateKey,
+        c: &[u8],
+        label: &[u8],
+    ) -> Option<(Vec<u8>, Vec<u8>)> {
+        let k = modulus_len(pub_key);
+        if c.len() != k {
+            return None;
+        }
+        let c = os2ip(c);
+        if k < 2 * self.hash.output_len() + 2 || c >= pub_key.n {
+            return None;
+        }
+        let em = rsa_decrypt(pub_key, priv_key, c.into()).ok()?;
+        self.decode(&i2osp(em.as_bigint(), k), label)
+    }
+
+    // encrypt: OAEP-encrypt m with a fresh random seed; label is optional associated data
+    pub fn encrypt(&self, pub_key: &PublicKey, m: &[u8], label: &[u8]) -> Option<Vec<u8>> {
+        self.encrypt_with_rng(pub_key, m, label, &mut OsRng)
+    }
+
+    pub fn encrypt_with_rng(
+        &self,
+        pub_key: &PublicKey,
+        m: &[u8],
+        label: &[u8],
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Option<Vec<u8>> {
+        let mut seed = vec![0u8; self.hash.output_len()];
+        rng.fill_bytes(&mut seed);
+        self.encrypt_with_seed(pub_key, m, label, &seed)
+    }
+
+    // decrypt: None for any invalid ciphertext, without saying why
+    pub fn decrypt(
+        &self,
+        pub_key: &PublicKey,
+        priv_key: &PrivateKey,
+        c: &[u8],
+        label: &[u8],
+    ) -> Option<Vec<u8>> {
+        self.decrypt_to_parts(pub_key, priv_key, c, label)
+            .map(|(_, m)| m)
+    }
+}
+
+pub fn max_message_len(pub_key: &PublicKey) -> usize {
+    SHA256.max_message_len(pub_key)
+}
+
+// encrypt: OAEP with SHA-256
+pub fn encrypt(pub_key: &PublicKey, m: &[u8], label: &[u8]) -> Option<Vec<u8>> {
+    SHA256.encrypt(pub_key, m, label)
+}
+
+pub fn encrypt_with_rng(
+    pub_key: &PublicKey,
+    m: &[u8],
+    label: &[u8],
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Option<Vec<u8>> {
+    SHA256.encrypt_with_rng(pub_key, m, label, rng)
+}
+
+pub fn decrypt(
+    pub_key: &PublicKey,
+    priv_key: &PrivateKey,
+    c: &[u8],
+    label: &[u8],
+) -> Option<Vec<u8>> {
+    SHA256.decrypt(pub_key, priv_key, c, label)
+}
+
+// DeterministicOaep: SHA-256 OAEP whose seed is an HMAC-SHA256 of the label and message
+pub struct DeterministicOaep {
+    key: Vec<u8>,
+}
+
+impl DeterministicOaep {
+    // new: the HMAC key decides which ciphertexts collide; keep it as secret as the data
+    pub fn new(mac_key: &[u8]) -> Self {
+        DeterministicOaep {
+            key: mac_key.to_vec(),
+        }
+    }
+
+    fn seed(&self, m: &[u8], label: &[u8]) -> Vec<u8> {
+        hmac_sha256(&self.key, &[&(label.len() as u64).to_be_bytes(), label, m])
+    }
+
+    pub fn encrypt(&self, pub_key: &PublicKey, m: &[u8], label: &[u8]) -> Option<Vec<u8>> {
+        SHA256.encrypt_with_seed(pub_key, m, label, &self.seed(m, label))
+    }
+
+    // decrypt: also rejects ciphertexts whose seed was not derived from their message
+    pub fn decrypt(
+        &self,
+        pub_key: &PublicKey,
+        priv_key: &PrivateKey,
+        c: &[u8],
+        label: &[u8],
+    ) -> Option<Vec<u8>> {
+        let (seed, m) = SHA256.decrypt_to_parts(pub_key, priv_key, c, label)?;
+        ct_eq(&seed, &self.seed(&m, label)).then_some(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::sync::OnceLock;
+
+    // OAEP needs a modulus of at least 66 bytes; generate one for the whole module
+    fn keys() -> &'static (PublicKey, PrivateKey) {
+        static KEYS: OnceLock<(PublicKey, PrivateKey)> = OnceLock::new();
+        KEYS.get_or_init(|| gen_keys_with_digits(100))
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let (pub_key, priv_key) = keys();
+        for m in [
+            &b""[..],
+            b"hi",
+            &[0u8; 16],
+            &vec![0xab; max_message_len(pub_key)],
+        ] {
+            let c = encrypt(pub_key, m, b"label").unwrap();
+            assert_eq!(decrypt(pub_key, priv_key, &c, b"label").unwrap(), m);
+            assert_eq!(decrypt(pub_key, priv_key, &c, b"other"), None);
+        }
+    }
+
+    #[test]
+    fn other_hashes_round_trip() {
+        let (pub_key, priv_key) = keys();
+        for hash in [HashAlg::Sha1, HashAlg::Sha3_256, HashAlg::Blake2s256] {
+            let oaep = Oaep::new(hash);
+            let c = oaep.encrypt(pub_key, b"hi", b"").unwrap();
+            assert_eq!(oaep.decrypt(pub_key, priv_key, &c, b"").unwrap(), b"hi");
+            assert_eq!(decrypt(pub_key, priv_key, &c, b""), None);
+        }
+    }
+
+    #[test]
+    fn tampered_ciphertexts_are_rejected() {
+        let (pub_key, priv_key) = keys();
+        let mut c = encrypt(pub_key, b"attack at dawn", b"").unwrap();
+        let last = c.len() - 1;
+        c[last] ^= 1;
+        assert_eq!(decrypt(pub_key, priv_key, &c, b""), None);
+    }
+
+    #[test]
+    fn ciphertexts_of_the_wrong_length_are_rejected() {
+        let (pub_key, priv_key) = keys();
+        let c = encrypt(pub_key, b"attack at dawn", b"").unwrap();
+        let padded = [&[0x00][..], &c].concat();
+        assert_eq!(decrypt(pub_key, priv_key, &padded, b""), None);
+        assert_eq!(decrypt(pub_key, priv_key, &c[1..], b""), None);
+    }
+
+    #[test]
+    fn seeded_rngs_give_reproducible_ciphertexts() {
+        let (pub_key, priv_key) = keys();
+        let c = encrypt_with_rng(pub_key, b"vector", b"", &mut StdRng::seed_from_u64(7)).unwrap();
+        assert_eq!(
+            Some(c.clone()),
+            encrypt_with_rng(pub_key, b"vector", b"", &mut StdRng::seed_from_u64(7))
+        );
+        assert_ne!(
+            Some(c.clone()),
+            encrypt_with_rng(pub_key, b"vector", b"", &mut StdRng::seed_from_u64(8))
+        );
+        assert_eq!(decrypt(pub_key, priv_key, &c, b"").unwrap(), b"vector");
+    }
+
+    #[test]
+    fn deterministic_mode_is_deterministic() {
+        let (pub_key, priv_key) = keys();
+        let siv = DeterministicOaep::new(b"dedup key");
+        let c1 = siv.encrypt(pub_key, b"same", b"").unwrap();
+        assert_eq!(Some(c1.clone()), siv.encrypt(pub_key, b"same", b""));
+        assert_ne!(Some(c1.clone()), siv.encrypt(pub_key, b"different", b""));
+        assert_ne!(Some(c1.clone()), encrypt(pub_key, b"same", b""));
+        assert_eq!(siv.decrypt(pub_key, priv_key, &c1, b"").unwrap(), b"same");
+        // An ordinary randomized OAEP ciphertext has no derived seed and is refused
+        let randomized = encrypt(pub_key, b"same", b"").unwrap();
+        assert_eq!(siv.decrypt(pub_key, priv_key, &randomized, b""), None);
+    }
+
+    #[test]
+    fn keys_too_small_or_messages_too_long_give_none() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        assert_eq!(max_message_len(&pub_key), 0);
+        assert_eq!(encrypt(&pub_key, b"", b""), None);
+        let (pub_key, _) = keys();
+        let too_long = vec![0xab; max_message_len(pub_key) + 1];
+        assert_eq!(encrypt(pub_key, &too_long, b""), None);
+        assert_eq!(
+            SHA256.encrypt_with_seed(pub_key, &too_long, b"", &[0; 32]),
+            None
+        );
+    }
+}
@@ -0,0 +1,129 @@
+// Okamoto-Uchiyama additively homomorphic encryption
+//
+// The modulus is n = p^2 q. The subgroup of (Z/p^2)* of order p behaves like the additive
+// group Z/p under the "logarithm" L(x) = (x - 1) / p, so c = g^m h^r mod n can be undone by
+// the holder of p while products of ciphertexts add their plaintexts. Compare with textbook
+// RSA, where products of ciphertexts multiply plaintexts.
+// https://en.wikipedia.org/wiki/Okamoto%E2%80%93Uchiyama_cryptosystem
+use crate::{invert, random_prime_with_digits};
+use num_bigint::{BigInt, RandBigInt};
+
+pub struct OuPublicKey {
+    n: BigInt,
+    g: BigInt,
+    h: BigInt,
+    bound: BigInt,
+}
+
+pub struct OuPrivateKey {
+    p: BigInt,
+    p_squared: BigInt,
+    b_inv: BigInt,
+}
+
+impl OuPublicKey {
+    pub fn modulus(&self) -> &BigInt {
+        &self.n
+    }
+
+    // message_bound: plaintexts must lie in [0, bound), a power of two below p
+    pub fn message_bound(&self) -> &BigInt {
+        &self.bound
+    }
+}
+
+// l: the logarithm on the order p subgroup of (Z/p^2)*
+fn l(x: &BigInt, p: &BigInt) -> BigInt {
+    (x - 1) / p
+}
+
+pub fn gen_keys(ndigits: u32) -> (OuPublicKey, OuPrivateKey) {
+    let p = random_prime_with_digits(ndigits);
+    let mut q = random_prime_with_digits(ndigits);
+    while q == p {
+        q = random_prime_with_digits(ndigits);
+    }
+    let p_squared = &p * &p;
+    let n = &p_squared * &q;
+    let p1 = &p - 1;
+    // g must have order divisible by p mod p^2, i.e. g^(p-1) != 1 mod p^2
+    let mut rng = rand::thread_rng();
+    let (g, b) = loop {
+        let g = rng.gen_bigint_range(&BigInt::from(2), &n);
+        let b = l(&g.modpow(&p1, &p_squared), &p);
+        if &b % &p != BigInt::from(0) {
+            break (g, b);
+        }
+    };
+    let h = g.modpow(&n, &n);
+    let b_inv = invert(b, p.clone());
+    let bound = BigInt::from(1) << (p.bits() - 1);
+    (
+        OuPublicKey { n, g, h, bound },
+        OuPrivateKey {
+            p,
+            p_squared,
+            b_inv,
+        },
+    )
+}
+
+pub fn encrypt(pub_key: &OuPublicKey, m: &BigInt) -> BigInt {
+    if *m < BigInt::from(0) || *m >= pub_key.bound {
+        panic!("{} is outside the message space [0, {})", m, pub_key.bound);
+    }
+    let r = rand::thread_rng().gen_bigint_range(&BigInt::from(1), &pub_key.n);
+    pub_key.g.modpow(m, &pub_key.n) * pub_key.h.modpow(&r, &pub_key.n) % &pub_key.n
+}
+
+pub fn decrypt(priv_key: &OuPrivateKey, c: &BigInt) -> BigInt {
+    let p1 = &priv_key.p - 1;
+    let a = l(&c.modpow(&p1, &priv_key.p_squared), &priv_key.p);
+    a * &priv_key.b_inv % &priv_key.p
+}
+
+// add: a ciphertext of m1 + m2, provided the sum stays below p
+pub fn add(pub_key: &OuPublicKey, c1: &BigInt, c2: &BigInt) -> BigInt {
+    c1 * c2 % &pub_key.n
+}
+
+// scalar_mul: a ciphertext of k * m
+pub fn scalar_mul(pub_key: &OuPublicKey, c: &BigInt, k: &BigInt) -> BigInt {
+    c.modpow(k, &pub_key.n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encryption_and_decryption_round_trip() {
+        let (pub_key, priv_key) = gen_keys(10);
+        for m in [0u32, 1, 42, 1 << 20] {
+            let m = BigInt::from(m);
+            assert_eq!(decrypt(&priv_key, &encrypt(&pub_key, &m)), m);
+        }
+    }
+
+    #[test]
+    fn ciphertexts_add_plaintexts() {
+        let (pub_key, priv_key) = gen_keys(10);
+        let c1 = encrypt(&pub_key, &BigInt::from(1234));
+        let c2 = encrypt(&pub_key, &BigInt::from(4321));
+        assert_eq!(
+            decrypt(&priv_key, &add(&pub_key, &c1, &c2)),
+            BigInt::from(5555)
+        );
+        assert_eq!(
+            decrypt(&priv_key, &scalar_mul(&pub_key, &c1, &BigInt::from(3))),
+            BigInt::from(3702)
+        );
+    }
+
+    #[test]
+    fn encryption_is_randomized() {
+        let (pub_key, _) = gen_keys(10);
+        let m = BigInt::from(7);
+        assert_ne!(encrypt(&pub_key, &m), encrypt(&pub_key, &m));
+    }
+}
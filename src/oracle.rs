@@ -0,0 +1,289 @@
+// Private-key operations behind a service boundary
+//
+// A DecryptionOracle computes x^d mod n for whoever holds it and nothing else. PrivateKeyOps
+// is what application code calls to decrypt and sign, and every oracle provides it by
+// blinding: the input is multiplied by r^e for a fresh random r before it goes to the
+// oracle and the answer by r^-1 after, so the oracle (or anyone watching the link to it)
+// sees only uniformly random group elements. The answer is checked against the public key
+// before it is unblinded, so a faulty oracle cannot leak a factor the way an unchecked CRT
+// signature does (see fault).
+//
+// LocalOracle keeps the key in the same process. ChannelOracle is the client half of a
+// remote oracle: requests and replies travel as wire-format bytes over mpsc channels, in
+// place of a socket, to an OracleServer that can run on another thread. The messages are
+// not serde types: serde is only an optional dependency here and the crate has no serde
+// format to turn them into bytes with, while the wire format is what every other message
+// in the crate already uses and works without any feature.
+use crate::error::ParseError;
+use crate::hardened::Blinding;
+use crate::wire::{Reader, Writer};
+use crate::{rsa_private, rsa_public};
+use crate::{Ciphertext, Plaintext, PrivateKey, PublicKey};
+use num_bigint::BigInt;
+use num_traits::Zero;
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const REQUEST_TAG: &str = "naive-rsa oracle request";
+const REPLY_TAG: &str = "naive-rsa oracle reply";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OracleError {
+    // The other end of the channel has gone away
+    Disconnected,
+    // A request or reply did not parse
+    Malformed(ParseError),
+    // The oracle declined, e.g. an input outside [0, n)
+    Refused(String),
+    // The answer is not an e-th root of what was asked
+    WrongAnswer,
+    // Too many operations for now, as enforced by metered::MeteredKey
+    RateLimited { retry_after: Duration },
+}
+
+impl fmt::Display for OracleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OracleError::Disconnected => write!(f, "the decryption oracle is not reachable"),
+            OracleError::Malformed(err) => write!(f, "malformed oracle message: {}", err),
+            OracleError::Refused(reason) => write!(f, "the oracle refused: {}", reason),
+            OracleError::WrongAnswer => write!(f, "the oracle's answer does not check out"),
+            OracleError::RateLimited { retry_after } => {
+                write!(f, "rate limited; retry in {:?}", retry_after)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OracleError {}
+
+pub trait DecryptionOracle {
+    fn public_key(&self) -> &PublicKey;
+
+    // apply: x^d mod n for x in [0, n)
+    fn apply(&self, x: &BigInt) -> Result<BigInt, OracleError>;
+}
+
+pub trait PrivateKeyOps {
+    fn public_key(&self) -> &PublicKey;
+
+    fn decrypt(&self, c: &Ciphertext) -> Result<Plaintext, OracleError>;
+
+    // sign: the textbook signature m^d mod n
+    fn sign(&self, m: &BigInt) -> Result<BigInt, OracleError>;
+}
+
+// blinded: x^d mod n from the oracle, without showing it x
+fn blinded(oracle: &(impl DecryptionOracle + ?Sized), x: &BigInt) -> Result<BigInt, OracleError> {
+    let pub_key = oracle.public_key();
+    let n = &pub_key.n;
+    if *x < BigInt::zero() || x >= n {
+        return Err(OracleError::Refused(format!("{} is outside [0, n)", x)));
+    }
+    let blinding = Blinding::new(pub_key, x);
+    let y = oracle.apply(&blinding.blinded)?;
+    if rsa_public(pub_key, &y) != blinding.blinded {
+        return Err(OracleError::WrongAnswer);
+    }
+    Ok(blinding.unblind(n, &y))
+}
+
+impl<O: DecryptionOracle + ?Sized> PrivateKeyOps for O {
+    fn public_key(&self) -> &PublicKey {
+        DecryptionOracle::public_key(self)
+    }
+
+    fn decrypt(&self, c: &Ciphertext) -> Result<Plaintext, OracleError> {
+        blinded(self, c.as_bigint()).map(Plaintext::from)
+    }
+
+    fn sign(&self, m: &BigInt) -> Result<BigInt, OracleError> {
+        blinded(self, m)
+    }
+}
+
+// apply_key: the oracle operation itself, shared by LocalOracle and OracleServer
+fn apply_key(
+    pub_key: &PublicKey,
+    priv_key: &PrivateKey,
+    x: &BigInt,
+) -> Result<BigInt, OracleError> {
+    if *x < BigInt::zero() || *x >= pub_key.n {
+        return Err(OracleError::Refused(format!("{} is outside [0, n)", x)));
+    }
+    Ok(rsa_private(pub_key, priv_key, x))
+}
+
+pub struct LocalOracle {
+    public: PublicKey,
+    private: PrivateKey,
+}
+
+impl LocalOracle {
+    pub fn new(public: PublicKey, private: PrivateKey) -> Self {
+        LocalOracle { public, private }
+    }
+}
+
+impl DecryptionOracle for LocalOracle {
+    fn public_key(&self) -> &PublicKey {
+        &self.public
+    }
+
+    fn apply(&self, x: &BigInt) -> Result<BigInt, OracleError> {
+        apply_key(&self.public, &self.private, x)
+    }
+}
+
+// A request carries a fresh id that the reply echoes, so a reply can't be mistaken for the
+// answer to another request
+pub struct ChannelOracle {
+    public: PublicKey,
+    link: Mutex<Link>,
+}
+
+struct Link {
+    requests: Sender<Vec<u8>>,
+    replies: Receiver<Vec<u8>>,
+    last_id: u64,
+}
+
+pub struct OracleServer {
+    public: PublicKey,
+    private: PrivateKey,
+    requests: Receiver<Vec<u8>>,
+    replies: Sender<Vec<u8>>,
+}
+
+// channel: a connected client and server for the key; run the server with serve
+pub fn channel(public: PublicKey, private: PrivateKey) -> (ChannelOracle, OracleServer) {
+    let (request_tx, request_rx) = mpsc::channel();
+    let (reply_tx, reply_rx) = mpsc::channel();
+    let client = ChannelOracle {
+        public: public.clone(),
+        link: Mutex::new(Link {
+            requests: request_tx,
+            replies: reply_rx,
+            last_id: 0,
+        }),
+    };
+    let server = OracleServer {
+        public,
+        private,
+        requests: request_rx,
+        replies: reply_tx,
+    };
+    (client, server)
+}
+
+impl DecryptionOracle for ChannelOracle {
+    fn public_key(&self) -> &PublicKey {
+        &self.public
+    }
+
+    fn apply(&self, x: &BigInt) -> Result<BigInt, OracleError> {
+        let mut link = self.link.lock().unwrap();
+        link.last_id += 1;
+        let id = link.last_id;
+        let request = Writer::new(REQUEST_TAG)
+            .bytes(&id.to_be_bytes())
+            .int(x)
+            .finish();
+        link.requests
+            .send(request)
+            .map_err(|_| OracleError::Disconnected)?;
+        let reply = link.replies.recv().map_err(|_| OracleError::Disconnected)?;
+        let mut r = Reader::new(&reply, REPLY_TAG).map_err(OracleError::Malformed)?;
+        let echoed = r.bytes().map_err(OracleError::Malformed)?;
+        if echoed != id.to_be_bytes() {
+            return Err(OracleError::WrongAnswer);
+        }
+        let result = match r.bytes().map_err(OracleError::Malformed)? {
+            b"ok" => Ok(r.int().map_err(OracleError::Malformed)?),
+            _ => Err(OracleError::Refused(
+                String::from_utf8_lossy(r.bytes().map_err(OracleError::Malformed)?).into_owned(),
+            )),
+        };
+        r.finish().map_err(OracleError::Malformed)?;
+        result
+    }
+}
+
+impl OracleServer {
+    // serve: answer requests until the client is dropped
+    pub fn serve(self) {
+        while let Ok(request) = self.requests.recv() {
+            let reply = self.answer(&request);
+            if self.replies.send(reply).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn answer(&self, request: &[u8]) -> Vec<u8> {
+        let parsed = Reader::new(request, REQUEST_TAG).and_then(|mut r| {
+            let id = r.bytes()?.to_vec();
+            let x = r.int()?;
+            r.finish()?;
+            Ok((id, x))
+        });
+        let (id, result) = match parsed {
+            Ok((id, x)) => (id, apply_key(&self.public, &self.private, &x)),
+            Err(err) => (Vec::new(), Err(OracleError::Malformed(err))),
+        };
+        let mut w = Writer::new(REPLY_TAG);
+        w.bytes(&id);
+        match result {
+            Ok(y) => w.bytes(b"ok").int(&y),
+            Err(err) => w.bytes(b"error").bytes(err.to_string().as_bytes()),
+        };
+        w.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encrypt, gen_keys_with_digits};
+
+    // round_trip: what application code does, whichever oracle it is given
+    fn round_trip(ops: &dyn PrivateKeyOps) {
+        let m = BigInt::from(4242);
+        let c = encrypt(ops.public_key(), m.clone().into()).unwrap();
+        assert_eq!(ops.decrypt(&c).unwrap(), m.clone().into());
+        let s = ops.sign(&m).unwrap();
+        assert_eq!(rsa_public(ops.public_key(), &s), m);
+        let too_big = ops.public_key().n.clone();
+        assert!(matches!(ops.sign(&too_big), Err(OracleError::Refused(_))));
+    }
+
+    #[test]
+    fn local_and_channel_oracles_behave_alike() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        round_trip(&LocalOracle::new(pub_key, priv_key));
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let (client, server) = channel(pub_key, priv_key);
+        let handle = std::thread::spawn(move || server.serve());
+        round_trip(&client);
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn oracle_answers_are_checked() {
+        // An oracle holding the wrong private key is caught before unblinding
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let (_, other_priv) = gen_keys_with_digits(20);
+        let oracle = LocalOracle::new(pub_key, other_priv);
+        assert_eq!(oracle.sign(&BigInt::from(7)), Err(OracleError::WrongAnswer));
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let (client, server) = channel(pub_key, priv_key);
+        drop(server);
+        assert_eq!(
+            client.sign(&BigInt::from(7)),
+            Err(OracleError::Disconnected)
+        );
+    }
+}
@@ -0,0 +1,160 @@
+// Encrypting byte strings under a padding scheme
+//
+// encrypt and decrypt work on raw integers, which is textbook RSA: deterministic and
+// malleable. PublicKey::encrypt_bytes and PrivateKey::decrypt_bytes take and return bytes
+// instead, convert them with I2OSP and OS2IP, and always pad, with OAEP (SHA-256 unless
+// another HashAlg is given, empty label) by default or PKCS #1 v1.5 where compatibility
+// needs it. Messages longer than the scheme allows for the modulus are refused rather than
+// truncated. Decryption failures of every kind, from a ciphertext of the wrong length to a
+// bad padding byte, are the same error, so the result does not become a padding oracle.
+use crate::hash_alg::HashAlg;
+use crate::oaep::Oaep;
+use crate::{modulus_len, os2ip, pkcs1v15, PrivateKey, PublicKey};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Padding {
+    Pkcs1v15,
+    Oaep(HashAlg),
+}
+
+impl Default for Padding {
+    fn default() -> Self {
+        Padding::Oaep(HashAlg::Sha256)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PaddingError {
+    MessageTooLong { len: usize, max: usize },
+    // The ciphertext is the wrong length, out of range or badly padded; deliberately not
+    // saying which
+    Decryption,
+}
+
+impl fmt::Display for PaddingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PaddingError::MessageTooLong { len, max } => write!(
+                f,
+                "a {} byte message is too long for this key and padding, at most {} bytes fit",
+                len, max
+            ),
+            PaddingError::Decryption => write!(f, "decryption failed"),
+        }
+    }
+}
+
+impl std::error::Error for PaddingError {}
+
+impl Padding {
+    // max_message_len: the longest message this padding fits under pub_key, 0 if none
+    pub fn max_message_len(self, pub_key: &PublicKey) -> usize {
+        match self {
+            Padding::Pkcs1v15 => pkcs1v15::max_message_len(pub_key),
+            Padding::Oaep(hash) => Oaep::new(hash).max_message_len(pub_key),
+        }
+    }
+}
+
+impl PublicKey {
+    pub fn encrypt_bytes(&self, m: &[u8], padding: Padding) -> Result<Vec<u8>, PaddingError> {
+        self.encrypt_bytes_with_rng(m, padding, &mut OsRng)
+    }
+
+    pub fn encrypt_bytes_with_rng(
+        &self,
+        m: &[u8],
+        padding: Padding,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Result<Vec<u8>, PaddingError> {
+        let max = padding.max_message_len(self);
+        if m.len() > max || max == 0 {
+            return Err(PaddingError::MessageTooLong { len: m.len(), max });
+        }
+        match padding {
+            Padding::Pkcs1v15 => Some(pkcs1v15::encrypt_with_rng(self, m, rng)),
+            Padding::Oaep(hash) => Oaep::new(hash).encrypt_with_rng(self, m, b"", rng),
+        }
+        .ok_or(PaddingError::MessageTooLong { len: m.len(), max })
+    }
+}
+
+impl PrivateKey {
+    // decrypt_bytes: the message in c, a ciphertext exactly as long as the modulus
+    pub fn decrypt_bytes(
+        &self,
+        pub_key: &PublicKey,
+        c: &[u8],
+        padding: Padding,
+    ) -> Result<Vec<u8>, PaddingError> {
+        if c.len() != modulus_len(pub_key) || os2ip(c) >= pub_key.n {
+            return Err(PaddingError::Decryption);
+        }
+        match padding {
+            Padding::Pkcs1v15 => pkcs1v15::decrypt(pub_key, self, c),
+            Padding::Oaep(hash) => Oaep::new(hash).decrypt(pub_key, self, c, b""),
+        }
+        .ok_or(PaddingError::Decryption)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn bytes_round_trip_under_each_padding() {
+        let (pub_key, priv_key) = gen_keys_with_digits(60);
+        for padding in [
+            Padding::default(),
+            Padding::Oaep(HashAlg::Sha1),
+            Padding::Pkcs1v15,
+        ] {
+            let c = pub_key.encrypt_bytes(b"attack at dawn", padding).unwrap();
+            assert_eq!(c.len(), modulus_len(&pub_key));
+            assert_ne!(
+                c,
+                pub_key.encrypt_bytes(b"attack at dawn", padding).unwrap()
+            );
+            let m = priv_key.decrypt_bytes(&pub_key, &c, padding).unwrap();
+            assert_eq!(m, b"attack at dawn");
+        }
+    }
+
+    #[test]
+    fn long_messages_are_refused() {
+        let (pub_key, _) = gen_keys_with_digits(60);
+        let max = Padding::default().max_message_len(&pub_key);
+        assert!(pub_key
+            .encrypt_bytes(&vec![1; max], Padding::default())
+            .is_ok());
+        assert_eq!(
+            pub_key.encrypt_bytes(&vec![1; max + 1], Padding::default()),
+            Err(PaddingError::MessageTooLong { len: max + 1, max })
+        );
+        // 20-digit primes leave no room for OAEP with SHA-256 at all
+        let (small, _) = gen_keys_with_digits(20);
+        assert!(small.encrypt_bytes(b"", Padding::default()).is_err());
+    }
+
+    #[test]
+    fn tampered_ciphertexts_fail_alike() {
+        let (pub_key, priv_key) = gen_keys_with_digits(60);
+        for padding in [Padding::default(), Padding::Pkcs1v15] {
+            let mut c = pub_key.encrypt_bytes(b"m", padding).unwrap();
+            assert_eq!(
+                priv_key.decrypt_bytes(&pub_key, &c[1..], padding),
+                Err(PaddingError::Decryption)
+            );
+            c[5] ^= 1;
+            assert_eq!(
+                priv_key.decrypt_bytes(&pub_key, &c, padding),
+                Err(PaddingError::Decryption)
+            );
+        }
+    }
+}
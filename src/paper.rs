@@ -0,0 +1,252 @@
+// A key format for writing on paper
+//
+// The key is serialized, encoded in base32 and cut into numbered lines of four
+// five-character groups, each followed by a CRC-8 of the line, and a final CRC-32 of the
+// whole key:
+//
+//   NAIVE-RSA PUBLIC KEY
+//   01 AAAAA FDOMF UXMZJ NOJZW  0D
+//   02 CIDQO VRGY2 LDEBV WK6IA  7C
+//   ...
+//   06 AAYBA AAQ  58
+//   CHECK 1A22C52B
+//
+// The per-line check says which line was copied wrong; the overall one catches lines that
+// are each fine but missing or out of order. Decoding ignores case and extra spaces, and
+// reads 0 and 1 as O and I.
+use crate::error::ParseError;
+use crate::keypair::KeyPair;
+use crate::wire::{Reader, Writer};
+use crate::{base32, checked_public_key, PrivateKey, PublicKey};
+
+const FORMAT: &str = "naive-rsa paper key";
+const PUBLIC_HEADER: &str = "NAIVE-RSA PUBLIC KEY";
+const PRIVATE_HEADER: &str = "NAIVE-RSA PRIVATE KEY";
+const GROUP_LEN: usize = 5;
+const GROUPS_PER_LINE: usize = 4;
+
+// crc8: CRC-8 with polynomial x^8 + x^2 + x + 1 (0x07)
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                crc << 1 ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+// crc32: the CRC-32 of zlib and PNG (reflected 0xEDB88320)
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |mut crc, &byte| {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                crc >> 1 ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    })
+}
+
+fn line_check(number: usize, chars: &str) -> u8 {
+    crc8(&[&(number as u32).to_be_bytes()[..], chars.as_bytes()].concat())
+}
+
+pub(crate) fn encode(header: &str, payload: &[u8]) -> String {
+    let chars = base32::encode(payload);
+    let mut out = format!("{}\n", header);
+    for (i, line) in chars
+        .as_bytes()
+        .chunks(GROUP_LEN * GROUPS_PER_LINE)
+        .enumerate()
+    {
+        let line = std::str::from_utf8(line).unwrap();
+        let groups: Vec<&str> = line
+            .as_bytes()
+            .chunks(GROUP_LEN)
+            .map(|group| std::str::from_utf8(group).unwrap())
+            .collect();
+        out += &format!(
+            "{:02} {}  {:02X}\n",
+            i + 1,
+            groups.join(" "),
+            line_check(i + 1, line)
+        );
+    }
+    out + &format!("CHECK {:08X}\n", crc32(payload))
+}
+
+// decode: the payload under the expected header; offsets in errors are those of the line
+pub(crate) fn decode(text: &str, header: &str) -> Result<Vec<u8>, ParseError> {
+    let mut lines = text
+        .lines()
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len() + 1;
+            Some((start, line.trim()))
+        })
+        .filter(|(_, line)| !line.is_empty());
+    match lines.next() {
+        Some((_, first)) if first.eq_ignore_ascii_case(header) => {}
+        Some((offset, first)) => {
+            return Err(ParseError::invalid(
+                FORMAT,
+                offset,
+                format!("expected {:?}, found {:?}", header, first),
+            ))
+        }
+        None => return Err(ParseError::invalid(FORMAT, 0, "the text is empty")),
+    }
+    let mut chars = String::new();
+    for (expected_number, (offset, line)) in (1..).zip(lines) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() == 2 && fields[0].eq_ignore_ascii_case("CHECK") {
+            let payload = base32::decode(&chars).ok_or_else(|| {
+                ParseError::invalid(FORMAT, offset, "the lines do not decode as base32")
+            })?;
+            let check = u32::from_str_radix(fields[1], 16).ok();
+            if check != Some(crc32(&payload)) {
+                return Err(ParseError::invalid(
+                    FORMAT,
+                    offset,
+                    "the overall checksum does not match; is a line missing or out of order?",
+                ));
+            }
+            return Ok(payload);
+        }
+        let invalid = |reason: String| ParseError::invalid(FORMAT, offset, reason);
+        let (number, groups, check) = match fields.as_slice() {
+            [number, groups @ .., check] if !groups.is_empty() => (number, groups, check),
+            _ => return Err(invalid(format!("line {:?} is not a key line", line))),
+        };
+        if number.parse::<usize>().ok() != Some(expected_number) {
+            return Err(invalid(format!(
+                "expected line {:02}, found {:?}",
+                expected_number, number
+            )));
+        }
+        let line_chars = groups
+            .concat()
+            .to_ascii_uppercase()
+            .replace('0', "O")
+            .replace('1', "I");
+        if u8::from_str_radix(check, 16).ok() != Some(line_check(expected_number, &line_chars)) {
+            return Err(invalid(format!(
+                "line {:02} has a copying mistake",
+                expected_number
+            )));
+        }
+        chars += &line_chars;
+    }
+    Err(ParseError::invalid(
+        FORMAT,
+        text.len(),
+        "the CHECK line is missing",
+    ))
+}
+
+pub fn encode_public(pub_key: &PublicKey) -> String {
+    let payload = Writer::new("naive-rsa public key")
+        .int(&pub_key.n)
+        .int(&pub_key.e)
+        .finish();
+    encode(PUBLIC_HEADER, &payload)
+}
+
+pub fn decode_public(text: &str) -> Result<PublicKey, ParseError> {
+    let payload = decode(text, PUBLIC_HEADER)?;
+    let mut r = Reader::new(&payload, "naive-rsa public key")?;
+    let (n, e) = (r.int()?, r.int()?);
+    r.finish()?;
+    checked_public_key(n, e)
+        .map_err(|reason| ParseError::invalid("naive-rsa public key", 0, reason))
+}
+
+pub fn encode_key_pair(key_pair: &KeyPair) -> String {
+    let (public, private) = (key_pair.public(), key_pair.private());
+    let payload = Writer::new("naive-rsa key pair")
+        .int(&public.n)
+        .int(&public.e)
+        .int(&private.d)
+        .finish();
+    encode(PRIVATE_HEADER, &payload)
+}
+
+pub fn decode_key_pair(text: &str) -> Result<KeyPair, ParseError> {
+    let payload = decode(text, PRIVATE_HEADER)?;
+    let mut r = Reader::new(&payload, "naive-rsa key pair")?;
+    let (n, e) = (r.int()?, r.int()?);
+    let private = PrivateKey::new(r.int()?);
+    r.finish()?;
+    let public = checked_public_key(n, e)
+        .map_err(|reason| ParseError::invalid("naive-rsa key pair", 0, reason))?;
+    Ok(KeyPair::new(public, private))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ParseErrorKind;
+    use crate::gen_keys_with_digits;
+    use num_bigint::BigInt;
+
+    fn reason(err: ParseError) -> String {
+        match err.kind() {
+            ParseErrorKind::Invalid(reason) => reason.clone(),
+            kind => panic!("unexpected {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn keys_round_trip_through_paper() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let text = encode_public(&pub_key);
+        assert!(text.starts_with("NAIVE-RSA PUBLIC KEY\n01 "));
+        assert_eq!(decode_public(&text).unwrap(), pub_key);
+        // Sloppy copying: lower case, 0 for O, 1 for I, blank lines and stray spaces
+        let (header, body) = text.split_once('\n').unwrap();
+        let body = body.to_lowercase().replace('o', "0").replace('i', "1");
+        let sloppy = format!("{}\n\n  {}", header, body.replace(' ', "  "));
+        assert_eq!(decode_public(&sloppy).unwrap(), pub_key);
+        let pair = KeyPair::new(pub_key.clone(), priv_key);
+        let decoded = decode_key_pair(&encode_key_pair(&pair)).unwrap();
+        assert_eq!(decoded.private().exponent(), pair.private().exponent());
+        assert!(decode_key_pair(&text).is_err());
+    }
+
+    #[test]
+    fn mistakes_are_located() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let text = encode_public(&pub_key);
+        let mut lines: Vec<String> = text.lines().map(String::from).collect();
+        let mut copied = lines.clone();
+        let mut chars: Vec<char> = lines[2].chars().collect();
+        chars[3] = if chars[3] == 'A' { 'B' } else { 'A' };
+        copied[2] = chars.into_iter().collect();
+        let err = decode_public(&copied.join("\n")).unwrap_err();
+        assert_eq!(reason(err), "line 02 has a copying mistake");
+        lines.remove(2);
+        let err = decode_public(&lines.join("\n")).unwrap_err();
+        assert_eq!(reason(err), "expected line 02, found \"03\"");
+        let tiny = PublicKey {
+            n: BigInt::from(1),
+            e: BigInt::from(3),
+        };
+        let err = decode_public(&encode_public(&tiny)).unwrap_err();
+        assert_eq!(reason(err), "the modulus must be at least 3");
+    }
+
+    #[test]
+    fn checksums_match_reference_values() {
+        // The "check" values listed for CRC-8/SMBUS and CRC-32/ISO-HDLC
+        assert_eq!(crc8(b"123456789"), 0xf4);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+}
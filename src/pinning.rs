@@ -0,0 +1,148 @@
+// Public key pinning
+//
+// A pin is the SHA-256 of a key's DER SubjectPublicKeyInfo, the "pin-sha256" of HTTP
+// Public Key Pinning (RFC 7469), so pins can be computed from certificates or key files with
+// standard tools. A PinSet accepts a key whose pin it holds. Backup pins are for keys that
+// are not in use yet but are kept offline to rotate to; a set without one locks its keys in
+// for good if the current key is lost, which is why has_backup is worth checking.
+use crate::error::ParseError;
+use crate::keyfile::spki_der;
+use crate::wire::{Reader, Writer};
+use crate::PublicKey;
+use sha2::{Digest, Sha256};
+
+pub type Pin = [u8; 32];
+
+// PinMatch: which kind of pin accepted a key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PinMatch {
+    Primary,
+    Backup,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PinSet {
+    primary: Vec<Pin>,
+    backup: Vec<Pin>,
+}
+
+pub fn fingerprint(pub_key: &PublicKey) -> Pin {
+    Sha256::digest(spki_der(pub_key)).into()
+}
+
+impl PinSet {
+    pub fn new() -> Self {
+        PinSet::default()
+    }
+
+    pub fn pin(&mut self, pub_key: &PublicKey) -> &mut Self {
+        self.add_pin(fingerprint(pub_key))
+    }
+
+    // add_pin: a pin computed elsewhere, e.g. from a certificate
+    pub fn add_pin(&mut self, pin: Pin) -> &mut Self {
+        if !self.primary.contains(&pin) {
+            self.primary.push(pin);
+        }
+        self
+    }
+
+    pub fn add_backup(&mut self, pin: Pin) -> &mut Self {
+        if !self.backup.contains(&pin) {
+            self.backup.push(pin);
+        }
+        self
+    }
+
+    // has_backup: whether a backup pin accepts a key that no primary pin does
+    pub fn has_backup(&self) -> bool {
+        self.backup.iter().any(|pin| !self.primary.contains(pin))
+    }
+
+    pub fn check(&self, pub_key: &PublicKey) -> Option<PinMatch> {
+        let pin = fingerprint(pub_key);
+        if self.primary.contains(&pin) {
+            Some(PinMatch::Primary)
+        } else if self.backup.contains(&pin) {
+            Some(PinMatch::Backup)
+        } else {
+            None
+        }
+    }
+
+    pub fn verify_pin(&self, pub_key: &PublicKey) -> bool {
+        self.check(pub_key).is_some()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::new("naive-rsa pin set");
+        w.bytes(&(self.primary.len() as u32).to_be_bytes());
+        for pin in &self.primary {
+            w.bytes(pin);
+        }
+        w.bytes(&(self.backup.len() as u32).to_be_bytes());
+        for pin in &self.backup {
+            w.bytes(pin);
+        }
+        w.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut r = Reader::new(bytes, "naive-rsa pin set")?;
+        let mut set = PinSet::new();
+        for backup in [false, true] {
+            for _ in 0..r.count()? {
+                let offset = r.offset();
+                let pin: Pin = r.bytes()?.try_into().map_err(|_| {
+                    ParseError::invalid("naive-rsa pin set", offset, "a pin must be 32 bytes")
+                })?;
+                if backup {
+                    set.add_backup(pin);
+                } else {
+                    set.add_pin(pin);
+                }
+            }
+        }
+        r.finish()?;
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn pinned_keys_are_accepted() {
+        let (current, _) = gen_keys_with_digits(20);
+        let (next, _) = gen_keys_with_digits(20);
+        let (attacker, _) = gen_keys_with_digits(20);
+        let mut pins = PinSet::new();
+        pins.pin(&current);
+        assert!(!pins.has_backup());
+        pins.add_backup(fingerprint(&next));
+        assert!(pins.has_backup());
+        assert_eq!(pins.check(&current), Some(PinMatch::Primary));
+        assert_eq!(pins.check(&next), Some(PinMatch::Backup));
+        assert!(!pins.verify_pin(&attacker));
+    }
+
+    // The pin is the hash of exactly the DER that OpenSSL exports for the key
+    #[test]
+    fn pins_match_openssl() {
+        let key: PublicKey = include_str!("../testdata/spki_public.pem").parse().unwrap();
+        let expected = Sha256::digest(include_bytes!("../testdata/spki_public.der"));
+        assert_eq!(fingerprint(&key)[..], expected[..]);
+    }
+
+    #[test]
+    fn pin_sets_round_trip_through_bytes() {
+        let (key, _) = gen_keys_with_digits(20);
+        let mut pins = PinSet::new();
+        pins.pin(&key).add_backup([7; 32]);
+        let decoded = PinSet::from_bytes(&pins.to_bytes()).unwrap();
+        assert_eq!(decoded, pins);
+        assert!(PinSet::from_bytes(&pins.to_bytes()[..40]).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+// RSAES-PKCS1-v1_5 (RFC 8017, section 7.2)
+//
+// The message is padded as EM = 0x00 || 0x02 || PS || 0x00 || M, with PS at least eight
+// random nonzero bytes, which makes encryption randomized. There is no integrity check
+// beyond the shape of the padding, so the scheme stays malleable enough for Bleichenbacher's
+// attack: an oracle telling whether a tampered ciphertext is well padded leaks the
+// plaintext. It is here for compatibility and comparison; prefer OAEP. The padding comes
+// from OsRng, or from the CryptoRng given to encrypt_with_rng.
+use crate::{
+    decrypt as rsa_decrypt, encrypt as rsa_encrypt, i2osp, modulus_len, os2ip, PrivateKey,
+    PublicKey,
+};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, Rng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, ConstantTimeLess};
+
+// The fixed overhead: two marker bytes, eight bytes of padding and the separator
+const OVERHEAD: usize = 11;
+
+pub fn max_message_len(pub_key: &PublicKey) -> usize {
+    modulus_len(pub_key).saturating_sub(OVERHEAD)
+}
+
+// encode: EM for a k-byte modulus, with padding bytes drawn from rng
+pub(crate) fn encode(k: usize, m: &[u8], rng: &mut impl Rng) -> Vec<u8> {
+    encode_with_padding(m, &padding(k, m.len(), rng))
+}
+
+// padding: the k - len - 3 nonzero bytes of PS
+pub(crate) fn padding(k: usize, len: usize, rng: &mut impl Rng) -> Vec<u8> {
+    (0..k - len - 3).map(|_| rng.gen_range(1..=255)).collect()
+}
+
+pub(crate) fn encode_with_padding(m: &[u8], ps: &[u8]) -> Vec<u8> {
+    [&[0x00, 0x02][..], ps, &[0x00], m].concat()
+}
+
+// decode: the message inside EM, or None; like OAEP's decoder it folds every check into a
+// single subtle::Choice so that all malformed encodings fail the same way
+pub(crate) fn decode(em: &[u8]) -> Option<Vec<u8>> {
+    if em.len() < OVERHEAD {
+        return None;
+    }
+    let mut bad = !em[0].ct_eq(&0x00) | !em[1].ct_eq(&0x02);
+    let mut looking = Choice::from(1);
+    let mut index = 0u64;
+    for (i, &byte) in em[2..].iter().enumerate() {
+        let is_zero = byte.ct_eq(&0x00);
+        index.conditional_assign(&(i as u64), looking & is_zero);
+        looking &= !is_zero;
+    }
+    bad |= looking;
+    // The separator must come after at least eight padding bytes
+    bad |= index.ct_lt(&8);
+    if bad.into() {
+        return None;
+    }
+    Some(em[2 + index as usize + 1..].to_vec())
+}
+
+pub fn encrypt(pub_key: &PublicKey, m: &[u8]) -> Vec<u8> {
+    encrypt_with_rng(pub_key, m, &mut OsRng)
+}
+
+pub fn encrypt_with_rng(
+    pub_key: &PublicKey,
+    m: &[u8],
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Vec<u8> {
+    if m.len() > max_message_len(pub_key) {
+        panic!(
+            "a {} byte message is too long for PKCS #1 v1.5 with this key, at most {} bytes fit",
+            m.len(),
+            max_message_len(pub_key)
+        );
+    }
+    let k = modulus_len(pub_key);
+    let em = encode(k, m, rng);
+    let c = rsa_encrypt(pub_key, os2ip(&em).into()).expect("em starts with a zero byte");
+    i2osp(c.as_bigint(), k)
+}
+
+// decrypt: None for any invalid ciphertext, without saying why
+pub fn decrypt(pub_key: &PublicKey, priv_key: &PrivateKey, c: &[u8]) -> Option<Vec<u8>> {
+    let k = modulus_len(pub_key);
+    if c.len() != k {
+        return None;
+    }
+    let c = os2ip(c);
+    if k < OVERHEAD || c >= pub_key.n {
+        return None;
+    }
+    let em = rsa_decrypt(pub_key, priv_key, c.into()).ok()?;
+    decode(&i2osp(em.as_bigint(), k))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn messages_round_trip() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let max = max_message_len(&pub_key);
+        for m in [&b""[..], b"hi", &vec![0xab; max]] {
+            let c = encrypt(&pub_key, m);
+            assert_eq!(decrypt(&pub_key, &priv_key, &c).unwrap(), m);
+        }
+        assert_ne!(encrypt(&pub_key, b"hi"), encrypt(&pub_key, b"hi"));
+        let seeded = |seed| encrypt_with_rng(&pub_key, b"hi", &mut StdRng::seed_from_u64(seed));
+        assert_eq!(seeded(1), seeded(1));
+    }
+
+    #[test]
+    fn malformed_padding_is_rejected() {
+        let mut rng = rand::thread_rng();
+        let em = encode(32, b"message", &mut rng);
+        assert_eq!(decode(&em).unwrap(), b"message");
+        let mut wrong_type = em.clone();
+        wrong_type[1] = 0x01;
+        assert_eq!(decode(&wrong_type), None);
+        // A separator inside the first eight padding bytes
+        let mut short_padding = em.clone();
+        short_padding[5] = 0x00;
+        assert_eq!(decode(&short_padding), None);
+        assert_eq!(decode(&[0x00, 0x02, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1]), None);
+    }
+
+    #[test]
+    fn ciphertexts_of_the_wrong_length_are_rejected() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let c = encrypt(&pub_key, b"hi");
+        let padded = [&[0x00][..], &c].concat();
+        assert_eq!(decrypt(&pub_key, &priv_key, &padded), None);
+    }
+}
@@ -0,0 +1,141 @@
+// Non-interactive proof of knowledge of an RSA plaintext
+//
+// A Schnorr-style (Guillou-Quisquater) sigma protocol for the statement "I know m with
+// m^e = c mod n": commit t = r^e, answer a challenge ch < e with z = r m^ch, and the
+// verifier checks z^e = t c^ch. Challenges are derived by hashing (Fiat-Shamir), and the
+// protocol is repeated until the challenge space covers SECURITY_BITS. For a padded
+// (e.g. OAEP) ciphertext the prover proves knowledge of the encoded message, which
+// determines the plaintext.
+use crate::error::ParseError;
+use crate::hash::{self, TranscriptHash};
+use crate::wire::{Reader, Writer};
+use crate::{random_unit, PublicKey};
+use num_bigint::BigInt;
+
+// Soundness target: a cheating prover succeeds with probability at most 2^-SECURITY_BITS
+pub(crate) const SECURITY_BITS: u64 = 128;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaintextProof {
+    t: Vec<BigInt>,
+    z: Vec<BigInt>,
+}
+
+// challenge_bits: the largest b with 2^b < e, so every challenge is below the exponent
+pub(crate) fn challenge_bits(e: &BigInt) -> u64 {
+    (e.bits() - 1).max(1)
+}
+
+pub(crate) fn num_rounds(e: &BigInt) -> usize {
+    SECURITY_BITS.div_ceil(challenge_bits(e)) as usize
+}
+
+// split_challenges: cut a hash output into one b-bit challenge per round
+pub(crate) fn split_challenges(seed: &[u8], rounds: usize, b: u64) -> Vec<BigInt> {
+    let b = b as usize;
+    let bits = hash::bits(&hash::expand(seed, (rounds * b).div_ceil(8)), rounds * b);
+    bits.chunks(b)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .fold(BigInt::from(0), |acc, &bit| (acc << 1) + u8::from(bit))
+        })
+        .collect()
+}
+
+fn challenges(pub_key: &PublicKey, c: &BigInt, t: &[BigInt], context: &[u8]) -> Vec<BigInt> {
+    let mut h = TranscriptHash::new("naive-rsa plaintext proof");
+    h.int(&pub_key.n).int(&pub_key.e).int(c);
+    for t in t {
+        h.int(t);
+    }
+    h.bytes(context);
+    split_challenges(&h.finish(), t.len(), challenge_bits(&pub_key.e))
+}
+
+// prove: prove knowledge of m for c = m^e mod n; context binds the proof to e.g. a session
+pub fn prove(pub_key: &PublicKey, c: &BigInt, m: &BigInt, context: &[u8]) -> PlaintextProof {
+    let rounds = num_rounds(&pub_key.e);
+    let r: Vec<BigInt> = (0..rounds).map(|_| random_unit(&pub_key.n)).collect();
+    let t: Vec<BigInt> = r.iter().map(|r| r.modpow(&pub_key.e, &pub_key.n)).collect();
+    let z = challenges(pub_key, c, &t, context)
+        .iter()
+        .zip(&r)
+        .map(|(ch, r)| r * m.modpow(ch, &pub_key.n) % &pub_key.n)
+        .collect();
+    PlaintextProof { t, z }
+}
+
+pub fn verify(pub_key: &PublicKey, c: &BigInt, proof: &PlaintextProof, context: &[u8]) -> bool {
+    let n = &pub_key.n;
+    let zero = BigInt::from(0);
+    if proof.t.len() != num_rounds(&pub_key.e)
+        || proof.z.len() != proof.t.len()
+        || proof.t.iter().any(|t| *t <= zero || t >= n)
+    {
+        return false;
+    }
+    challenges(pub_key, c, &proof.t, context)
+        .iter()
+        .zip(&proof.t)
+        .zip(&proof.z)
+        .all(|((ch, t), z)| z.modpow(&pub_key.e, n) == t * c.modpow(ch, n) % n)
+}
+
+impl PlaintextProof {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::new("naive-rsa plaintext proof");
+        w.bytes(&(self.t.len() as u32).to_be_bytes());
+        for (t, z) in self.t.iter().zip(&self.z) {
+            w.int(t).int(z);
+        }
+        w.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut r = Reader::new(bytes, "naive-rsa plaintext proof")?;
+        let rounds = r.count()?;
+        let (mut t, mut z) = (Vec::new(), Vec::new());
+        for _ in 0..rounds {
+            t.push(r.int()?);
+            z.push(r.int()?);
+        }
+        r.finish()?;
+        Ok(PlaintextProof { t, z })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encrypt, gen_keys_with_digits};
+
+    #[test]
+    fn proof_of_the_real_plaintext_verifies() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let m = BigInt::from(123456789);
+        let c = encrypt(&pub_key, m.clone().into()).unwrap().into();
+        let proof = prove(&pub_key, &c, &m, b"auction 1");
+        assert!(verify(&pub_key, &c, &proof, b"auction 1"));
+        assert!(!verify(&pub_key, &c, &proof, b"auction 2"));
+    }
+
+    #[test]
+    fn proof_without_the_plaintext_fails() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let c = encrypt(&pub_key, BigInt::from(5).into()).unwrap().into();
+        let proof = prove(&pub_key, &c, &BigInt::from(6), b"");
+        assert!(!verify(&pub_key, &c, &proof, b""));
+    }
+
+    #[test]
+    fn proof_round_trips_through_bytes() {
+        let (pub_key, _) = gen_keys_with_digits(20);
+        let m = BigInt::from(99);
+        let c = encrypt(&pub_key, m.clone().into()).unwrap().into();
+        let proof = prove(&pub_key, &c, &m, b"");
+        let decoded = PlaintextProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(decoded, proof);
+        assert!(verify(&pub_key, &c, &decoded, b""));
+    }
+}
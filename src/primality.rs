@@ -0,0 +1,460 @@
+// Side-by-side evaluation of primality tests
+//
+// evaluate runs each Strategy over a workload of candidates whose primality is known and
+// counts what it got wrong and how long it took, to show what each one buys:
+// - MillerRabin is what the prime search uses, 100 rounds with random bases
+// - SieveMillerRabin first divides by the primes below 1000, which throws out most
+//   composites at the cost of a few hundred small divisions
+// - BailliePsw is one Miller-Rabin round to base 2 followed by a strong Lucas test; no
+//   composite is known to pass both, and it costs about three modular exponentiations
+// - DeterministicSmall uses the first twelve primes as bases, which is a proof of
+//   primality below 3.18 * 10^23 and an ordinary probable-prime test above it
+// The workload mixes random primes, products of two primes and a list of pseudoprimes that
+// fool weaker tests: strong pseudoprimes to several bases, Carmichael numbers and strong
+// Lucas pseudoprimes. Timings are totals over the whole workload.
+//
+// Every strategy can also say why it rejected a number: Strategy::check and check, the
+// counterpart of is_probable_prime, return a Composite holding the evidence, such as the
+// Miller-Rabin base that is a witness or the Lucas parameters the sequence failed for, and
+// Composite::verify checks that evidence again without any randomness.
+use crate::prime_search::small_primes;
+use crate::{
+    extended_gcd, is_strong_probable_prime, jacobi, miller_rabin_witness, random_prime_with_digits,
+};
+use num_bigint::BigInt;
+use num_traits::{One, Signed, Zero};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+const MR_ROUNDS: usize = 100;
+
+const SIEVE_LIMIT: u32 = 1000;
+
+// Bases that make Miller-Rabin deterministic below 318665857834031151167461
+const SMALL_BASES: [u32; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+// Composites that some of the strategies could be fooled by
+const PSEUDOPRIMES: [&str; 10] = [
+    // Strong pseudoprimes to base 2
+    "2047",
+    "3277",
+    "4033",
+    // Carmichael numbers
+    "561",
+    "1105",
+    "1729",
+    // Strong Lucas pseudoprimes
+    "5459",
+    "5777",
+    // Strong pseudoprimes to bases 2, 3, 5 and 7, and to all prime bases up to 23
+    "3215031751",
+    "3825123056546413051",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    MillerRabin,
+    SieveMillerRabin,
+    BailliePsw,
+    DeterministicSmall,
+}
+
+// Composite: evidence that n is not prime
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Composite {
+    // 0 and 1, which are neither prime nor composite
+    BelowTwo,
+    // A divisor other than 1 and n, from evenness, trial division or a gcd
+    Factor(BigInt),
+    // base^d != 1 and base^(d 2^r) != -1 for all r < s, where n - 1 = d 2^s
+    MillerRabin { base: BigInt },
+    // n = root^2, for which the Lucas test has no parameters
+    PerfectSquare { root: BigInt },
+    // The strong Lucas test failed for the sequence with these Selfridge parameters
+    Lucas { d: BigInt, p: BigInt, q: BigInt },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    pub n: BigInt,
+    pub is_prime: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Evaluation {
+    pub strategy: Strategy,
+    pub candidates: usize,
+    // Composites reported prime
+    pub false_positives: usize,
+    // Primes reported composite
+    pub false_negatives: usize,
+    pub elapsed: Duration,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 4] = [
+        Strategy::MillerRabin,
+        Strategy::SieveMillerRabin,
+        Strategy::BailliePsw,
+        Strategy::DeterministicSmall,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::MillerRabin => "mr",
+            Strategy::SieveMillerRabin => "sieve-mr",
+            Strategy::BailliePsw => "bpsw",
+            Strategy::DeterministicSmall => "deterministic-small",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Strategy> {
+        Strategy::ALL
+            .into_iter()
+            .find(|strategy| strategy.name() == name)
+    }
+
+    pub fn is_prime(self, n: &BigInt) -> bool {
+        self.check(n).is_ok()
+    }
+
+    // check: Ok if n passes, or the evidence that it is composite
+    pub fn check(self, n: &BigInt) -> Result<(), Composite> {
+        if let Some(answer) = small_case(n) {
+            return answer;
+        }
+        match self {
+            Strategy::MillerRabin => check(n, MR_ROUNDS),
+            Strategy::SieveMillerRabin => sieve(n).unwrap_or_else(|| check(n, MR_ROUNDS)),
+            Strategy::BailliePsw => {
+                strong_probable_prime(n, &BigInt::from(2))?;
+                strong_lucas(n)
+            }
+            Strategy::DeterministicSmall => SMALL_BASES
+                .iter()
+                .try_for_each(|&a| strong_probable_prime(n, &BigInt::from(a))),
+        }
+    }
+}
+
+// check: is_probable_prime, with the evidence when n is composite
+pub fn check(n: &BigInt, num_rounds: usize) -> Result<(), Composite> {
+    if let Some(answer) = small_case(n) {
+        return answer;
+    }
+    match miller_rabin_witness(n, num_rounds, &mut rand::thread_rng()) {
+        Some(base) => Err(Composite::MillerRabin { base }),
+        None => Ok(()),
+    }
+}
+
+fn strong_probable_prime(n: &BigInt, base: &BigInt) -> Result<(), Composite> {
+    match is_strong_probable_prime(n, base) {
+        true => Ok(()),
+        false => Err(Composite::MillerRabin { base: base.clone() }),
+    }
+}
+
+impl Composite {
+    // verify: whether this really proves n composite
+    pub fn verify(&self, n: &BigInt) -> bool {
+        let one = BigInt::one();
+        match self {
+            Composite::BelowTwo => *n < BigInt::from(2),
+            Composite::Factor(f) => *f > one && f < n && (n % f).is_zero(),
+            Composite::MillerRabin { base } => {
+                *n > BigInt::from(3)
+                    && !(n % 2u32).is_zero()
+                    && *base > one
+                    && *base < n - 1u32
+                    && !is_strong_probable_prime(n, base)
+            }
+            Composite::PerfectSquare { root } => *root > one && root * root == *n,
+            Composite::Lucas { d, p, q } => {
+                !(n % 2u32).is_zero()
+                    && *p == one
+                    && *q == (1 - d) / 4
+                    && jacobi(d, n) == -1
+                    && !lucas_sequence_passes(n, d)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Composite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Composite::BelowTwo => write!(f, "numbers below 2 are not prime"),
+            Composite::Factor(factor) => write!(f, "divisible by {}", factor),
+            Composite::MillerRabin { base } => {
+                write!(f, "{} is a Miller-Rabin witness", base)
+            }
+            Composite::PerfectSquare { root } => write!(f, "the square of {}", root),
+            Composite::Lucas { d, p, q } => write!(
+                f,
+                "fails the strong Lucas test with D = {}, P = {}, Q = {}",
+                d, p, q
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// small_case: the answer for n below 41, even n and the small bases, which the tests
+// themselves do not handle
+fn small_case(n: &BigInt) -> Option<Result<(), Composite>> {
+    if *n < BigInt::from(2) {
+        return Some(Err(Composite::BelowTwo));
+    }
+    if *n < BigInt::from(41) {
+        let n = u32::try_from(n).unwrap();
+        return Some(match (2..n).find(|&a| n.is_multiple_of(a)) {
+            Some(a) => Err(Composite::Factor(BigInt::from(a))),
+            None => Ok(()),
+        });
+    }
+    if (n % 2u32).is_zero() {
+        return Some(Err(Composite::Factor(BigInt::from(2))));
+    }
+    None
+}
+
+// sieve: trial division by the odd primes below SIEVE_LIMIT; None if n survives it
+fn sieve(n: &BigInt) -> Option<Result<(), Composite>> {
+    for &p in small_primes().iter().take_while(|&&p| p < SIEVE_LIMIT) {
+        if *n == BigInt::from(p) {
+            return Some(Ok(()));
+        }
+        if (n % p).is_zero() {
+            return Some(Err(Composite::Factor(BigInt::from(p))));
+        }
+    }
+    None
+}
+
+// half: x / 2 mod odd n, for 0 <= x < n
+fn half(x: BigInt, n: &BigInt) -> BigInt {
+    if (&x % 2u32).is_zero() {
+        x / 2u32
+    } else {
+        (x + n) / 2u32
+    }
+}
+
+// strong_lucas: the strong Lucas probable-prime test with Selfridge's parameters, for odd
+// n > 40. D is the first of 5, -7, 9, -11, ... with (D/n) = -1, P = 1 and Q = (1 - D) / 4.
+pub(crate) fn strong_lucas(n: &BigInt) -> Result<(), Composite> {
+    let root = n.sqrt();
+    if &root * &root == *n {
+        // No D has (D/n) = -1 when n is a square
+        return Err(Composite::PerfectSquare { root });
+    }
+    let mut d = BigInt::from(5);
+    loop {
+        match jacobi(&d, n) {
+            -1 => break,
+            0 if d.abs() != *n => {
+                let (factor, _, _) = extended_gcd(d.abs(), n.clone());
+                return Err(Composite::Factor(factor));
+            }
+            _ => d = if d.is_positive() { -d - 2 } else { -d + 2 },
+        }
+    }
+    match lucas_sequence_passes(n, &d) {
+        true => Ok(()),
+        false => Err(Composite::Lucas {
+            q: (1 - &d) / 4,
+            d,
+            p: BigInt::one(),
+        }),
+    }
+}
+
+// lucas_sequence_passes: with n + 1 = k 2^s, whether U_k = 0 or V_(k 2^r) = 0 for some
+// r < s in the Lucas sequences for P = 1 and Q = (1 - d) / 4
+fn lucas_sequence_passes(n: &BigInt, d: &BigInt) -> bool {
+    let reduce = |x: BigInt| ((x % n) + n) % n;
+    let dd = reduce(d.clone());
+    let q = reduce((1 - d) / 4u32);
+    let (mut s, mut k) = (0, n + 1u32);
+    while (&k % 2u32).is_zero() {
+        k /= 2u32;
+        s += 1;
+    }
+    // Walk the bits of k from the top, doubling the index and adding one when the bit is set
+    let (mut u, mut v, mut qk) = (BigInt::one(), BigInt::one(), q.clone());
+    for i in (0..k.bits() - 1).rev() {
+        u = &u * &v % n;
+        v = reduce(&v * &v - 2 * &qk);
+        qk = &qk * &qk % n;
+        if k.bit(i) {
+            let next_u = half((&u + &v) % n, n);
+            v = half((&dd * &u + &v) % n, n);
+            u = next_u;
+            qk = &qk * &q % n;
+        }
+    }
+    if u.is_zero() || v.is_zero() {
+        return true;
+    }
+    for _ in 1..s {
+        v = reduce(&v * &v - 2 * &qk);
+        if v.is_zero() {
+            return true;
+        }
+        qk = &qk * &qk % n;
+    }
+    false
+}
+
+// workload: count ndigits primes, count products of two primes of half that size, and the
+// pseudoprimes
+pub fn workload(ndigits: u32, count: usize) -> Vec<Candidate> {
+    if ndigits < 4 {
+        panic!("a workload needs at least 4 digits, not {}", ndigits);
+    }
+    let primes = (0..count).map(|_| Candidate {
+        n: random_prime_with_digits(ndigits),
+        is_prime: true,
+    });
+    let products = (0..count).map(|_| Candidate {
+        n: random_prime_with_digits(ndigits / 2) * random_prime_with_digits(ndigits - ndigits / 2),
+        is_prime: false,
+    });
+    let pseudoprimes = PSEUDOPRIMES.iter().map(|n| Candidate {
+        n: n.parse().unwrap(),
+        is_prime: false,
+    });
+    primes.chain(products).chain(pseudoprimes).collect()
+}
+
+pub fn evaluate(strategies: &[Strategy], candidates: &[Candidate]) -> Vec<Evaluation> {
+    strategies
+        .iter()
+        .map(|&strategy| {
+            let start = Instant::now();
+            let answers: Vec<bool> = candidates.iter().map(|c| strategy.is_prime(&c.n)).collect();
+            let elapsed = start.elapsed();
+            let wrong = |is_prime: bool| {
+                candidates
+                    .iter()
+                    .zip(&answers)
+                    .filter(|(c, &answer)| c.is_prime == is_prime && answer != is_prime)
+                    .count()
+            };
+            Evaluation {
+                strategy,
+                candidates: candidates.len(),
+                false_positives: wrong(false),
+                false_negatives: wrong(true),
+                elapsed,
+            }
+        })
+        .collect()
+}
+
+impl Evaluation {
+    pub fn correct(&self) -> usize {
+        self.candidates - self.false_positives - self.false_negatives
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        self.correct() as f64 / self.candidates as f64
+    }
+
+    pub fn per_candidate(&self) -> Duration {
+        self.elapsed / self.candidates.max(1) as u32
+    }
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}/{} correct, {} false positives, {} false negatives, {:?} ({:?} per candidate)",
+            self.strategy,
+            self.correct(),
+            self.candidates,
+            self.false_positives,
+            self.false_negatives,
+            self.elapsed,
+            self.per_candidate()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bi;
+
+    #[test]
+    fn every_strategy_classifies_the_workload() {
+        let candidates = workload(20, 5);
+        for evaluation in evaluate(&Strategy::ALL, &candidates) {
+            assert_eq!(evaluation.candidates, 20);
+            assert_eq!(evaluation.correct(), 20, "{}", evaluation);
+        }
+    }
+
+    #[test]
+    fn strategies_agree_on_small_numbers() {
+        for n in 0..2000u32 {
+            let expected = Strategy::SieveMillerRabin.is_prime(&BigInt::from(n));
+            for strategy in Strategy::ALL {
+                assert_eq!(
+                    strategy.is_prime(&BigInt::from(n)),
+                    expected,
+                    "{} {}",
+                    strategy,
+                    n
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rejections_come_with_evidence_that_verifies() {
+        let cases = [
+            (Strategy::DeterministicSmall, "3215031751"),
+            (Strategy::BailliePsw, "2047"),
+            (Strategy::BailliePsw, "5777"),
+            (Strategy::BailliePsw, "1369"),
+            (Strategy::SieveMillerRabin, "1001"),
+            (Strategy::MillerRabin, "1"),
+            (Strategy::MillerRabin, "1000002"),
+        ];
+        for (strategy, n) in cases {
+            let n = bi!(n);
+            let evidence = strategy.check(&n).unwrap_err();
+            assert!(evidence.verify(&n), "{} {}: {}", strategy, n, evidence);
+        }
+        assert!(matches!(
+            Strategy::BailliePsw.check(&bi!("2047")),
+            Err(Composite::Lucas { .. })
+        ));
+        assert_eq!(
+            strong_lucas(&bi!("1369")),
+            Err(Composite::PerfectSquare { root: bi!("37") })
+        );
+        let evidence = check(&bi!("561"), 20).unwrap_err();
+        assert!(evidence.verify(&bi!("561")));
+        assert!(check(&bi!("1000003"), 20).is_ok());
+        assert!(!Composite::MillerRabin { base: bi!("2") }.verify(&bi!("2047")));
+    }
+
+    #[test]
+    fn lucas_and_base_2_catch_each_others_pseudoprimes() {
+        assert!(strong_lucas(&bi!("5777")).is_ok());
+        assert!(!is_strong_probable_prime(&bi!("5777"), &BigInt::from(2)));
+        assert!(is_strong_probable_prime(&bi!("2047"), &BigInt::from(2)));
+        assert!(strong_lucas(&bi!("2047")).is_err());
+        assert!(strong_lucas(&bi!("1000003")).is_ok());
+        assert_eq!(Strategy::from_name("bpsw"), Some(Strategy::BailliePsw));
+    }
+}
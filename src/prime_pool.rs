@@ -0,0 +1,235 @@
+// A pool of primes generated ahead of time on background threads
+//
+// Most of the time gen_keys takes goes into the prime search, and an interactive tool
+// spends most of its own time waiting for input. PrimePool runs worker threads that keep a
+// stock of primes for each configured size topped up. Only the pool's own gen_keys and
+// gen_keys_with_digits take primes from the stock, so that after warm-up a key pair costs
+// one multiplication and one inversion; the free gen_keys functions and KeyGenParams know
+// nothing of pools and always search, and keys drawn from a pool have e = 65537 and none of
+// KeyGenParams' distance or safe-prime constraints. A size that is not configured, or whose
+// stock has run dry, is served by an ordinary search on the calling thread. Dropping the
+// pool stops and joins the workers; primes still in the stock are discarded with it.
+use crate::{invert, random_prime_with_digits, PrivateKey, PublicKey};
+use num_bigint::BigInt;
+use num_traits::Zero;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+// PoolStats: how many primes were taken from the stock and how many searched for inline
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Default)]
+struct Stock {
+    primes: Vec<BigInt>,
+    // Primes workers are currently searching for
+    pending: usize,
+}
+
+struct State {
+    stocks: HashMap<u32, Stock>,
+    stats: PoolStats,
+    stopping: bool,
+}
+
+struct Shared {
+    per_size: usize,
+    state: Mutex<State>,
+    // Signalled when a stock drops below per_size or the pool stops
+    wanted: Condvar,
+    // Signalled when a worker adds a prime
+    added: Condvar,
+}
+
+pub struct PrimePool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Shared {
+    // next_size: a size whose stock, counting primes being searched for, is short
+    fn next_size(&self, state: &State) -> Option<u32> {
+        let mut sizes: Vec<_> = state
+            .stocks
+            .iter()
+            .filter(|(_, stock)| stock.primes.len() + stock.pending < self.per_size)
+            .collect();
+        sizes.sort_by_key(|(_, stock)| stock.primes.len() + stock.pending);
+        sizes.first().map(|(&ndigits, _)| ndigits)
+    }
+
+    fn work(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.stopping {
+                return;
+            }
+            let Some(ndigits) = self.next_size(&state) else {
+                state = self.wanted.wait(state).unwrap();
+                continue;
+            };
+            state.stocks.get_mut(&ndigits).unwrap().pending += 1;
+            drop(state);
+            let p = random_prime_with_digits(ndigits);
+            state = self.state.lock().unwrap();
+            let stock = state.stocks.get_mut(&ndigits).unwrap();
+            stock.pending -= 1;
+            stock.primes.push(p);
+            self.added.notify_all();
+        }
+    }
+}
+
+impl PrimePool {
+    // new: a pool keeping per_size primes of each of the given digit counts, on one thread
+    pub fn new(sizes: &[u32], per_size: usize) -> Self {
+        PrimePool::with_threads(sizes, per_size, 1)
+    }
+
+    pub fn with_threads(sizes: &[u32], per_size: usize, threads: usize) -> Self {
+        if threads == 0 {
+            panic!("a prime pool needs at least one worker thread");
+        }
+        if let Some(ndigits) = sizes.iter().find(|&&ndigits| ndigits < 2) {
+            panic!("cannot pool {}-digit primes", ndigits);
+        }
+        let shared = Arc::new(Shared {
+            per_size,
+            state: Mutex::new(State {
+                stocks: sizes
+                    .iter()
+                    .map(|&ndigits| (ndigits, Stock::default()))
+                    .collect(),
+                stats: PoolStats::default(),
+                stopping: false,
+            }),
+            wanted: Condvar::new(),
+            added: Condvar::new(),
+        });
+        let workers = (0..threads)
+            .map(|i| {
+                let shared = Arc::clone(&shared);
+                thread::Builder::new()
+                    .name(format!("prime-pool-{}", i))
+                    .spawn(move || shared.work())
+                    .expect("failed to spawn a prime pool worker")
+            })
+            .collect();
+        PrimePool { shared, workers }
+    }
+
+    // available: primes of ndigits digits ready to be taken
+    pub fn available(&self, ndigits: u32) -> usize {
+        let state = self.shared.state.lock().unwrap();
+        state
+            .stocks
+            .get(&ndigits)
+            .map_or(0, |stock| stock.primes.len())
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        self.shared.state.lock().unwrap().stats
+    }
+
+    // warm_up: block until every stock is full
+    pub fn warm_up(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+        while state
+            .stocks
+            .values()
+            .any(|stock| stock.primes.len() < self.shared.per_size)
+        {
+            state = self.shared.added.wait(state).unwrap();
+        }
+    }
+
+    // prime: a prime of ndigits digits from the stock, or freshly searched for if there
+    // is none
+    pub fn prime(&self, ndigits: u32) -> BigInt {
+        let mut state = self.shared.state.lock().unwrap();
+        if let Some(p) = state.stocks.get_mut(&ndigits).and_then(|s| s.primes.pop()) {
+            state.stats.hits += 1;
+            self.shared.wanted.notify_one();
+            return p;
+        }
+        state.stats.misses += 1;
+        drop(state);
+        random_prime_with_digits(ndigits)
+    }
+
+    pub fn gen_keys(&self) -> (PublicKey, PrivateKey) {
+        self.gen_keys_with_digits(100)
+    }
+
+    // gen_keys_with_digits: as the free function, with the primes taken from the pool
+    pub fn gen_keys_with_digits(&self, ndigits: u32) -> (PublicKey, PrivateKey) {
+        let e = BigInt::from(65537);
+        let usable = |p: &BigInt| !((p - 1u32) % &e).is_zero();
+        let p = std::iter::repeat_with(|| self.prime(ndigits))
+            .find(usable)
+            .unwrap();
+        let q = std::iter::repeat_with(|| self.prime(ndigits))
+            .find(|q| *q != p && usable(q))
+            .unwrap();
+        let n = &p * &q;
+        let phi = (&p - 1) * (&q - 1);
+        let d = invert(e.clone(), phi);
+        (PublicKey { n, e }, PrivateKey::with_primes(d, p, q))
+    }
+}
+
+impl Drop for PrimePool {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().stopping = true;
+        self.shared.wanted.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decrypt, encrypt, Plaintext};
+
+    #[test]
+    fn keys_from_a_warm_pool_work() {
+        let pool = PrimePool::new(&[20], 4);
+        pool.warm_up();
+        assert_eq!(pool.available(20), 4);
+        let (pub_key, priv_key) = pool.gen_keys_with_digits(20);
+        let m = Plaintext::from(BigInt::from(12345));
+        assert_eq!(
+            decrypt(&pub_key, &priv_key, encrypt(&pub_key, m.clone()).unwrap()).unwrap(),
+            m
+        );
+        assert!(pool.stats().hits >= 2);
+        assert_eq!(pool.stats().misses, 0);
+    }
+
+    #[test]
+    fn pool_refills_after_primes_are_taken() {
+        let pool = PrimePool::with_threads(&[15, 20], 3, 2);
+        pool.warm_up();
+        for _ in 0..3 {
+            pool.prime(15);
+        }
+        pool.warm_up();
+        assert_eq!(pool.available(15), 3);
+        assert_eq!(pool.available(20), 3);
+    }
+
+    #[test]
+    fn unpooled_sizes_are_searched_for_inline() {
+        let pool = PrimePool::new(&[20], 1);
+        let p = pool.prime(12);
+        assert!(p > BigInt::from(10).pow(11));
+        assert_eq!(pool.stats().misses, 1);
+        assert_eq!(pool.available(12), 0);
+    }
+}
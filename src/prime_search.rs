@@ -0,0 +1,185 @@
+// Candidate search for random primes
+//
+// A 1024-bit odd number has about a 1 in 355 chance of being prime, so a search that runs
+// Miller-Rabin on every point of the mod 210 wheel spends most of its exponentiations on
+// numbers with a factor below a few thousand. Candidates walks
+// the wheel upward from a random start, computing the start's residue mod each prime in
+// a small table once, and trial divides a candidate at start + offset using those residues
+// and machine-word arithmetic alone. Only survivors become BigInts for Miller-Rabin. In
+// safe mode a candidate q must also leave 2q + 1 without a small factor, for safe primes
+// p = 2q + 1. fips_rounds scales the Miller-Rabin rounds to the size of the candidates
+// after FIPS 186-5 appendix B.3; the counts there are for random candidates, not numbers
+// an adversary picked, so is_probable_prime keeps its caller's round count.
+use crate::wheel::{self, Wheel};
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive};
+use std::sync::OnceLock;
+
+// Odd primes below this go into the sieve table
+const SMALL_PRIME_LIMIT: u32 = 1 << 12;
+
+// (minimum prime bits, rounds): FIPS 186-5 table B.1 for 1024 bits and up, FIPS 186-4
+// table C.3 for 512; smaller primes are test sizes and cheap to test more often
+const FIPS_ROUNDS: [(u64, usize); 4] = [(1536, 4), (1024, 5), (512, 7), (0, 40)];
+
+// small_primes: the odd primes below SMALL_PRIME_LIMIT, by the sieve of Eratosthenes
+pub(crate) fn small_primes() -> &'static [u32] {
+    static PRIMES: OnceLock<Vec<u32>> = OnceLock::new();
+    PRIMES.get_or_init(|| {
+        let mut composite = vec![false; SMALL_PRIME_LIMIT as usize];
+        let mut primes = Vec::new();
+        for p in (3..SMALL_PRIME_LIMIT).step_by(2) {
+            if composite[p as usize] {
+                continue;
+            }
+            primes.push(p);
+            for multiple in (p * p..SMALL_PRIME_LIMIT).step_by(p as usize) {
+                composite[multiple as usize] = true;
+            }
+        }
+        primes
+    })
+}
+
+// fips_rounds: Miller-Rabin rounds for a random candidate of bits bits
+pub(crate) fn fips_rounds(bits: u64) -> usize {
+    FIPS_ROUNDS
+        .iter()
+        .find(|&&(min_bits, _)| bits >= min_bits)
+        .map_or(40, |&(_, rounds)| rounds)
+}
+
+pub(crate) struct Candidates {
+    start: BigInt,
+    offset: u64,
+    // Position of start + offset among the wheel's residues
+    index: usize,
+    // start mod each of small_primes()
+    residues: Vec<u32>,
+    // start, if it is small enough that a candidate can be one of the small primes
+    small_start: Option<u64>,
+    safe: bool,
+}
+
+impl Candidates {
+    // starting_at: integers at least start with no factor below SMALL_PRIME_LIMIT
+    pub(crate) fn starting_at(start: &BigInt) -> Self {
+        if !start.is_positive() {
+            panic!(
+                "the candidate search starts at a positive number, not {}",
+                start
+            );
+        }
+        let start = Wheel::starting_at(start).next().unwrap();
+        let r = (&start % wheel::MODULUS).to_u8().unwrap();
+        let index = Wheel::residues().iter().position(|&res| res == r).unwrap();
+        let residues = small_primes()
+            .iter()
+            .map(|&p| (&start % p).to_u32().unwrap())
+            .collect();
+        let small_start = start.to_u64().filter(|&s| s < u64::from(SMALL_PRIME_LIMIT));
+        Candidates {
+            start,
+            offset: 0,
+            index,
+            residues,
+            small_start,
+            safe: false,
+        }
+    }
+
+    // safe_starting_at: candidates q for which 2q + 1 has no small factor either
+    pub(crate) fn safe_starting_at(start: &BigInt) -> Self {
+        Candidates {
+            safe: true,
+            ..Candidates::starting_at(start)
+        }
+    }
+
+    fn passes(&self) -> bool {
+        let offset = self.offset;
+        let candidate = self.small_start.map(|s| s + offset);
+        small_primes()
+            .iter()
+            .zip(&self.residues)
+            // A small candidate is not ruled out by itself or the primes above it
+            .take_while(|(&p, _)| candidate.is_none_or(|c| u64::from(p) < c))
+            .all(|(&p, &r)| {
+                let p = u64::from(p);
+                let c = (u64::from(r) + offset % p) % p;
+                c != 0 && !(self.safe && (2 * c + 1).is_multiple_of(p))
+            })
+    }
+
+    fn advance(&mut self) {
+        let residues = Wheel::residues();
+        let next = (self.index + 1) % residues.len();
+        let gap = if next == 0 {
+            wheel::MODULUS - u32::from(residues[self.index]) + u32::from(residues[0])
+        } else {
+            u32::from(residues[next] - residues[self.index])
+        };
+        self.index = next;
+        self.offset += u64::from(gap);
+    }
+}
+
+impl Iterator for Candidates {
+    type Item = BigInt;
+
+    fn next(&mut self) -> Option<BigInt> {
+        loop {
+            let found = self.passes().then(|| &self.start + self.offset);
+            self.advance();
+            if found.is_some() {
+                return found;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidates_are_the_wheel_without_small_multiples() {
+        let start = BigInt::from(1) << 64;
+        let expected: Vec<BigInt> = Wheel::starting_at(&start)
+            .filter(|c| small_primes().iter().all(|&p| (c % p) != BigInt::from(0)))
+            .take(50)
+            .collect();
+        let found: Vec<BigInt> = Candidates::starting_at(&start).take(50).collect();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn small_starts_keep_the_small_primes() {
+        let found: Vec<BigInt> = Candidates::starting_at(&BigInt::from(4000))
+            .take_while(|c| *c < BigInt::from(4200))
+            .collect();
+        let primes = [
+            4001, 4003, 4007, 4013, 4019, 4021, 4027, 4049, 4051, 4057, 4073,
+        ];
+        assert_eq!(&found[..primes.len()], primes.map(BigInt::from));
+        assert_eq!(small_primes().len(), 563);
+    }
+
+    #[test]
+    fn safe_candidates_leave_2q_plus_1_unsieved() {
+        for q in Candidates::safe_starting_at(&(BigInt::from(1) << 80)).take(20) {
+            let p: BigInt = 2 * &q + 1u32;
+            assert!(small_primes()
+                .iter()
+                .all(|&f| (&q % f) != BigInt::from(0) && (&p % f) != BigInt::from(0)));
+        }
+    }
+
+    #[test]
+    fn rounds_shrink_as_primes_grow() {
+        assert_eq!(fips_rounds(2048), 4);
+        assert_eq!(fips_rounds(1024), 5);
+        assert_eq!(fips_rounds(512), 7);
+        assert_eq!(fips_rounds(64), 40);
+    }
+}
@@ -0,0 +1,191 @@
+// Proxy re-encryption by secret splitting (Ivan-Dodis)
+//
+// Alice splits her private exponent, d_A = d_proxy + d_bob mod L, where L is phi(n_A) if her
+// key knows its primes and otherwise e d_A - 1, a multiple of lambda(n_A). The proxy keeps
+// d_proxy in [0, L), so its exponentiation needs no inverse and works for every c, 0 and
+// non-units included, and turns every ciphertext c for Alice into (c, c^d_proxy); Bob
+// receives d_bob wrapped under his own public key and finishes with m = c^d_proxy * c^d_bob
+// mod n_A. d_bob is drawn from a range much wider than L, which leaves d_proxy close to
+// uniform mod L, so neither the proxy nor Bob alone learns anything about d_A, and Alice
+// revokes the delegation by telling the proxy to drop its share. A proxy colluding with Bob
+// does recover d_A, as in every scheme of this kind.
+use crate::error::{ParseError, RsaError};
+use crate::wire::{Reader, Writer};
+use crate::{decrypt, encrypt, PrivateKey, PublicKey};
+use num_bigint::{BigInt, RandBigInt};
+
+// Bits of slack on top of the modulus size when sampling shares, for statistical hiding
+const HIDING_BITS: u64 = 128;
+
+// DelegationKey: the proxy's share of Alice's exponent
+pub struct DelegationKey {
+    pub_key: PublicKey,
+    d: BigInt,
+}
+
+// DelegationToken: Bob's share of Alice's exponent, encrypted to Bob as base-n_B digits
+pub struct DelegationToken {
+    digits: Vec<BigInt>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReEncrypted {
+    c: BigInt,
+    partial: BigInt,
+}
+
+// delegate: let the proxy turn ciphertexts for alice into ciphertexts only bob can open
+pub fn delegate(
+    alice_pub: &PublicKey,
+    alice_priv: &PrivateKey,
+    bob_pub: &PublicKey,
+) -> (DelegationKey, DelegationToken) {
+    let bound = BigInt::from(1) << (alice_pub.n.bits() + HIDING_BITS);
+    let d_bob = rand::thread_rng().gen_bigint_range(&BigInt::from(0), &bound);
+    let order = match alice_priv.primes() {
+        Some((p, q)) => (p - 1) * (q - 1),
+        None => &alice_pub.e * &alice_priv.d - 1,
+    };
+    let d_proxy = ((&alice_priv.d - &d_bob) % &order + &order) % &order;
+    // Write d_bob in base n_B so that every digit can be encrypted to Bob
+    let mut digits = Vec::new();
+    let mut rest = d_bob;
+    while rest > BigInt::from(0) {
+        let digit = encrypt(bob_pub, (&rest % &bob_pub.n).into()).expect("digits are below n");
+        digits.push(digit.into());
+        rest /= &bob_pub.n;
+    }
+    (
+        DelegationKey {
+            pub_key: alice_pub.clone(),
+            d: d_proxy,
+        },
+        DelegationToken { digits },
+    )
+}
+
+impl DelegationKey {
+    // transform: the proxy's half of decryption; on its own it reveals nothing about m
+    pub fn transform(&self, c: &BigInt) -> ReEncrypted {
+        ReEncrypted {
+            c: c.clone(),
+            partial: c.modpow(&self.d, &self.pub_key.n),
+        }
+    }
+}
+
+impl DelegationToken {
+    // open: Bob recovers his share of Alice's exponent with his own private key
+    fn open(&self, bob_pub: &PublicKey, bob_priv: &PrivateKey) -> Result<BigInt, RsaError> {
+        self.digits
+            .iter()
+            .rev()
+            .try_fold(BigInt::from(0), |acc, digit| {
+                let digit = decrypt(bob_pub, bob_priv, digit.clone().into())?;
+                Ok(acc * &bob_pub.n + BigInt::from(digit))
+            })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::new("naive-rsa delegation token");
+        for digit in &self.digits {
+            w.int(digit);
+        }
+        w.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut r = Reader::new(bytes, "naive-rsa delegation token")?;
+        let mut digits = Vec::new();
+        while !r.is_empty() {
+            digits.push(r.int()?);
+        }
+        r.finish()?;
+        Ok(DelegationToken { digits })
+    }
+}
+
+impl ReEncrypted {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Writer::new("naive-rsa re-encrypted")
+            .int(&self.c)
+            .int(&self.partial)
+            .finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut r = Reader::new(bytes, "naive-rsa re-encrypted")?;
+        let (c, partial) = (r.int()?, r.int()?);
+        r.finish()?;
+        Ok(ReEncrypted { c, partial })
+    }
+}
+
+// decrypt_delegated: Bob's decryption of a transformed ciphertext originally for alice_pub;
+// an error if the token's digits do not decrypt under his key
+pub fn decrypt_delegated(
+    alice_pub: &PublicKey,
+    bob_pub: &PublicKey,
+    bob_priv: &PrivateKey,
+    token: &DelegationToken,
+    ct: &ReEncrypted,
+) -> Result<BigInt, RsaError> {
+    let d_bob = token.open(bob_pub, bob_priv)?;
+    Ok(&ct.partial * ct.c.modpow(&d_bob, &alice_pub.n) % &alice_pub.n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn bob_decrypts_transformed_ciphertexts() {
+        let (alice_pub, alice_priv) = gen_keys_with_digits(20);
+        let (bob_pub, bob_priv) = gen_keys_with_digits(20);
+        let (proxy_key, token) = delegate(&alice_pub, &alice_priv, &bob_pub);
+        let m = BigInt::from(31337);
+        let ct = proxy_key.transform(&encrypt(&alice_pub, m.clone().into()).unwrap().into());
+        assert_ne!(ct.partial, m);
+        let ct = ReEncrypted::from_bytes(&ct.to_bytes()).unwrap();
+        let token = DelegationToken::from_bytes(&token.to_bytes()).unwrap();
+        assert_eq!(
+            decrypt_delegated(&alice_pub, &bob_pub, &bob_priv, &token, &ct),
+            Ok(m)
+        );
+    }
+
+    #[test]
+    fn zero_and_non_units_transform_too() {
+        let (alice_pub, alice_priv) = gen_keys_with_digits(20);
+        let (bob_pub, bob_priv) = gen_keys_with_digits(20);
+        let p = alice_priv.primes().unwrap().0.clone();
+        let without_primes = PrivateKey::new(alice_priv.exponent().clone());
+        for priv_key in [&without_primes, &alice_priv] {
+            let (proxy_key, token) = delegate(&alice_pub, priv_key, &bob_pub);
+            assert!(proxy_key.d >= BigInt::from(0));
+            for m in [BigInt::from(0), p.clone()] {
+                let c = encrypt(&alice_pub, m.clone().into()).unwrap().into();
+                let ct = proxy_key.transform(&c);
+                assert_eq!(
+                    decrypt_delegated(&alice_pub, &bob_pub, &bob_priv, &token, &ct),
+                    Ok(m)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn token_is_useless_with_the_wrong_private_key() {
+        let (alice_pub, alice_priv) = gen_keys_with_digits(20);
+        let (bob_pub, _) = gen_keys_with_digits(20);
+        let (carol_pub, carol_priv) = gen_keys_with_digits(20);
+        let (proxy_key, token) = delegate(&alice_pub, &alice_priv, &bob_pub);
+        let m = BigInt::from(7);
+        let ct = proxy_key.transform(&encrypt(&alice_pub, m.clone().into()).unwrap().into());
+        assert_ne!(
+            decrypt_delegated(&alice_pub, &carol_pub, &carol_priv, &token, &ct),
+            Ok(m)
+        );
+    }
+}
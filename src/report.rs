@@ -0,0 +1,209 @@
+// A structured description of a key pair
+//
+// describe collects what one usually prints about a key: sizes, exponents, whether the
+// primes and CRT parameters are at hand, a strength estimate and the formats this crate
+// can write the key in. KeyReport's fields are plain data for callers to render as they
+// like; its Display gives an English summary with ASCII digits and no locale-dependent
+// formatting, which is what the CLI prints.
+//
+// The strength estimate follows NIST SP 800-57 Part 1, table 2: 80 bits for a 1024-bit
+// modulus, 112 for 2048, 128 for 3072, 192 for 7680 and 256 for 15360. A modulus between
+// two sizes gets the lower strength, one below 1024 bits none at all.
+use crate::compact::{qr_version, to_compact, QrLevel};
+use crate::exponents::carmichael;
+use crate::keypair::KeyPair;
+use crate::pinning::{fingerprint, Pin};
+use num_bigint::BigInt;
+use std::fmt;
+
+const STRENGTHS: [(u64, u32); 5] = [
+    (15360, 256),
+    (7680, 192),
+    (3072, 128),
+    (2048, 112),
+    (1024, 80),
+];
+
+// Primes: where the report's prime factors came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Primes {
+    // Given with the key pair, or kept by the private key
+    Stored,
+    // Not stored, but factored out of n with d
+    RecoveredFromD,
+    Unavailable,
+}
+
+// Format: a way the crate can write the key out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    // The hand-copyable key pair of the paper module
+    Paper,
+    // Shamir shares, from the backup module
+    Backup,
+    // The compact public key encoding
+    Compact,
+    // A QR code of the compact encoding, with the qr feature
+    Qr,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyReport {
+    pub modulus_bits: u64,
+    pub public_exponent: BigInt,
+    pub private_exponent_bits: u64,
+    pub primes: Primes,
+    // Sizes of p and q in bits, in the order the key pair gives them
+    pub prime_bits: Option<(u64, u64)>,
+    pub crt_params: bool,
+    // Whether d is below lambda(n), i.e. the smallest exponent that works; None without primes
+    pub d_is_minimal: Option<bool>,
+    pub security_bits: Option<u32>,
+    pub fingerprint: Pin,
+    pub formats: Vec<Format>,
+    // The QR code version the compact public key needs at level M
+    pub qr_version: Option<u8>,
+}
+
+pub fn security_bits(modulus_bits: u64) -> Option<u32> {
+    STRENGTHS
+        .iter()
+        .find(|&&(bits, _)| modulus_bits >= bits)
+        .map(|&(_, strength)| strength)
+}
+
+pub fn describe(key: &KeyPair) -> KeyReport {
+    let (public, private) = (key.public(), key.private());
+    let primes = key.primes();
+    let qr_version = qr_version(to_compact(public).len(), QrLevel::M);
+    let mut formats = vec![Format::Paper, Format::Backup, Format::Compact];
+    if cfg!(feature = "qr") && qr_version.is_some() {
+        formats.push(Format::Qr);
+    }
+    KeyReport {
+        modulus_bits: public.n.bits(),
+        public_exponent: public.e.clone(),
+        private_exponent_bits: private.d.bits(),
+        primes: match (&primes, key.has_stored_primes()) {
+            (_, true) => Primes::Stored,
+            (Some(_), false) => Primes::RecoveredFromD,
+            (None, false) => Primes::Unavailable,
+        },
+        prime_bits: primes.as_ref().map(|(p, q)| (p.bits(), q.bits())),
+        crt_params: key.crt_params().is_some(),
+        d_is_minimal: primes.map(|(p, q)| private.d < carmichael(&p, &q)),
+        security_bits: security_bits(public.n.bits()),
+        fingerprint: fingerprint(public),
+        formats,
+        qr_version,
+    }
+}
+
+impl fmt::Display for KeyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "modulus: {} bits", self.modulus_bits)?;
+        writeln!(f, "public exponent: {}", self.public_exponent)?;
+        writeln!(f, "private exponent: {} bits", self.private_exponent_bits)?;
+        match (self.primes, self.prime_bits) {
+            (Primes::Unavailable, _) | (_, None) => writeln!(f, "primes: unavailable")?,
+            (primes, Some((p, q))) => writeln!(
+                f,
+                "primes: {} and {} bits, {}",
+                p,
+                q,
+                if primes == Primes::Stored {
+                    "stored"
+                } else {
+                    "recovered from d"
+                }
+            )?,
+        }
+        writeln!(
+            f,
+            "CRT parameters: {}",
+            if self.crt_params {
+                "available"
+            } else {
+                "unavailable"
+            }
+        )?;
+        match self.d_is_minimal {
+            Some(true) => writeln!(f, "d: reduced mod lambda(n)")?,
+            Some(false) => writeln!(f, "d: not reduced mod lambda(n)")?,
+            None => {}
+        }
+        match self.security_bits {
+            Some(bits) => writeln!(f, "strength: {} bits (SP 800-57)", bits)?,
+            None => writeln!(f, "strength: below 80 bits (SP 800-57)")?,
+        }
+        let hex: String = self
+            .fingerprint
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        writeln!(f, "fingerprint: sha256/{}", hex)?;
+        let formats: Vec<&str> = self
+            .formats
+            .iter()
+            .map(|format| match format {
+                Format::Paper => "paper",
+                Format::Backup => "backup shares",
+                Format::Compact => "compact",
+                Format::Qr => "QR code",
+            })
+            .collect();
+        write!(f, "formats: {}", formats.join(", "))?;
+        match self.qr_version {
+            Some(version) => write!(f, " (QR version {} at level M)", version),
+            None => write!(f, " (too large for a QR code)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gen_keys_with_digits, PrivateKey};
+
+    #[test]
+    fn reports_describe_the_key() {
+        let key = KeyPair::generate(20);
+        let report = describe(&key);
+        assert_eq!(report.modulus_bits, key.public().n.bits());
+        assert_eq!(report.public_exponent, BigInt::from(65537));
+        assert_eq!(report.primes, Primes::Stored);
+        assert!(report.crt_params);
+        let (p, q) = key.primes().unwrap();
+        let minimal = *key.private().exponent() < carmichael(&p, &q);
+        assert_eq!(report.d_is_minimal, Some(minimal));
+        assert_eq!(report.security_bits, None);
+        assert!(report
+            .formats
+            .starts_with(&[Format::Paper, Format::Backup, Format::Compact]));
+        let text = report.to_string();
+        assert!(text.starts_with(&format!("modulus: {} bits\n", report.modulus_bits)));
+        assert!(text.contains("strength: below 80 bits"));
+    }
+
+    #[test]
+    fn primes_are_recovered_when_not_stored() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let d = priv_key.exponent().clone();
+        // The private key of gen_keys keeps its primes, which count as stored
+        let report = describe(&KeyPair::new(pub_key.clone(), priv_key));
+        assert_eq!(report.primes, Primes::Stored);
+        let report = describe(&KeyPair::new(pub_key, PrivateKey::new(d)));
+        assert_eq!(report.primes, Primes::RecoveredFromD);
+        assert!(report.to_string().contains("recovered from d"));
+    }
+
+    #[test]
+    fn strength_follows_the_nist_table() {
+        assert_eq!(security_bits(1023), None);
+        assert_eq!(security_bits(1024), Some(80));
+        assert_eq!(security_bits(2047), Some(80));
+        assert_eq!(security_bits(2048), Some(112));
+        assert_eq!(security_bits(4096), Some(128));
+        assert_eq!(security_bits(16384), Some(256));
+    }
+}
@@ -0,0 +1,250 @@
+// Rivest-Shamir-Tauman ring signatures
+//
+// A ring signature convinces a verifier that one of the holders of a set of RSA public keys
+// signed the message, without revealing which one. Every key's RSA permutation is extended
+// to a common b-bit domain, and a keyed symmetric cipher E_k with k = H(m) chains the ring:
+// z_0 = v, z_{i+1} = E_k(g_i(x_i) xor z_i), and the signature is valid when z_r = v. Anyone
+// can pick all the x_i but one; closing the ring needs one g_i inverted, i.e. a private key.
+// https://en.wikipedia.org/wiki/Ring_signature
+use crate::error::ParseError;
+use crate::hash::{self, TranscriptHash};
+use crate::trapdoor::{extended_forward, extended_inverse};
+use crate::wire::{Reader, Writer};
+use crate::{checked_public_key, PrivateKey, PublicKey};
+use num_bigint::{BigInt, RandBigInt, Sign};
+
+// Extra bits of the common domain above the largest modulus, so that the extended
+// permutations are the RSA permutation on all but a negligible fraction of the domain
+pub(crate) const DOMAIN_SLACK_BITS: u64 = 160;
+
+// Feistel rounds of the combining cipher; four give a strong pseudorandom permutation
+const FEISTEL_ROUNDS: u8 = 4;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RingSignature {
+    ring: Vec<PublicKey>,
+    v: BigInt,
+    x: Vec<BigInt>,
+}
+
+impl RingSignature {
+    pub fn ring(&self) -> &[PublicKey] {
+        &self.ring
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::new("naive-rsa ring signature");
+        w.int(&self.v);
+        w.bytes(&(self.ring.len() as u32).to_be_bytes());
+        for (key, x) in self.ring.iter().zip(&self.x) {
+            w.int(&key.n).int(&key.e).int(x);
+        }
+        w.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        const FORMAT: &str = "naive-rsa ring signature";
+        let mut r = Reader::new(bytes, FORMAT)?;
+        let v = r.int()?;
+        let len = r.count()?;
+        let (mut ring, mut x) = (Vec::new(), Vec::new());
+        for _ in 0..len {
+            let offset = r.offset();
+            let (n, e) = (r.int()?, r.int()?);
+            let key = checked_public_key(n, e)
+                .map_err(|reason| ParseError::invalid(FORMAT, offset, reason))?;
+            ring.push(key);
+            x.push(r.int()?);
+        }
+        r.finish()?;
+        Ok(RingSignature { ring, v, x })
+    }
+}
+
+// domain_bytes: size of the common domain in bytes, even so that it splits into two halves
+pub(crate) fn domain_bytes(keys: &[PublicKey]) -> usize {
+    let max_bits = keys.iter().map(|key| key.n.bits()).max().unwrap_or(0);
+    let len = (max_bits + DOMAIN_SLACK_BITS).div_ceil(8) as usize;
+    len + len % 2
+}
+
+pub(crate) fn to_block(x: &BigInt, len: usize) -> Vec<u8> {
+    let (_, bytes) = x.to_bytes_be();
+    let mut block = vec![0u8; len - bytes.len()];
+    block.extend_from_slice(&bytes);
+    block
+}
+
+pub(crate) fn from_block(block: &[u8]) -> BigInt {
+    BigInt::from_bytes_be(Sign::Plus, block)
+}
+
+pub(crate) fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(a, b)| a ^ b).collect()
+}
+
+// Cipher: a Luby-Rackoff Feistel network over SHA-256, the ring's combining function
+struct Cipher {
+    key: Vec<u8>,
+}
+
+impl Cipher {
+    fn round(&self, i: u8, half: &[u8]) -> Vec<u8> {
+        let seed = TranscriptHash::new("naive-rsa ring cipher")
+            .bytes(&self.key)
+            .bytes(&[i])
+            .bytes(half)
+            .finish();
+        hash::expand(&seed, half.len())
+    }
+
+    fn encrypt(&self, block: &[u8]) -> Vec<u8> {
+        let (l, r) = block.split_at(block.len() / 2);
+        let (mut l, mut r) = (l.to_vec(), r.to_vec());
+        for i in 0..FEISTEL_ROUNDS {
+            let next_r = xor(&l, &self.round(i, &r));
+            l = std::mem::replace(&mut r, next_r);
+        }
+        [l, r].concat()
+    }
+
+    fn decrypt(&self, block: &[u8]) -> Vec<u8> {
+        let (l, r) = block.split_at(block.len() / 2);
+        let (mut l, mut r) = (l.to_vec(), r.to_vec());
+        for i in (0..FEISTEL_ROUNDS).rev() {
+            let prev_l = xor(&r, &self.round(i, &l));
+            r = std::mem::replace(&mut l, prev_l);
+        }
+        [l, r].concat()
+    }
+}
+
+fn cipher_for(ring: &[PublicKey], m: &[u8]) -> Cipher {
+    let mut h = TranscriptHash::new("naive-rsa ring signature");
+    for key in ring {
+        h.int(&key.n).int(&key.e);
+    }
+    h.bytes(m);
+    Cipher { key: h.finish() }
+}
+
+// sign: sign m on behalf of the ring, using the private key belonging to ring[signer]
+pub fn sign(ring: &[PublicKey], signer: usize, priv_key: &PrivateKey, m: &[u8]) -> RingSignature {
+    if signer >= ring.len() {
+        panic!(
+            "signer index {} is outside a ring of {}",
+            signer,
+            ring.len()
+        );
+    }
+    let len = domain_bytes(ring);
+    let bound = BigInt::from(1) << (8 * len);
+    let cipher = cipher_for(ring, m);
+    let mut rng = rand::thread_rng();
+    let v = rng.gen_bigint_range(&BigInt::from(0), &bound);
+    let mut x: Vec<BigInt> = (0..ring.len())
+        .map(|_| rng.gen_bigint_range(&BigInt::from(0), &bound))
+        .collect();
+    let y: Vec<Vec<u8>> = ring
+        .iter()
+        .zip(&x)
+        .map(|(key, x)| to_block(&extended_forward(key, x, len), len))
+        .collect();
+    // Walk forward from v up to the signer ...
+    let mut z = to_block(&v, len);
+    for y in &y[..signer] {
+        z = cipher.encrypt(&xor(y, &z));
+    }
+    // ... and backward from v down to just after the signer
+    let mut w = to_block(&v, len);
+    for y in y[signer + 1..].iter().rev() {
+        w = xor(&cipher.decrypt(&w), y);
+    }
+    // The signer's y closes the gap, and their private key turns it into x
+    let y_signer = from_block(&xor(&cipher.decrypt(&w), &z));
+    x[signer] = extended_inverse(&ring[signer], priv_key, &y_signer, len);
+    RingSignature {
+        ring: ring.to_vec(),
+        v,
+        x,
+    }
+}
+
+pub fn verify(sig: &RingSignature, m: &[u8]) -> bool {
+    if sig.ring.is_empty() || sig.x.len() != sig.ring.len() {
+        return false;
+    }
+    // A member with n below 3 has no permutation to extend
+    if sig.ring.iter().any(|key| key.n < BigInt::from(3)) {
+        return false;
+    }
+    let len = domain_bytes(&sig.ring);
+    let bound = BigInt::from(1) << (8 * len);
+    let in_domain = |x: &BigInt| *x >= BigInt::from(0) && *x < bound;
+    if !in_domain(&sig.v) || !sig.x.iter().all(in_domain) {
+        return false;
+    }
+    let cipher = cipher_for(&sig.ring, m);
+    let z = sig
+        .ring
+        .iter()
+        .zip(&sig.x)
+        .fold(to_block(&sig.v, len), |z, (key, x)| {
+            let y = to_block(&extended_forward(key, x, len), len);
+            cipher.encrypt(&xor(&y, &z))
+        });
+    from_block(&z) == sig.v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn any_member_can_sign_for_the_ring() {
+        let keys: Vec<_> = (0..3).map(|_| gen_keys_with_digits(20)).collect();
+        let ring: Vec<PublicKey> = keys.iter().map(|(p, _)| p.clone()).collect();
+        for (i, (_, priv_key)) in keys.iter().enumerate() {
+            let sig = sign(&ring, i, priv_key, b"one of us");
+            assert!(verify(&sig, b"one of us"));
+            assert!(!verify(&sig, b"none of us"));
+        }
+    }
+
+    #[test]
+    fn wrong_private_key_does_not_close_the_ring() {
+        let (alice_pub, alice_priv) = gen_keys_with_digits(20);
+        let (bob_pub, _) = gen_keys_with_digits(20);
+        let sig = sign(&[alice_pub, bob_pub], 1, &alice_priv, b"m");
+        assert!(!verify(&sig, b"m"));
+    }
+
+    #[test]
+    fn signature_round_trips_through_bytes() {
+        let (alice_pub, alice_priv) = gen_keys_with_digits(20);
+        let (bob_pub, _) = gen_keys_with_digits(20);
+        let sig = sign(&[alice_pub, bob_pub], 0, &alice_priv, b"m");
+        let decoded = RingSignature::from_bytes(&sig.to_bytes()).unwrap();
+        assert_eq!(decoded, sig);
+        assert!(verify(&decoded, b"m"));
+    }
+
+    #[test]
+    fn degenerate_members_are_rejected() {
+        let (alice_pub, alice_priv) = gen_keys_with_digits(20);
+        let mut sig = sign(&[alice_pub.clone(), alice_pub], 0, &alice_priv, b"m");
+        for n in [0, 1] {
+            sig.ring[1].n = BigInt::from(n);
+            assert!(RingSignature::from_bytes(&sig.to_bytes()).is_err());
+            assert!(!verify(&sig, b"m"));
+        }
+    }
+
+    #[test]
+    fn cipher_inverts() {
+        let cipher = Cipher { key: vec![1, 2, 3] };
+        let block = vec![7u8; 32];
+        assert_eq!(cipher.decrypt(&cipher.encrypt(&block)), block);
+    }
+}
@@ -0,0 +1,100 @@
+// serde support for the key types, behind the serde feature
+//
+// PublicKey serializes as a struct { n, e } and PrivateKey as { d }, the integers in
+// num-bigint's own serde form, so any self-describing format (JSON, CBOR, ...) can carry
+// them; deserializing accepts the fields as a map in any order or as a sequence. Nothing is
+// validated on the way in; for keys from outside, prefer from_str_components or keyfile.
+// Serializing a PrivateKey writes d in the clear, so mind where the output goes.
+use crate::{checked_public_key, PrivateKey, PublicKey};
+use num_bigint::BigInt;
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+const PUBLIC_FIELDS: &[&str] = &["n", "e"];
+const PRIVATE_FIELDS: &[&str] = &["d"];
+
+impl Serialize for PublicKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("PublicKey", 2)?;
+        s.serialize_field("n", &self.n)?;
+        s.serialize_field("e", &self.e)?;
+        s.end()
+    }
+}
+
+impl Serialize for PrivateKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("PrivateKey", 1)?;
+        s.serialize_field("d", &self.d)?;
+        s.end()
+    }
+}
+
+// Fields: a visitor for a struct of BigInt fields, returned in the order of names
+struct Fields {
+    name: &'static str,
+    names: &'static [&'static str],
+}
+
+impl<'de> Visitor<'de> for Fields {
+    type Value = Vec<BigInt>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a {} with fields {}", self.name, self.names.join(", "))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<BigInt>, A::Error> {
+        (0..self.names.len())
+            .map(|i| {
+                seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(i, &self))
+            })
+            .collect()
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Vec<BigInt>, A::Error> {
+        let mut values: Vec<Option<BigInt>> = vec![None; self.names.len()];
+        while let Some(key) = map.next_key::<String>()? {
+            let i = self
+                .names
+                .iter()
+                .position(|name| *name == key)
+                .ok_or_else(|| de::Error::unknown_field(&key, self.names))?;
+            if values[i].is_some() {
+                return Err(de::Error::duplicate_field(self.names[i]));
+            }
+            values[i] = Some(map.next_value()?);
+        }
+        values
+            .into_iter()
+            .zip(self.names)
+            .map(|(value, name)| value.ok_or_else(|| de::Error::missing_field(name)))
+            .collect()
+    }
+}
+
+fn deserialize_fields<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    name: &'static str,
+    names: &'static [&'static str],
+) -> Result<Vec<BigInt>, D::Error> {
+    deserializer.deserialize_struct(name, names, Fields { name, names })
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut fields = deserialize_fields(deserializer, "PublicKey", PUBLIC_FIELDS)?;
+        let e = fields.pop().unwrap();
+        let n = fields.pop().unwrap();
+        checked_public_key(n, e).map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for PrivateKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut fields = deserialize_fields(deserializer, "PrivateKey", PRIVATE_FIELDS)?;
+        Ok(PrivateKey::new(fields.pop().unwrap()))
+    }
+}
@@ -0,0 +1,300 @@
+// RSASSA-PKCS1-v1_5 and RSASSA-PSS signatures (RFC 8017, sections 8.1 and 8.2)
+//
+// Both hash the message with a HashAlg (SHA-256, -384 or -512 in practice), encode the
+// digest to the width of the modulus and raise it to d; verification raises the signature
+// to e and checks the encoding. PKCS #1 v1.5 is deterministic: EM = 0x00 || 0x01 || 0xff..
+// || 0x00 || DigestInfo, and the verifier rebuilds EM and compares all of it, the check whose
+// absence allows Bleichenbacher's e = 3 forgery. PSS mixes in a random salt as long as the
+// digest and masks it with MGF1 over the same hash, which gives it a security proof; its
+// salt comes from OsRng, or from the CryptoRng given to sign_with_rng.
+//
+// sign refuses a modulus too short for the encoding and hash, and verify reports one as a
+// malformed signature. verify says why a signature was rejected. Unlike a padding failure
+// on decryption, that tells an attacker nothing, since anyone can run verification.
+use crate::audit::Operation;
+use crate::error::RsaError;
+use crate::hash::ct_eq;
+use crate::hash_alg::HashAlg;
+use crate::{i2osp, modulus_len, os2ip, rsa_private_as, rsa_public, PrivateKey, PublicKey};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Emsa {
+    Pkcs1v15,
+    Pss,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    // Signatures are exactly as long as the modulus
+    WrongLength { expected: usize, actual: usize },
+    // The signature as an integer is not below n
+    OutOfRange,
+    // s^e is not an encoding of this scheme, e.g. a PSS trailer other than 0xbc
+    Malformed(&'static str),
+    // A well-formed encoding, of a different message or under a different hash
+    Mismatch,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::WrongLength { expected, actual } => write!(
+                f,
+                "a signature for this key is {} bytes, not {}",
+                expected, actual
+            ),
+            VerifyError::OutOfRange => write!(f, "the signature is not below the modulus"),
+            VerifyError::Malformed(reason) => write!(f, "malformed signature: {}", reason),
+            VerifyError::Mismatch => write!(f, "the signature does not match the message"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+const TOO_SHORT: &str = "the modulus is too short for this encoding and hash";
+
+// pkcs1v15_encode: EMSA-PKCS1-v1_5 of m in k bytes, if DigestInfo and eight bytes of
+// padding fit
+fn pkcs1v15_encode(m: &[u8], hash: HashAlg, k: usize) -> Option<Vec<u8>> {
+    let t = hash.digest_info(m);
+    if k < t.len() + 11 {
+        return None;
+    }
+    Some([&[0x00, 0x01][..], &vec![0xff; k - t.len() - 3], &[0x00], &t].concat())
+}
+
+// pss_h: H(0x00 x 8 || mHash || salt)
+fn pss_h(hash: HashAlg, m_hash: &[u8], salt: &[u8]) -> Vec<u8> {
+    hash.digest(&[&[0u8; 8][..], m_hash, salt].concat())
+}
+
+// pss_encode: EMSA-PSS of m for an em_bits-bit encoding, with the salt used as given, if
+// the digest and salt fit
+fn pss_encode(m: &[u8], hash: HashAlg, salt: &[u8], em_bits: usize) -> Option<Vec<u8>> {
+    let (h_len, em_len) = (hash.output_len(), em_bits.div_ceil(8));
+    if em_len < h_len + salt.len() + 2 {
+        return None;
+    }
+    let h = pss_h(hash, &hash.digest(m), salt);
+    let mut db = vec![0u8; em_len - salt.len() - h_len - 2];
+    db.push(0x01);
+    db.extend_from_slice(salt);
+    for (b, mask) in db.iter_mut().zip(hash.mgf1(&h, em_len - h_len - 1)) {
+        *b ^= mask;
+    }
+    db[0] &= 0xff >> (8 * em_len - em_bits);
+    Some([db, h, vec![0xbc]].concat())
+}
+
+fn pss_verify(m: &[u8], hash: HashAlg, em: &[u8], em_bits: usize) -> Result<(), VerifyError> {
+    let h_len = hash.output_len();
+    let em_len = em_bits.div_ceil(8);
+    if em.len() != em_len || em_len < 2 * h_len + 2 {
+        return Err(VerifyError::Malformed("too short for PSS with this hash"));
+    }
+    if em[em_len - 1] != 0xbc {
+        return Err(VerifyError::Malformed("the trailer byte is not 0xbc"));
+    }
+    let (masked_db, rest) = em.split_at(em_len - h_len - 1);
+    let h = &rest[..h_len];
+    let top_bits = 0xff >> (8 * em_len - em_bits);
+    if masked_db[0] & !top_bits != 0 {
+        return Err(VerifyError::Malformed("the leftmost bits are not zero"));
+    }
+    let mut db: Vec<u8> = masked_db
+        .iter()
+        .zip(hash.mgf1(h, masked_db.len()))
+        .map(|(b, mask)| b ^ mask)
+        .collect();
+    db[0] &= top_bits;
+    let ps_len = em_len - 2 * h_len - 2;
+    if db[..ps_len].iter().any(|&b| b != 0) || db[ps_len] != 0x01 {
+        return Err(VerifyError::Mismatch);
+    }
+    let salt = &db[ps_len + 1..];
+    if ct_eq(&pss_h(hash, &hash.digest(m), salt), h) {
+        Ok(())
+    } else {
+        Err(VerifyError::Mismatch)
+    }
+}
+
+pub fn sign(
+    pub_key: &PublicKey,
+    priv_key: &PrivateKey,
+    m: &[u8],
+    emsa: Emsa,
+    hash: HashAlg,
+) -> Result<Vec<u8>, RsaError> {
+    sign_with_rng(pub_key, priv_key, m, emsa, hash, &mut OsRng)
+}
+
+// sign_with_rng: sign, with the PSS salt drawn from rng; PKCS #1 v1.5 does not use it
+pub fn sign_with_rng(
+    pub_key: &PublicKey,
+    priv_key: &PrivateKey,
+    m: &[u8],
+    emsa: Emsa,
+    hash: HashAlg,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<Vec<u8>, RsaError> {
+    let k = modulus_len(pub_key);
+    let em = match emsa {
+        Emsa::Pkcs1v15 => pkcs1v15_encode(m, hash, k),
+        Emsa::Pss => {
+            let mut salt = vec![0u8; hash.output_len()];
+            rng.fill_bytes(&mut salt);
+            pss_encode(m, hash, &salt, pub_key.n.bits() as usize - 1)
+        }
+    };
+    let em = em.ok_or(RsaError::InvalidKey(TOO_SHORT))?;
+    let s = rsa_private_as(pub_key, priv_key, &os2ip(&em), Operation::Sign);
+    Ok(i2osp(&s, k))
+}
+
+pub fn verify(
+    pub_key: &PublicKey,
+    m: &[u8],
+    sig: &[u8],
+    emsa: Emsa,
+    hash: HashAlg,
+) -> Result<(), VerifyError> {
+    let k = modulus_len(pub_key);
+    if sig.len() != k {
+        return Err(VerifyError::WrongLength {
+            expected: k,
+            actual: sig.len(),
+        });
+    }
+    let s = os2ip(sig);
+    if s >= pub_key.n {
+        return Err(VerifyError::OutOfRange);
+    }
+    let x = rsa_public(pub_key, &s);
+    match emsa {
+        Emsa::Pkcs1v15 => {
+            let em = i2osp(&x, k);
+            if em[..2] != [0x00, 0x01] {
+                return Err(VerifyError::Malformed("not a PKCS #1 v1.5 signature block"));
+            }
+            let expected = pkcs1v15_encode(m, hash, k).ok_or(VerifyError::Malformed(TOO_SHORT))?;
+            if ct_eq(&em, &expected) {
+                Ok(())
+            } else {
+                Err(VerifyError::Mismatch)
+            }
+        }
+        Emsa::Pss => {
+            let em_bits = pub_key.n.bits() as usize - 1;
+            if x.bits() as usize > em_bits {
+                return Err(VerifyError::Malformed("s^e is wider than the encoding"));
+            }
+            pss_verify(m, hash, &i2osp(&x, em_bits.div_ceil(8)), em_bits)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::KeyPair;
+
+    const KEY: &str = include_str!("../testdata/rsa_private.pem");
+
+    #[test]
+    fn signatures_verify_under_each_encoding_and_hash() {
+        let pair: KeyPair = KEY.parse().unwrap();
+        let (pub_key, priv_key) = (pair.public(), pair.private());
+        for emsa in [Emsa::Pkcs1v15, Emsa::Pss] {
+            for hash in [HashAlg::Sha256, HashAlg::Sha384, HashAlg::Sha512] {
+                // PSS with SHA-512 and a 64-byte salt needs more than 1024 bits
+                if (emsa, hash) == (Emsa::Pss, HashAlg::Sha512) {
+                    continue;
+                }
+                let sig = sign(pub_key, priv_key, b"hello", emsa, hash).unwrap();
+                assert_eq!(sig.len(), 128);
+                assert_eq!(verify(pub_key, b"hello", &sig, emsa, hash), Ok(()));
+                assert_eq!(
+                    verify(pub_key, b"hellp", &sig, emsa, hash),
+                    Err(VerifyError::Mismatch)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pkcs1v15_is_deterministic_and_pss_is_not() {
+        let pair: KeyPair = KEY.parse().unwrap();
+        let (pub_key, priv_key) = (pair.public(), pair.private());
+        let sign_twice = |emsa| {
+            (
+                sign(pub_key, priv_key, b"m", emsa, HashAlg::Sha256).unwrap(),
+                sign(pub_key, priv_key, b"m", emsa, HashAlg::Sha256).unwrap(),
+            )
+        };
+        let (a, b) = sign_twice(Emsa::Pkcs1v15);
+        assert_eq!(a, b);
+        let (a, b) = sign_twice(Emsa::Pss);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn rejections_say_why() {
+        let pair: KeyPair = KEY.parse().unwrap();
+        let (pub_key, priv_key) = (pair.public(), pair.private());
+        let sig = sign(pub_key, priv_key, b"m", Emsa::Pss, HashAlg::Sha256).unwrap();
+        assert_eq!(
+            verify(pub_key, b"m", &sig[1..], Emsa::Pss, HashAlg::Sha256),
+            Err(VerifyError::WrongLength {
+                expected: 128,
+                actual: 127
+            })
+        );
+        assert_eq!(
+            verify(pub_key, b"m", &[0xff; 128], Emsa::Pss, HashAlg::Sha256),
+            Err(VerifyError::OutOfRange)
+        );
+        // A PSS signature is not a PKCS #1 v1.5 one, and a SHA-256 one not a SHA-384 one
+        assert!(matches!(
+            verify(pub_key, b"m", &sig, Emsa::Pkcs1v15, HashAlg::Sha256),
+            Err(VerifyError::Malformed(_))
+        ));
+        let sig = sign(pub_key, priv_key, b"m", Emsa::Pkcs1v15, HashAlg::Sha256).unwrap();
+        assert_eq!(
+            verify(pub_key, b"m", &sig, Emsa::Pkcs1v15, HashAlg::Sha384),
+            Err(VerifyError::Mismatch)
+        );
+    }
+
+    #[test]
+    fn short_moduli_are_refused_not_panicked_on() {
+        let (pub_key, priv_key) = crate::keygen::KeyGenParams::bits(512)
+            .generate()
+            .unwrap()
+            .0
+            .into_keys();
+        for emsa in [Emsa::Pkcs1v15, Emsa::Pss] {
+            assert_eq!(
+                sign(&pub_key, &priv_key, b"m", emsa, HashAlg::Sha512),
+                Err(RsaError::InvalidKey(TOO_SHORT))
+            );
+        }
+        // A forged s whose s^e starts 00 01 reaches the encoder on verification
+        let x = os2ip(&[&[0x00, 0x01][..], &[0xff; 62]].concat());
+        let s = crate::rsa_private(&pub_key, &priv_key, &x);
+        assert_eq!(
+            verify(
+                &pub_key,
+                b"m",
+                &i2osp(&s, 64),
+                Emsa::Pkcs1v15,
+                HashAlg::Sha512
+            ),
+            Err(VerifyError::Malformed(TOO_SHORT))
+        );
+    }
+}
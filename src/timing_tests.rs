@@ -0,0 +1,194 @@
+// Statistical checks that the padding decoders do not leak why an input was rejected
+//
+// Wall-clock measurements are noisy, so these tests only run with
+// `cargo test --release --features timing-tests`. Each case is a family of encoded messages
+// that passes decoding or fails it at a different step. Batches of decodes are timed with
+// the cases interleaved, so that drift in machine load hits all of them alike, and the
+// median batch time of every case must stay within TOLERANCE of the valid one. A Manger or
+// Bleichenbacher style attack needs exactly such a difference, most of all between a bad
+// leading byte and the later checks.
+use crate::hash_alg::HashAlg;
+use crate::oaep::{self, Oaep};
+use crate::pkcs1v15;
+use crate::{encrypt, gen_keys_with_digits, i2osp, modulus_len, os2ip, random_bytes};
+use rand::Rng;
+use std::hint::black_box;
+use std::time::Instant;
+
+const EM_LEN: usize = 256;
+const BATCHES: usize = 301;
+const BATCH_SIZE: usize = 20;
+const TOLERANCE: f64 = 0.2;
+const LABEL: &[u8] = b"timing";
+
+// An encoded message family: a name for reports and a fresh k-byte member on every call
+struct Case {
+    name: &'static str,
+    make: fn(usize) -> Vec<u8>,
+}
+
+fn valid_em(k: usize) -> Vec<u8> {
+    let m = random_bytes(rand::thread_rng().gen_range(0..=32));
+    Oaep::new(HashAlg::Sha256)
+        .encode(k, &m, LABEL, &random_bytes(32))
+        .unwrap()
+}
+
+fn oaep_cases() -> Vec<Case> {
+    vec![
+        Case {
+            name: "valid",
+            make: valid_em,
+        },
+        Case {
+            name: "nonzero leading byte",
+            make: |k| {
+                let mut em = valid_em(k);
+                em[0] = 0x01;
+                em
+            },
+        },
+        Case {
+            name: "wrong label",
+            make: |k| {
+                Oaep::new(HashAlg::Sha256)
+                    .encode(k, b"m", b"other label", &random_bytes(32))
+                    .unwrap()
+            },
+        },
+        Case {
+            name: "random bytes",
+            make: |k| [&[0x00][..], &random_bytes(k - 1)].concat(),
+        },
+    ]
+}
+
+fn valid_v15_em(k: usize) -> Vec<u8> {
+    let m = random_bytes(rand::thread_rng().gen_range(0..=32));
+    pkcs1v15::encode(k, &m, &mut rand::thread_rng())
+}
+
+// nonzero_bytes: len random bytes in 1..=255, as PS is made of
+fn nonzero_bytes(len: usize) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    (0..len).map(|_| rng.gen_range(1..=255)).collect()
+}
+
+fn pkcs1v15_cases() -> Vec<Case> {
+    vec![
+        Case {
+            name: "valid",
+            make: valid_v15_em,
+        },
+        Case {
+            name: "bad leading byte",
+            make: |k| {
+                let mut em = valid_v15_em(k);
+                em[0] = 0x01;
+                em
+            },
+        },
+        Case {
+            name: "missing separator",
+            make: |k| [&[0x00, 0x02][..], &nonzero_bytes(k - 2)].concat(),
+        },
+        Case {
+            name: "short padding",
+            make: |k| pkcs1v15::encode_with_padding(&random_bytes(k - 10), &nonzero_bytes(7)),
+        },
+    ]
+}
+
+// median_batch_times: the median time, in nanoseconds, of a batch of decodes per case
+fn median_batch_times(cases: &[Case], decode: impl Fn(&[u8]) -> bool) -> Vec<f64> {
+    let inputs: Vec<Vec<Vec<u8>>> = cases
+        .iter()
+        .map(|case| (0..BATCH_SIZE).map(|_| (case.make)(EM_LEN)).collect())
+        .collect();
+    let mut times = vec![Vec::with_capacity(BATCHES); cases.len()];
+    for batch in 0..BATCHES {
+        for offset in 0..cases.len() {
+            let i = (batch + offset) % cases.len();
+            let start = Instant::now();
+            for em in &inputs[i] {
+                black_box(decode(black_box(em)));
+            }
+            times[i].push(start.elapsed().as_nanos() as f64);
+        }
+    }
+    times
+        .into_iter()
+        .map(|mut t| {
+            t.sort_by(f64::total_cmp);
+            t[t.len() / 2]
+        })
+        .collect()
+}
+
+fn assert_indistinguishable(cases: &[Case], decode: impl Fn(&[u8]) -> bool) {
+    let medians = median_batch_times(cases, decode);
+    let report: Vec<String> = cases
+        .iter()
+        .zip(&medians)
+        .map(|(case, t)| format!("{}: {:.0}ns", case.name, t))
+        .collect();
+    for t in &medians[1..] {
+        let ratio = t / medians[0];
+        assert!(
+            (ratio - 1.0).abs() <= TOLERANCE,
+            "decode timings differ by more than {}%: {}",
+            TOLERANCE * 100.0,
+            report.join(", ")
+        );
+    }
+}
+
+#[test]
+fn oaep_decode_timing_does_not_depend_on_the_failure() {
+    assert_indistinguishable(&oaep_cases(), |em| {
+        Oaep::new(HashAlg::Sha256).decode(em, LABEL).is_some()
+    });
+}
+
+#[test]
+fn oaep_decrypt_fails_the_same_way_for_every_bad_padding() {
+    let (pub_key, priv_key) = gen_keys_with_digits(100);
+    let k = modulus_len(&pub_key);
+    for case in &oaep_cases()[1..] {
+        let em = os2ip(&(case.make)(k));
+        if em >= pub_key.n {
+            continue;
+        }
+        let c = i2osp(encrypt(&pub_key, em.into()).unwrap().as_bigint(), k);
+        assert_eq!(
+            oaep::decrypt(&pub_key, &priv_key, &c, LABEL),
+            None,
+            "{}",
+            case.name
+        );
+    }
+}
+
+#[test]
+fn pkcs1v15_decode_timing_does_not_depend_on_the_failure() {
+    assert_indistinguishable(&pkcs1v15_cases(), |em| pkcs1v15::decode(em).is_some());
+}
+
+#[test]
+fn pkcs1v15_decrypt_fails_the_same_way_for_every_bad_padding() {
+    let (pub_key, priv_key) = gen_keys_with_digits(100);
+    let k = modulus_len(&pub_key);
+    for case in &pkcs1v15_cases()[1..] {
+        let em = os2ip(&(case.make)(k));
+        if em >= pub_key.n {
+            continue;
+        }
+        let c = i2osp(encrypt(&pub_key, em.into()).unwrap().as_bigint(), k);
+        assert_eq!(
+            pkcs1v15::decrypt(&pub_key, &priv_key, &c),
+            None,
+            "{}",
+            case.name
+        );
+    }
+}
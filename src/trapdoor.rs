@@ -0,0 +1,127 @@
+// Trapdoor permutations, the abstraction the signature protocols are written against
+//
+// A trapdoor permutation is public and easy to compute, and hard to invert without a
+// secret: RSA is x -> x^e mod n on [0, n), with d as the trapdoor. FDH, blind and
+// aggregate signatures only ever call forward, inverse and domain, so they take any
+// TrapdoorPermutation and another instantiation (Rabin squaring on quadratic residues,
+// say) can reuse them unchanged. Multiplicative marks the permutations with
+// f(a) f(b) = f(ab), which blind signatures need.
+//
+// extended_forward and extended_inverse stretch a permutation of [0, N) to one of the
+// b-bit integers, as ring and aggregate signatures use to put keys of different sizes on a
+// common domain.
+use crate::wire::Writer;
+use crate::{rsa_private, rsa_public, PrivateKey, PublicKey};
+use num_bigint::BigInt;
+
+pub trait TrapdoorPermutation {
+    type Trapdoor;
+
+    // domain: the permutation acts on [0, domain)
+    fn domain(&self) -> &BigInt;
+
+    fn forward(&self, x: &BigInt) -> BigInt;
+
+    fn inverse(&self, trapdoor: &Self::Trapdoor, y: &BigInt) -> BigInt;
+
+    // encode: bytes identifying the permutation, which protocols hash into their transcripts
+    fn encode(&self) -> Vec<u8>;
+
+    // is_well_formed: whether the public parameters make sense beyond the domain, for
+    // protocols that take keys from others
+    fn is_well_formed(&self) -> bool {
+        true
+    }
+}
+
+// Multiplicative: forward(a b mod domain) = forward(a) forward(b) mod domain
+pub trait Multiplicative: TrapdoorPermutation {}
+
+impl TrapdoorPermutation for PublicKey {
+    type Trapdoor = PrivateKey;
+
+    fn domain(&self) -> &BigInt {
+        &self.n
+    }
+
+    fn forward(&self, x: &BigInt) -> BigInt {
+        rsa_public(self, x)
+    }
+
+    fn inverse(&self, trapdoor: &PrivateKey, y: &BigInt) -> BigInt {
+        rsa_private(self, trapdoor, y)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        Writer::new("naive-rsa public key")
+            .int(&self.n)
+            .int(&self.e)
+            .finish()
+    }
+
+    // is_well_formed: e = 1 is the identity and e = 2 is not a permutation of Z_n
+    fn is_well_formed(&self) -> bool {
+        self.e >= BigInt::from(3)
+    }
+}
+
+impl Multiplicative for PublicKey {}
+
+// extended_forward: the permutation on the full copies of its domain inside [0, 2^(8 len)),
+// the identity on the rest
+pub(crate) fn extended_forward<P: TrapdoorPermutation>(perm: &P, x: &BigInt, len: usize) -> BigInt {
+    extend(perm.domain(), x, len, |r| perm.forward(r))
+}
+
+pub(crate) fn extended_inverse<P: TrapdoorPermutation>(
+    perm: &P,
+    trapdoor: &P::Trapdoor,
+    y: &BigInt,
+    len: usize,
+) -> BigInt {
+    extend(perm.domain(), y, len, |r| perm.inverse(trapdoor, r))
+}
+
+fn extend(n: &BigInt, x: &BigInt, len: usize, f: impl Fn(&BigInt) -> BigInt) -> BigInt {
+    let (q, r) = (x / n, x % n);
+    if (&q + 1) * n <= BigInt::from(1) << (8 * len) {
+        q * n + f(&r)
+    } else {
+        x.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn rsa_is_a_trapdoor_permutation() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let x = BigInt::from(123456);
+        let y = pub_key.forward(&x);
+        assert_ne!(y, x);
+        assert_eq!(pub_key.inverse(&priv_key, &y), x);
+        let (a, b) = (BigInt::from(11), BigInt::from(13));
+        let n = pub_key.domain();
+        assert_eq!(
+            pub_key.forward(&(&a * &b % n)),
+            pub_key.forward(&a) * pub_key.forward(&b) % n
+        );
+    }
+
+    #[test]
+    fn extended_permutations_invert() {
+        let (pub_key, priv_key) = gen_keys_with_digits(20);
+        let len = 40;
+        for x in [
+            BigInt::from(5),
+            pub_key.domain() * 3 + 7,
+            (BigInt::from(1) << (8 * len)) - 1,
+        ] {
+            let y = extended_forward(&pub_key, &x, len);
+            assert_eq!(extended_inverse(&pub_key, &priv_key, &y, len), x);
+        }
+    }
+}
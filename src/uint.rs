@@ -0,0 +1,156 @@
+// The big-integer operations the crate depends on, as a trait
+//
+// Everything that would differ between big-integer libraries, or that a constant-time or
+// WASM-tuned backend would want to replace, goes through Uint: modular exponentiation and
+// inversion, sampling in a range and conversion to and from big-endian bytes. num-bigint's
+// BigInt implements it and is the backend the rest of the crate is written against.
+// ModRing, random_unit, mod_inverse, i2osp and os2ip call these methods rather than
+// BigInt's own, and so do the schemes built on them. The modules that do their own
+// arithmetic still call BigInt::modpow directly: accumulator, attacks, backup, batch, bbs,
+// commitment, components, dvs, exponents, fault, gm, ibs, mrsa, okamoto_uchiyama,
+// plaintext_proof, proxy and well_formed. Another backend (rug/GMP, crypto-bigint) is added
+// by implementing Uint for its type and switching the call sites above and in those
+// modules. Values are non-negative; the BigInt impl ignores the sign in to_be_bytes.
+use crate::extended_gcd;
+use num_bigint::{BigInt, RandBigInt, Sign};
+use num_traits::One;
+use rand::{CryptoRng, RngCore};
+
+pub(crate) trait Uint: Clone + Eq + Ord + Sized {
+    fn from_u64(x: u64) -> Self;
+
+    // from_be_bytes: a big-endian byte string, any length, as an integer
+    fn from_be_bytes(bytes: &[u8]) -> Self;
+
+    // to_be_bytes: the shortest big-endian encoding, empty for zero
+    fn to_be_bytes(&self) -> Vec<u8>;
+
+    // pow_mod: self^exponent mod modulus, for modulus > 1
+    fn pow_mod(&self, exponent: &Self, modulus: &Self) -> Self;
+
+    // inv_mod: the x in [0, modulus) with self * x = 1, if gcd(self, modulus) = 1
+    fn inv_mod(&self, modulus: &Self) -> Option<Self>;
+
+    // random_range: uniform in [low, high), for low < high
+    fn random_range(low: &Self, high: &Self, rng: &mut (impl CryptoRng + RngCore)) -> Self;
+}
+
+impl Uint for BigInt {
+    fn from_u64(x: u64) -> Self {
+        BigInt::from(x)
+    }
+
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        BigInt::from_bytes_be(Sign::Plus, bytes)
+    }
+
+    fn to_be_bytes(&self) -> Vec<u8> {
+        match self.sign() {
+            Sign::NoSign => Vec::new(),
+            _ => self.to_bytes_be().1,
+        }
+    }
+
+    fn pow_mod(&self, exponent: &Self, modulus: &Self) -> Self {
+        self.modpow(exponent, modulus)
+    }
+
+    fn inv_mod(&self, modulus: &Self) -> Option<Self> {
+        let (gcd, (s, _), _) = extended_gcd(self.clone(), modulus.clone());
+        if !gcd.is_one() {
+            return None;
+        }
+        let s = s % modulus;
+        Some(if s.sign() == Sign::Minus {
+            s + modulus
+        } else {
+            s
+        })
+    }
+
+    fn random_range(low: &Self, high: &Self, rng: &mut (impl CryptoRng + RngCore)) -> Self {
+        rng.gen_bigint_range(low, high)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    // A second backend on machine words, enough for textbook-sized keys
+    impl Uint for u64 {
+        fn from_u64(x: u64) -> Self {
+            x
+        }
+
+        fn from_be_bytes(bytes: &[u8]) -> Self {
+            bytes.iter().fold(0, |acc, &b| (acc << 8) | u64::from(b))
+        }
+
+        fn to_be_bytes(&self) -> Vec<u8> {
+            let bytes = u64::to_be_bytes(*self);
+            let skip = bytes.iter().take_while(|&&b| b == 0).count();
+            bytes[skip..].to_vec()
+        }
+
+        fn pow_mod(&self, exponent: &Self, modulus: &Self) -> Self {
+            let m = u128::from(*modulus);
+            let (mut base, mut e, mut acc) = (u128::from(*self) % m, *exponent, 1 % m);
+            while e > 0 {
+                if e & 1 == 1 {
+                    acc = acc * base % m;
+                }
+                base = base * base % m;
+                e >>= 1;
+            }
+            acc as u64
+        }
+
+        fn inv_mod(&self, modulus: &Self) -> Option<Self> {
+            let (mut r0, mut r1) = (i128::from(*modulus), i128::from(*self));
+            let (mut t0, mut t1) = (0i128, 1i128);
+            while r1 != 0 {
+                let q = r0 / r1;
+                (r0, r1) = (r1, r0 - q * r1);
+                (t0, t1) = (t1, t0 - q * t1);
+            }
+            (r0 == 1).then(|| t0.rem_euclid(i128::from(*modulus)) as u64)
+        }
+
+        fn random_range(low: &Self, high: &Self, rng: &mut (impl CryptoRng + RngCore)) -> Self {
+            rng.gen_range(*low..*high)
+        }
+    }
+
+    // textbook_round_trip: written once against Uint, run on each backend
+    fn textbook_round_trip<U: Uint>() {
+        // n = 61 * 53, phi = 3120
+        let (n, e, phi) = (U::from_u64(3233), U::from_u64(17), U::from_u64(3120));
+        let d = e.inv_mod(&phi).unwrap();
+        assert!(d == U::from_u64(2753));
+        for _ in 0..20 {
+            let m = U::random_range(&U::from_u64(0), &n, &mut OsRng);
+            assert!(m.pow_mod(&e, &n).pow_mod(&d, &n) == m);
+        }
+        assert!(U::from_u64(6).inv_mod(&U::from_u64(9)).is_none());
+        assert_eq!(U::from_u64(3233).to_be_bytes(), vec![0x0c, 0xa1]);
+        assert!(U::from_be_bytes(&[0, 0x0c, 0xa1]) == n);
+        assert!(U::from_u64(0).to_be_bytes().is_empty());
+    }
+
+    #[test]
+    fn bigint_backend_does_textbook_rsa() {
+        textbook_round_trip::<BigInt>();
+    }
+
+    #[test]
+    fn any_backend_runs_the_same_code() {
+        textbook_round_trip::<u64>();
+        assert_eq!(
+            BigInt::from_u64(1234567).pow_mod(&BigInt::from(65537), &BigInt::from(99991)),
+            BigInt::from(1234567u64.pow_mod(&65537, &99991))
+        );
+    }
+}
@@ -0,0 +1,242 @@
+// Known-answer test vectors
+//
+// generate makes a key and count encryptions under it, recording everything an independent
+// implementation needs to reproduce each ciphertext exactly: the key, the message and the
+// randomness the scheme consumed (the OAEP seed, or the PKCS #1 v1.5 padding string PS).
+// Raw vectors also carry the textbook signature m^d. to_json writes them as
+//
+//   {"scheme": "oaep-sha256", "seed": "...",
+//    "key": {"n": "...", "e": "...", "d": "...", "p": "...", "q": "..."},
+//    "vectors": [{"message": "...", "seed": "...", "ciphertext": "..."}, ...]}
+//
+// with every value in lowercase hex, integers big-endian without leading zeros. The same
+// 32-byte seed always gives the same file, so a set of vectors can be regenerated rather
+// than stored.
+use crate::hash::TranscriptHash;
+use crate::hash_alg::HashAlg;
+use crate::keygen_transcript::gen_keys_from_seed;
+use crate::oaep::Oaep;
+use crate::{i2osp, modulus_len, os2ip, pkcs1v15, rsa_private, rsa_public, PrivateKey, PublicKey};
+use num_bigint::BigInt;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VectorScheme {
+    // Textbook RSA on the message as a big-endian integer
+    Raw,
+    Pkcs1v15,
+    // OAEP with SHA-256 and an empty label
+    Oaep,
+}
+
+impl VectorScheme {
+    pub const ALL: [VectorScheme; 3] = [
+        VectorScheme::Raw,
+        VectorScheme::Pkcs1v15,
+        VectorScheme::Oaep,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            VectorScheme::Raw => "raw",
+            VectorScheme::Pkcs1v15 => "pkcs1v15",
+            VectorScheme::Oaep => "oaep",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        VectorScheme::ALL.into_iter().find(|s| s.name() == name)
+    }
+
+    fn max_message_len(self, pub_key: &PublicKey) -> usize {
+        match self {
+            VectorScheme::Raw => (pub_key.n.bits() as usize - 1) / 8,
+            VectorScheme::Pkcs1v15 => pkcs1v15::max_message_len(pub_key),
+            VectorScheme::Oaep => crate::oaep::max_message_len(pub_key),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Vector {
+    pub message: Vec<u8>,
+    // The randomness the encryption used; empty for raw vectors
+    pub seed: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    // m^d, for raw vectors only
+    pub signature: Option<Vec<u8>>,
+}
+
+pub struct VectorSet {
+    pub scheme: VectorScheme,
+    pub seed: [u8; 32],
+    pub public: PublicKey,
+    pub private: PrivateKey,
+    pub primes: (BigInt, BigInt),
+    pub vectors: Vec<Vector>,
+}
+
+// generate: count vectors under a key of ndigits primes, all determined by seed; None if
+// the key is too small for the scheme to hold any message
+pub fn generate(
+    scheme: VectorScheme,
+    count: usize,
+    ndigits: u32,
+    seed: &[u8; 32],
+) -> Option<VectorSet> {
+    let key_seed: [u8; 32] = TranscriptHash::new("naive-rsa vectors key")
+        .bytes(seed)
+        .finish()
+        .try_into()
+        .unwrap();
+    let (public, private, transcript) = gen_keys_from_seed(&key_seed, ndigits);
+    let max_len = scheme.max_message_len(&public);
+    if max_len == 0 {
+        return None;
+    }
+    let mut rng = StdRng::from_seed(*seed);
+    let k = modulus_len(&public);
+    let vectors = (0..count)
+        .map(|_| {
+            let mut message = vec![0u8; rng.gen_range(1..=max_len)];
+            rng.fill_bytes(&mut message);
+            match scheme {
+                VectorScheme::Raw => {
+                    let m = os2ip(&message);
+                    Vector {
+                        seed: Vec::new(),
+                        ciphertext: i2osp(&rsa_public(&public, &m), k),
+                        signature: Some(i2osp(&rsa_private(&public, &private, &m), k)),
+                        message,
+                    }
+                }
+                VectorScheme::Pkcs1v15 => {
+                    let ps = pkcs1v15::padding(k, message.len(), &mut rng);
+                    let em = os2ip(&pkcs1v15::encode_with_padding(&message, &ps));
+                    Vector {
+                        ciphertext: i2osp(&rsa_public(&public, &em), k),
+                        seed: ps,
+                        signature: None,
+                        message,
+                    }
+                }
+                VectorScheme::Oaep => {
+                    let oaep = Oaep::new(HashAlg::Sha256);
+                    let mut oaep_seed = vec![0u8; oaep.hash().output_len()];
+                    rng.fill_bytes(&mut oaep_seed);
+                    Vector {
+                        ciphertext: oaep
+                            .encrypt_with_seed(&public, &message, b"", &oaep_seed)
+                            .expect("messages are at most max_message_len bytes"),
+                        seed: oaep_seed,
+                        signature: None,
+                        message,
+                    }
+                }
+            }
+        })
+        .collect();
+    let (p, q) = transcript.primes();
+    Some(VectorSet {
+        scheme,
+        seed: *seed,
+        primes: (p.prime().clone(), q.prime().clone()),
+        public,
+        private,
+        vectors,
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn int_hex(x: &BigInt) -> String {
+    x.to_str_radix(16)
+}
+
+impl VectorSet {
+    // check: whether the private key decrypts every vector back to its message
+    pub fn check(&self) -> bool {
+        self.vectors.iter().all(|v| {
+            let m = match self.scheme {
+                VectorScheme::Raw => Some(i2osp(
+                    &rsa_private(&self.public, &self.private, &os2ip(&v.ciphertext)),
+                    v.message.len(),
+                )),
+                VectorScheme::Pkcs1v15 => {
+                    pkcs1v15::decrypt(&self.public, &self.private, &v.ciphertext)
+                }
+                VectorScheme::Oaep => {
+                    crate::oaep::decrypt(&self.public, &self.private, &v.ciphertext, b"")
+                }
+            };
+            m.as_deref() == Some(&v.message[..])
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let scheme = match self.scheme {
+            VectorScheme::Oaep => "oaep-sha256",
+            scheme => scheme.name(),
+        };
+        let mut out = format!(
+            "{{\n  \"scheme\": \"{}\",\n  \"seed\": \"{}\",\n",
+            scheme,
+            hex(&self.seed)
+        );
+        out += &format!(
+            "  \"key\": {{\"n\": \"{}\", \"e\": \"{}\", \"d\": \"{}\", \"p\": \"{}\", \"q\": \"{}\"}},\n",
+            int_hex(&self.public.n),
+            int_hex(&self.public.e),
+            int_hex(&self.private.d),
+            int_hex(&self.primes.0),
+            int_hex(&self.primes.1)
+        );
+        out += "  \"vectors\": [";
+        for (i, v) in self.vectors.iter().enumerate() {
+            out += if i == 0 { "\n" } else { ",\n" };
+            out += &format!(
+                "    {{\"message\": \"{}\", \"seed\": \"{}\", \"ciphertext\": \"{}\"",
+                hex(&v.message),
+                hex(&v.seed),
+                hex(&v.ciphertext)
+            );
+            if let Some(signature) = &v.signature {
+                out += &format!(", \"signature\": \"{}\"", hex(signature));
+            }
+            out += "}";
+        }
+        out + "\n  ]\n}\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vectors_are_reproducible_and_decrypt() {
+        for scheme in VectorScheme::ALL {
+            let set = generate(scheme, 5, 50, &[7; 32]).unwrap();
+            assert_eq!(set.vectors.len(), 5);
+            assert!(set.check(), "{:?}", scheme);
+            let again = generate(scheme, 5, 50, &[7; 32]).unwrap();
+            assert_eq!(again.vectors, set.vectors);
+            assert_eq!(again.to_json(), set.to_json());
+        }
+    }
+
+    #[test]
+    fn json_lists_every_vector() {
+        let set = generate(VectorScheme::Raw, 3, 20, &[1; 32]).unwrap();
+        let json = set.to_json();
+        assert!(json.starts_with("{\n  \"scheme\": \"raw\",\n  \"seed\": \"0101"));
+        assert_eq!(json.matches("\"message\"").count(), 3);
+        assert_eq!(json.matches("\"signature\"").count(), 3);
+        assert!(json.contains(&format!("\"n\": \"{}\"", set.public.n.to_str_radix(16))));
+        // 20-digit keys are too small for OAEP's two hashes
+        assert!(generate(VectorScheme::Oaep, 1, 20, &[1; 32]).is_none());
+    }
+}
@@ -0,0 +1,336 @@
+// Non-interactive proof that a modulus is the product of two distinct primes
+//
+// Following Goldberg, Reyzin, Sagga and Baldimtsi ("Efficient noninteractive certification
+// of RSA moduli and beyond", 2019), for n = pq with p = q = 3 mod 4. Challenges y_i are
+// derived by hashing n, so the prover cannot choose them:
+// - an n-th root of each of the first few y_i shows gcd(n, phi(n)) = 1, so no prime appears
+//   twice; a square factor p^2 leaves at most a 1/p fraction of elements with n-th roots,
+//   and trial division up to small_factor_bound makes that fraction small
+// - a fourth root of one of y_i, -y_i, w y_i, -w y_i, for a published w with Jacobi
+//   symbol -1, shows that n has at most two prime factors: with three or more, at least
+//   half of the challenges have no fourth root among the four
+// The verifier also checks that n is odd, is not prime, and has exactly twice the claimed
+// prime size in bits. The proof does not show that the two primes are of equal size; only
+// that neither is below small_factor_bound.
+// https://eprint.iacr.org/2018/057
+use crate::error::ParseError;
+use crate::hash::{self, TranscriptHash};
+use crate::keypair::KeyPair;
+use crate::wire::{Reader, Writer};
+use crate::{
+    extended_gcd, invert, is_probable_prime, jacobi, random_prime, random_unit, PublicKey,
+};
+use num_bigint::BigInt;
+use num_traits::{One, Zero};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WellFormedParams {
+    security_bits: u64,
+    small_factor_bound: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WellFormedProof {
+    prime_bits: u64,
+    w: BigInt,
+    // n-th roots of the first challenges
+    z: Vec<BigInt>,
+    // Per challenge: whether -1 and w were multiplied in, and the fourth root
+    signs: Vec<(bool, bool)>,
+    x: Vec<BigInt>,
+}
+
+impl Default for WellFormedParams {
+    fn default() -> Self {
+        WellFormedParams::new()
+    }
+}
+
+impl WellFormedParams {
+    // new: 128-bit soundness, trial division below 2^16
+    pub fn new() -> Self {
+        WellFormedParams {
+            security_bits: 128,
+            small_factor_bound: 1 << 16,
+        }
+    }
+
+    pub fn security_bits(self, security_bits: u64) -> Self {
+        WellFormedParams {
+            security_bits,
+            ..self
+        }
+    }
+
+    // small_factor_bound: the verifier divides n by every odd number below bound; larger
+    // bounds need fewer n-th roots
+    pub fn small_factor_bound(self, bound: u32) -> Self {
+        if bound < 3 {
+            panic!("the small factor bound must be at least 3, not {}", bound);
+        }
+        WellFormedParams {
+            small_factor_bound: bound,
+            ..self
+        }
+    }
+
+    // Each n-th root catches a square factor p^2 with p > bound except with probability 1/bound
+    fn root_rounds(&self) -> usize {
+        let bound_bits = (u32::BITS - self.small_factor_bound.leading_zeros() - 1) as u64;
+        self.security_bits.div_ceil(bound_bits) as usize
+    }
+
+    // Each fourth root catches more than two prime factors except with probability 1/2
+    fn fourth_root_rounds(&self) -> usize {
+        self.security_bits as usize
+    }
+}
+
+// gen_provable_key_pair: a key pair from two prime_bits primes that are 3 mod 4, as prove
+// needs
+pub fn gen_provable_key_pair(prime_bits: u64) -> KeyPair {
+    let mut rng = rand::thread_rng();
+    let e = BigInt::from(65537);
+    let mut blum_prime = || loop {
+        let p = random_prime(prime_bits, &mut rng);
+        if &p % 4u32 == BigInt::from(3) && !((&p - 1u32) % &e).is_zero() {
+            return p;
+        }
+    };
+    let p = blum_prime();
+    let q = loop {
+        let q = blum_prime();
+        if q != p {
+            break q;
+        }
+    };
+    KeyPair::from_primes(p, q, e)
+}
+
+fn challenges(
+    n: &BigInt,
+    prime_bits: u64,
+    w: &BigInt,
+    count: usize,
+    context: &[u8],
+) -> Vec<BigInt> {
+    (0..count as u32)
+        .map(|i| {
+            let seed = TranscriptHash::new("naive-rsa well-formed modulus")
+                .int(n)
+                .bytes(&prime_bits.to_be_bytes())
+                .int(w)
+                .bytes(&i.to_be_bytes())
+                .bytes(context)
+                .finish();
+            hash::full_domain(&seed, n)
+        })
+        .collect()
+}
+
+fn is_qr(a: &BigInt, p: &BigInt) -> bool {
+    jacobi(&(a % p), p) == 1
+}
+
+// fourth_root: the fourth root of a that is itself a square, mod a prime p = 3 mod 4
+fn fourth_root(a: &BigInt, p: &BigInt) -> BigInt {
+    let e: BigInt = (p + 1u32) / 4u32;
+    a.modpow(&(&e * &e), p)
+}
+
+// prove: prove that the key pair's modulus is the product of its two primes; context binds
+// the proof to e.g. a ceremony
+pub fn prove(params: &WellFormedParams, pair: &KeyPair, context: &[u8]) -> WellFormedProof {
+    let (p, q) = pair
+        .primes()
+        .expect("proving a modulus well formed needs its primes");
+    let three = BigInt::from(3);
+    if &p % 4u32 != three || &q % 4u32 != three {
+        panic!("both primes must be 3 mod 4, as from gen_provable_key_pair");
+    }
+    let n = &pair.public().n;
+    let phi: BigInt = (&p - 1u32) * (&q - 1u32);
+    if !extended_gcd(n.clone(), phi.clone()).0.is_one() {
+        panic!("n shares a factor with phi(n), so it has no n-th roots to give");
+    }
+    let n_inv = invert(n.clone(), phi);
+    // w: a square mod q but not mod p, so (w/n) = -1
+    let w = loop {
+        let w = random_unit(n);
+        if !is_qr(&w, &p) && is_qr(&w, &q) {
+            break w;
+        }
+    };
+    let prime_bits = p.bits();
+    let rounds = params.root_rounds().max(params.fourth_root_rounds());
+    let y = challenges(n, prime_bits, &w, rounds, context);
+    let z = y[..params.root_rounds()]
+        .iter()
+        .map(|y| y.modpow(&n_inv, n))
+        .collect();
+    let crt = pair
+        .crt()
+        .expect("the primes of a well-formed modulus are coprime");
+    let (mut signs, mut x) = (Vec::new(), Vec::new());
+    for y in &y[..params.fourth_root_rounds()] {
+        // -1 is a non-square mod both primes, w only mod p
+        let negate = !is_qr(y, &q);
+        let y_signed = if negate { n - y } else { y.clone() };
+        let times_w = !is_qr(&y_signed, &p);
+        let target = if times_w {
+            &y_signed * &w % n
+        } else {
+            y_signed
+        };
+        let (xp, xq) = (fourth_root(&target, &p), fourth_root(&target, &q));
+        x.push(crt.recombine(xp, &xq));
+        signs.push((negate, times_w));
+    }
+    WellFormedProof {
+        prime_bits,
+        w,
+        z,
+        signs,
+        x,
+    }
+}
+
+pub fn verify(
+    params: &WellFormedParams,
+    pub_key: &PublicKey,
+    proof: &WellFormedProof,
+    context: &[u8],
+) -> bool {
+    let n = &pub_key.n;
+    let one = BigInt::one();
+    let bound = params.small_factor_bound;
+    if n.bits() != 2 * proof.prime_bits
+        || *n <= BigInt::from(bound)
+        || (3..bound).step_by(2).any(|f| (n % f).is_zero())
+        || (n % 2u32).is_zero()
+        || is_probable_prime(n.clone(), 40)
+        || proof.z.len() != params.root_rounds()
+        || proof.x.len() != params.fourth_root_rounds()
+        || proof.signs.len() != proof.x.len()
+        || proof.w <= one
+        || proof.w >= *n
+        || jacobi(&proof.w, n) != -1
+    {
+        return false;
+    }
+    let rounds = proof.z.len().max(proof.x.len());
+    let y = challenges(n, proof.prime_bits, &proof.w, rounds, context);
+    let roots_ok = proof.z.iter().zip(&y).all(|(z, y)| z.modpow(n, n) == *y);
+    let fourth_roots_ok =
+        proof
+            .x
+            .iter()
+            .zip(&proof.signs)
+            .zip(&y)
+            .all(|((x, &(negate, times_w)), y)| {
+                let mut target = if negate { (n - y) % n } else { y.clone() };
+                if times_w {
+                    target = target * &proof.w % n;
+                }
+                x.modpow(&BigInt::from(4), n) == target
+            });
+    roots_ok && fourth_roots_ok
+}
+
+impl WellFormedProof {
+    // prime_bits: the claimed size of each prime
+    pub fn prime_bits(&self) -> u64 {
+        self.prime_bits
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::new("naive-rsa well-formed modulus proof");
+        w.bytes(&self.prime_bits.to_be_bytes()).int(&self.w);
+        w.bytes(&(self.z.len() as u32).to_be_bytes());
+        for z in &self.z {
+            w.int(z);
+        }
+        w.bytes(&(self.x.len() as u32).to_be_bytes());
+        for (x, &(negate, times_w)) in self.x.iter().zip(&self.signs) {
+            w.bytes(&[u8::from(negate) | u8::from(times_w) << 1]).int(x);
+        }
+        w.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        const FORMAT: &str = "naive-rsa well-formed modulus proof";
+        let mut r = Reader::new(bytes, FORMAT)?;
+        let offset = r.offset();
+        let prime_bits: [u8; 8] = r
+            .bytes()?
+            .try_into()
+            .map_err(|_| ParseError::invalid(FORMAT, offset, "the prime size must be 8 bytes"))?;
+        let prime_bits = u64::from_be_bytes(prime_bits);
+        let w = r.int()?;
+        let z = (0..r.count()?).map(|_| r.int()).collect::<Result<_, _>>()?;
+        let (mut signs, mut x) = (Vec::new(), Vec::new());
+        for _ in 0..r.count()? {
+            let offset = r.offset();
+            match r.bytes()? {
+                &[flags] if flags < 4 => signs.push((flags & 1 == 1, flags & 2 == 2)),
+                _ => return Err(ParseError::invalid(FORMAT, offset, "bad sign flags")),
+            }
+            x.push(r.int()?);
+        }
+        r.finish()?;
+        Ok(WellFormedProof {
+            prime_bits,
+            w,
+            z,
+            signs,
+            x,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_keys_with_digits;
+
+    #[test]
+    fn honest_moduli_are_accepted() {
+        let params = WellFormedParams::new();
+        let pair = gen_provable_key_pair(128);
+        let proof = prove(&params, &pair, b"setup 1");
+        assert_eq!(proof.prime_bits(), 128);
+        assert!(verify(&params, pair.public(), &proof, b"setup 1"));
+        assert!(!verify(&params, pair.public(), &proof, b"setup 2"));
+        let decoded = WellFormedProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(decoded, proof);
+    }
+
+    #[test]
+    fn proof_is_bound_to_its_modulus() {
+        let params = WellFormedParams::new().security_bits(64);
+        let proof = prove(&params, &gen_provable_key_pair(64), b"");
+        let other = gen_provable_key_pair(64);
+        assert!(!verify(&params, other.public(), &proof, b""));
+        let (unrelated, _) = gen_keys_with_digits(20);
+        assert!(!verify(&params, &unrelated, &proof, b""));
+    }
+
+    #[test]
+    fn tampered_proofs_and_bad_moduli_are_rejected() {
+        let params = WellFormedParams::new().security_bits(32);
+        let pair = gen_provable_key_pair(64);
+        let proof = prove(&params, &pair, b"");
+        let mut flipped = proof.clone();
+        flipped.signs[0].0 = !flipped.signs[0].0;
+        assert!(!verify(&params, pair.public(), &flipped, b""));
+        let mut wrong_root = proof.clone();
+        wrong_root.z[0] += 1;
+        assert!(!verify(&params, pair.public(), &wrong_root, b""));
+        // A prime modulus of the right size is refused outright
+        let prime = PublicKey {
+            n: random_prime(128, &mut rand::thread_rng()),
+            e: BigInt::from(65537),
+        };
+        assert!(!verify(&params, &prime, &proof, b""));
+    }
+}
@@ -0,0 +1,151 @@
+// A tiny length-prefixed binary format for serializing protocol values
+//
+// Every encoding starts with a type tag, then a sequence of items each written as a
+// big-endian u32 length followed by that many bytes. Integers are two's-complement
+// big-endian, as produced by BigInt::to_signed_bytes_be. These are exactly the string and
+// mpint encodings of SSH (RFC 4251, section 5), so the reader also parses OpenSSH keys.
+use crate::error::{ParseError, ParseErrorKind};
+use num_bigint::BigInt;
+
+pub(crate) struct Writer(Vec<u8>);
+
+impl Writer {
+    pub(crate) fn new(tag: &str) -> Self {
+        let mut w = Writer(Vec::new());
+        w.bytes(tag.as_bytes());
+        w
+    }
+
+    pub(crate) fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.0
+            .extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+        self.0.extend_from_slice(bytes);
+        self
+    }
+
+    pub(crate) fn int(&mut self, x: &BigInt) -> &mut Self {
+        self.bytes(&x.to_signed_bytes_be())
+    }
+
+    pub(crate) fn finish(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.0)
+    }
+}
+
+// Reader: errors carry the tag as the format name and the offset of the failing item
+pub(crate) struct Reader<'a> {
+    buf: &'a [u8],
+    offset: usize,
+    tag: &'a str,
+}
+
+impl<'a> Reader<'a> {
+    // new: an error unless the input starts with the expected tag
+    pub(crate) fn new(buf: &'a [u8], tag: &'a str) -> Result<Self, ParseError> {
+        let mut r = Reader {
+            buf,
+            offset: 0,
+            tag,
+        };
+        let actual = r.bytes()?;
+        if actual != tag.as_bytes() {
+            return Err(ParseError::new(
+                tag,
+                0,
+                ParseErrorKind::UnexpectedTag {
+                    expected: tag.to_string(),
+                    actual: String::from_utf8_lossy(actual).into_owned(),
+                },
+            ));
+        }
+        Ok(r)
+    }
+
+    // untagged: a reader for formats without a leading tag; format names them in errors
+    pub(crate) fn untagged(buf: &'a [u8], format: &'a str) -> Self {
+        Reader {
+            buf,
+            offset: 0,
+            tag: format,
+        }
+    }
+
+    fn truncated(&self, needed: usize, available: usize) -> ParseError {
+        ParseError::new(
+            self.tag,
+            self.offset,
+            ParseErrorKind::Truncated { needed, available },
+        )
+    }
+
+    pub(crate) fn bytes(&mut self) -> Result<&'a [u8], ParseError> {
+        if self.buf.len() < 4 {
+            return Err(self.truncated(4, self.buf.len()));
+        }
+        let (len, rest) = self.buf.split_at(4);
+        let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+        if rest.len() < len {
+            return Err(self.truncated(4 + len, self.buf.len()));
+        }
+        let (item, rest) = rest.split_at(len);
+        self.buf = rest;
+        self.offset += 4 + len;
+        Ok(item)
+    }
+
+    pub(crate) fn int(&mut self) -> Result<BigInt, ParseError> {
+        self.bytes().map(BigInt::from_signed_bytes_be)
+    }
+
+    // raw: len bytes without a length prefix
+    pub(crate) fn raw(&mut self, len: usize) -> Result<&'a [u8], ParseError> {
+        if self.buf.len() < len {
+            return Err(self.truncated(len, self.buf.len()));
+        }
+        let (item, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        self.offset += len;
+        Ok(item)
+    }
+
+    pub(crate) fn raw_u32(&mut self) -> Result<u32, ParseError> {
+        Ok(u32::from_be_bytes(self.raw(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    // count: a four-byte item holding a number of repetitions, as written for lists
+    pub(crate) fn count(&mut self) -> Result<u32, ParseError> {
+        let offset = self.offset;
+        let item = self.bytes()?;
+        let item: [u8; 4] = item.try_into().map_err(|_| {
+            ParseError::invalid(
+                self.tag,
+                offset,
+                format!("a count must be 4 bytes, not {}", item.len()),
+            )
+        })?;
+        Ok(u32::from_be_bytes(item))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    // finish: an error if there are trailing bytes after the last item
+    pub(crate) fn finish(self) -> Result<(), ParseError> {
+        if self.buf.is_empty() {
+            Ok(())
+        } else {
+            Err(ParseError::new(
+                self.tag,
+                self.offset,
+                ParseErrorKind::TrailingBytes {
+                    count: self.buf.len(),
+                },
+            ))
+        }
+    }
+}
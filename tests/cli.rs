@@ -0,0 +1,90 @@
+// End-to-end runs of the naive-rsa binary: keygen, then encrypt and decrypt, sign and verify
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+// scratch_dir: a fresh directory under the system temp dir for one test's files
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("naive-rsa-cli-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_naive-rsa"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn run_ok(args: &[&str]) {
+    let output = run(args);
+    assert!(
+        output.status.success(),
+        "naive-rsa {}: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+fn path(dir: &Path, name: &str) -> String {
+    dir.join(name).to_str().unwrap().to_string()
+}
+
+// keygen: (private key file, public key file) for a fresh 1024-bit key in dir
+fn keygen(dir: &Path) -> (String, String) {
+    let (key, pubkey) = (path(dir, "key.pem"), path(dir, "pub.pem"));
+    run_ok(&[
+        "keygen", "--bits", "1024", "--out", &key, "--pubout", &pubkey,
+    ]);
+    (key, pubkey)
+}
+
+#[test]
+fn encrypted_files_decrypt_under_each_scheme() {
+    let dir = scratch_dir("encrypt");
+    let (key, pubkey) = keygen(&dir);
+    let plain = path(&dir, "plain.txt");
+    std::fs::write(&plain, b"attack at dawn").unwrap();
+    for scheme in ["oaep", "pkcs1v15"] {
+        let (enc, dec) = (path(&dir, "enc.bin"), path(&dir, "dec.txt"));
+        run_ok(&[
+            "encrypt", "--pubkey", &pubkey, "--scheme", scheme, "--in", &plain, "--out", &enc,
+        ]);
+        run_ok(&[
+            "decrypt", "--key", &key, "--scheme", scheme, "--in", &enc, "--out", &dec,
+        ]);
+        assert_eq!(
+            std::fs::read(&dec).unwrap(),
+            b"attack at dawn",
+            "{}",
+            scheme
+        );
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn signatures_verify_and_tampered_messages_do_not() {
+    let dir = scratch_dir("sign");
+    let (key, pubkey) = keygen(&dir);
+    let (msg, sig) = (path(&dir, "msg.txt"), path(&dir, "msg.sig"));
+    std::fs::write(&msg, b"attack at dawn").unwrap();
+    for scheme in ["pkcs1v15", "pss"] {
+        run_ok(&[
+            "sign", "--key", &key, "--scheme", scheme, "--in", &msg, "--out", &sig,
+        ]);
+        let verify = |msg: &str| {
+            run(&[
+                "verify", "--pubkey", &pubkey, "--sig", &sig, "--scheme", scheme, "--in", msg,
+            ])
+        };
+        let ok = verify(&msg);
+        assert!(ok.status.success(), "{}", scheme);
+        assert_eq!(String::from_utf8_lossy(&ok.stdout).trim(), "Verified OK");
+        let tampered = path(&dir, "tampered.txt");
+        std::fs::write(&tampered, b"attack at dusk").unwrap();
+        assert_eq!(verify(&tampered).status.code(), Some(1), "{}", scheme);
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}